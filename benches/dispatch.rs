@@ -0,0 +1,46 @@
+//! Compares dispatching through the type table of `FlatHandlers` with walking the `Sequence`
+//! chain it was built from.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[derive(Copy, Clone)]
+struct Numbered<const N: usize>;
+
+macro_rules! twelve_handlers {
+    () => {
+        xcept::builder(|_: Numbered<0>| xcept::Result::new(0))
+            .handle(|_: Numbered<1>| xcept::Result::new(1))
+            .handle(|_: Numbered<2>| xcept::Result::new(2))
+            .handle(|_: Numbered<3>| xcept::Result::new(3))
+            .handle(|_: Numbered<4>| xcept::Result::new(4))
+            .handle(|_: Numbered<5>| xcept::Result::new(5))
+            .handle(|_: Numbered<6>| xcept::Result::new(6))
+            .handle(|_: Numbered<7>| xcept::Result::new(7))
+            .handle(|_: Numbered<8>| xcept::Result::new(8))
+            .handle(|_: Numbered<9>| xcept::Result::new(9))
+            .handle(|_: Numbered<10>| xcept::Result::new(10))
+            .handle(|_: Numbered<11>| xcept::Result::new(11))
+    };
+}
+
+#[inline(never)]
+fn raise_last() -> xcept::Result<i32> {
+    xcept::Result::new_error(Numbered::<11>)
+}
+
+fn dispatch(c: &mut Criterion) {
+    let flat = twelve_handlers!().build_flat();
+    let sequence = twelve_handlers!().build();
+
+    let mut group = c.benchmark_group("twelve handlers, last matches");
+    group.bench_function("flat", |b| {
+        b.iter(|| xcept::try_or_handle(raise_last, black_box(flat)).unwrap())
+    });
+    group.bench_function("sequence", |b| {
+        b.iter(|| xcept::try_or_handle(raise_last, black_box(sequence)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, dispatch);
+criterion_main!(benches);
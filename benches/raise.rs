@@ -0,0 +1,76 @@
+//! The cost of scopes whose function doesn't fail, with the raise and handling paths present
+//! but not taken, and the latency of a raise itself. See also `tests/code_size.rs` for the size
+//! of the generated code.
+//!
+//! Run with `--all-features` to include the metadata recorded for every raise.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[derive(Copy, Clone)]
+struct Overflow(u32);
+
+#[inline(never)]
+fn add_checked(x: u32, y: u32) -> xcept::Result<u32> {
+    match x.checked_add(y) {
+        Some(sum) => xcept::Result::new(sum),
+        None => xcept::Result::new_error(Overflow(x)),
+    }
+}
+
+#[inline(never)]
+fn add_checked_bare(x: u32, y: u32) -> xcept::Result<u32> {
+    match x.checked_add(y) {
+        Some(sum) => xcept::Result::new(sum),
+        None => xcept::Result::new_error_bare(Overflow(x)),
+    }
+}
+
+fn happy_path(c: &mut Criterion) {
+    let handlers = xcept::builder(|err: Overflow| xcept::Result::new(err.0))
+        .handle(|_: &'static str| xcept::Result::new(0))
+        .build();
+
+    let mut group = c.benchmark_group("happy path");
+    group.bench_function("raise site", |b| {
+        b.iter(|| add_checked(black_box(1), black_box(2)).unwrap())
+    });
+    group.bench_function("try_or_handle_one", |b| {
+        b.iter(|| {
+            xcept::try_or_handle_one(|| add_checked(black_box(1), black_box(2)), |err: Overflow| {
+                xcept::Result::new(err.0)
+            })
+            .unwrap()
+        })
+    });
+    group.bench_function("try_or_handle", |b| {
+        b.iter(|| xcept::try_or_handle(|| add_checked(black_box(1), black_box(2)), black_box(handlers)).unwrap())
+    });
+    group.finish();
+}
+
+fn raise_latency(c: &mut Criterion) {
+    let mut group = c.benchmark_group("raise");
+    group.bench_function("unhandled", |b| {
+        b.iter(|| add_checked(black_box(u32::MAX), black_box(1)).is_error())
+    });
+    group.bench_function("captured", |b| {
+        let mut storage = xcept::context::SingleErrorStorage::<Overflow>::default();
+        let scope = std::pin::pin!(xcept::context::ScopeNode::new(&mut storage));
+        // Safety: the guard is dropped below
+        let guard = unsafe { scope.register() };
+        b.iter(|| add_checked(black_box(u32::MAX), black_box(1)).is_error());
+        drop(guard);
+    });
+    group.bench_function("captured bare", |b| {
+        let mut storage = xcept::context::SingleErrorStorage::<Overflow>::default();
+        let scope = std::pin::pin!(xcept::context::ScopeNode::new(&mut storage));
+        // Safety: the guard is dropped below
+        let guard = unsafe { scope.register() };
+        b.iter(|| add_checked_bare(black_box(u32::MAX), black_box(1)).is_error());
+        drop(guard);
+    });
+    group.finish();
+}
+
+criterion_group!(benches, happy_path, raise_latency);
+criterion_main!(benches);
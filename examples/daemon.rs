@@ -0,0 +1,114 @@
+//! A long-running service: workers send their errors to a supervisor, which handles them with a
+//! policy that is reloaded on `SIGHUP`, and drains the remaining errors on shutdown.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use xcept::policy::PolicyCell;
+use xcept::supervisor::{Mailbox, MailboxHandle};
+
+#[derive(Debug, Copy, Clone)]
+struct JobFailed {
+    worker: usize,
+    attempt: u32,
+}
+
+#[derive(Debug)]
+struct Policy {
+    max_attempts: u32,
+}
+
+static RELOAD: AtomicBool = AtomicBool::new(false);
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+fn install_signal_handlers() {
+    extern "C" fn on_hup(_: libc::c_int) {
+        RELOAD.store(true, Ordering::Relaxed);
+    }
+    extern "C" fn on_term(_: libc::c_int) {
+        SHUTDOWN.store(true, Ordering::Relaxed);
+    }
+    // Safety: the handlers only store to atomics, which is async-signal-safe
+    unsafe {
+        libc::signal(libc::SIGHUP, on_hup as *const () as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, on_term as *const () as libc::sighandler_t);
+        libc::signal(libc::SIGINT, on_term as *const () as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+fn install_signal_handlers() {}
+
+fn worker(id: usize, handle: MailboxHandle<JobFailed>) {
+    let mut attempt = 0;
+    while !SHUTDOWN.load(Ordering::Relaxed) {
+        attempt += 1;
+        if attempt % 3 == 0 {
+            handle.raise(JobFailed { worker: id, attempt });
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn main() {
+    install_signal_handlers();
+
+    let policy = PolicyCell::new(Policy { max_attempts: 3 });
+    let (mut scope, handle) = Mailbox::<JobFailed>::new();
+    let workers: Vec<_> = (0..3)
+        .map(|id| {
+            let handle = handle.clone();
+            std::thread::spawn(move || worker(id, handle))
+        })
+        .collect();
+    drop(handle);
+
+    // Handlers are rebuilt from the policy snapshot each round, so a reload applies atomically
+    let handlers = |policy: Arc<Policy>| {
+        xcept::builder(move |err: JobFailed| {
+            if err.attempt <= policy.max_attempts {
+                xcept::Result::new(format!("worker {} retries after attempt {}", err.worker, err.attempt))
+            } else {
+                xcept::Result::new(format!("worker {} gave up after attempt {}", err.worker, err.attempt))
+            }
+        })
+        .build()
+    };
+
+    for round in 0.. {
+        if round == 5 {
+            // Simulate an operator asking for a reload, then a shutdown a bit later
+            #[cfg(unix)]
+            unsafe {
+                libc::raise(libc::SIGHUP);
+            }
+            #[cfg(not(unix))]
+            RELOAD.store(true, Ordering::Relaxed);
+        }
+        if round == 10 {
+            SHUTDOWN.store(true, Ordering::Relaxed);
+        }
+        if SHUTDOWN.load(Ordering::Relaxed) {
+            break;
+        }
+        if RELOAD.swap(false, Ordering::Relaxed) {
+            let previous = policy.swap(Policy { max_attempts: 6 });
+            println!("reloaded policy, previous was {previous:?}");
+        }
+
+        scope.wait_timeout(Duration::from_millis(20));
+        for res in scope.process(handlers(policy.load())) {
+            println!("{}", res.unwrap());
+        }
+    }
+
+    // Workers see the shutdown flag and exit, dropping their handles
+    for res in scope.drain(handlers(policy.load())) {
+        println!("drained: {}", res.unwrap());
+    }
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    println!("shut down");
+}
@@ -0,0 +1,18 @@
+//! `main` returning an `xcept::Result`.
+//!
+//! Run with an argument that isn't a number to exit with a failure code, after the unhandled
+//! error is reported on stderr.
+
+fn parse_arg() -> xcept::Result<u32> {
+    let arg = std::env::args().nth(1).unwrap_or_else(|| String::from("1"));
+    arg.parse::<u32>().into()
+}
+
+fn main() -> xcept::Result<()> {
+    // Only overflows are handled, a parse error escapes `main`
+    let doubled = xcept::try_or_handle_one(
+        || parse_arg().and_then(|x| xcept::Result::from(x.checked_mul(2))),
+        |_: xcept::NoneError| xcept::Result::new(u32::MAX),
+    );
+    doubled.map(|x| println!("{x}"))
+}
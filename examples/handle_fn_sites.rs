@@ -0,0 +1,59 @@
+//! A synthetic program with 1000 single handler scopes, comparing [`xcept::try_or_handle_fn`]
+//! with the generic [`xcept::try_or_handle_one`].
+//!
+//! Building with the `XCEPT_HANDLE_GENERIC` environment variable set uses the generic version at
+//! every call site, see `tests/code_size.rs`.
+
+#[derive(Debug)]
+#[allow(dead_code)] // The value is only observed through `Debug`
+struct SiteError(u32);
+
+type Site = fn(u32) -> xcept::Result<u32>;
+
+#[inline(never)]
+fn compute(x: u32, n: u32) -> xcept::Result<u32> {
+    if x == u32::MAX - n {
+        xcept::Result::new_error(SiteError(n))
+    } else {
+        xcept::Result::new(x.wrapping_add(n))
+    }
+}
+
+fn fallback(_: SiteError) -> xcept::Result<u32> {
+    xcept::Result::new(0)
+}
+
+#[inline(never)]
+fn site<const N: u32>(x: u32) -> xcept::Result<u32> {
+    if option_env!("XCEPT_HANDLE_GENERIC").is_some() {
+        xcept::try_or_handle_one(|| compute(x, N), fallback)
+    } else {
+        xcept::try_or_handle_fn(&mut || compute(x, N), fallback)
+    }
+}
+
+macro_rules! sites {
+    ($($row:literal)*; $cols:tt) => {
+        [$(sites!(@row $row $cols)),*]
+    };
+    (@row $row:literal [$($col:literal)*]) => {
+        [$(site::<{ $row * 10 + $col }> as Site),*]
+    };
+}
+
+static SITES: [[Site; 10]; 100] = sites!(
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32 33 34
+    35 36 37 38 39 40 41 42 43 44 45 46 47 48 49 50 51 52 53 54 55 56 57 58 59 60 61 62 63 64 65 66
+    67 68 69 70 71 72 73 74 75 76 77 78 79 80 81 82 83 84 85 86 87 88 89 90 91 92 93 94 95 96 97 98
+    99;
+    [0 1 2 3 4 5 6 7 8 9]
+);
+
+fn main() {
+    let input = std::env::args().count() as u32;
+    let mut sum = 0u32;
+    for site in SITES.iter().flatten() {
+        sum = sum.wrapping_add(site(input).unwrap_or(1));
+    }
+    println!("sum = {sum}");
+}
@@ -1,4 +1,5 @@
 #[derive(Debug)]
+#[allow(dead_code)] // The value is only observed through `Debug`
 struct CustomError(i32);
 
 fn hello_world(x: i32) -> xcept::Result<&'static str> {
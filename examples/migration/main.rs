@@ -0,0 +1,27 @@
+//! The same CSV ingest written twice, once with `std::result::Result` and `thiserror`, and once
+//! with xcept, to compare the two side by side.
+//!
+//! Run with the files to ingest as arguments, or without arguments to ingest the fixtures. Both
+//! implementations print the same report for every file, which `tests/migration.rs` checks.
+
+mod report;
+mod std_impl;
+mod xcept_impl;
+
+fn main() {
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/migration/fixtures");
+    let mut paths: Vec<_> = std::env::args_os().skip(1).map(std::path::PathBuf::from).collect();
+    if paths.is_empty() {
+        paths = std::fs::read_dir(&fixtures)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+        paths.push(fixtures.join("missing.csv"));
+    }
+    for path in paths {
+        println!("{}", path.display());
+        println!("  std:   {}", std_impl::ingest(&path));
+        println!("  xcept: {}", xcept_impl::ingest(&path));
+    }
+}
@@ -0,0 +1,15 @@
+//! The report shared by both implementations.
+
+/// The report for a successfully ingested file.
+pub fn summary(records: &[(&str, u32, u32)]) -> String {
+    if records.is_empty() {
+        return "no records".to_string();
+    }
+    let total: u32 = records.iter().map(|(_, _, score)| score).sum();
+    let oldest = records.iter().max_by_key(|(_, age, _)| age).unwrap().0;
+    format!(
+        "{} records, mean score {}, oldest {oldest}",
+        records.len(),
+        total / records.len() as u32
+    )
+}
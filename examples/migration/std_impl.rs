@@ -0,0 +1,92 @@
+//! The ingest implemented with `std::result::Result` and `thiserror`.
+//!
+//! Every function returns the error enum, and every caller propagates it with `?`. The report is
+//! produced by matching on the enum at the top.
+
+use std::io;
+use std::path::Path;
+
+#[derive(Debug)]
+pub struct Record {
+    pub name: String,
+    pub age: u32,
+    pub score: u32,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum IngestError {
+    #[error("reading {path}: {source}")]
+    Io { path: String, source: io::Error },
+    #[error("line {line}: invalid {field} '{text}'")]
+    Parse {
+        line: usize,
+        field: &'static str,
+        text: String,
+    },
+    #[error("line {line}: {what}")]
+    Invalid { line: usize, what: String },
+}
+
+fn parse_field(line: usize, field: &'static str, text: &str) -> Result<u32, IngestError> {
+    text.parse().map_err(|_| IngestError::Parse {
+        line,
+        field,
+        text: text.to_string(),
+    })
+}
+
+fn parse_record(line: usize, row: &str) -> Result<Record, IngestError> {
+    let fields: Vec<_> = row.split(',').map(str::trim).collect();
+    if fields.len() != 3 {
+        return Err(IngestError::Invalid {
+            line,
+            what: format!("expected 3 fields, got {}", fields.len()),
+        });
+    }
+    let age = parse_field(line, "age", fields[1])?;
+    let score = parse_field(line, "score", fields[2])?;
+    if age > 150 {
+        return Err(IngestError::Invalid {
+            line,
+            what: format!("age {age} out of range"),
+        });
+    }
+    if score > 100 {
+        return Err(IngestError::Invalid {
+            line,
+            what: format!("score {score} out of range"),
+        });
+    }
+    Ok(Record {
+        name: fields[0].to_string(),
+        age,
+        score,
+    })
+}
+
+pub fn load(path: &Path) -> Result<Vec<Record>, IngestError> {
+    let text = std::fs::read_to_string(path).map_err(|source| IngestError::Io {
+        path: path.display().to_string(),
+        source,
+    })?;
+    text.lines()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, row)| !row.trim().is_empty())
+        .map(|(index, row)| parse_record(index + 1, row))
+        .collect()
+}
+
+/// Ingest the file at `path`, returning a one line report.
+pub fn ingest(path: &Path) -> String {
+    match load(path) {
+        Ok(records) => {
+            let rows: Vec<_> = records.iter().map(|r| (r.name.as_str(), r.age, r.score)).collect();
+            crate::report::summary(&rows)
+        }
+        Err(IngestError::Io { path, source }) if source.kind() == io::ErrorKind::NotFound => {
+            format!("error: {path} not found")
+        }
+        Err(err) => format!("error: {err}"),
+    }
+}
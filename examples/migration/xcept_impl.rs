@@ -0,0 +1,111 @@
+//! The ingest implemented with xcept.
+//!
+//! Errors are separate types, raised where they happen and handled by a multihandler at the top.
+//! Functions in between only return the error ID. Context the handlers need, such as the path, is
+//! known at the top and captured by the handlers instead of being carried by every error.
+
+use std::io;
+use std::path::Path;
+use xcept::interop::StdResultExt;
+
+/// Yield the value, or return the error ID from the enclosing function.
+macro_rules! propagate {
+    ($res:expr) => {
+        match $res.try_unwrap() {
+            Ok(value) => value,
+            Err(id) => return xcept::Result::new_with_error_id(id),
+        }
+    };
+}
+
+#[derive(Debug)]
+pub struct Record {
+    pub name: String,
+    pub age: u32,
+    pub score: u32,
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub field: &'static str,
+    pub text: String,
+}
+
+#[derive(Debug)]
+pub struct Invalid {
+    pub line: usize,
+    pub what: String,
+}
+
+fn parse_field(line: usize, field: &'static str, text: &str) -> xcept::Result<u32> {
+    text.parse::<u32>().raise_with(|_| ParseError {
+        line,
+        field,
+        text: text.to_string(),
+    })
+}
+
+fn parse_record(line: usize, row: &str) -> xcept::Result<Record> {
+    let fields: Vec<_> = row.split(',').map(str::trim).collect();
+    if fields.len() != 3 {
+        return xcept::Result::new_error(Invalid {
+            line,
+            what: format!("expected 3 fields, got {}", fields.len()),
+        });
+    }
+    let age = propagate!(parse_field(line, "age", fields[1]));
+    let score = propagate!(parse_field(line, "score", fields[2]));
+    if age > 150 {
+        return xcept::Result::new_error(Invalid {
+            line,
+            what: format!("age {age} out of range"),
+        });
+    }
+    if score > 100 {
+        return xcept::Result::new_error(Invalid {
+            line,
+            what: format!("score {score} out of range"),
+        });
+    }
+    xcept::Result::new(Record {
+        name: fields[0].to_string(),
+        age,
+        score,
+    })
+}
+
+pub fn load(path: &Path) -> xcept::Result<Vec<Record>> {
+    let text = propagate!(std::fs::read_to_string(path).raise());
+    let mut records = Vec::new();
+    for (index, row) in text.lines().enumerate().skip(1) {
+        if row.trim().is_empty() {
+            continue;
+        }
+        records.push(propagate!(parse_record(index + 1, row)));
+    }
+    xcept::Result::new(records)
+}
+
+fn summarize(path: &Path) -> xcept::Result<String> {
+    let records = propagate!(load(path));
+    let rows: Vec<_> = records.iter().map(|r| (r.name.as_str(), r.age, r.score)).collect();
+    xcept::Result::new(crate::report::summary(&rows))
+}
+
+/// Ingest the file at `path`, returning a one line report.
+pub fn ingest(path: &Path) -> String {
+    let shown = path.display().to_string();
+    let handlers = xcept::builder(|err: ParseError| {
+        xcept::Result::new(format!("error: line {}: invalid {} '{}'", err.line, err.field, err.text))
+    })
+    .handle(|err: Invalid| xcept::Result::new(format!("error: line {}: {}", err.line, err.what)))
+    .handle(|err: io::Error| xcept::Result::new(format!("error: reading {shown}: {err}")))
+    .build();
+
+    // A missing file is handled on its own, other IO errors are forwarded to `handlers`
+    let not_found = xcept::interop::handle_io_kind(io::ErrorKind::NotFound, |_| {
+        xcept::Result::new(format!("error: {} not found", path.display()))
+    });
+    xcept::try_or_handle(|| xcept::try_or_handle_one(|| summarize(path), not_found), handlers).unwrap()
+}
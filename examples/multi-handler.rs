@@ -1,4 +1,5 @@
 #[derive(Debug, Copy, Clone)]
+#[allow(dead_code)] // The value is only observed through `Debug`
 struct CustomError(i32);
 
 fn hello_world(x: i32) -> xcept::Result<&'static str> {
@@ -30,11 +31,13 @@ fn main() {
         .handle(custom_error_handler)
         .build();
 
-    println!("Result = {}", xcept::try_or_handle(|| hello_world(0), handlers.clone()).unwrap());
-    println!("Result = {}", xcept::try_or_handle(|| hello_world(1), handlers.clone()).unwrap());
-    println!("Result = {}", xcept::try_or_handle(|| hello_world(3), handlers.clone()).unwrap());
+    println!("Result = {}", xcept::try_or_handle(|| hello_world(0), handlers).unwrap());
+    println!("Result = {}", xcept::try_or_handle(|| hello_world(1), handlers).unwrap());
+    println!("Result = {}", xcept::try_or_handle(|| hello_world(3), handlers).unwrap());
 
-    let res = xcept::try_or_handle(|| hello_world(11), handlers.clone());
+    let res = xcept::try_or_handle(|| hello_world(11), handlers);
 
-    println!("Error ID = {:?}", res.error_id())
+    if let Some(id) = res.error_id() {
+        println!("Unhandled {id}, raw ID = {}", id.to_raw());
+    }
 }
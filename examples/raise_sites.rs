@@ -0,0 +1,47 @@
+//! A synthetic program with 200 raise sites, used to measure the code generated per site.
+//!
+//! Building with the `XCEPT_SIZE_BASELINE` environment variable set removes the raises, see
+//! `tests/code_size.rs`.
+
+#[derive(Debug)]
+#[allow(dead_code)] // The value is only observed through `Debug`
+struct SiteError(u32);
+
+type Site = fn(u32) -> xcept::Result<u32>;
+
+#[inline(never)]
+fn site<const N: u32>(x: u32) -> xcept::Result<u32> {
+    if option_env!("XCEPT_SIZE_BASELINE").is_none() && x == u32::MAX - N {
+        xcept::Result::new_error(SiteError(N))
+    } else {
+        xcept::Result::new(x.wrapping_add(N))
+    }
+}
+
+macro_rules! sites {
+    ($($row:literal)*; $cols:tt) => {
+        [$(sites!(@row $row $cols)),*]
+    };
+    (@row $row:literal [$($col:literal)*]) => {
+        [$(site::<{ $row * 10 + $col }> as Site),*]
+    };
+}
+
+static SITES: [[Site; 10]; 20] = sites!(
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19;
+    [0 1 2 3 4 5 6 7 8 9]
+);
+
+fn main() {
+    let input = std::env::args().count() as u32;
+    let mut sum = 0u32;
+    let mut errors = 0;
+    for site in SITES.iter().flatten() {
+        let res = xcept::try_or_handle_one(|| site(input), |_: SiteError| xcept::Result::new(0));
+        match res.ok() {
+            Some(x) => sum = sum.wrapping_add(x),
+            None => errors += 1,
+        }
+    }
+    println!("sum = {sum}, errors = {errors}");
+}
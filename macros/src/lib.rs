@@ -0,0 +1,452 @@
+//! Procedural macros for `xcept`.
+//!
+//! These are re-exported by `xcept` when its `macros` feature is enabled.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use proc_macro2::{Group, TokenTree};
+use quote::format_ident;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, GenericParam, Index, LitStr, Member, Path, Type};
+
+/// Derive `xcept::capture::CaptureSet` for an enum.
+///
+/// Every variant must hold exactly one unnamed field, the error type captured into that variant.
+#[proc_macro_derive(CaptureSet)]
+pub fn derive_capture_set(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    capture_set(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn capture_set(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "CaptureSet can only be derived for enums",
+        ));
+    };
+
+    let mut captures = Vec::new();
+    let mut payloads = Vec::new();
+    for variant in &data.variants {
+        let ty = match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => &fields.unnamed[0].ty,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "CaptureSet variants must hold exactly one unnamed error type",
+                ))
+            }
+        };
+        let name = &variant.ident;
+        captures.push(quote! {
+            if let ::core::option::Option::Some(value) = ::xcept::capture::take::<#ty>(error) {
+                return ::core::option::Option::Some(Self::#name(value));
+            }
+        });
+        payloads.push(ty);
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut where_clause = where_clause
+        .cloned()
+        .unwrap_or_else(|| syn::parse_quote!(where));
+    where_clause.predicates.push(syn::parse_quote!(Self: 'static));
+    for ty in payloads {
+        where_clause.predicates.push(syn::parse_quote!(#ty: 'static));
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::xcept::capture::CaptureSet for #ident #ty_generics #where_clause {
+            unsafe fn try_capture(
+                error: &::xcept::context::ReportedError,
+            ) -> ::core::option::Option<Self> {
+                #(#captures)*
+                ::core::option::Option::None
+            }
+        }
+
+        impl #impl_generics ::xcept::capture::IntoCaptureSet for #ident #ty_generics #where_clause {
+            type Set = Self;
+        }
+    })
+}
+
+/// Derive `xcept::LocatedError` for a struct.
+///
+/// The location is stored in the field marked `#[xcept(location)]`, or else in the field named
+/// `location`. The field type must be constructible with `From<&'static Location<'static>>`,
+/// such as the reference itself or an `Option` of it.
+#[proc_macro_derive(LocatedError, attributes(xcept))]
+pub fn derive_located_error(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    located_error(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn located_error(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "LocatedError can only be derived for structs",
+        ));
+    };
+
+    let mut marked = None;
+    let mut named = None;
+    for (index, field) in data.fields.iter().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(index)),
+        };
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("xcept")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("location") {
+                    if marked.is_some() {
+                        return Err(meta.error("only one field can be marked `#[xcept(location)]`"));
+                    }
+                    marked = Some(member.clone());
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported xcept attribute"))
+                }
+            })?;
+        }
+        if field.ident.as_ref().is_some_and(|ident| ident == "location") {
+            named = Some(member);
+        }
+    }
+    let Some(field) = marked.or(named) else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "LocatedError needs a field named `location` or marked `#[xcept(location)]`",
+        ));
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut where_clause = where_clause
+        .cloned()
+        .unwrap_or_else(|| syn::parse_quote!(where));
+    where_clause.predicates.push(syn::parse_quote!(Self: 'static));
+
+    Ok(quote! {
+        impl #impl_generics ::xcept::LocatedError for #ident #ty_generics #where_clause {
+            fn set_location(
+                &mut self,
+                location: &'static ::core::panic::Location<'static>,
+            ) {
+                self.#field = ::core::convert::From::from(location);
+            }
+        }
+    })
+}
+
+/// Derive `xcept::ToStatic` for a struct whose fields all implement it.
+///
+/// A struct named `{Name}Owned` is generated, with the same fields converted to their owned
+/// types, and used as `Owned`. Derives for the owned struct are listed with
+/// `#[xcept(derive(...))]`. Only lifetime parameters are supported.
+#[proc_macro_derive(ToStatic, attributes(xcept))]
+pub fn derive_to_static(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    to_static(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Replace the `lifetimes` in `tokens` with `'static`.
+fn make_static(tokens: proc_macro2::TokenStream, lifetimes: &[syn::Ident]) -> proc_macro2::TokenStream {
+    let mut out = Vec::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == '\'' => {
+                match tokens.peek() {
+                    Some(TokenTree::Ident(ident)) if lifetimes.contains(ident) => {
+                        let span = ident.span();
+                        tokens.next();
+                        out.push(TokenTree::Punct(punct));
+                        out.push(TokenTree::Ident(syn::Ident::new("static", span)));
+                    }
+                    _ => out.push(TokenTree::Punct(punct)),
+                }
+            }
+            TokenTree::Group(group) => {
+                let mut replaced = Group::new(group.delimiter(), make_static(group.stream(), lifetimes));
+                replaced.set_span(group.span());
+                out.push(TokenTree::Group(replaced));
+            }
+            token => out.push(token),
+        }
+    }
+    out.into_iter().collect()
+}
+
+fn to_static(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "ToStatic can only be derived for structs",
+        ));
+    };
+
+    let mut lifetimes = Vec::new();
+    for param in &input.generics.params {
+        match param {
+            GenericParam::Lifetime(param) => lifetimes.push(param.lifetime.ident.clone()),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    param,
+                    "ToStatic can only be derived for structs with lifetime parameters only",
+                ))
+            }
+        }
+    }
+
+    let mut derives: Vec<Path> = Vec::new();
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("xcept")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("derive") {
+                meta.parse_nested_meta(|derive| {
+                    derives.push(derive.path);
+                    Ok(())
+                })
+            } else {
+                Err(meta.error("unsupported xcept attribute"))
+            }
+        })?;
+    }
+
+    let mut owned_fields = Vec::new();
+    let mut conversions = Vec::new();
+    for (index, field) in data.fields.iter().enumerate() {
+        let vis = &field.vis;
+        let ty = &field.ty;
+        let ty = make_static(quote!(#ty), &lifetimes);
+        let owned_ty = quote!(<#ty as ::xcept::ToStatic>::Owned);
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(index)),
+        };
+        match &field.ident {
+            Some(ident) => owned_fields.push(quote!(#vis #ident: #owned_ty)),
+            None => owned_fields.push(quote!(#vis #owned_ty)),
+        }
+        conversions.push(quote!(#member: ::xcept::ToStatic::to_static(self.#member)));
+    }
+
+    let ident = &input.ident;
+    let owned = format_ident!("{}Owned", ident);
+    let vis = &input.vis;
+    let doc = format!("Owned version of [`{ident}`], see `xcept::ToStatic`.");
+    let body = match &data.fields {
+        Fields::Named(_) => quote!({ #(#owned_fields),* }),
+        Fields::Unnamed(_) => quote!(( #(#owned_fields),* );),
+        Fields::Unit => quote!(;),
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(#(#derives),*)]
+        #vis struct #owned #body
+
+        impl #impl_generics ::xcept::ToStatic for #ident #ty_generics #where_clause {
+            type Owned = #owned;
+
+            fn to_static(self) -> #owned {
+                #owned { #(#conversions),* }
+            }
+        }
+    })
+}
+
+/// Derive `xcept::stable_id::StableError`.
+///
+/// The UUID is given with `#[xcept(uuid = "...")]`. The type is its own portable representation
+/// unless one is named with `#[xcept(portable = Type)]`, which must implement `From<Self>` while
+/// `Self` implements `From<Type>`. Deriving promises the portable type has the same layout as the
+/// portable type of every other type with this UUID.
+#[proc_macro_derive(StableError, attributes(xcept))]
+pub fn derive_stable_error(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    stable_error(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn stable_error(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut uuid: Option<LitStr> = None;
+    let mut portable: Option<Type> = None;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("xcept")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("uuid") {
+                uuid = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("portable") {
+                portable = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported xcept attribute"))
+            }
+        })?;
+    }
+    let Some(uuid) = uuid else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "StableError needs `#[xcept(uuid = \"...\")]`",
+        ));
+    };
+    let digits = uuid.value().chars().filter(|c| *c != '-').collect::<String>();
+    if digits.len() != 32 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(syn::Error::new_spanned(&uuid, "invalid UUID, expected 32 hex digits"));
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut where_clause = where_clause
+        .cloned()
+        .unwrap_or_else(|| syn::parse_quote!(where));
+    where_clause.predicates.push(syn::parse_quote!(Self: 'static));
+    let (portable, into_portable, from_portable) = match portable {
+        Some(portable) => (
+            quote!(#portable),
+            quote!(::core::convert::From::from(self)),
+            quote!(::core::convert::From::from(portable)),
+        ),
+        None => (quote!(Self), quote!(self), quote!(portable)),
+    };
+
+    Ok(quote! {
+        unsafe impl #impl_generics ::xcept::stable_id::StableError for #ident #ty_generics #where_clause {
+            const STABLE_ID: ::xcept::stable_id::StableTypeId = ::xcept::stable_id::StableTypeId::parse(#uuid);
+            type Portable = #portable;
+
+            fn into_portable(self) -> Self::Portable {
+                #into_portable
+            }
+
+            fn from_portable(portable: Self::Portable) -> Self {
+                #from_portable
+            }
+        }
+    })
+}
+
+/// Install the top-level scope and reporter of `xcept::entry` around `main`.
+///
+/// The function returns an `xcept::Result<T>` or nothing, and expands to a function of the same
+/// name returning `std::process::ExitCode` through `xcept::entry::run_main`. Optional arguments:
+///
+/// * `backtrace`: add a backtrace of the raise to the report
+/// * `exit_code = N`: the exit code on an error, 1 by default
+/// * `handlers = f`: a function returning a handler chain, wrapped around the body like
+///   `xcept::try_or_handle`
+#[proc_macro_attribute]
+pub fn main(args: TokenStream, input: TokenStream) -> TokenStream {
+    entry_point(Entry::Main, args, input)
+}
+
+/// Run a test in the top-level scope of `xcept::entry`, used instead of `#[test]`.
+///
+/// The test fails with a report of the error if it returns an error, or if any error it raised
+/// wasn't handled by a scope. Takes the arguments of `#[xcept::main]` except `exit_code`.
+#[proc_macro_attribute]
+pub fn test(args: TokenStream, input: TokenStream) -> TokenStream {
+    entry_point(Entry::Test, args, input)
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Entry {
+    Main,
+    Test,
+}
+
+fn entry_point(entry: Entry, args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut options = EntryArgs::default();
+    let parser = syn::meta::parser(|meta| options.parse(entry, meta));
+    parse_macro_input!(args with parser);
+    let input = parse_macro_input!(input as syn::ItemFn);
+    expand_entry(entry, options, input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct EntryArgs {
+    backtrace: bool,
+    exit_code: Option<syn::LitInt>,
+    handlers: Option<syn::Expr>,
+}
+
+impl EntryArgs {
+    fn parse(&mut self, entry: Entry, meta: syn::meta::ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("backtrace") {
+            self.backtrace = true;
+            Ok(())
+        } else if meta.path.is_ident("exit_code") && entry == Entry::Main {
+            let exit_code: syn::LitInt = meta.value()?.parse()?;
+            exit_code.base10_parse::<u8>()?;
+            self.exit_code = Some(exit_code);
+            Ok(())
+        } else if meta.path.is_ident("handlers") {
+            self.handlers = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("exit_code") {
+            Err(meta.error("`exit_code` is only supported by `#[xcept::main]`"))
+        } else {
+            Err(meta.error("unsupported argument, expected `backtrace`, `exit_code` or `handlers`"))
+        }
+    }
+}
+
+fn expand_entry(entry: Entry, args: EntryArgs, input: syn::ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let sig = &input.sig;
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(syn::Error::new_spanned(asyncness, "async functions are not supported"));
+    }
+    if !sig.inputs.is_empty() {
+        return Err(syn::Error::new_spanned(&sig.inputs, "the function must not take any arguments"));
+    }
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        return Err(syn::Error::new_spanned(&sig.generics, "the function must not be generic"));
+    }
+
+    let syn::ItemFn { attrs, vis, block, .. } = &input;
+    let ident = &sig.ident;
+    let (output, call) = match &sig.output {
+        syn::ReturnType::Default => (quote!(), quote!(::xcept::Result::new(__xcept_body()))),
+        syn::ReturnType::Type(_, ty) => (quote!(-> #ty), quote!(__xcept_body())),
+    };
+    let body = match &args.handlers {
+        Some(handlers) => quote!(::xcept::try_or_handle(|| #call, (#handlers)())),
+        None => call,
+    };
+
+    let backtrace = args.backtrace;
+    let mut options = quote!(::xcept::entry::EntryOptions::new().backtrace(#backtrace));
+    if let Some(exit_code) = &args.exit_code {
+        options = quote!(#options.exit_code(#exit_code));
+    }
+    let (test_attr, output_ty, run) = match entry {
+        Entry::Main => (
+            quote!(),
+            quote!(-> ::std::process::ExitCode),
+            quote!(::xcept::entry::run_main),
+        ),
+        Entry::Test => (quote!(#[::core::prelude::v1::test]), quote!(), quote!(::xcept::entry::run_test)),
+    };
+
+    Ok(quote! {
+        #test_attr
+        #(#attrs)*
+        #vis fn #ident() #output_ty {
+            fn __xcept_body() #output #block
+            #run(#options, || #body)
+        }
+    })
+}
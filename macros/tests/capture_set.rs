@@ -0,0 +1,66 @@
+use xcept_macros::CaptureSet;
+
+#[derive(Debug)]
+struct NotFound(String);
+
+#[derive(CaptureSet)]
+enum MyErrs {
+    Io(std::io::Error),
+    Parse(std::num::ParseIntError),
+    Missing(NotFound),
+}
+
+fn parse(s: &str) -> xcept::Result<i32> {
+    match s {
+        "" => xcept::Result::new_error(NotFound("input".to_string())),
+        "io" => xcept::Result::new_error(std::io::Error::other("disk")),
+        "flag" => xcept::Result::new_error(true),
+        _ => s.parse().into(),
+    }
+}
+
+fn handle(s: &str) -> xcept::Result<i32> {
+    let handlers = xcept::capture_set::<MyErrs>().handle(|err| {
+        xcept::Result::new(match err {
+            MyErrs::Io(e) => {
+                assert_eq!(e.to_string(), "disk");
+                -1
+            }
+            MyErrs::Parse(e) => {
+                assert_eq!(e.to_string(), "invalid digit found in string");
+                -2
+            }
+            MyErrs::Missing(NotFound(what)) => {
+                assert_eq!(what, "input");
+                -3
+            }
+        })
+    });
+    xcept::try_or_handle(|| parse(s), handlers)
+}
+
+#[test]
+fn derived_capture_set() {
+    assert_eq!(handle("12").unwrap(), 12);
+    assert_eq!(handle("io").unwrap(), -1);
+    assert_eq!(handle("abc").unwrap(), -2);
+    assert_eq!(handle("").unwrap(), -3);
+    assert!(handle("flag").is_error());
+}
+
+#[test]
+fn derived_into_inner() {
+    use xcept::capture::CaptureSetStorage;
+
+    let mut storage = CaptureSetStorage::<MyErrs>::default();
+    let res = {
+        let scope = std::pin::pin!(xcept::context::ScopeNode::new(&mut storage));
+        // Safety: the guard is dropped at the end of the block
+        let _guard = unsafe { scope.register() };
+        parse("x1")
+    };
+
+    let (id, err) = storage.into_inner().unwrap();
+    assert_eq!(Some(id), res.error_id());
+    assert!(matches!(err, MyErrs::Parse(_)));
+}
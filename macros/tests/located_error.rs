@@ -0,0 +1,53 @@
+use std::panic::Location;
+use xcept_macros::LocatedError;
+
+#[derive(Debug, LocatedError)]
+struct MissingKey {
+    key: &'static str,
+    location: Option<&'static Location<'static>>,
+}
+
+#[derive(Debug, LocatedError)]
+struct Timeout(u32, #[xcept(location)] &'static Location<'static>);
+
+#[track_caller]
+fn missing(key: &'static str) -> xcept::Result<()> {
+    xcept::Result::new_located_error(MissingKey { key, location: None })
+}
+
+#[track_caller]
+fn timeout() -> xcept::Result<()> {
+    xcept::Result::new_located_error(Timeout(5, Location::caller()))
+}
+
+#[test]
+fn named_location_field() {
+    let mut caught = None;
+    let line = line!() + 2;
+    let res = xcept::try_or_handle_one(
+        || missing("db.host"),
+        |err: MissingKey| {
+            caught = Some(err);
+            xcept::Result::new(())
+        },
+    );
+    assert!(res.is_ok());
+    let err = caught.unwrap();
+    let location = err.location.unwrap();
+    assert_eq!(err.key, "db.host");
+    assert_eq!((location.file(), location.line()), (file!(), line));
+}
+
+#[test]
+fn marked_tuple_field() {
+    let mut caught = None;
+    let line = line!() + 1;
+    let res = xcept::try_or_handle_one(|| timeout(), |err: Timeout| {
+        caught = Some(err);
+        xcept::Result::new(())
+    });
+    assert!(res.is_ok());
+    let Timeout(secs, location) = caught.unwrap();
+    assert_eq!(secs, 5);
+    assert_eq!((location.file(), location.line()), (file!(), line));
+}
@@ -0,0 +1,85 @@
+//! The mains generated by `#[xcept::main]`, run as subprocesses of this test binary.
+
+use std::process::{Command, ExitCode};
+
+struct Invalid(&'static str);
+
+#[xcept_macros::main]
+fn succeeds() -> xcept::Result<()> {
+    println!("ran");
+    xcept::Result::new(())
+}
+
+#[xcept_macros::main]
+fn plain() {
+    let _ignored: xcept::Result<()> = xcept::Result::new_error(1u8);
+    println!("ran");
+}
+
+#[xcept_macros::main(exit_code = 3)]
+fn fails() -> xcept::Result<ExitCode> {
+    xcept::redact::register(|err: &Invalid| xcept::redact::RedactedSummary::new(err.0));
+    xcept::Result::new_error(Invalid("bad header"))
+}
+
+#[xcept_macros::main(backtrace)]
+fn fails_with_backtrace() -> xcept::Result<()> {
+    xcept::Result::new_error(Invalid("bad body"))
+}
+
+fn fallback() -> impl xcept::multihandler::TryHandle<Value = ExitCode> + xcept::context::ErrorHandlingContext {
+    xcept::builder(|_: Invalid| xcept::Result::new(ExitCode::from(7))).build()
+}
+
+#[xcept_macros::main(handlers = fallback, exit_code = 3)]
+fn handled() -> xcept::Result<ExitCode> {
+    xcept::Result::new_error(Invalid("handled"))
+}
+
+type Main = fn() -> ExitCode;
+
+const MAINS: [(&str, Main); 5] = [
+    ("succeeds", succeeds),
+    ("plain", plain),
+    ("fails", fails),
+    ("fails_with_backtrace", fails_with_backtrace),
+    ("handled", handled),
+];
+
+fn run(name: &str) -> (Option<i32>, String, String) {
+    let output = Command::new(std::env::current_exe().unwrap())
+        .arg(name)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+fn main() -> ExitCode {
+    // Arguments passed by `cargo test` are ignored
+    let name = std::env::args().nth(1).unwrap_or_default();
+    if let Some((_, main)) = MAINS.iter().find(|(main, _)| *main == name) {
+        return main();
+    }
+
+    assert_eq!(run("succeeds"), (Some(0), "ran\n".into(), String::new()));
+    assert_eq!(run("plain"), (Some(0), "ran\n".into(), String::new()));
+
+    let (code, _, stderr) = run("fails");
+    assert_eq!(code, Some(3));
+    assert!(stderr.starts_with("Error: unhandled error #"), "{stderr}");
+    assert!(stderr.contains("of type `main_subprocess::Invalid`"), "{stderr}");
+    assert!(stderr.ends_with(": bad header\n"), "{stderr}");
+    assert!(!stderr.contains("Stack backtrace"), "{stderr}");
+
+    let (code, _, stderr) = run("fails_with_backtrace");
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("\n\nStack backtrace:\n"), "{stderr}");
+
+    assert_eq!(run("handled").0, Some(7));
+    println!("generated mains: ok");
+    ExitCode::SUCCESS
+}
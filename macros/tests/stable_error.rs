@@ -0,0 +1,70 @@
+use xcept::stable_id::StableError as _;
+use xcept_macros::StableError;
+
+/// Plain data, its own portable representation
+#[derive(Debug, PartialEq, StableError)]
+#[repr(C)]
+#[xcept(uuid = "0b5e7c1a-2f3d-4e6b-8a9c-d0e1f2a3b4c5")]
+struct Overloaded {
+    load: u32,
+}
+
+/// The same error, compiled elsewhere
+#[derive(StableError)]
+#[repr(C)]
+#[xcept(uuid = "0B5E7C1A2F3D4E6B8A9CD0E1F2A3B4C5")]
+struct RemoteOverloaded {
+    load: u32,
+}
+
+#[repr(C)]
+struct WirePath {
+    len: usize,
+}
+
+/// An owned error crossing in a plain representation
+#[derive(StableError)]
+#[xcept(uuid = "11111111-2222-3333-4444-555555555555", portable = WirePath)]
+struct PathTooLong(String);
+
+impl From<PathTooLong> for WirePath {
+    fn from(err: PathTooLong) -> Self {
+        WirePath { len: err.0.len() }
+    }
+}
+
+impl From<WirePath> for PathTooLong {
+    fn from(wire: WirePath) -> Self {
+        PathTooLong("?".repeat(wire.len))
+    }
+}
+
+#[test]
+fn derived_ids() {
+    assert_eq!(Overloaded::STABLE_ID, RemoteOverloaded::STABLE_ID);
+    assert_ne!(Overloaded::STABLE_ID, PathTooLong::STABLE_ID);
+}
+
+#[test]
+fn handled_by_uuid() {
+    let handlers = xcept::builder(|_: u8| xcept::Result::new(0))
+        .handle_stable(|err: Overloaded| xcept::Result::new(err.load))
+        .build();
+    let res = xcept::try_or_handle(|| xcept::Result::new_error_stable(RemoteOverloaded { load: 97 }), handlers);
+    assert_eq!(res.unwrap(), 97);
+}
+
+#[test]
+fn portable_conversion() {
+    let res = xcept::try_or_handle_one(
+        || xcept::Result::new_error_stable(PathTooLong("/very/long".to_string())),
+        |wire: WirePath| xcept::Result::new(wire.len),
+    );
+    assert_eq!(res.unwrap(), 10);
+
+    let handlers = xcept::builder(|_: u8| xcept::Result::new(String::new()))
+        .handle_stable(|err: PathTooLong| xcept::Result::new(err.0))
+        .build();
+    let res = xcept::try_or_handle(|| xcept::Result::new_error_stable(PathTooLong("abc".to_string())), handlers);
+    assert_eq!(res.unwrap(), "???");
+}
@@ -0,0 +1,36 @@
+use xcept_macros::test;
+
+struct Invalid(&'static str);
+
+fn parse(s: &str) -> xcept::Result<u32> {
+    s.parse::<u32>().into()
+}
+
+#[test]
+fn returns_ok() -> xcept::Result<()> {
+    parse("12").map(|x| assert_eq!(x, 12))
+}
+
+#[test]
+fn handled_raises_pass() {
+    let res = xcept::try_or_handle_one(|| parse("x"), |_: std::num::ParseIntError| xcept::Result::new(0));
+    assert_eq!(res.unwrap(), 0);
+}
+
+#[test]
+#[should_panic(expected = "test returned an unhandled error #")]
+fn returned_error_fails() -> xcept::Result<()> {
+    parse("x").map(|_| ())
+}
+
+#[test]
+#[should_panic(expected = "test raised 1 error(s) not handled by any scope")]
+fn ignored_raise_fails() {
+    let _ = parse("x");
+}
+
+#[test(handlers = || xcept::builder(|err: Invalid| -> xcept::Result<()> { panic!("{}", err.0) }).build())]
+#[should_panic(expected = "bad header")]
+fn raise_reaches_handlers() -> xcept::Result<()> {
+    xcept::Result::new_error(Invalid("bad header"))
+}
@@ -0,0 +1,54 @@
+use std::borrow::Cow;
+use xcept_macros::ToStatic;
+
+#[derive(ToStatic)]
+#[xcept(derive(Debug, PartialEq))]
+struct ParseError<'a> {
+    input: &'a str,
+    reason: Cow<'a, str>,
+    line: u32,
+}
+
+#[derive(ToStatic)]
+#[xcept(derive(Debug, PartialEq))]
+struct Garbage<'a>(&'a [u8], Option<&'a str>);
+
+fn parse(input: &str) -> xcept::Result<u32> {
+    match input.parse() {
+        Ok(x) => xcept::Result::new(x),
+        Err(_) if input.is_empty() => xcept::Result::raise_owned(Garbage(input.as_bytes(), None)),
+        Err(_) => xcept::Result::raise_owned(ParseError {
+            input,
+            reason: Cow::Borrowed("not a number"),
+            line: 3,
+        }),
+    }
+}
+
+#[test]
+fn derived_owned_types() {
+    let mut parse_errors = Vec::new();
+    let mut garbage = Vec::new();
+    for input in [String::from("12"), String::from("x"), String::new()] {
+        let handlers = xcept::builder(|err: ParseErrorOwned| {
+            parse_errors.push(err);
+            xcept::Result::new(0)
+        })
+        .handle(|err: GarbageOwned| {
+            garbage.push(err);
+            xcept::Result::new(0)
+        })
+        .build();
+        assert!(xcept::try_or_handle(|| parse(&input), handlers).is_ok());
+    }
+
+    assert_eq!(
+        parse_errors,
+        [ParseErrorOwned {
+            input: "x".to_string(),
+            reason: "not a number".to_string(),
+            line: 3,
+        }]
+    );
+    assert_eq!(garbage, [GarbageOwned(Vec::new(), None)]);
+}
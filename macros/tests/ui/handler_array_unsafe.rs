@@ -0,0 +1,9 @@
+struct A;
+
+unsafe fn danger() -> i32 {
+    1
+}
+
+fn main() {
+    let _handlers = xcept::handler_array![A => |_| xcept::Result::new(danger())];
+}
@@ -0,0 +1,14 @@
+#[xcept_macros::main(exit_code = 256)]
+fn out_of_range() -> xcept::Result<()> {
+    xcept::Result::new(())
+}
+
+#[xcept_macros::main(verbose)]
+fn unknown() -> xcept::Result<()> {
+    xcept::Result::new(())
+}
+
+#[xcept_macros::test(exit_code = 2)]
+fn exit_code_in_test() {}
+
+fn main() {}
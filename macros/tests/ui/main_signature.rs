@@ -0,0 +1,14 @@
+#[xcept_macros::main]
+async fn asynchronous() -> xcept::Result<()> {
+    xcept::Result::new(())
+}
+
+#[xcept_macros::main]
+fn with_args(_args: Vec<String>) -> xcept::Result<()> {
+    xcept::Result::new(())
+}
+
+#[xcept_macros::test]
+fn generic<T>() {}
+
+fn main() {}
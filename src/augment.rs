@@ -0,0 +1,207 @@
+//! Handlers a callee adds in front of its caller's handlers.
+//!
+//! A library function often wants to handle some errors itself while it runs, and let the rest
+//! reach whatever handlers its caller installed. It can't use a typed handler chain for that when
+//! the value type belongs to the caller. [`SideEffectHandlers`] run an action instead of producing
+//! a value, and [`augment_scope`] installs them for the duration of a closure.
+
+use crate::context::{ContextExt, ErrorHandlingContext, ReportedError, ScopeNode, TrySetErrorResult};
+use std::any::{Any, TypeId};
+
+struct Entry<'a> {
+    type_id: TypeId,
+    type_name: &'static str,
+    action: Box<dyn FnOnce(Box<dyn Any>) + 'a>,
+}
+
+/// Handlers running an action for an error instead of producing a value.
+///
+/// Used with [`augment_scope`]. Every action is run at most once, for the error the closure of
+/// [`augment_scope`] returns.
+#[derive(Default)]
+pub struct SideEffectHandlers<'a> {
+    entries: Vec<Entry<'a>>,
+    captured: Option<(crate::ErrorId, usize, Box<dyn Any>)>,
+}
+
+impl<'a> SideEffectHandlers<'a> {
+    /// Create an empty set of handlers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an action run for errors of type `E`.
+    ///
+    /// Earlier actions are asked first, only the first action for a type is ever run.
+    pub fn on<E: crate::Error>(mut self, action: impl FnOnce(E) + 'a) -> Self {
+        self.entries.push(Entry {
+            type_id: TypeId::of::<E>(),
+            type_name: std::any::type_name::<E>(),
+            action: Box::new(move |value| {
+                if let Ok(value) = value.downcast::<E>() {
+                    action(*value)
+                }
+            }),
+        });
+        self
+    }
+
+    fn entry_for(&self, type_id: TypeId) -> Option<usize> {
+        if crate::runtime::is_suppressed(type_id) {
+            return None;
+        }
+        self.entries.iter().position(|entry| entry.type_id == type_id)
+    }
+
+    /// Run the action for the captured error if it has the ID `error_id`.
+    fn try_run(mut self, error_id: crate::ErrorId) -> bool {
+        match self.captured.take() {
+            Some((id, index, value)) if id == error_id => {
+                (self.entries.swap_remove(index).action)(value);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl ErrorHandlingContext for SideEffectHandlers<'_> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        match self.entry_for(error.type_id()) {
+            Some(index) => {
+                self.captured = Some((error.id(), index, error.take_boxed()));
+                TrySetErrorResult::NeedForget
+            }
+            None => TrySetErrorResult::NotHandled,
+        }
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        Some(self)
+    }
+}
+
+impl ContextExt for SideEffectHandlers<'_> {
+    fn accepts(&mut self, type_id: TypeId) -> Option<bool> {
+        Some(self.entry_for(type_id).is_some())
+    }
+
+    fn has_pending(&mut self) -> bool {
+        self.captured.is_some()
+    }
+
+    fn pending_type_name(&mut self) -> Option<&'static str> {
+        self.captured.as_ref().map(|(_, index, _)| self.entries[*index].type_name)
+    }
+
+    fn debug_name(&self) -> &'static str {
+        "SideEffectHandlers"
+    }
+}
+
+/// Execute `func` with `side_handlers` installed in front of the active scopes.
+///
+/// The side handlers form a scope of their own, pushed inside whatever scopes the caller
+/// installed, so they are asked first for every error `func` raises outside its own scopes. The
+/// types they handle never reach the caller while `func` runs, every other error passes through
+/// unchanged. The scope is removed before returning, errors raised later are not affected.
+///
+/// If `func` returns an error handled by a side handler, its action is run and `Ok(None)` is
+/// returned. Any other error is returned with its ID, for the caller's handlers. A value is
+/// returned as `Some`.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::augment::SideEffectHandlers;
+///
+/// struct Blank;
+/// struct Invalid;
+///
+/// // A library function skipping blank lines, without knowing its caller's value type
+/// fn parse_line(line: &str) -> xcept::Result<Option<i32>> {
+///     let skipped = std::cell::Cell::new(0);
+///     let side = SideEffectHandlers::new().on(|_: Blank| skipped.set(skipped.get() + 1));
+///     xcept::augment_scope(side, || match line.trim() {
+///         "" => xcept::Result::new_error(Blank),
+///         line => line.parse().map_err(|_| Invalid).into(),
+///     })
+/// }
+///
+/// let parse = |line| xcept::try_or_handle_one(|| parse_line(line), |_: Invalid| xcept::Result::new(Some(-1)));
+/// assert_eq!(parse("12").unwrap(), Some(12));
+/// assert_eq!(parse("  ").unwrap(), None);
+/// assert_eq!(parse("x").unwrap(), Some(-1));
+/// ```
+pub fn augment_scope<T>(
+    mut side_handlers: SideEffectHandlers<'_>,
+    func: impl FnOnce() -> crate::Result<T>,
+) -> crate::Result<Option<T>> {
+    let res = {
+        let scope = std::pin::pin!(ScopeNode::new(&mut side_handlers));
+        // Safety: the guard is dropped at the end of the block
+        let _guard = unsafe { scope.register() };
+        func()
+    };
+    match res.try_unwrap() {
+        Ok(value) => crate::Result::new(Some(value)),
+        Err(id) if side_handlers.try_run(id) => crate::Result::new(None),
+        Err(id) => crate::Result::new_with_error_id(id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{augment_scope, SideEffectHandlers};
+    use std::cell::RefCell;
+
+    #[derive(Debug, PartialEq)]
+    struct Warning(&'static str);
+    struct Fatal(u32);
+
+    fn library(log: &RefCell<Vec<&'static str>>, fatal: bool) -> crate::Result<Option<u32>> {
+        let side = SideEffectHandlers::new().on(|w: Warning| log.borrow_mut().push(w.0));
+        augment_scope(side, || {
+            // Nothing but the side handlers is asked for warnings
+            assert_eq!(crate::context::scopes_accept::<Warning>(), Some(true));
+            if fatal {
+                crate::Result::new_error(Fatal(7))
+            } else {
+                crate::Result::new_error(Warning("deprecated field"))
+            }
+        })
+    }
+
+    #[test]
+    fn swallow_and_pass_through() {
+        let log = RefCell::new(Vec::new());
+        let caller = |fatal| {
+            let handlers = crate::builder(|f: Fatal| crate::Result::new(Some(f.0)))
+                .handle(|_: Warning| crate::Result::new(Some(0)))
+                .build();
+            crate::try_or_handle(|| library(&log, fatal), handlers)
+        };
+
+        assert_eq!(caller(false).unwrap(), None);
+        assert_eq!(*log.borrow(), ["deprecated field"]);
+        assert_eq!(caller(true).unwrap(), Some(7));
+        assert_eq!(log.borrow().len(), 1);
+
+        // Removed once the library returns
+        assert_eq!(crate::context::scopes_accept::<Warning>(), Some(false));
+    }
+
+    #[test]
+    fn unhandled_in_side_scope() {
+        // An error swallowed by the side handlers but not returned runs no action
+        let ran = RefCell::new(false);
+        let side = SideEffectHandlers::new().on(|_: Warning| *ran.borrow_mut() = true);
+        let res = augment_scope(side, || {
+            let _: crate::Result<()> = crate::Result::new_error(Warning("ignored"));
+            crate::Result::new(1)
+        });
+        assert_eq!(res.unwrap(), Some(1));
+        assert!(!*ran.borrow());
+    }
+}
@@ -0,0 +1,301 @@
+//! Gather many errors and propagate them outward as a single [`BatchError`].
+//!
+//! [`VecErrorStorage`] captures every error of one type instead of only the first. Once the
+//! batch is complete, [`VecErrorStorage::into_batch_raise`] raises one [`BatchError`]
+//! referencing the children by ID. The children themselves are dropped, but their metadata stays
+//! available through [`metadata_for`] for as long as the `BatchError` is alive.
+
+use crate::context::{Acceptance, ContextExt, ErrorHandlingContext, ReportedError, ReportedErrorDescriptor, TrySetErrorResult};
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::panic::Location;
+
+/// What is known about a raised error after the error itself is gone.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ErrorMetadata {
+    /// The ID of the error
+    pub id: crate::ErrorId,
+    /// The name of the type of the error
+    pub type_name: &'static str,
+    /// Where the error was raised, if known. Requires the `diagnostics` feature.
+    pub location: Option<&'static Location<'static>>,
+}
+
+thread_local! {
+    // Metadata pinned by live batches, with the number of batches pinning each entry
+    static PINNED: RefCell<HashMap<crate::ErrorId, (usize, ErrorMetadata)>> = RefCell::new(HashMap::new());
+}
+
+/// Look up the metadata of an error referenced by a live [`BatchError`] on this thread.
+pub fn metadata_for(id: crate::ErrorId) -> Option<ErrorMetadata> {
+    PINNED.with(|pinned| pinned.borrow().get(&id).map(|(_, metadata)| *metadata))
+}
+
+/// Keeps the metadata of a batch's children resolvable while alive.
+struct Pin {
+    ids: Vec<crate::ErrorId>,
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl Pin {
+    fn new(children: &[ErrorMetadata]) -> Self {
+        PINNED.with(|pinned| {
+            let mut pinned = pinned.borrow_mut();
+            for metadata in children {
+                pinned.entry(metadata.id).or_insert((0, *metadata)).0 += 1;
+            }
+        });
+        Self {
+            ids: children.iter().map(|metadata| metadata.id).collect(),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Drop for Pin {
+    fn drop(&mut self) {
+        let _ = PINNED.try_with(|pinned| {
+            let mut pinned = pinned.borrow_mut();
+            for id in &self.ids {
+                if let Some((count, _)) = pinned.get_mut(id) {
+                    *count -= 1;
+                    if *count == 0 {
+                        pinned.remove(id);
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// A summary of several errors, raised by [`VecErrorStorage::into_batch_raise`].
+pub struct BatchError {
+    /// The number of children
+    pub count: usize,
+    /// The IDs of the children, in the order they were raised
+    pub children: Vec<crate::ErrorId>,
+    _pin: Pin,
+}
+
+impl std::fmt::Debug for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchError")
+            .field("count", &self.count)
+            .field("children", &self.children)
+            .finish()
+    }
+}
+
+impl std::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "batch of {} errors", self.count)
+    }
+}
+
+/// The children are only referenced by ID, so there is no `source`.
+impl std::error::Error for BatchError {}
+
+/// Storage capturing every error of type `E`, not only the first.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::batch::{metadata_for, VecErrorStorage};
+///
+/// let mut storage = VecErrorStorage::<&str>::default();
+/// storage.run(|| {
+///     for field in ["name", "email"] {
+///         let _: xcept::Result<()> = xcept::Result::new_error(field);
+///     }
+/// });
+/// assert_eq!(storage.len(), 2);
+///
+/// let res = xcept::try_or_handle_one(
+///     || storage.into_batch_raise::<()>(),
+///     |batch: xcept::BatchError| {
+///         for id in &batch.children {
+///             assert_eq!(metadata_for(*id).unwrap().type_name, "&str");
+///         }
+///         xcept::Result::new(())
+///     },
+/// );
+/// assert!(res.is_ok());
+/// ```
+pub struct VecErrorStorage<E> {
+    errors: Vec<(ErrorMetadata, E)>,
+}
+
+impl<E> Default for VecErrorStorage<E> {
+    fn default() -> Self {
+        Self { errors: Vec::new() }
+    }
+}
+
+impl<E: crate::Error> VecErrorStorage<E> {
+    /// The number of captured errors.
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Test if no error has been captured.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// The captured errors and their IDs, in the order they were raised.
+    pub fn into_inner(self) -> Vec<(crate::ErrorId, E)> {
+        self.errors.into_iter().map(|(metadata, err)| (metadata.id, err)).collect()
+    }
+
+    /// Execute `func` with this storage registered as a handling scope on the current thread.
+    pub fn run<T>(&mut self, func: impl FnOnce() -> T) -> T {
+        let scope = std::pin::pin!(crate::context::ScopeNode::new(self));
+        // Safety: the guard is dropped on return, also when unwinding
+        let _guard = unsafe { scope.register() };
+        func()
+    }
+
+    /// Drop the captured errors and raise a [`BatchError`] referencing them instead.
+    ///
+    /// The metadata of the children can be looked up with [`metadata_for`] until the
+    /// `BatchError` is dropped. A `BatchError` is raised even if nothing was captured.
+    #[cfg_attr(feature = "metadata", track_caller)]
+    pub fn into_batch_raise<T>(self) -> crate::Result<T> {
+        let children: Vec<_> = self.errors.into_iter().map(|(metadata, _)| metadata).collect();
+        crate::Result::new_error(BatchError {
+            count: children.len(),
+            children: children.iter().map(|metadata| metadata.id).collect(),
+            _pin: Pin::new(&children),
+        })
+    }
+}
+
+impl<E: crate::Error> ErrorHandlingContext for VecErrorStorage<E> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        match crate::capture::take::<E>(error) {
+            Some(err) => {
+                let metadata = ErrorMetadata {
+                    id: error.id(),
+                    type_name: error.type_name(),
+                    location: error.location(),
+                };
+                self.errors.push((metadata, err));
+                TrySetErrorResult::NeedForget
+            }
+            None => TrySetErrorResult::NotHandled,
+        }
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        Some(self)
+    }
+}
+
+impl<E: crate::Error> ContextExt for VecErrorStorage<E> {
+    fn accepts(&mut self, type_id: TypeId) -> Option<bool> {
+        Some(type_id == TypeId::of::<E>() && !crate::runtime::is_suppressed(type_id))
+    }
+
+    fn would_accept(&mut self, descriptor: &ReportedErrorDescriptor) -> Acceptance {
+        descriptor.acceptance_for::<E>()
+    }
+
+    fn has_pending(&mut self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    fn pending_count(&mut self) -> usize {
+        self.errors.len()
+    }
+
+    fn pending_type_name(&mut self) -> Option<&'static str> {
+        self.errors.first().map(|(metadata, _)| metadata.type_name)
+    }
+
+    fn debug_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{metadata_for, BatchError, VecErrorStorage};
+
+    #[derive(Debug)]
+    struct Invalid(&'static str);
+
+    fn validate(storage: &mut VecErrorStorage<Invalid>) -> Vec<u32> {
+        storage.run(|| {
+            let lines = [line!() + 2, line!() + 3, line!() + 4];
+            let results: [crate::Result<()>; 3] = [
+                crate::Result::new_error(Invalid("name")),
+                crate::Result::new_error(Invalid("email")),
+                crate::Result::new_error(Invalid("age")),
+            ];
+            assert!(results.iter().all(|res| res.is_error()));
+            lines.to_vec()
+        })
+    }
+
+    #[test]
+    fn outer_handler_renders_children() {
+        let mut storage = VecErrorStorage::default();
+        let lines = validate(&mut storage);
+        assert_eq!(storage.len(), 3);
+
+        let res = crate::try_or_handle_one(
+            || storage.into_batch_raise::<Vec<String>>(),
+            |batch: BatchError| {
+                assert_eq!(batch.count, 3);
+                let rendered = batch
+                    .children
+                    .iter()
+                    .zip(&lines)
+                    .map(|(id, line)| {
+                        let metadata = metadata_for(*id).unwrap();
+                        if cfg!(feature = "metadata") {
+                            assert_eq!(metadata.location.unwrap().line(), *line);
+                        }
+                        format!("{}: {}", metadata.id, metadata.type_name.rsplit("::").next().unwrap())
+                    })
+                    .collect();
+                crate::Result::new(rendered)
+            },
+        );
+        let rendered = res.unwrap();
+        assert_eq!(rendered.len(), 3);
+        assert!(rendered.iter().all(|line| line.ends_with(": Invalid")));
+    }
+
+    #[test]
+    fn metadata_released_with_batch() {
+        let mut storage = VecErrorStorage::default();
+        validate(&mut storage);
+
+        let mut children = Vec::new();
+        let res: crate::Result<()> = crate::try_or_handle_one(
+            || storage.into_batch_raise(),
+            |batch: BatchError| {
+                children = batch.children.clone();
+                assert!(children.iter().all(|id| metadata_for(*id).is_some()));
+                crate::Result::new(())
+            },
+        );
+        assert!(res.is_ok());
+        assert_eq!(children.len(), 3);
+        assert!(children.iter().all(|id| metadata_for(*id).is_none()));
+    }
+
+    #[test]
+    fn into_inner_keeps_values() {
+        let mut storage = VecErrorStorage::default();
+        let lines = validate(&mut storage);
+        let fields: Vec<_> = storage.into_inner().into_iter().map(|(_, Invalid(field))| field).collect();
+        assert_eq!(fields, ["name", "email", "age"]);
+        assert_eq!(lines.len(), 3);
+    }
+}
@@ -0,0 +1,271 @@
+//! Interrupting blocking work from another thread.
+//!
+//! An [`Interrupter`] is signaled from any thread. Code run by [`interruptible`] polls it with
+//! [`checkpoint`], and blocking reads done with [`read_with_checkpoints`] wake up regularly to
+//! poll it. Both raise [`Interrupted`] once it is signaled, which is handled like any other error.
+//! They also raise [`ScopeTimedOut`] once a scope run by
+//! [`scope_with_timeout`](crate::watchdog::scope_with_timeout) has expired.
+
+use crate::watchdog::ScopeTimedOut;
+use std::cell::RefCell;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long [`read_with_checkpoints`] blocks at most before polling its interrupter.
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The error raised when an [`Interrupter`] has been signaled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Interrupted;
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("interrupted")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Signals interruptible work to stop, from any thread.
+///
+/// Clones share the same signal.
+#[derive(Clone, Debug, Default)]
+pub struct Interrupter {
+    signaled: Arc<AtomicBool>,
+}
+
+impl Interrupter {
+    /// Create an interrupter that isn't signaled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Signal the interrupter, work polling it raises [`Interrupted`] from now on.
+    pub fn interrupt(&self) {
+        self.signaled.store(true, Ordering::Release);
+    }
+
+    /// Test if the interrupter has been signaled.
+    pub fn is_interrupted(&self) -> bool {
+        self.signaled.load(Ordering::Acquire)
+    }
+
+    /// Clear the signal, so the interrupter can be used again.
+    pub fn reset(&self) {
+        self.signaled.store(false, Ordering::Release);
+    }
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Vec<Interrupter>> = const { RefCell::new(Vec::new()) };
+}
+
+struct Unregister;
+
+impl Drop for Unregister {
+    fn drop(&mut self) {
+        let _ = ACTIVE.try_with(|active| active.borrow_mut().pop());
+    }
+}
+
+/// Execute `func` with `interrupter` registered for [`checkpoint`].
+///
+/// No thread is spawned, `func` is only interrupted at its checkpoints. Calls can be nested, a
+/// checkpoint raises if any of the registered interrupters is signaled.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::blocking::{checkpoint, interruptible, Interrupted, Interrupter};
+///
+/// let interrupter = Interrupter::new();
+/// let res = xcept::try_or_handle_one(
+///     || {
+///         interruptible(&interrupter, || {
+///             let mut steps = 0;
+///             loop {
+///                 if let Err(id) = checkpoint().try_unwrap() {
+///                     return xcept::Result::new_with_error_id(id);
+///                 }
+///                 steps += 1;
+///                 if steps == 3 {
+///                     interrupter.interrupt();
+///                 }
+///             }
+///         })
+///     },
+///     |_: Interrupted| xcept::Result::new(-1),
+/// );
+/// assert_eq!(res.unwrap(), -1);
+/// ```
+pub fn interruptible<T>(interrupter: &Interrupter, func: impl FnOnce() -> crate::Result<T>) -> crate::Result<T> {
+    ACTIVE.with(|active| active.borrow_mut().push(interrupter.clone()));
+    let _unregister = Unregister;
+    func()
+}
+
+/// Raise [`Interrupted`] if an interrupter registered with [`interruptible`] is signaled.
+///
+/// Raises [`ScopeTimedOut`] instead if a timed out scope has expired, see
+/// [`watchdog::checkpoint`](crate::watchdog::checkpoint).
+pub fn checkpoint() -> crate::Result<()> {
+    if let Some(err) = crate::watchdog::expired() {
+        return crate::Result::new_error(err);
+    }
+    let interrupted = ACTIVE.with(|active| active.borrow().iter().any(Interrupter::is_interrupted));
+    if interrupted {
+        crate::Result::new_error(Interrupted)
+    } else {
+        crate::Result::new(())
+    }
+}
+
+/// A reader whose blocking reads can be limited by a timeout.
+pub trait TimeoutRead: io::Read {
+    /// The current read timeout, `None` blocks indefinitely.
+    fn read_timeout(&self) -> io::Result<Option<Duration>>;
+
+    /// Change the read timeout.
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl TimeoutRead for std::net::TcpStream {
+    fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        std::net::TcpStream::read_timeout(self)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        std::net::TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl TimeoutRead for std::os::unix::net::UnixStream {
+    fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        std::os::unix::net::UnixStream::read_timeout(self)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+/// Why [`read_with_checkpoints`] stopped without data.
+enum Stopped {
+    Interrupted,
+    TimedOut(ScopeTimedOut),
+    Failed(io::Error),
+}
+
+/// Read into `buf`, blocking until data arrives or `interrupter` is signaled.
+///
+/// The read blocks for at most [`CHECKPOINT_INTERVAL`] at a time, so an interruption is noticed
+/// within that time. Raises [`Interrupted`] when interrupted, [`ScopeTimedOut`] when a timed out
+/// scope expires, or the `std::io::Error` of a failed read. The timeout of `reader` is restored
+/// before returning.
+pub fn read_with_checkpoints<R: TimeoutRead>(
+    reader: &mut R,
+    buf: &mut [u8],
+    interrupter: &Interrupter,
+) -> crate::Result<usize> {
+    let previous = match reader.read_timeout() {
+        Ok(previous) => previous,
+        Err(err) => return crate::Result::new_error(err),
+    };
+    if let Err(err) = reader.set_read_timeout(Some(CHECKPOINT_INTERVAL)) {
+        return crate::Result::new_error(err);
+    }
+    let res = loop {
+        if interrupter.is_interrupted() {
+            break Err(Stopped::Interrupted);
+        }
+        if let Some(err) = crate::watchdog::expired() {
+            break Err(Stopped::TimedOut(err));
+        }
+        match reader.read(buf) {
+            Ok(read) => break Ok(read),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+                ) => {}
+            Err(err) => break Err(Stopped::Failed(err)),
+        }
+    };
+    let restored = reader.set_read_timeout(previous);
+    match (res, restored) {
+        (Ok(read), Ok(())) => crate::Result::new(read),
+        (Err(Stopped::Interrupted), _) => crate::Result::new_error(Interrupted),
+        (Err(Stopped::TimedOut(err)), _) => crate::Result::new_error(err),
+        (Err(Stopped::Failed(err)), _) | (Ok(_), Err(err)) => crate::Result::new_error(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checkpoint, interruptible, read_with_checkpoints, Interrupted, Interrupter};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn interrupt_chunked_read() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        // Keep the server side open without ever writing
+        let (_server, _) = listener.accept().unwrap();
+
+        let interrupter = Interrupter::new();
+        let signal = {
+            let interrupter = interrupter.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                let signaled = Instant::now();
+                interrupter.interrupt();
+                signaled
+            })
+        };
+
+        let res = crate::try_or_handle_one(
+            || -> crate::Result<usize> {
+                let mut buf = [0; 64];
+                loop {
+                    let res = read_with_checkpoints(&mut client, &mut buf, &interrupter);
+                    if let Err(id) = res.try_unwrap() {
+                        return crate::Result::new_with_error_id(id);
+                    }
+                }
+            },
+            |_: Interrupted| crate::Result::new(usize::MAX),
+        );
+        let stopped = Instant::now();
+        let signaled = signal.join().unwrap();
+
+        assert_eq!(res.unwrap(), usize::MAX);
+        assert!(stopped.duration_since(signaled) < Duration::from_millis(100));
+        assert_eq!(client.read_timeout().unwrap(), None);
+    }
+
+    #[test]
+    fn nested_checkpoints() {
+        let outer = Interrupter::new();
+        let inner = Interrupter::new();
+        let res = crate::try_or_handle_one(
+            || {
+                interruptible(&outer, || {
+                    let res = interruptible(&inner, || {
+                        outer.interrupt();
+                        checkpoint()
+                    });
+                    assert!(res.is_error());
+                    res
+                })
+            },
+            |_: Interrupted| crate::Result::new(()),
+        );
+        assert!(res.is_ok());
+
+        // Unregistered once left
+        assert!(checkpoint().is_ok());
+    }
+}
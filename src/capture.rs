@@ -0,0 +1,261 @@
+//! Capture any of a closed set of error types into a single value.
+//!
+//! Instead of building a chain with one handler per error type, a set of error types can be
+//! captured into one enum and handled with a single `match`. The enum is either derived with
+//! `#[derive(CaptureSet)]` (requires the `macros` feature), or one of the generic [`OneOf2`] to
+//! [`OneOf8`] enums created by [`capture_set`] from a tuple of error types.
+
+use crate::context::{ErrorHandlingContext, ReportedError, TrySetErrorResult};
+use crate::multihandler::TryHandle;
+use std::any::TypeId;
+
+#[cfg(feature = "macros")]
+pub use xcept_macros::CaptureSet;
+
+/// A type that can be constructed from any of a closed set of error types.
+///
+/// Usually derived with `#[derive(CaptureSet)]` on an enum whose variants each hold a single
+/// error type. If the same error type appears in several variants the first one is used.
+pub trait CaptureSet: Sized + 'static {
+    /// Try to move the reported error into a new value.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`ErrorHandlingContext::try_set_error`]. If `Some` is returned the
+    /// caller must `forget` the original value.
+    unsafe fn try_capture(error: &ReportedError) -> Option<Self>;
+}
+
+/// Maps a type to the [`CaptureSet`] used by [`capture_set`].
+///
+/// Implemented for tuples of up to 8 error types, and by `#[derive(CaptureSet)]`.
+pub trait IntoCaptureSet {
+    type Set: CaptureSet;
+}
+
+/// Move the reported error out if it is of type `T`, unless handling `T` is suppressed.
+///
+/// See [`suppress_handling`](crate::runtime::suppress_handling).
+///
+/// # Safety
+///
+/// Same requirements as [`ErrorHandlingContext::try_set_error`]. If `Some` is returned the caller
+/// must `forget` the original value.
+#[inline]
+pub unsafe fn take<T: crate::Error>(error: &ReportedError) -> Option<T> {
+    if TypeId::of::<T>() == error.type_id() && !crate::runtime::is_suppressed(error.type_id()) {
+        Some((error.value_ptr() as *mut T).read())
+    } else {
+        None
+    }
+}
+
+/// Storage for a [`CaptureSet`].
+pub struct CaptureSetStorage<S> {
+    inner: Option<(crate::ErrorId, S)>,
+}
+
+impl<S> Default for CaptureSetStorage<S> {
+    #[inline]
+    fn default() -> Self {
+        Self { inner: None }
+    }
+}
+
+impl<S> CaptureSetStorage<S> {
+    /// The captured error and its ID, if one was captured.
+    #[inline(always)]
+    pub fn into_inner(self) -> Option<(crate::ErrorId, S)> {
+        self.inner
+    }
+
+    /// Bind a handler to the storage, creating a handling context for [`try_or_handle`].
+    ///
+    /// [`try_or_handle`]: crate::try_or_handle
+    pub fn handle<H, V>(self, handler: H) -> CaptureSetHandler<S, H>
+    where
+        H: FnOnce(S) -> crate::Result<V>,
+    {
+        CaptureSetHandler {
+            storage: self,
+            handler,
+        }
+    }
+}
+
+impl<S: CaptureSet> ErrorHandlingContext for CaptureSetStorage<S> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        match S::try_capture(error) {
+            Some(value) => {
+                self.inner = Some((error.id(), value));
+                TrySetErrorResult::NeedForget
+            }
+            None => TrySetErrorResult::NotHandled,
+        }
+    }
+}
+
+/// A [`CaptureSetStorage`] bound to a handler.
+pub struct CaptureSetHandler<S, H> {
+    storage: CaptureSetStorage<S>,
+    handler: H,
+}
+
+impl<S: CaptureSet, H> ErrorHandlingContext for CaptureSetHandler<S, H> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        self.storage.try_set_error(error)
+    }
+}
+
+impl<S, H, V> TryHandle for CaptureSetHandler<S, H>
+where
+    H: FnOnce(S) -> crate::Result<V>,
+{
+    type Value = V;
+    fn try_handle(self, error_id: crate::ErrorId) -> Option<crate::Result<V>> {
+        match self.storage.into_inner() {
+            Some((id, err)) if id == error_id => Some((self.handler)(err)),
+            _ => None,
+        }
+    }
+}
+
+crate::multihandler::opaque_flat_chain! {
+    impl [S, H] for CaptureSetHandler<S, H>;
+}
+
+/// Create storage capturing any error of the set `S`.
+///
+/// `S` is either a type deriving `CaptureSet`, or a tuple of error types which are then captured
+/// into the matching `OneOfN` enum.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::capture::OneOf2;
+///
+/// type ParseError = <i32 as std::str::FromStr>::Err;
+///
+/// fn parse(s: &str) -> xcept::Result<i32> {
+///     if s.is_empty() {
+///         xcept::Result::new_error("empty")
+///     } else {
+///         s.parse().into()
+///     }
+/// }
+///
+/// let handlers = xcept::capture_set::<(ParseError, &str)>().handle(|err| match err {
+///     OneOf2::A(_) => xcept::Result::new(-1),
+///     OneOf2::B(_) => xcept::Result::new(-2),
+/// });
+///
+/// assert_eq!(xcept::try_or_handle(|| parse(""), handlers).unwrap(), -2);
+/// ```
+pub fn capture_set<S: IntoCaptureSet>() -> CaptureSetStorage<S::Set> {
+    CaptureSetStorage::default()
+}
+
+macro_rules! one_of {
+    ($(#[$doc:meta])* $name:ident: $($var:ident),+) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name<$($var),+> {
+            $($var($var)),+
+        }
+
+        impl<$($var: crate::Error),+> CaptureSet for $name<$($var),+> {
+            unsafe fn try_capture(error: &ReportedError) -> Option<Self> {
+                $(
+                    if let Some(value) = take::<$var>(error) {
+                        return Some(Self::$var(value));
+                    }
+                )+
+                None
+            }
+        }
+
+        impl<$($var: crate::Error),+> IntoCaptureSet for ($($var,)+) {
+            type Set = $name<$($var),+>;
+        }
+    };
+}
+
+one_of!(
+    /// One of two error types.
+    OneOf2: A, B
+);
+one_of!(
+    /// One of three error types.
+    OneOf3: A, B, C
+);
+one_of!(
+    /// One of four error types.
+    OneOf4: A, B, C, D
+);
+one_of!(
+    /// One of five error types.
+    OneOf5: A, B, C, D, E
+);
+one_of!(
+    /// One of six error types.
+    OneOf6: A, B, C, D, E, F
+);
+one_of!(
+    /// One of seven error types.
+    OneOf7: A, B, C, D, E, F, G
+);
+one_of!(
+    /// One of eight error types.
+    OneOf8: A, B, C, D, E, F, G, H
+);
+
+#[cfg(test)]
+mod tests {
+    use super::{capture_set, OneOf3};
+
+    #[derive(Debug, PartialEq)]
+    struct NotFound(&'static str);
+
+    #[test]
+    fn tuple_capture_set() {
+        fn run(which: i32) -> crate::Result<String> {
+            let handlers = capture_set::<(NotFound, i32, bool)>().handle(|err| {
+                crate::Result::new(match err {
+                    OneOf3::A(NotFound(what)) => format!("missing {what}"),
+                    OneOf3::B(code) => format!("code {code}"),
+                    OneOf3::C(flag) => format!("flag {flag}"),
+                })
+            });
+            crate::try_or_handle(
+                || match which {
+                    0 => crate::Result::new_error(NotFound("key")),
+                    1 => crate::Result::new_error(42),
+                    2 => crate::Result::new_error(true),
+                    3 => crate::Result::new_error("unknown"),
+                    _ => crate::Result::new("ok".to_string()),
+                },
+                handlers,
+            )
+        }
+
+        assert_eq!(run(0).unwrap(), "missing key");
+        assert_eq!(run(1).unwrap(), "code 42");
+        assert_eq!(run(2).unwrap(), "flag true");
+        assert!(run(3).is_error());
+        assert_eq!(run(4).unwrap(), "ok");
+    }
+
+    #[test]
+    fn storage_into_inner() {
+        let mut storage = capture_set::<(NotFound, i32)>();
+        let res: crate::Result<()> = {
+            let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut storage));
+            let _guard = unsafe { scope.register() };
+            crate::Result::new_error(NotFound("x"))
+        };
+
+        let (id, err) = storage.into_inner().unwrap();
+        assert_eq!(Some(id), res.error_id());
+        assert_eq!(err, super::OneOf2::A(NotFound("x")));
+    }
+}
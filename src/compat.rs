@@ -0,0 +1,187 @@
+//! Interoperability with `std::error::Error` based code.
+//!
+//! Every error type introduced by this crate, like [`BatchError`](crate::BatchError),
+//! [`Interrupted`](crate::blocking::Interrupted), [`GuardFailure`](crate::GuardFailure) and
+//! [`Flag`](crate::Flag), implements `Display` and `std::error::Error`. A [`BoxedOutcome`]
+//! describes an error that escaped its handlers as a `std::error::Error + Send + Sync`, so it fits
+//! `Box<dyn Error + Send + Sync>` pipelines like a `main` returning one.
+
+use crate::context::ErasedError;
+use crate::redact::RedactedSummary;
+use std::fmt;
+use std::panic::Location;
+
+/// An error that escaped its handlers, as a `std::error::Error`.
+///
+/// The content is only rendered through [`crate::redact`]. When created from an ID the value is
+/// gone, and only what the metadata of the raise still tells is shown.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoxedOutcome {
+    id: crate::ErrorId,
+    type_name: Option<&'static str>,
+    summary: Option<RedactedSummary>,
+    location: Option<&'static Location<'static>>,
+}
+
+impl BoxedOutcome {
+    /// Describe the error `id`, with the metadata still known about it on this thread.
+    ///
+    /// The type name and location are looked up in the children of live
+    /// [`BatchError`](crate::BatchError)s and, with the `diagnostics` feature, in the recent
+    /// raises.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xcept::compat::BoxedOutcome;
+    ///
+    /// fn run() -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+    ///     let res: xcept::Result<u32> = xcept::Result::new_error("unhandled");
+    ///     match res.try_unwrap() {
+    ///         Ok(value) => Ok(value),
+    ///         Err(id) => Err(BoxedOutcome::from_id(id).into()),
+    ///     }
+    /// }
+    /// assert!(run().unwrap_err().to_string().starts_with("error "));
+    /// ```
+    pub fn from_id(error_id: crate::ErrorId) -> Self {
+        let mut outcome = Self {
+            id: error_id,
+            type_name: None,
+            summary: None,
+            location: None,
+        };
+        if let Some(metadata) = crate::batch::metadata_for(error_id) {
+            outcome.type_name = Some(metadata.type_name);
+            outcome.location = metadata.location;
+        }
+        #[cfg(feature = "diagnostics")]
+        if outcome.type_name.is_none() {
+            if let Some(record) = crate::debug::recent_raises().into_iter().rev().find(|record| record.id == error_id) {
+                outcome.type_name = Some(record.type_name);
+                outcome.location = record.location;
+            }
+        }
+        outcome
+    }
+
+    /// Describe an erased error, rendering its value through its redactor.
+    pub fn from_erased(error: &ErasedError) -> Self {
+        let summary = error.summary();
+        Self {
+            id: error.id(),
+            type_name: Some(error.type_name()),
+            summary: Some(summary),
+            location: None,
+        }
+    }
+
+    /// The ID of the error.
+    pub fn id(&self) -> crate::ErrorId {
+        self.id
+    }
+
+    /// The name of the type of the error, if known.
+    pub fn type_name(&self) -> Option<&'static str> {
+        self.type_name
+    }
+
+    /// The redacted content of the error, if it was still available.
+    pub fn summary(&self) -> Option<&RedactedSummary> {
+        self.summary.as_ref()
+    }
+
+    /// Where the error was raised, if known.
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.location
+    }
+}
+
+impl From<ErasedError> for BoxedOutcome {
+    fn from(error: ErasedError) -> Self {
+        Self::from_erased(&error)
+    }
+}
+
+impl fmt::Display for BoxedOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error {}", self.id)?;
+        match (&self.summary, self.type_name) {
+            (Some(summary), _) => write!(f, ": {summary}")?,
+            (None, Some(type_name)) => write!(f, ": {type_name}")?,
+            (None, None) => f.write_str(" of unknown type")?,
+        }
+        if let Some(location) = self.location {
+            write!(f, " at {location}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BoxedOutcome {}
+
+#[cfg(test)]
+mod tests {
+    use super::BoxedOutcome;
+    use std::error::Error;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn std_citizens() {
+        assert_send_sync::<BoxedOutcome>();
+
+        crate::flag!(Shutdown);
+        let mut storage = crate::batch::VecErrorStorage::<u8>::default();
+        storage.run(|| {
+            let _: crate::Result<()> = crate::Result::new_error(1u8);
+        });
+        let batch = crate::try_or_handle_one(
+            || storage.into_batch_raise::<()>().map(|_| None),
+            |batch: crate::BatchError| crate::Result::new(Some(batch)),
+        )
+        .unwrap()
+        .unwrap();
+        let child = BoxedOutcome::from_id(batch.children[0]);
+        assert_eq!(child.type_name(), Some("u8"));
+
+        let guard = crate::try_or_handle_one(
+            || crate::guard_eq(1, 2).map(|_| None),
+            |err: crate::GuardFailure<i32, i32>| crate::Result::new(Some(err)),
+        )
+        .unwrap()
+        .unwrap();
+
+        let errors: Vec<Box<dyn Error>> = vec![
+            Box::new(batch),
+            Box::new(crate::blocking::Interrupted),
+            Box::new(guard),
+            Box::new(crate::Flag::<Shutdown>::new()),
+            Box::new(child),
+            Box::new(BoxedOutcome::from_id(crate::ErrorId::from_raw(0))),
+        ];
+        for err in &errors {
+            assert!(!err.to_string().is_empty(), "{err:?}");
+            assert!(err.source().is_none(), "{err:?}");
+        }
+    }
+
+    #[test]
+    fn from_erased() {
+        struct Leak(&'static str);
+        crate::redact::register(|leak: &Leak| crate::redact::RedactedSummary::new(format!("<{} hidden>", leak.0.len())));
+
+        let mut ctx = crate::context::FirstErrorContext::default();
+        let _: crate::Result<()> = {
+            let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut ctx));
+            let _guard = unsafe { scope.register() };
+            crate::Result::new_error(Leak("password"))
+        };
+
+        let erased = ctx.into_inner().unwrap();
+        let boxed: Box<dyn Error + Send + Sync> = BoxedOutcome::from(erased).into();
+        let message = boxed.to_string();
+        assert!(message.contains("<8 hidden>"), "{message}");
+        assert!(!message.contains("password"));
+    }
+}
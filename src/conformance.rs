@@ -0,0 +1,169 @@
+//! The behavioral contract of the handling scopes, as a reusable test suite.
+//!
+//! Alternative implementations of the scope storage, such as forks keeping the scopes in fiber
+//! local storage, implement [`ScopeBackend`] and call [`run_all`] from a test to check that they
+//! behave like the thread local storage used by this crate, [`ThreadLocalBackend`].
+//!
+//! Every check panics with a description of the violated rule.
+
+use crate::context::{
+    CatchAllContext, ErrorHandlingContext, ReportedError, ScopeNode, SingleErrorStorage, TrySetErrorResult,
+};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// The operations of a scope storage implementation exercised by the suite.
+pub trait ScopeBackend {
+    /// Unregisters the scope when dropped.
+    type Guard;
+
+    /// Register `scope` as the innermost handling scope.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`UnregisteredScope::register`](crate::context::UnregisteredScope::register),
+    /// and `scope` must not be moved or dropped until the guard is dropped.
+    unsafe fn push_scope(scope: &mut ScopeNode) -> Self::Guard;
+
+    /// Offer `err` to the registered scopes, innermost first, and return its ID.
+    fn raise<E: crate::Error>(err: E) -> crate::ErrorId;
+}
+
+/// The thread local scope storage of this crate.
+pub struct ThreadLocalBackend;
+
+impl ScopeBackend for ThreadLocalBackend {
+    type Guard = crate::context::PopScopeGuard;
+
+    unsafe fn push_scope(scope: &mut ScopeNode) -> Self::Guard {
+        crate::context::push_scope(scope)
+    }
+
+    fn raise<E: crate::Error>(err: E) -> crate::ErrorId {
+        crate::context::push_error(err)
+    }
+}
+
+/// Run every check of the suite.
+pub fn run_all<B: ScopeBackend>() {
+    innermost_first::<B>();
+    forget_and_drop::<B>();
+    nested_scopes::<B>();
+    two_errors::<B>();
+    no_scope::<B>();
+    handler_decline::<B>();
+    unique_ids::<B>();
+}
+
+fn with_scope<B: ScopeBackend, C: ErrorHandlingContext, T>(context: &mut C, func: impl FnOnce() -> T) -> T {
+    let mut scope = ScopeNode::raw(context);
+    // Safety: scope is kept alive, guard is dropped before `scope` is used again
+    let guard = unsafe { B::push_scope(&mut scope) };
+    let res = func();
+    drop(guard);
+    res
+}
+
+/// Counts how many times values sharing the counter are dropped.
+struct Counted(Rc<Cell<usize>>);
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+/// Never accepts an error.
+struct Decline;
+
+impl ErrorHandlingContext for Decline {
+    unsafe fn try_set_error(&mut self, _error: &ReportedError) -> TrySetErrorResult {
+        TrySetErrorResult::NotHandled
+    }
+}
+
+/// The innermost scope accepting an error gets it, outer scopes don't see it.
+pub fn innermost_first<B: ScopeBackend>() {
+    let mut outer = SingleErrorStorage::<u32>::default();
+    let mut inner = SingleErrorStorage::<u32>::default();
+    let id = with_scope::<B, _, _>(&mut outer, || with_scope::<B, _, _>(&mut inner, || B::raise(7u32)));
+    assert_eq!(inner.into_inner(), Some((id, 7)), "the innermost scope must capture the error");
+    assert_eq!(outer.into_inner(), None, "outer scopes must not see a captured error");
+}
+
+/// A value is dropped exactly once, whether it is captured, dropped by a scope or unhandled.
+pub fn forget_and_drop<B: ScopeBackend>() {
+    let drops = Rc::new(Cell::new(0));
+
+    let mut storage = SingleErrorStorage::<Counted>::default();
+    with_scope::<B, _, _>(&mut storage, || B::raise(Counted(drops.clone())));
+    assert_eq!(drops.get(), 0, "a captured value must not be dropped by the raise");
+    drop(storage);
+    assert_eq!(drops.get(), 1, "a captured value must be dropped once with its storage");
+
+    let mut catch_all = CatchAllContext { inner: None };
+    with_scope::<B, _, _>(&mut catch_all, || B::raise(Counted(drops.clone())));
+    assert_eq!(drops.get(), 2, "a value a scope asks to drop must be dropped once");
+    assert!(catch_all.inner.is_some());
+
+    B::raise(Counted(drops.clone()));
+    assert_eq!(drops.get(), 3, "an unhandled value must be dropped once");
+}
+
+/// An error not accepted by the inner scope reaches the outer scope.
+pub fn nested_scopes<B: ScopeBackend>() {
+    let mut outer = SingleErrorStorage::<&'static str>::default();
+    let mut inner = SingleErrorStorage::<u32>::default();
+    let id = with_scope::<B, _, _>(&mut outer, || with_scope::<B, _, _>(&mut inner, || B::raise("outer")));
+    assert_eq!(inner.into_inner(), None, "a scope must not capture errors of other types");
+    assert_eq!(outer.into_inner(), Some((id, "outer")), "the error must reach the outer scope");
+
+    // The outer scope is the innermost again once the inner one is unregistered
+    let mut outer = SingleErrorStorage::<u32>::default();
+    let mut inner = SingleErrorStorage::<u32>::default();
+    let id = with_scope::<B, _, _>(&mut outer, || {
+        with_scope::<B, _, _>(&mut inner, || ());
+        B::raise(1u32)
+    });
+    assert_eq!(inner.into_inner(), None, "an unregistered scope must not capture errors");
+    assert_eq!(outer.into_inner(), Some((id, 1)));
+}
+
+/// Two errors raised in one scope are both delivered, and both values are dropped once.
+pub fn two_errors<B: ScopeBackend>() {
+    let drops = Rc::new(Cell::new(0));
+    let mut storage = SingleErrorStorage::<Counted>::default();
+    let (first, second) = with_scope::<B, _, _>(&mut storage, || {
+        (B::raise(Counted(drops.clone())), B::raise(Counted(drops.clone())))
+    });
+    assert_ne!(first, second, "every raise must get its own ID");
+    assert_eq!(drops.get(), 1, "the replaced value must be dropped when the second is captured");
+    let (id, value) = storage.into_inner().unwrap();
+    assert_eq!(id, second, "the storage must hold the most recent error");
+    drop(value);
+    assert_eq!(drops.get(), 2);
+}
+
+/// Raising without any registered scope is allowed.
+pub fn no_scope<B: ScopeBackend>() {
+    let first = B::raise("nobody listens");
+    let second = B::raise(1u8);
+    assert_ne!(first, second, "unhandled raises must still get unique IDs");
+}
+
+/// A scope declining an error passes it on to the enclosing scope.
+pub fn handler_decline<B: ScopeBackend>() {
+    let mut outer = SingleErrorStorage::<u32>::default();
+    let mut decline = Decline;
+    let id = with_scope::<B, _, _>(&mut outer, || with_scope::<B, _, _>(&mut decline, || B::raise(3u32)));
+    assert_eq!(outer.into_inner(), Some((id, 3)), "a declined error must reach the outer scope");
+}
+
+/// IDs are unique among recent raises on the same thread.
+pub fn unique_ids<B: ScopeBackend>() {
+    let mut storage = SingleErrorStorage::<u32>::default();
+    let mut ids: Vec<_> = with_scope::<B, _, _>(&mut storage, || (0..1000u32).map(B::raise).collect());
+    ids.sort_unstable_by_key(|id| id.to_raw());
+    ids.dedup();
+    assert_eq!(ids.len(), 1000, "IDs of recent raises must be unique");
+}
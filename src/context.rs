@@ -1,21 +1,404 @@
-use std::any::TypeId;
-use std::cell::RefCell;
+use std::alloc::Layout;
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::marker::{PhantomData, PhantomPinned};
+use std::mem::ManuallyDrop;
 use std::ops::DerefMut;
+use std::panic::Location;
+use std::pin::Pin;
+use std::ptr::NonNull;
 use std::thread_local;
 
-pub struct ReportedError
+/// Optional information about a raise.
+///
+/// This is only built when a feature or raise variant needs it, keeping the common raise small.
+#[derive(Default, Debug)]
+#[non_exhaustive]
+pub struct ReportExt
+{
+    /// Where the error was raised
+    pub location: Option<&'static Location<'static>>,
+    /// The isolated operation active when the error was raised, 0 if none
+    pub operation: u64,
+    /// The stable identity of the error, if raised with
+    /// [`Result::new_error_stable`](crate::Result::new_error_stable)
+    pub stable_id: Option<crate::stable_id::StableTypeId>,
+}
+
+/// An error being offered to the handling scopes.
+///
+/// Use the accessor methods to read it, the public fields are kept for compatibility only.
+#[non_exhaustive]
+pub struct ReportedError<'a>
 {
-    pub id: u32,
+    #[deprecated(note = "use `ReportedError::id()` instead")]
+    pub id: u64,
+    #[deprecated(note = "use `ReportedError::type_id()` instead")]
     pub type_id: TypeId,
+    #[deprecated(note = "use `ReportedError::value_ptr()` instead")]
     pub value: *mut (),
+    type_name: &'static str,
+    error_type: &'static crate::ErrorType,
+    layout: Layout,
+    drop_value: unsafe fn(*mut ()),
+    box_value: unsafe fn(*mut ()) -> Box<dyn Any>,
+    clone_value: Option<CloneFn>,
+    ext: Option<&'a ReportExt>,
+}
+
+unsafe fn drop_value_impl<E>(value: *mut ()) {
+    (value as *mut E).drop_in_place()
+}
+
+unsafe fn box_value_impl<E: crate::Error>(value: *mut ()) -> Box<dyn Any> {
+    Box::new((value as *mut E).read())
+}
+
+/// Clones an erased value of the type it was made for into a box.
+type CloneFn = unsafe fn(*const ()) -> Box<dyn Any>;
+
+unsafe fn clone_value_impl<E: crate::Error + Clone>(value: *const ()) -> Box<dyn Any> {
+    Box::new((*(value as *const E)).clone())
 }
 
-impl ReportedError {
-    fn new<E: crate::Error>(id: u32, err: &mut E) -> Self {
+#[allow(deprecated)]
+impl<'a> ReportedError<'a> {
+    /// Describe `err` as a reported error with the ID `id`.
+    ///
+    /// The value is still owned by the caller, who must uphold the `forget`/`drop` contract of
+    /// [`ErrorHandlingContext::try_set_error`] when offering it to contexts.
+    pub fn new<E: crate::Error>(id: crate::ErrorId, err: &'a mut E) -> Self {
         Self {
-            id,
+            id: id.to_raw(),
             type_id: TypeId::of::<E>(),
-            value: err as *const _ as *mut (),
+            value: err as *mut E as *mut (),
+            type_name: std::any::type_name::<E>(),
+            error_type: &<E as crate::HasErrorType>::ERROR_TYPE,
+            layout: Layout::new::<E>(),
+            drop_value: drop_value_impl::<E>,
+            box_value: box_value_impl::<E>,
+            clone_value: None,
+            ext: None,
+        }
+    }
+
+    /// Describe `err` like [`ReportedError::new`], letting contexts clone the value with
+    /// [`ReportedError::clone_value`].
+    pub fn new_cloneable<E: crate::Error + Clone>(id: crate::ErrorId, err: &'a mut E) -> Self {
+        Self {
+            clone_value: Some(clone_value_impl::<E>),
+            ..Self::new(id, err)
+        }
+    }
+
+    /// Attach extended information to the reported error.
+    #[inline]
+    pub fn with_ext(mut self, ext: &'a ReportExt) -> Self {
+        self.ext = Some(ext);
+        self
+    }
+
+    /// The ID of the reported error.
+    #[inline]
+    pub fn id(&self) -> crate::ErrorId {
+        crate::ErrorId::from_raw(self.id)
+    }
+
+    /// The `TypeId` of the reported error.
+    #[inline]
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// Pointer to the reported error value.
+    ///
+    /// The value is of the type identified by [`ReportedError::type_id`].
+    #[inline]
+    pub fn value_ptr(&self) -> *mut () {
+        self.value
+    }
+
+    /// The name of the type of the reported error.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Move the reported error value into a box.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as `ErrorHandlingContext::try_set_error`, and the caller must return
+    /// `TrySetErrorResult::NeedForget` for this error.
+    #[inline]
+    pub unsafe fn take_boxed(&self) -> Box<dyn Any> {
+        (self.box_value)(self.value)
+    }
+
+    /// A copy of the reported error value, if it is an `E` raised as cloneable.
+    ///
+    /// Lets a context that only observes the raise keep a copy and still return
+    /// [`TrySetErrorResult::NotHandled`], so the walk continues to an enclosing context taking the
+    /// value.
+    /// Errors are only cloneable when raised with
+    /// [`Result::new_error_cloneable`](crate::Result::new_error_cloneable), for all other raises,
+    /// including errors raised again after being captured, this returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xcept::context::{ErrorHandlingContext, ReportedError, ScopeNode, TrySetErrorResult};
+    ///
+    /// #[derive(Default)]
+    /// struct Audit(Vec<String>);
+    ///
+    /// impl ErrorHandlingContext for Audit {
+    ///     unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+    ///         self.0.extend(error.clone_value::<String>());
+    ///         TrySetErrorResult::NotHandled
+    ///     }
+    /// }
+    ///
+    /// let mut audit = Audit::default();
+    /// let res = xcept::try_or_handle_one(
+    ///     || {
+    ///         // Scopes are asked innermost first, the audit sees errors before the handler
+    ///         let scope = std::pin::pin!(ScopeNode::new(&mut audit));
+    ///         let _guard = unsafe { scope.register() };
+    ///         let _ = xcept::Result::<usize>::new_error(String::from("not cloneable"));
+    ///         xcept::Result::<usize>::new_error_cloneable(String::from("disk full"))
+    ///     },
+    ///     |err: String| xcept::Result::new(err.len()),
+    /// );
+    ///
+    /// assert_eq!(res.unwrap(), 9);
+    /// assert_eq!(audit.0, ["disk full"]);
+    /// ```
+    #[inline]
+    pub fn clone_value<E: crate::Error + Clone>(&self) -> Option<E> {
+        if self.clone_value.is_some() && self.type_id == TypeId::of::<E>() {
+            // Safety: the value is an initialized `E`, owned by the raise until a context takes it
+            Some(unsafe { (*(self.value as *const E)).clone() })
+        } else {
+            None
+        }
+    }
+
+    /// A boxed copy of the reported error value, if it was raised as cloneable.
+    ///
+    /// See [`ReportedError::clone_value`].
+    #[inline]
+    pub fn clone_boxed(&self) -> Option<Box<dyn Any>> {
+        // Safety: the value is initialized and of the type the thunk was made for
+        self.clone_value.map(|clone| unsafe { clone(self.value) })
+    }
+
+    /// The extended information of the raise, if any was attached.
+    #[inline]
+    pub fn ext(&self) -> Option<&'a ReportExt> {
+        self.ext
+    }
+
+    /// Where the error was raised, if known.
+    #[inline]
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.ext.and_then(|ext| ext.location)
+    }
+
+    /// The ID, type name and location of the error, enabled by the `diagnostics` feature.
+    #[cfg(feature = "diagnostics")]
+    pub fn info(&self) -> ErrorInfo {
+        ErrorInfo {
+            id: self.id(),
+            type_name: self.type_name(),
+            location: self.location(),
+        }
+    }
+
+    /// The stable identity of the error, if it was raised with
+    /// [`Result::new_error_stable`](crate::Result::new_error_stable).
+    ///
+    /// The value is then the portable representation of the error, see
+    /// [`StableError`](crate::stable_id::StableError).
+    #[inline]
+    pub fn stable_id(&self) -> Option<crate::stable_id::StableTypeId> {
+        self.ext.and_then(|ext| ext.stable_id)
+    }
+
+    /// The memory layout of the reported error value.
+    #[inline]
+    pub(crate) fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// The token of the isolated operation active when the error was raised, 0 if none.
+    ///
+    /// See [`enter_operation`].
+    #[inline]
+    pub fn operation(&self) -> u64 {
+        self.ext.map_or(0, |ext| ext.operation)
+    }
+}
+
+/// An error value whose type has been erased.
+///
+/// The value is owned by the `ErasedError` and is dropped together with it, unless it is raised
+/// again.
+pub struct ErasedError
+{
+    id: crate::ErrorId,
+    type_id: TypeId,
+    type_name: &'static str,
+    error_type: &'static crate::ErrorType,
+    value: NonNull<u8>,
+    layout: Layout,
+    drop_value: unsafe fn(*mut ()),
+    box_value: unsafe fn(*mut ()) -> Box<dyn Any>,
+}
+
+impl ErasedError {
+    /// Move the value out of a reported error.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as `ErrorHandlingContext::try_set_error`, and the caller must return
+    /// `TrySetErrorResult::NeedForget` for `error`.
+    pub unsafe fn take(error: &ReportedError) -> Self {
+        let value = if error.layout.size() == 0 {
+            // Zero-sized values need no storage, an aligned dangling pointer is enough
+            NonNull::new_unchecked(std::ptr::without_provenance_mut(error.layout.align()))
+        } else {
+            let ptr = std::alloc::alloc(error.layout);
+            if ptr.is_null() {
+                std::alloc::handle_alloc_error(error.layout);
+            }
+            std::ptr::copy_nonoverlapping(error.value_ptr() as *const u8, ptr, error.layout.size());
+            NonNull::new_unchecked(ptr)
+        };
+        Self {
+            id: error.id(),
+            type_id: error.type_id(),
+            type_name: error.type_name,
+            error_type: error.error_type,
+            value,
+            layout: error.layout,
+            drop_value: error.drop_value,
+            box_value: error.box_value,
+        }
+    }
+
+    /// The ID the error was reported with.
+    pub fn id(&self) -> crate::ErrorId {
+        self.id
+    }
+
+    /// The `TypeId` of the erased value.
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// The name of the type of the erased value.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Render the value through its redactor, see [`crate::redact`].
+    pub fn summary(&self) -> crate::redact::RedactedSummary {
+        // Safety: the value is owned by `self` and hasn't been moved out
+        unsafe { crate::redact::summarize(&self.reported()) }
+    }
+
+    /// Move the value into a box, for downcasting.
+    pub fn into_box(self) -> Box<dyn Any> {
+        self.into_box_with_shape().0
+    }
+
+    /// Move the value into a box, keeping what is needed to erase it again.
+    pub(crate) fn into_box_with_shape(mut self) -> (Box<dyn Any>, ErasedShape) {
+        let shape = ErasedShape {
+            id: self.id,
+            type_id: self.type_id,
+            type_name: self.type_name,
+            error_type: self.error_type,
+            layout: self.layout,
+            drop_value: self.drop_value,
+            box_value: self.box_value,
+        };
+        // Safety: the value is owned and still initialized, and is neither dropped nor used after
+        // being moved out
+        unsafe {
+            let boxed = (self.box_value)(self.value.as_ptr() as *mut ());
+            self.deallocate();
+            std::mem::forget(self);
+            (boxed, shape)
+        }
+    }
+
+    #[allow(deprecated)]
+    fn reported(&self) -> ReportedError<'_> {
+        ReportedError {
+            id: self.id.to_raw(),
+            type_id: self.type_id,
+            value: self.value.as_ptr() as *mut (),
+            type_name: self.type_name,
+            error_type: self.error_type,
+            layout: self.layout,
+            drop_value: self.drop_value,
+            box_value: self.box_value,
+            clone_value: None,
+            ext: None,
+        }
+    }
+
+    /// Release the storage without dropping the value.
+    unsafe fn deallocate(&mut self) {
+        if self.layout.size() != 0 {
+            std::alloc::dealloc(self.value.as_ptr(), self.layout);
+        }
+    }
+}
+
+/// The ID and type information of an [`ErasedError`] whose value has been boxed.
+pub(crate) struct ErasedShape {
+    id: crate::ErrorId,
+    type_id: TypeId,
+    type_name: &'static str,
+    error_type: &'static crate::ErrorType,
+    layout: Layout,
+    drop_value: unsafe fn(*mut ()),
+    box_value: unsafe fn(*mut ()) -> Box<dyn Any>,
+}
+
+impl ErasedShape {
+    /// Erase a boxed value again, with the original ID.
+    ///
+    /// Fails, returning the box, if it holds a value of another type.
+    pub(crate) fn restore(self, value: Box<dyn Any>) -> Result<ErasedError, Box<dyn Any>> {
+        if (*value).type_id() != self.type_id {
+            return Err(value);
+        }
+        // Safety: a box of a sized type is allocated with the global allocator using the layout
+        // of the type, or is dangling if the type is zero-sized, same as `ErasedError::take`
+        let value = unsafe { NonNull::new_unchecked(Box::into_raw(value) as *mut u8) };
+        Ok(ErasedError {
+            id: self.id,
+            type_id: self.type_id,
+            type_name: self.type_name,
+            error_type: self.error_type,
+            value,
+            layout: self.layout,
+            drop_value: self.drop_value,
+            box_value: self.box_value,
+        })
+    }
+}
+
+impl Drop for ErasedError {
+    fn drop(&mut self) {
+        // Safety: the value is owned and still initialized
+        unsafe {
+            (self.drop_value)(self.value.as_ptr() as *mut ());
+            self.deallocate();
         }
     }
 }
@@ -31,6 +414,45 @@ pub enum TrySetErrorResult
     NeedForget,
     /// The error was handled, the caller must `drop` the actual error
     NeedDrop,
+    /// The error was rejected, the caller must `drop` the actual error without offering it to any
+    /// other scope
+    Suppress,
+}
+
+/// What happened to a raised error.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Disposition
+{
+    /// A scope took ownership of the error
+    Captured,
+    /// A scope handled the error by dropping it
+    Dropped,
+    /// A scope suppressed the error
+    Suppressed,
+    /// No scope accepted the error, it was dropped
+    Unhandled,
+    /// The error was replaced by a later alternative, see [`supersede`]
+    ///
+    /// Never the disposition of a raise, only reported afterwards through
+    /// [`ContextExt::on_disposition`].
+    Superseded,
+}
+
+impl Disposition {
+    pub(crate) fn from_result(result: &TrySetErrorResult) -> Self {
+        match result {
+            TrySetErrorResult::NotHandled => Self::Unhandled,
+            TrySetErrorResult::NeedForget => Self::Captured,
+            TrySetErrorResult::NeedDrop => Self::Dropped,
+            TrySetErrorResult::Suppress => Self::Suppressed,
+        }
+    }
+
+    /// Test if the value was moved out of the raise site.
+    #[inline]
+    pub fn is_captured(&self) -> bool {
+        matches!(self, Self::Captured)
+    }
 }
 
 pub trait ErrorHandlingContext
@@ -42,33 +464,343 @@ pub trait ErrorHandlingContext
     ///
     /// ## Safety
     ///
-    ///   * The `TypeId` of the actual type of the pointer that `error.value` points to must match
-    ///     `error.type_id`.
+    ///   * The `TypeId` of the actual type of the pointer that `error.value_ptr()` points to must
+    ///     match `error.type_id()`.
     ///   * If this function returns true, the caller must ensure to `forget` the original value
     ///     since it is effectively moved to some other location.
     unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult;
+
+    /// The optional extensions of this context.
+    ///
+    /// New capabilities are added to [`ContextExt`] instead of this trait, so existing contexts
+    /// keep compiling. Contexts that don't implement it are treated as knowing nothing.
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        None
+    }
+}
+
+/// The identity of an error without its value, for asking contexts whether they would accept it.
+///
+/// Holds any of the `TypeId`, the type name and the stable identity of the error type, for
+/// instance of a recorded raise whose value is gone. See [`ContextExt::would_accept`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ReportedErrorDescriptor {
+    type_id: Option<TypeId>,
+    type_name: Option<&'static str>,
+    stable_id: Option<crate::stable_id::StableTypeId>,
+}
+
+impl ReportedErrorDescriptor {
+    /// An error of type `E` raised with [`Result::new_error`](crate::Result::new_error).
+    pub fn of<E: crate::Error>() -> Self {
+        Self {
+            type_id: Some(TypeId::of::<E>()),
+            type_name: Some(std::any::type_name::<E>()),
+            stable_id: None,
+        }
+    }
+
+    /// An error known by its type name only, as listed by `std::any::type_name`.
+    pub const fn named(type_name: &'static str) -> Self {
+        Self {
+            type_id: None,
+            type_name: Some(type_name),
+            stable_id: None,
+        }
+    }
+
+    /// An error raised with [`Result::new_error_stable`](crate::Result::new_error_stable).
+    pub const fn stable(stable_id: crate::stable_id::StableTypeId) -> Self {
+        Self {
+            type_id: None,
+            type_name: None,
+            stable_id: Some(stable_id),
+        }
+    }
+
+    /// The `TypeId` of the error, if known.
+    pub fn type_id(&self) -> Option<TypeId> {
+        self.type_id
+    }
+
+    /// The type name of the error, if known.
+    pub fn type_name(&self) -> Option<&'static str> {
+        self.type_name
+    }
+
+    /// The stable identity of the error, if it's raised as a stable error.
+    pub fn stable_id(&self) -> Option<crate::stable_id::StableTypeId> {
+        self.stable_id
+    }
+
+    /// Whether a context accepting only errors of type `T` would accept this error.
+    pub(crate) fn acceptance_for<T: crate::Error>(&self) -> Acceptance {
+        let matches = match (self.type_id, self.type_name) {
+            (Some(type_id), _) => type_id == TypeId::of::<T>(),
+            (None, Some(type_name)) => type_name == std::any::type_name::<T>(),
+            // The portable type of a stable error isn't known
+            (None, None) => return Acceptance::Indeterminate,
+        };
+        if matches && !crate::runtime::is_suppressed(TypeId::of::<T>()) {
+            Acceptance::Accept
+        } else {
+            Acceptance::Reject
+        }
+    }
+}
+
+/// The answer of a context asked about a [`ReportedErrorDescriptor`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Acceptance {
+    /// The context accepts the error
+    Accept,
+    /// The context doesn't accept the error
+    Reject,
+    /// The context can't tell without the value, for instance because it runs a predicate on it
+    Indeterminate,
+}
+
+impl From<Option<bool>> for Acceptance {
+    /// Convert an answer of [`ContextExt::accepts`].
+    fn from(accepts: Option<bool>) -> Self {
+        match accepts {
+            Some(true) => Self::Accept,
+            Some(false) => Self::Reject,
+            None => Self::Indeterminate,
+        }
+    }
+}
+
+/// Optional capabilities of an [`ErrorHandlingContext`], returned by
+/// [`ErrorHandlingContext::ext`].
+///
+/// Every method has a default meaning "unknown" or "nothing to do", so methods can be added
+/// without breaking implementations.
+pub trait ContextExt {
+    /// Test if the context would accept an error of type `type_id`.
+    ///
+    /// `None` if it can't tell without being offered the error.
+    fn accepts(&mut self, type_id: TypeId) -> Option<bool> {
+        let _ = type_id;
+        None
+    }
+
+    /// Test if the context would accept the error described by `descriptor`, without its value.
+    ///
+    /// Defaults to asking [`ContextExt::accepts`] for the `TypeId` of the descriptor, and to
+    /// [`Acceptance::Indeterminate`] if it has none.
+    fn would_accept(&mut self, descriptor: &ReportedErrorDescriptor) -> Acceptance {
+        descriptor
+            .type_id()
+            .map_or(Acceptance::Indeterminate, |type_id| self.accepts(type_id).into())
+    }
+
+    /// Test if the context holds a captured error that hasn't been handled yet.
+    fn has_pending(&mut self) -> bool {
+        false
+    }
+
+    /// The number of captured errors that haven't been handled yet.
+    ///
+    /// Defaults to one if [`ContextExt::has_pending`], contexts holding several errors override
+    /// it. See [`pending_count`].
+    fn pending_count(&mut self) -> usize {
+        usize::from(self.has_pending())
+    }
+
+    /// The type name of the pending error, if known. See [`ContextExt::has_pending`].
+    fn pending_type_name(&mut self) -> Option<&'static str> {
+        None
+    }
+
+    /// The pending error rendered through [`crate::redact`], if the context can render it.
+    fn pending_summary(&mut self) -> Option<crate::redact::RedactedSummary> {
+        None
+    }
+
+    /// The [`SingleErrorStorage`] holding a pending error of type `type_id`, if there is one.
+    ///
+    /// Used by [`FlatHandlers::map_captured`](crate::multihandler::FlatHandlers::map_captured) to
+    /// find the storage of a chain entry.
+    fn pending_storage(&mut self, type_id: TypeId) -> Option<&mut dyn Any> {
+        let _ = type_id;
+        None
+    }
+
+    /// A name describing the context, for debugging.
+    fn debug_name(&self) -> &'static str {
+        ""
+    }
+
+    /// Called after the context accepted the error `error_id`.
+    ///
+    /// Also called for every active context with [`Disposition::Superseded`] when an error is
+    /// [superseded](supersede), a context still holding the error should drop it.
+    fn on_disposition(&mut self, error_id: crate::ErrorId, disposition: Disposition) {
+        let _ = (error_id, disposition);
+    }
 }
 
 #[derive(Copy, Clone)]
 pub struct SingleErrorStorage<T>
 {
-    inner: Option<(u32, T)>
+    inner: Option<(crate::ErrorId, T)>,
+    #[cfg(feature = "metadata")]
+    location: Option<&'static Location<'static>>,
+}
+
+/// What is known about a raise besides the error value, enabled by the `diagnostics` feature.
+///
+/// Kept by [`SingleErrorStorage`] with the captured error, so handlers added with
+/// [`Builder::handle_with_info`](crate::multihandler::Builder::handle_with_info) can log where
+/// the error they handle was raised.
+#[cfg(feature = "diagnostics")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ErrorInfo {
+    id: crate::ErrorId,
+    type_name: &'static str,
+    location: Option<&'static Location<'static>>,
+}
+
+#[cfg(feature = "diagnostics")]
+impl ErrorInfo {
+    /// The ID of the error.
+    pub fn id(&self) -> crate::ErrorId {
+        self.id
+    }
+
+    /// The name of the type of the error.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Where the error was raised, `None` for errors raised with
+    /// [`Result::new_error_bare`](crate::Result::new_error_bare).
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.location
+    }
 }
 
 impl<T> Default for SingleErrorStorage<T> {
     #[inline]
     fn default() -> Self {
         Self {
-            inner: None
+            inner: None,
+            #[cfg(feature = "metadata")]
+            location: None,
         }
     }
 }
 
 impl<T> SingleErrorStorage<T> {
     #[inline(always)]
-    pub fn into_inner(self) -> Option<(u32, T)> {
+    pub fn into_inner(self) -> Option<(crate::ErrorId, T)> {
         self.inner
     }
+
+    /// The captured error with what is known about its raise, enabled by the `diagnostics`
+    /// feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xcept::context::{ScopeNode, SingleErrorStorage};
+    ///
+    /// let mut storage = SingleErrorStorage::<u8>::default();
+    /// let line = line!() + 4;
+    /// {
+    ///     let scope = std::pin::pin!(ScopeNode::new(&mut storage));
+    ///     let _guard = unsafe { scope.register() };
+    ///     let _: xcept::Result<()> = xcept::Result::new_error(3u8);
+    /// }
+    /// let (err, info) = storage.into_inner_with_info().unwrap();
+    /// assert_eq!(err, 3);
+    /// assert_eq!(info.location().unwrap().line(), line);
+    /// ```
+    #[cfg(feature = "diagnostics")]
+    pub fn into_inner_with_info(self) -> Option<(T, ErrorInfo)> {
+        let location = self.location;
+        self.inner.map(|(id, err)| {
+            let info = ErrorInfo {
+                id,
+                type_name: std::any::type_name::<T>(),
+                location,
+            };
+            (err, info)
+        })
+    }
+
+    /// Replace the captured error with the one returned by `f`, keeping its ID.
+    ///
+    /// Lets adapters transform an error, for instance to sanitize it, before the handler it was
+    /// raised for sees it. Returns `false`, without calling `f`, if no error is captured.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xcept::context::ScopeNode;
+    ///
+    /// let mut storage = xcept::context::SingleErrorStorage::<String>::default();
+    /// let res: xcept::Result<String> = {
+    ///     let scope = std::pin::pin!(ScopeNode::new(&mut storage));
+    ///     let _guard = unsafe { scope.register() };
+    ///     xcept::Result::new_error(String::from("/home/user/data.csv"))
+    /// };
+    ///
+    /// assert!(storage.replace_with(|path| path.rsplit('/').next().unwrap().to_string()));
+    /// let res = storage.try_handle(res, |name| xcept::Result::new(name));
+    /// assert_eq!(res.unwrap(), "data.csv");
+    /// ```
+    pub fn replace_with(&mut self, f: impl FnOnce(T) -> T) -> bool {
+        match self.inner.take() {
+            Some((id, err)) => {
+                self.inner = Some((id, f(err)));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T: crate::Error> ContextExt for SingleErrorStorage<T> {
+    fn accepts(&mut self, type_id: TypeId) -> Option<bool> {
+        Some(type_id == TypeId::of::<T>() && !crate::runtime::is_suppressed(type_id))
+    }
+
+    fn would_accept(&mut self, descriptor: &ReportedErrorDescriptor) -> Acceptance {
+        descriptor.acceptance_for::<T>()
+    }
+
+    fn has_pending(&mut self) -> bool {
+        self.inner.is_some()
+    }
+
+    fn pending_type_name(&mut self) -> Option<&'static str> {
+        self.inner.as_ref().map(|_| std::any::type_name::<T>())
+    }
+
+    fn pending_summary(&mut self) -> Option<crate::redact::RedactedSummary> {
+        self.inner.as_ref().map(|(_, err)| crate::redact::summarize_value(err))
+    }
+
+    fn pending_storage(&mut self, type_id: TypeId) -> Option<&mut dyn Any> {
+        if self.inner.is_some() && type_id == TypeId::of::<T>() {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn on_disposition(&mut self, error_id: crate::ErrorId, disposition: Disposition) {
+        if disposition == Disposition::Superseded && self.inner.as_ref().is_some_and(|(id, _)| *id == error_id) {
+            self.inner = None;
+        }
+    }
+
+    fn debug_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
 
 impl<T: crate::Error> SingleErrorStorage<T> {
@@ -79,18 +811,36 @@ impl<T: crate::Error> SingleErrorStorage<T> {
         }
 
         // Safety: checked access
-        unsafe { self.unchecked_try_handle(error, handler) }
+        unsafe { self.try_handle_unchecked(error, handler) }
     }
 
     /// Unchecked version of `try_handle`.
     ///
+    /// Not available with the `deny-unchecked` feature.
+    ///
     /// # Safety
     ///
     /// `error.is_error()` must return true.
+    #[cfg(not(feature = "deny-unchecked"))]
     #[inline]
     pub unsafe fn unchecked_try_handle<V>(self, error: crate::Result<V>, handler: impl FnOnce(T) -> crate::Result<V>) -> crate::Result<V> {
+        self.try_handle_unchecked(error, handler)
+    }
+
+    /// Crate-private version of `unchecked_try_handle`, available regardless of features.
+    ///
+    /// # Safety
+    ///
+    /// `error.is_error()` must return true.
+    #[inline]
+    pub(crate) unsafe fn try_handle_unchecked<V>(self, error: crate::Result<V>, handler: impl FnOnce(T) -> crate::Result<V>) -> crate::Result<V> {
+        paranoid_assert!(error.is_error(), "called `unchecked_try_handle` with an ok `Result`");
         match self.inner {
-            Some((id, err)) if id == error.unchecked_error_id() => handler(err),
+            Some((id, err)) if id.to_raw() == error.error_id_unchecked() => {
+                #[cfg(feature = "paranoid")]
+                crate::id_state::handled(id);
+                handler(err)
+            }
             _ => error,
         }
     }
@@ -99,70 +849,553 @@ impl<T: crate::Error> SingleErrorStorage<T> {
 impl<T: crate::Error> ErrorHandlingContext for SingleErrorStorage<T>
 {
     unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
-        if TypeId::of::<T>() == error.type_id {
-            self.inner = Some((error.id, (error.value as *mut T).read()));
+        if TypeId::of::<T>() == error.type_id() && !crate::runtime::is_suppressed(error.type_id()) {
+            #[cfg(feature = "paranoid")]
+            crate::id_state::captured(error.id());
+            self.inner = Some((error.id(), (error.value_ptr() as *mut T).read()));
+            #[cfg(feature = "metadata")]
+            {
+                self.location = error.location();
+            }
             TrySetErrorResult::NeedForget
         }
         else {
             TrySetErrorResult::NotHandled
         }
     }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        Some(self)
+    }
 }
 
 pub struct CatchAllContext
 {
-    pub inner: Option<(u32, TypeId)>
+    pub inner: Option<(crate::ErrorId, TypeId)>
 }
 
 impl ErrorHandlingContext for CatchAllContext {
     unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
-        self.inner = Some((error.id, error.type_id));
+        #[cfg(feature = "paranoid")]
+        crate::id_state::captured(error.id());
+        self.inner = Some((error.id(), error.type_id()));
         TrySetErrorResult::NeedDrop
     }
-}
 
-struct HandlingScopes
-{
-    error_id: u32,
-    scopes: *mut ScopeNode
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        Some(self)
+    }
 }
 
-impl HandlingScopes {
-    fn new() -> Self {
-        Self {
-            error_id: 0,
-            scopes: core::ptr::null_mut(),
-        }
+impl ContextExt for CatchAllContext {
+    fn accepts(&mut self, _type_id: TypeId) -> Option<bool> {
+        Some(true)
+    }
+
+    fn would_accept(&mut self, _descriptor: &ReportedErrorDescriptor) -> Acceptance {
+        Acceptance::Accept
+    }
+
+    fn has_pending(&mut self) -> bool {
+        self.inner.is_some()
+    }
+
+    fn debug_name(&self) -> &'static str {
+        "CatchAllContext"
+    }
+}
+
+/// Captures errors of any type, keeping the most recent value in a box.
+///
+/// Unlike [`CatchAllContext`], which only records the `TypeId`, the value can be downcast by the
+/// handler.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::context::{BoxingCatchAll, ScopeNode};
+///
+/// let mut catch_all = BoxingCatchAll::default();
+/// let res: xcept::Result<u32> = {
+///     let scope = std::pin::pin!(ScopeNode::new(&mut catch_all));
+///     let _guard = unsafe { scope.register() };
+///     xcept::Result::new_error(std::io::Error::other("disk full"))
+/// };
+///
+/// let res = catch_all.try_handle(res, |err| match err.downcast::<std::io::Error>() {
+///     Ok(err) => xcept::Result::new(err.to_string().len() as u32),
+///     Err(_) => xcept::Result::new(0),
+/// });
+/// assert_eq!(res.unwrap(), 9);
+/// ```
+#[derive(Default)]
+pub struct BoxingCatchAll {
+    inner: Option<(crate::ErrorId, &'static str, Box<dyn Any>)>,
+}
+
+impl BoxingCatchAll {
+    /// The captured error and its ID, if one was captured.
+    pub fn into_inner(self) -> Option<(crate::ErrorId, Box<dyn Any>)> {
+        self.inner.map(|(id, _, value)| (id, value))
+    }
+
+    /// Handle `error` with `handler` if it is the captured error, otherwise return it unchanged.
+    pub fn try_handle<V>(self, error: crate::Result<V>, handler: impl FnOnce(Box<dyn Any>) -> crate::Result<V>) -> crate::Result<V> {
+        match (self.inner, error.error_id()) {
+            (Some((id, _, value)), Some(error_id)) if id == error_id => {
+                #[cfg(feature = "paranoid")]
+                crate::id_state::handled(id);
+                handler(value)
+            }
+            _ => error,
+        }
+    }
+}
+
+impl ErrorHandlingContext for BoxingCatchAll {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        #[cfg(feature = "paranoid")]
+        crate::id_state::captured(error.id());
+        self.inner = Some((error.id(), error.type_name(), error.take_boxed()));
+        TrySetErrorResult::NeedForget
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        Some(self)
+    }
+}
+
+impl ContextExt for BoxingCatchAll {
+    fn accepts(&mut self, _type_id: TypeId) -> Option<bool> {
+        Some(true)
+    }
+
+    fn would_accept(&mut self, _descriptor: &ReportedErrorDescriptor) -> Acceptance {
+        Acceptance::Accept
+    }
+
+    fn has_pending(&mut self) -> bool {
+        self.inner.is_some()
+    }
+
+    fn pending_type_name(&mut self) -> Option<&'static str> {
+        self.inner.as_ref().map(|(_, type_name, _)| *type_name)
+    }
+
+    fn pending_summary(&mut self) -> Option<crate::redact::RedactedSummary> {
+        self.inner.as_ref().map(|(_, type_name, value)| {
+            // Safety: the pointer and type ID both come from the boxed value
+            unsafe {
+                crate::redact::summarize_erased((**value).type_id(), type_name, &**value as *const dyn Any as *const ())
+            }
+        })
+    }
+
+    fn debug_name(&self) -> &'static str {
+        "BoxingCatchAll"
+    }
+}
+
+/// Captures the first error of any type, and suppresses all errors after it.
+#[derive(Default)]
+pub struct FirstErrorContext
+{
+    first: Option<ErasedError>
+}
+
+impl FirstErrorContext {
+    #[inline(always)]
+    pub fn into_inner(self) -> Option<ErasedError> {
+        self.first
     }
 }
 
+impl ErrorHandlingContext for FirstErrorContext {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        if self.first.is_none() {
+            self.first = Some(ErasedError::take(error));
+            TrySetErrorResult::NeedForget
+        }
+        else {
+            TrySetErrorResult::Suppress
+        }
+    }
+}
+
+/// The number of high bits of an error ID holding the scope epoch it was raised in.
+///
+/// The remaining low bits hold a per thread counter, so IDs raised in the same epoch only repeat
+/// after `2^32 - 1` raises, the same as the IDs of a thread that never pops a scope.
+pub const EPOCH_BITS: u32 = 32;
+const COUNTER_BITS: u32 = u64::BITS - EPOCH_BITS;
+
+/// The error ID left behind by [`Result::take`](crate::Result::take).
+///
+/// Its counter is all ones, which the counter skips, so it is never allocated to a raise and no
+/// handler matches it.
+pub const TAKEN_ID: crate::ErrorId = crate::ErrorId::from_raw(u64::MAX);
+/// The number of counter values, the counter wraps to 0 before reaching the one of [`TAKEN_ID`].
+const COUNTER_RANGE: u64 = u32::MAX as u64;
+
+/// The scope epoch an error ID was raised in.
+#[inline]
+pub const fn epoch_of(id: u64) -> u32 {
+    (id >> COUNTER_BITS) as u32
+}
+
+/// The per thread counter part of an error ID, below the epoch bits.
+#[inline]
+pub const fn counter_of(id: u64) -> u32 {
+    id as u32
+}
+
+/// Test if epoch `a` comes before epoch `b`.
+///
+/// Epochs wrap around, so `a` comes before `b` if `b` is less than half the epoch range ahead of
+/// it, the way serial numbers are compared. With 32 [`EPOCH_BITS`] that is up to `2^31 - 1`
+/// epochs ahead, an epoch `2^31` or more epochs back is taken as not preceding.
+#[inline]
+pub const fn epoch_precedes(a: u32, b: u32) -> bool {
+    let ahead = b.wrapping_sub(a);
+    ahead != 0 && ahead < (1 << (EPOCH_BITS - 1))
+}
+
+/// The scope epoch of the current thread.
+///
+/// The epoch starts at 0 and is incremented, wrapping at `2^EPOCH_BITS`, every time a scope is
+/// popped. Errors carry the epoch they were raised in, see [`epoch_of`], and scopes the epoch
+/// they were pushed in, see [`RegisteredScope::epoch`], so telling if an error predates a scope is a
+/// comparison instead of a lookup.
+///
+/// Every `try_or_handle` call pops a scope. Comparisons are reliable for errors raised fewer than
+/// `2^31` pops ago, see [`epoch_precedes`].
+pub fn current_epoch() -> u32 {
+    CONTEXTS.with(|contexts| contexts.borrow().epoch)
+}
+
+/// Test if the error `id` was raised before the innermost active scope was pushed, or if no
+/// scope is active, before the last scope was popped.
+pub(crate) fn is_stale_id(id: u64) -> bool {
+    CONTEXTS.with(|contexts| {
+        // Borrowed while an error is delivered, nothing has been popped since it was raised
+        let Ok(ctx) = contexts.try_borrow() else {
+            return false;
+        };
+        if ctx.scopes.is_null() {
+            epoch_precedes(epoch_of(id), ctx.epoch)
+        } else {
+            // Safety: All scopes must be kept alive by the contract of push and pop scope
+            unsafe { (*ctx.scopes).predates(crate::ErrorId::from_raw(id)) }
+        }
+    })
+}
+
+struct HandlingScopes
+{
+    error_id: u32,
+    epoch: u32,
+    scopes: *mut ScopeNode,
+    operation: u64,
+    last_operation: u64,
+    suppress_secondary: bool,
+    raises: u64,
+}
+
+impl HandlingScopes {
+    const fn new() -> Self {
+        Self {
+            error_id: 0,
+            epoch: 0,
+            scopes: core::ptr::null_mut(),
+            operation: 0,
+            last_operation: 0,
+            suppress_secondary: false,
+            raises: 0,
+        }
+    }
+}
+
+impl HandlingScopes {
+    /// The next ID, with the counter part `candidate` if there is one, see [`id_candidate`].
+    fn next_id(&mut self, candidate: Option<u64>) -> u64 {
+        (u64::from(self.epoch) << COUNTER_BITS) | self.next_counter(candidate)
+    }
+
+    /// The counter part of the next ID, never the counter of [`TAKEN_ID`].
+    fn next_counter(&mut self, candidate: Option<u64>) -> u64 {
+        if let Some(counter) = candidate {
+            return counter % COUNTER_RANGE;
+        }
+        self.error_id = ((u64::from(self.error_id) + 1) % COUNTER_RANGE) as u32;
+        u64::from(self.error_id)
+    }
+}
+
+/// The counter part of the next ID from the installed [`sim`](crate::sim) ID source, `None` to
+/// use the counter.
+///
+/// Taken before `CONTEXTS` is borrowed, so the source may use the scopes.
+#[inline(always)]
+fn id_candidate() -> Option<u64> {
+    #[cfg(feature = "simulation")]
+    return crate::sim::next_id();
+    #[cfg(not(feature = "simulation"))]
+    None
+}
+
+/// The number of new errors raised on this thread so far, raising captured errors again
+/// doesn't count.
+pub(crate) fn raise_count() -> u64 {
+    CONTEXTS.with(|contexts| contexts.borrow().raises)
+}
+
+/// Take a fresh error ID without raising anything.
+pub(crate) fn fresh_id() -> u64 {
+    let candidate = id_candidate();
+    CONTEXTS.with(|contexts| contexts.borrow_mut().next_id(candidate))
+}
+
+/// Restores the enclosing operation when dropped, see [`enter_operation`].
+#[must_use = "the operation ends when this is dropped"]
+pub struct OperationGuard {
+    token: u64,
+    previous: u64,
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl OperationGuard {
+    /// The token of the entered operation.
+    pub fn token(&self) -> u64 {
+        self.token
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        let _ = CONTEXTS.try_with(|contexts| contexts.borrow_mut().operation = self.previous);
+    }
+}
+
+/// Start a new isolated operation on the current thread.
+///
+/// Every error raised until the guard is dropped is stamped with the token of the operation,
+/// see [`ReportedError::operation`]. Tokens are never reused on a thread, and operations entered
+/// while another is active get their own token.
+pub fn enter_operation() -> OperationGuard {
+    CONTEXTS.with(|contexts| {
+        let mut ctx = contexts.borrow_mut();
+        ctx.last_operation += 1;
+        let token = ctx.last_operation;
+        let previous = std::mem::replace(&mut ctx.operation, token);
+        OperationGuard {
+            token,
+            previous,
+            _not_send: PhantomData,
+        }
+    })
+}
+
+type ExtVisitor<'v> = dyn FnMut(Option<&mut dyn ContextExt>) -> bool + 'v;
+
+/// A handling scope in the list of scopes of a thread.
+///
+/// Created unregistered by [`ScopeNode::new`], see [`UnregisteredScope`].
 pub struct ScopeNode
 {
     context: *mut (),
     try_set_error: unsafe fn(*mut (), &ReportedError) -> TrySetErrorResult,
+    visit_ext: unsafe fn(*mut (), &mut ExtVisitor) -> bool,
     next: *mut ScopeNode,
+    epoch: u32,
+    #[cfg(feature = "paranoid")]
+    registered: bool,
 }
 
 unsafe fn try_set_error_impl<Ctx: ErrorHandlingContext>(ctx: *mut (), error: &ReportedError) -> TrySetErrorResult {
     (*(ctx as *mut Ctx)).try_set_error(error)
 }
 
+unsafe fn visit_ext_impl<Ctx: ErrorHandlingContext>(ctx: *mut (), visitor: &mut ExtVisitor) -> bool {
+    visitor((*(ctx as *mut Ctx)).ext())
+}
+
 impl ScopeNode {
-    pub fn new<Ctx: ErrorHandlingContext>(context: &mut Ctx) -> Self {
+    /// Create a scope delivering errors to `context`, to be registered with
+    /// [`UnregisteredScope::register`].
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<Ctx: ErrorHandlingContext>(context: &mut Ctx) -> UnregisteredScope<'_> {
+        UnregisteredScope {
+            node: Self::raw(context),
+            _context: PhantomData,
+            _pinned: PhantomPinned,
+        }
+    }
+
+    /// Create a scope for [`push_scope`], which nothing ties to `context`.
+    pub(crate) fn raw<Ctx: ErrorHandlingContext>(context: &mut Ctx) -> Self {
         Self {
             context: context as *mut _ as *mut (),
             try_set_error: try_set_error_impl::<Ctx>,
+            visit_ext: visit_ext_impl::<Ctx>,
             next: core::ptr::null_mut(),
+            epoch: 0,
+            #[cfg(feature = "paranoid")]
+            registered: false,
         }
     }
 
+    /// Test if the error `id` was raised before this scope was pushed, with a scope left in
+    /// between.
+    ///
+    /// Such an error was never offered to this scope. Subject to the same 2^31 pop limit as
+    /// [`RegisteredScope::predates`].
+    pub(crate) fn predates(&self, id: crate::ErrorId) -> bool {
+        epoch_precedes(epoch_of(id.to_raw()), self.epoch)
+    }
+
     unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
         (self.try_set_error)(self.context, error)
     }
+
+    unsafe fn visit_ext(&mut self, visitor: &mut ExtVisitor) -> bool {
+        (self.visit_ext)(self.context, visitor)
+    }
+}
+
+#[cfg(feature = "paranoid")]
+impl Drop for ScopeNode {
+    fn drop(&mut self) {
+        // The guard was forgotten, so the list would point to a dead scope and context
+        if self.registered && !std::thread::panicking() {
+            panic!("`ScopeNode` dropped while still registered");
+        }
+    }
 }
 
 thread_local! {
-    static CONTEXTS: RefCell<HandlingScopes> = RefCell::new(HandlingScopes::new());
+    // Const initialized so the first raise on a thread doesn't have to initialize it
+    static CONTEXTS: RefCell<HandlingScopes> = const { RefCell::new(HandlingScopes::new()) };
+}
+
+/// A scope created by [`ScopeNode::new`], not registered yet.
+///
+/// Registering needs the scope pinned, so it can't move while the thread's list of scopes points
+/// to it, and the returned [`RegisteredScope`] borrows it, so it can't be registered twice. The
+/// context is borrowed for as long as the scope exists.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::context::{ScopeNode, SingleErrorStorage};
+///
+/// let mut storage = SingleErrorStorage::<u8>::default();
+/// let res: xcept::Result<u8> = {
+///     let scope = std::pin::pin!(ScopeNode::new(&mut storage));
+///     // Safety: the guard is dropped at the end of the block
+///     let _guard = unsafe { scope.register() };
+///     xcept::Result::new_error(3u8)
+/// };
+/// assert_eq!(storage.try_handle(res, |err| xcept::Result::new(err)).unwrap(), 3);
+/// ```
+///
+/// A scope that isn't pinned can't be registered:
+///
+/// ```compile_fail,E0277
+/// use xcept::context::{ScopeNode, SingleErrorStorage};
+///
+/// let mut storage = SingleErrorStorage::<u8>::default();
+/// let mut scope = ScopeNode::new(&mut storage);
+/// let _guard = unsafe { std::pin::Pin::new(&mut scope).register() };
+/// ```
+///
+/// A registered scope can't be moved while its guard is alive:
+///
+/// ```compile_fail,E0505
+/// use xcept::context::{ScopeNode, SingleErrorStorage};
+///
+/// let mut storage = SingleErrorStorage::<u8>::default();
+/// let mut scope = ScopeNode::new(&mut storage);
+/// let guard = unsafe { std::pin::Pin::new_unchecked(&mut scope).register() };
+/// let moved = scope;
+/// drop(guard);
+/// ```
+///
+/// Nor registered twice:
+///
+/// ```compile_fail,E0499
+/// use xcept::context::{ScopeNode, SingleErrorStorage};
+///
+/// let mut storage = SingleErrorStorage::<u8>::default();
+/// let mut scope = std::pin::pin!(ScopeNode::new(&mut storage));
+/// let first = unsafe { scope.as_mut().register() };
+/// let second = unsafe { scope.as_mut().register() };
+/// drop(first);
+/// ```
+pub struct UnregisteredScope<'ctx> {
+    node: ScopeNode,
+    _context: PhantomData<&'ctx mut ()>,
+    _pinned: PhantomPinned,
+}
+
+impl UnregisteredScope<'_> {
+    /// Register the scope as the innermost scope of the thread, until the returned guard is
+    /// dropped.
+    ///
+    /// The scope can be registered again once the guard is dropped.
+    ///
+    /// # Safety
+    ///
+    /// The returned guard must be dropped, it must not be forgotten. A forgotten guard leaves the
+    /// scope registered after the scope and its context are gone.
+    ///
+    /// With the `paranoid` feature, guards dropped out of order panic.
+    pub unsafe fn register(self: Pin<&mut Self>) -> RegisteredScope<'_> {
+        // Safety: the node is never moved out of the pinned scope
+        let node = &mut self.get_unchecked_mut().node;
+        RegisteredScope {
+            guard: push_scope(node),
+            _scope: PhantomData,
+        }
+    }
+}
+
+/// A registered scope, unregistered when dropped. Returned by [`UnregisteredScope::register`].
+pub struct RegisteredScope<'scope> {
+    guard: PopScopeGuard,
+    _scope: PhantomData<&'scope mut ()>,
+}
+
+impl RegisteredScope<'_> {
+    /// The scope epoch of the thread when the scope was registered, see [`current_epoch`].
+    pub fn epoch(&self) -> u32 {
+        // Safety: the node is alive and pinned while the guard borrows it
+        unsafe { (*self.guard.0).epoch }
+    }
+
+    /// Test if the error `id` was raised before the scope was registered, with a scope left in
+    /// between.
+    ///
+    /// Such an error was never offered to this scope. Only reliable for errors raised fewer than
+    /// 2^31 scope pops before the scope was registered, older errors wrap around to look recent,
+    /// see [`epoch_precedes`].
+    pub fn predates(&self, id: crate::ErrorId) -> bool {
+        epoch_precedes(epoch_of(id.to_raw()), self.epoch())
+    }
+}
+
+/// Push a scope created by [`ScopeNode::new`] to the list of scopes.
+///
+/// # Safety
+///
+/// `scope` must not be moved or dropped until the returned guard is dropped, and the guard must
+/// be dropped, it must not be forgotten.
+#[deprecated(note = "pin the scope and use `UnregisteredScope::register` instead")]
+#[doc(hidden)]
+pub unsafe fn push_handling_scope(scope: &mut UnregisteredScope<'_>) -> PopScopeGuard {
+    push_scope(&mut scope.node)
 }
 
 /// Push a new error handling scope to the list of scopes
@@ -177,10 +1410,19 @@ thread_local! {
 ///   * The context that `scope` refers to must be kept alive until the guard is dropped
 ///   * The returned guard must be dropped, it must not be forgotten.
 ///
-pub unsafe fn push_handling_scope(scope: &mut ScopeNode) -> PopScopeGuard {
+/// With the `paranoid` feature, guards dropped out of order or more than once, and scopes dropped
+/// while still registered, panic instead.
+///
+pub(crate) unsafe fn push_scope(scope: &mut ScopeNode) -> PopScopeGuard {
     CONTEXTS.with(move |contexts| {
         let mut ctx = contexts.borrow_mut();
+        #[cfg(feature = "paranoid")]
+        {
+            assert!(!scope.registered, "`ScopeNode` pushed while already registered");
+            scope.registered = true;
+        }
         scope.next = ctx.scopes;
+        scope.epoch = ctx.epoch;
         ctx.scopes = scope;
         PopScopeGuard(scope)
     })
@@ -195,20 +1437,68 @@ pub unsafe fn push_handling_scope(scope: &mut ScopeNode) -> PopScopeGuard {
 unsafe fn pop_handling_scope(scope: *mut ScopeNode) {
     CONTEXTS.with(move |contexts| {
         let mut ctx = contexts.borrow_mut();
+        #[cfg(feature = "paranoid")]
+        {
+            assert!((*scope).registered, "`PopScopeGuard` dropped twice");
+            assert!(ctx.scopes == scope, "popped a scope that is not the innermost one");
+            (*scope).registered = false;
+        }
         ctx.scopes = (*scope).next;
+        ctx.epoch = ctx.epoch.wrapping_add(1);
+    })
+}
+
+/// The scope state of the thread, restored by [`restore_scopes`].
+pub(crate) struct ScopesSnapshot {
+    scopes: *mut ScopeNode,
+    operation: u64,
+    suppress_secondary: bool,
+}
+
+pub(crate) fn snapshot_scopes() -> ScopesSnapshot {
+    CONTEXTS.with(|contexts| {
+        let ctx = contexts.borrow();
+        ScopesSnapshot {
+            scopes: ctx.scopes,
+            operation: ctx.operation,
+            suppress_secondary: ctx.suppress_secondary,
+        }
     })
 }
 
+/// Forget every scope pushed after `snapshot` was taken, as if they were all popped.
+///
+/// # Safety
+///
+/// `snapshot` must have been taken on this thread, and the scopes it refers to must still be
+/// registered.
+pub(crate) unsafe fn restore_scopes(snapshot: ScopesSnapshot) {
+    CONTEXTS.with(|contexts| {
+        let mut ctx = contexts.borrow_mut();
+        if ctx.scopes != snapshot.scopes {
+            ctx.scopes = snapshot.scopes;
+            ctx.epoch = ctx.epoch.wrapping_add(1);
+        }
+        ctx.operation = snapshot.operation;
+        ctx.suppress_secondary = snapshot.suppress_secondary;
+    });
+}
+
 /// Scope guard to automatically pop a scope when it is destroyed.
 ///
-/// This is created by pushing scopes and then manually dropping the guard.
+/// Returned by the deprecated `push_handling_scope`, see [`RegisteredScope`].
 pub struct PopScopeGuard(*mut ScopeNode);
 
 impl Drop for PopScopeGuard {
     fn drop(&mut self) {
-        // Safety: the guard is only created by `push_handling_scope`
+        // Safety: the guard is only created by `push_scope`
         // and the safety guarantees required by that function extends to the guard
-        unsafe { pop_handling_scope(self.0) }
+        unsafe {
+            if std::thread::panicking() {
+                report_abandoned(self.0);
+            }
+            pop_handling_scope(self.0)
+        }
     }
 }
 
@@ -216,31 +1506,794 @@ unsafe fn try_scope(scope: *mut ScopeNode, err: &ReportedError) -> TrySetErrorRe
     (*scope).try_set_error(err)
 }
 
-pub fn push_error<E: crate::Error>(mut err: E) -> u32 {
-    CONTEXTS.with(move |contexts| {
+/// Offer an error to all scopes, innermost first.
+///
+/// If the returned disposition is `Captured` a scope took ownership of the value and it must be
+/// forgotten. Otherwise the caller must drop it.
+///
+/// # Safety
+///
+/// All scopes must be kept alive by the contract of push and pop scope
+unsafe fn deliver(scopes: *mut ScopeNode, error: &ReportedError) -> Disposition {
+    let mut iter = scopes;
+    while !iter.is_null() {
+        match try_scope(iter, error) {
+            TrySetErrorResult::NotHandled => {}
+            result => {
+                let disposition = Disposition::from_result(&result);
+                (*iter).visit_ext(&mut |ext| {
+                    if let Some(ext) = ext {
+                        ext.on_disposition(error.id(), disposition);
+                    }
+                    false
+                });
+                return disposition;
+            }
+        }
+        iter = (*iter).next
+    }
+    Disposition::Unhandled
+}
+
+/// Visit the extensions of the active scopes, innermost first, until `visitor` returns `false`.
+///
+/// Nothing is visited while an error is being raised on this thread.
+fn visit_scope_exts(visitor: &mut ExtVisitor) {
+    let scopes = CONTEXTS.with(|contexts| contexts.try_borrow().map_or(core::ptr::null_mut(), |ctx| ctx.scopes));
+    // Safety: All scopes must be kept alive by the contract of push and pop scope, and none are
+    // pushed or popped while visiting
+    unsafe { visit_exts_from(scopes, visitor) }
+}
+
+/// # Safety
+///
+/// All scopes must be kept alive by the contract of push and pop scope
+unsafe fn visit_exts_from(scopes: *mut ScopeNode, visitor: &mut ExtVisitor) {
+    let mut iter = scopes;
+    while !iter.is_null() {
+        if !(*iter).visit_ext(visitor) {
+            return;
+        }
+        iter = (*iter).next;
+    }
+}
+
+/// # Safety
+///
+/// All scopes must be kept alive by the contract of push and pop scope
+unsafe fn pending_in(scopes: *mut ScopeNode) -> bool {
+    let mut pending = false;
+    visit_exts_from(scopes, &mut |ext| {
+        pending = ext.is_some_and(|ext| ext.has_pending());
+        !pending
+    });
+    pending
+}
+
+/// Test if the active scopes would accept an error of type `E`.
+///
+/// Returns the answer of the innermost scope that knows, or `None` if a scope that can't tell
+/// is found first. `Some(false)` means no active scope accepts it.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::context::scopes_accept;
+///
+/// assert_eq!(scopes_accept::<i32>(), Some(false));
+/// let res = xcept::try_or_handle_one(
+///     || xcept::Result::new(scopes_accept::<i32>()),
+///     |_: i32| xcept::Result::new(None),
+/// );
+/// assert_eq!(res.unwrap(), Some(true));
+/// ```
+pub fn scopes_accept<E: crate::Error>() -> Option<bool> {
+    let mut answer = Some(false);
+    visit_scope_exts(&mut |ext| match ext.and_then(|ext| ext.accepts(TypeId::of::<E>())) {
+        Some(false) => true,
+        known => {
+            answer = known;
+            false
+        }
+    });
+    answer
+}
+
+/// Test if any active scope holds a captured error that hasn't been handled yet.
+///
+/// Scopes whose contexts don't implement [`ContextExt`] are never pending.
+pub fn scopes_pending() -> bool {
+    let scopes = CONTEXTS.with(|contexts| contexts.try_borrow().map_or(core::ptr::null_mut(), |ctx| ctx.scopes));
+    // Safety: All scopes must be kept alive by the contract of push and pop scope
+    unsafe { pending_in(scopes) }
+}
+
+/// Mark the error `error_id` as replaced by a later alternative, like [`Result::or_try`] does.
+///
+/// Every active context is told through [`ContextExt::on_disposition`] with
+/// [`Disposition::Superseded`], so a context still holding the error drops it instead of
+/// reporting it as pending or abandoned. With the `diagnostics` feature the disposition of the
+/// raise in [`recent_raises`](crate::debug::recent_raises) is updated too.
+///
+/// [`Result::or_try`]: crate::Result::or_try
+pub fn supersede(error_id: crate::ErrorId) {
+    visit_scope_exts(&mut |ext| {
+        if let Some(ext) = ext {
+            ext.on_disposition(error_id, Disposition::Superseded);
+        }
+        true
+    });
+    #[cfg(feature = "metadata")]
+    crate::metadata::superseded(error_id.to_raw());
+}
+
+/// The number of captured errors that haven't been handled yet, summed over the active scopes
+/// of the current thread.
+///
+/// A cheap liveness probe for scopes that capture errors and never drain them. Scopes whose
+/// contexts don't implement [`ContextExt`] count as zero, see [`ContextExt::pending_count`].
+///
+/// # Examples
+///
+/// ```
+/// let res = xcept::try_or_handle_one(
+///     || {
+///         let _ = xcept::Result::<usize>::new_error(1u8);
+///         xcept::Result::new(xcept::pending_count())
+///     },
+///     |_: u8| xcept::Result::new(0),
+/// );
+/// assert_eq!(res.unwrap(), 1);
+/// assert_eq!(xcept::pending_count(), 0);
+/// ```
+pub fn pending_count() -> usize {
+    let mut count = 0;
+    visit_scope_exts(&mut |ext| {
+        count += ext.map_or(0, |ext| ext.pending_count());
+        true
+    });
+    count
+}
+
+struct PendingAlarm {
+    threshold: usize,
+    armed: bool,
+    callback: Box<dyn FnMut(usize)>,
+}
+
+thread_local! {
+    static PENDING_ALARM: RefCell<Option<PendingAlarm>> = const { RefCell::new(None) };
+}
+
+/// Call `callback` with the [`pending_count`] when a capture on the current thread brings it to
+/// `threshold` or above.
+///
+/// The callback runs once per crossing, it is armed again by a capture that leaves the count
+/// below `threshold`. Replaces the alarm set on this thread before, if any.
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+///
+/// let alarms = Rc::new(Cell::new(0));
+/// let counter = alarms.clone();
+/// xcept::set_pending_alarm(2, move |_| counter.set(counter.get() + 1));
+///
+/// let mut storage = xcept::batch::VecErrorStorage::<u8>::default();
+/// storage.run(|| {
+///     for i in 0..4u8 {
+///         let _ = xcept::Result::<()>::new_error(i);
+///     }
+/// });
+/// assert_eq!(alarms.get(), 1);
+/// xcept::clear_pending_alarm();
+/// ```
+pub fn set_pending_alarm(threshold: usize, callback: impl FnMut(usize) + 'static) {
+    PENDING_ALARM.with(|alarm| {
+        *alarm.borrow_mut() = Some(PendingAlarm {
+            threshold,
+            armed: true,
+            callback: Box::new(callback),
+        })
+    });
+}
+
+/// Remove the alarm set with [`set_pending_alarm`] on the current thread.
+pub fn clear_pending_alarm() {
+    let _ = PENDING_ALARM.try_with(|alarm| alarm.borrow_mut().take());
+}
+
+/// Check the pending alarm after a scope accepted an error.
+fn check_pending_alarm() {
+    let _ = PENDING_ALARM.try_with(|alarm| {
+        // Already borrowed while the callback raises an error
+        let Ok(mut alarm) = alarm.try_borrow_mut() else {
+            return;
+        };
+        let Some(alarm) = alarm.as_mut() else {
+            return;
+        };
+        let count = pending_count();
+        if count < alarm.threshold {
+            alarm.armed = true;
+        } else if alarm.armed {
+            alarm.armed = false;
+            (alarm.callback)(count);
+        }
+    });
+}
+
+struct SecondaryGuard {
+    previous: bool,
+}
+
+impl Drop for SecondaryGuard {
+    fn drop(&mut self) {
+        let _ = CONTEXTS.try_with(|contexts| contexts.borrow_mut().suppress_secondary = self.previous);
+    }
+}
+
+/// Execute `func`, dropping every error it raises while an active scope has a pending error.
+///
+/// Useful in cleanup code, where a secondary error must not mask the error that caused the
+/// cleanup. Dropped errors still produce an error `Result`, but no scope sees them. See also
+/// [`Result::new_error_if_clear`](crate::Result::new_error_if_clear).
+///
+/// # Examples
+///
+/// ```
+/// let handlers = xcept::builder(|err: &str| xcept::Result::new(err)).build();
+/// let res = xcept::try_or_handle(
+///     || {
+///         let primary: xcept::Result<()> = xcept::Result::new_error("primary");
+///         xcept::suppressing_secondary(|| {
+///             let _: xcept::Result<()> = xcept::Result::new_error("cleanup failed");
+///         });
+///         primary.and(xcept::Result::new("unreachable"))
+///     },
+///     handlers,
+/// );
+/// assert_eq!(res.unwrap(), "primary");
+/// ```
+pub fn suppressing_secondary<T>(func: impl FnOnce() -> T) -> T {
+    let previous = CONTEXTS.with(|contexts| std::mem::replace(&mut contexts.borrow_mut().suppress_secondary, true));
+    let guard = SecondaryGuard { previous };
+    let res = func();
+    drop(guard);
+    res
+}
+
+/// The context installed by [`panic_on_raise`].
+struct PanicOnRaise;
+
+impl ErrorHandlingContext for PanicOnRaise {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        // Panicking here, instead of after the walk, keeps the raising frame on the stack
+        let summary = crate::redact::summarize(error);
+        match error.location() {
+            Some(location) => panic!("error {} raised where errors are bugs: {} at {}", error.id(), summary, location),
+            None => panic!("error {} raised where errors are bugs: {}", error.id(), summary),
+        }
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        Some(self)
+    }
+}
+
+impl ContextExt for PanicOnRaise {
+    fn accepts(&mut self, _type_id: TypeId) -> Option<bool> {
+        Some(true)
+    }
+
+    fn would_accept(&mut self, _descriptor: &ReportedErrorDescriptor) -> Acceptance {
+        Acceptance::Accept
+    }
+
+    fn debug_name(&self) -> &'static str {
+        "PanicOnRaise"
+    }
+}
+
+/// Execute `func`, panicking on every error it raises that no scope inside `func` accepts.
+///
+/// Meant for regions where any error means a bug, like corrupted in-memory state, which should
+/// crash loudly instead of reaching a distant generic handler. The panic message holds the error
+/// ID, the type name, the content rendered through [`crate::redact`] and, with the `diagnostics`
+/// feature, the raise location. The panic starts inside the raise, so backtraces show the raiser.
+///
+/// The error value is not dropped while the panic unwinds through the raise, it is leaked. The
+/// raise path only drops values that no scope accepted, and the raise is abandoned before that
+/// decision, so the value can't be dropped twice.
+///
+/// # Examples
+///
+/// ```
+/// struct Corrupted;
+///
+/// let res = std::panic::catch_unwind(|| {
+///     xcept::panic_on_raise(|| -> xcept::Result<()> { xcept::Result::new_error(Corrupted) })
+/// });
+/// let message = res.err().unwrap().downcast::<String>().unwrap();
+/// assert!(message.contains("Corrupted"));
+/// ```
+pub fn panic_on_raise<T>(func: impl FnOnce() -> T) -> T {
+    let mut ctx = PanicOnRaise;
+    let scope = std::pin::pin!(ScopeNode::new(&mut ctx));
+    // Safety: the guard is dropped on return, also when unwinding
+    let _guard = unsafe { scope.register() };
+    func()
+}
+
+type Probe = Box<dyn FnMut(*const ())>;
+
+thread_local! {
+    static PROBES: RefCell<Vec<(usize, TypeId, Probe)>> = const { RefCell::new(Vec::new()) };
+    static NEXT_PROBE: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A probe registered with [`on_raise`], unregistered when dropped.
+#[must_use = "the probe is unregistered when this is dropped"]
+pub struct RaiseProbe {
+    key: usize,
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl Drop for RaiseProbe {
+    fn drop(&mut self) {
+        let _ = PROBES.try_with(|probes| {
+            probes.borrow_mut().retain(|(key, _, _)| *key != self.key);
+        });
+    }
+}
+
+/// Run `probe` on every error of type `E` raised on the current thread.
+///
+/// The probe runs before the error is delivered to any handling scope, and doesn't affect the
+/// delivery. Errors raised from within a probe are not probed. Probes must not register or
+/// unregister other probes.
+///
+/// # Examples
+///
+/// ```
+/// let probe = xcept::on_raise(|err: &&str| println!("raised: {}", err));
+/// let _: xcept::Result<()> = xcept::Result::new_error("failed");
+/// drop(probe);
+/// ```
+pub fn on_raise<E, F>(mut probe: F) -> RaiseProbe
+where
+    E: crate::Error,
+    F: FnMut(&E) + 'static,
+{
+    let key = NEXT_PROBE.with(|next| next.replace(next.get() + 1));
+    let probe: Probe = Box::new(move |err| probe(unsafe { &*(err as *const E) }));
+    PROBES.with(|probes| probes.borrow_mut().push((key, TypeId::of::<E>(), probe)));
+    RaiseProbe {
+        key,
+        _not_send: PhantomData,
+    }
+}
+
+/// An error captured by a scope that was left by a panic before the error was handled.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Abandoned {
+    /// The type name of the error, if the context knows it
+    pub type_name: Option<&'static str>,
+    /// The error rendered through [`crate::redact`], if the context can render it
+    pub summary: Option<crate::redact::RedactedSummary>,
+    /// The debug name of the context that captured the error, see [`ContextExt::debug_name`]
+    pub context: &'static str,
+}
+
+type AbandonedHookFn = Box<dyn FnMut(&Abandoned)>;
+
+thread_local! {
+    static ABANDONED_HOOKS: RefCell<Vec<(usize, AbandonedHookFn)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A hook registered with [`on_abandoned`], unregistered when dropped.
+#[must_use = "the hook is unregistered when this is dropped"]
+pub struct AbandonedHook {
+    key: usize,
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl Drop for AbandonedHook {
+    fn drop(&mut self) {
+        let _ = ABANDONED_HOOKS.try_with(|hooks| {
+            hooks.borrow_mut().retain(|(key, _)| *key != self.key);
+        });
+    }
+}
+
+/// Run `hook` when a panic unwinds out of a scope holding a captured, unhandled error.
+///
+/// Such an error is dropped with the scope's context without reaching any handler or outer
+/// scope. The hook runs while the thread is panicking, so it must not panic itself. Only
+/// contexts implementing [`ContextExt::has_pending`] are detected.
+///
+/// # Examples
+///
+/// ```
+/// let hook = xcept::context::on_abandoned(|abandoned| eprintln!("lost {:?}", abandoned.type_name));
+/// let res = std::panic::catch_unwind(|| {
+///     xcept::try_or_handle_one(
+///         || {
+///             let _: xcept::Result<()> = xcept::Result::new_error(5);
+///             std::panic::resume_unwind(Box::new("cancelled"))
+///         },
+///         |_: i32| xcept::Result::new(()),
+///     )
+/// });
+/// assert!(res.is_err());
+/// drop(hook);
+/// ```
+pub fn on_abandoned<F>(hook: F) -> AbandonedHook
+where
+    F: FnMut(&Abandoned) + 'static,
+{
+    let key = NEXT_PROBE.with(|next| next.replace(next.get() + 1));
+    ABANDONED_HOOKS.with(|hooks| hooks.borrow_mut().push((key, Box::new(hook))));
+    AbandonedHook {
+        key,
+        _not_send: PhantomData,
+    }
+}
+
+/// # Safety
+///
+/// `scope` must be registered and kept alive by the contract of push and pop scope
+unsafe fn report_abandoned(scope: *mut ScopeNode) {
+    let mut abandoned = None;
+    (*scope).visit_ext(&mut |ext| {
+        if let Some(ext) = ext {
+            if ext.has_pending() {
+                abandoned = Some(Abandoned {
+                    type_name: ext.pending_type_name(),
+                    summary: ext.pending_summary(),
+                    context: ext.debug_name(),
+                });
+            }
+        }
+        false
+    });
+    if let Some(abandoned) = abandoned {
+        let _ = ABANDONED_HOOKS.try_with(|hooks| {
+            if let Ok(mut hooks) = hooks.try_borrow_mut() {
+                for (_, hook) in hooks.iter_mut() {
+                    hook(&abandoned);
+                }
+            }
+        });
+        crate::fatal::abandoned(&abandoned);
+    }
+}
+
+/// Test if a probe is registered for `E`, without running it.
+fn has_probes<E: crate::Error>() -> bool {
+    PROBES
+        .try_with(|probes| {
+            // Already borrowed while a probe raises an error, and then probes don't run
+            probes
+                .try_borrow()
+                .is_ok_and(|probes| probes.iter().any(|(_, type_id, _)| *type_id == TypeId::of::<E>()))
+        })
+        .unwrap_or(false)
+}
+
+fn run_probes<E: crate::Error>(err: &E) {
+    let _ = PROBES.try_with(|probes| {
+        // Already borrowed while a probe raises an error
+        if let Ok(mut probes) = probes.try_borrow_mut() {
+            for (_, type_id, probe) in probes.iter_mut() {
+                if *type_id == TypeId::of::<E>() {
+                    probe(err as *const E as *const ());
+                }
+            }
+        }
+    });
+}
+
+/// Options of a single raise, see [`push_error_with`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RaiseFlags(u8);
+
+impl RaiseFlags {
+    /// The default raise.
+    pub const NONE: Self = Self(0);
+    /// Only deliver the value to the scopes, without recording anything about the raise.
+    ///
+    /// Probes registered with [`on_raise`] aren't run, no location is recorded, and the raise
+    /// doesn't show up in the metadata, like [`recent_raises`](crate::debug::recent_raises).
+    pub const BARE: Self = Self(1);
+    /// Record everything about the raise that the enabled features record.
+    ///
+    /// Every raise that isn't [`BARE`](Self::BARE) is currently recorded in full, this keeps a
+    /// raise recorded if recording is ever reduced for other raises.
+    pub const TRACED: Self = Self(2);
+
+    /// Test if all flags of `other` are set in `self`.
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The flags set in either `self` or `other`.
+    #[inline]
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+// Kept out of line so raise sites only contain a call, raising is the rare path
+#[cfg_attr(feature = "metadata", track_caller)]
+#[cold]
+#[inline(never)]
+pub fn push_error<E: crate::Error>(err: E) -> crate::ErrorId {
+    crate::ErrorId::from_raw(raise(err, None, RaiseFlags::NONE, None))
+}
+
+/// Same as [`push_error`], letting observing contexts clone the value, see
+/// [`ReportedError::clone_value`].
+#[cfg_attr(feature = "metadata", track_caller)]
+#[cold]
+#[inline(never)]
+pub fn push_error_cloneable<E: crate::Error + Clone>(err: E) -> crate::ErrorId {
+    crate::ErrorId::from_raw(raise(err, None, RaiseFlags::NONE, Some(clone_value_impl::<E>)))
+}
+
+/// Same as [`push_error`], with the options in `flags`.
+#[cfg_attr(feature = "metadata", track_caller)]
+#[cold]
+#[inline(never)]
+pub fn push_error_with<E: crate::Error>(err: E, flags: RaiseFlags) -> crate::ErrorId {
+    crate::ErrorId::from_raw(raise(err, None, flags, None))
+}
+
+/// Same as [`push_error`], only creating the error with `make` if an active scope may accept it.
+///
+/// The scopes are asked with [`scopes_accept`], without delivering anything. Probes registered
+/// with [`on_raise`] need the value, so one for `E` has it created too. Otherwise, if no scope
+/// accepts an `E`, the raise is recorded as not handled by any scope without creating the value:
+/// it is counted, and the metadata consumers see it without the redacted content.
+#[cfg_attr(feature = "metadata", track_caller)]
+pub fn push_error_lazy<E: crate::Error>(make: impl FnOnce() -> E) -> crate::ErrorId {
+    if scopes_accept::<E>() != Some(false) || has_probes::<E>() {
+        return push_error(make());
+    }
+    let candidate = id_candidate();
+    let id = CONTEXTS.with(|contexts| {
+        let mut ctx = contexts.borrow_mut();
+        ctx.raises += 1;
+        ctx.next_id(candidate)
+    });
+    #[cfg(feature = "paranoid")]
+    {
+        crate::id_state::raised(crate::ErrorId::from_raw(id));
+        crate::id_state::delivered(crate::ErrorId::from_raw(id), Disposition::Unhandled);
+    }
+    #[cfg(feature = "metadata")]
+    let location = Some(Location::caller());
+    #[cfg(not(feature = "metadata"))]
+    let location = None;
+    #[cfg(feature = "metadata")]
+    crate::metadata::raised(
+        id,
+        TypeId::of::<E>(),
+        std::any::type_name::<E>(),
+        location,
+        Disposition::Unhandled,
+        crate::metadata::Eager::default(),
+    );
+    crate::fatal::unhandled(id, std::any::type_name::<E>(), location);
+    crate::ErrorId::from_raw(id)
+}
+
+/// Raise the portable representation of a stable error, carrying its stable identity.
+#[cfg_attr(feature = "metadata", track_caller)]
+#[cold]
+#[inline(never)]
+pub(crate) fn push_stable_error<E: crate::Error>(portable: E, stable_id: crate::stable_id::StableTypeId) -> u64 {
+    raise(portable, Some(stable_id), RaiseFlags::NONE, None)
+}
+
+#[cfg_attr(feature = "metadata", track_caller)]
+#[inline(always)]
+fn raise<E: crate::Error>(
+    err: E,
+    stable_id: Option<crate::stable_id::StableTypeId>,
+    flags: RaiseFlags,
+    clone_value: Option<CloneFn>,
+) -> u64 {
+    let bare = flags.contains(RaiseFlags::BARE);
+    #[cfg(feature = "metadata")]
+    let location = if bare { None } else { Some(std::panic::Location::caller()) };
+    if !bare {
+        run_probes(&err);
+    }
+    #[cfg(feature = "metadata")]
+    let eager = if bare {
+        crate::metadata::Eager::default()
+    } else {
+        crate::metadata::Eager::capture(&err)
+    };
+    let candidate = id_candidate();
+    let (id, disposition) = CONTEXTS.with(move |contexts| {
         let mut ctx = contexts.borrow_mut();
         let ctx = ctx.deref_mut();
 
-        ctx.error_id = ctx.error_id.wrapping_add(1);
-        let reported_error = ReportedError::new(ctx.error_id, &mut err);
+        let id = ctx.next_id(candidate);
+        ctx.raises += 1;
+        let mut err = ManuallyDrop::new(err);
+        let reported_error = ReportedError {
+            clone_value,
+            ..ReportedError::new(crate::ErrorId::from_raw(id), &mut *err)
+        };
+        let ext = ReportExt {
+            #[cfg(feature = "metadata")]
+            location,
+            #[cfg(not(feature = "metadata"))]
+            location: None,
+            operation: ctx.operation,
+            stable_id,
+        };
+        let reported_error = if ext.location.is_some() || ext.operation != 0 || stable_id.is_some() {
+            reported_error.with_ext(&ext)
+        } else {
+            reported_error
+        };
 
+        #[cfg(feature = "paranoid")]
+        crate::id_state::raised(reported_error.id());
         // Safety: All scopes must be kept alive by the contract of push and pop scope
-        let mut iter = ctx.scopes;
-        while !iter.is_null() {
-            match unsafe { try_scope(iter, &reported_error) } {
-                // SAFETY: We must ensure to forget err if we end up here!
-                TrySetErrorResult::NeedForget => {
-                    std::mem::forget(err);
-                    return reported_error.id;
-                },
-                TrySetErrorResult::NeedDrop => {
-                    drop(err);
-                    return reported_error.id
-                },
-                _ => {}
+        let disposition = unsafe {
+            if ctx.suppress_secondary && pending_in(ctx.scopes) {
+                Disposition::Suppressed
+            } else {
+                deliver(ctx.scopes, &reported_error)
             }
-            iter = unsafe { (*iter).next }
+        };
+        #[cfg(feature = "paranoid")]
+        crate::id_state::delivered(reported_error.id(), disposition);
+        // Flags and other types without drop glue need no bookkeeping here
+        if std::mem::needs_drop::<E>() && !disposition.is_captured() {
+            // SAFETY: no scope took the value, so we still own it
+            unsafe { ManuallyDrop::drop(&mut err) }
         }
-        reported_error.id
-    })
+        (id, disposition)
+    });
+    if matches!(disposition, Disposition::Captured | Disposition::Dropped) {
+        check_pending_alarm();
+    }
+    #[cfg(feature = "metadata")]
+    if !bare {
+        crate::metadata::raised(id, TypeId::of::<E>(), std::any::type_name::<E>(), location, disposition, eager);
+    }
+    if disposition == Disposition::Unhandled {
+        #[cfg(not(feature = "metadata"))]
+        let location = None;
+        crate::fatal::unhandled(id, std::any::type_name::<E>(), location);
+    }
+    id
+}
+
+/// Raise an erased error again, keeping its original ID.
+#[cold]
+#[inline(never)]
+pub fn push_erased_error(err: ErasedError) -> crate::ErrorId {
+    push_erased(err).0
+}
+
+/// Raise an erased error again like [`push_erased_error`], also returning its type for the
+/// `Result` of the raise.
+pub(crate) fn push_erased(mut err: ErasedError) -> (crate::ErrorId, &'static crate::ErrorType) {
+    let (id, error_type) = (err.id, err.error_type);
+    #[cfg(feature = "paranoid")]
+    crate::id_state::reraised(id);
+    #[cfg(feature = "metadata")]
+    let eager = crate::metadata::Eager::capture_erased(&err);
+    let disposition = CONTEXTS.with(|contexts| {
+        let ctx = contexts.borrow();
+        let ext = ReportExt {
+            location: None,
+            operation: ctx.operation,
+            stable_id: None,
+        };
+        // Safety: All scopes must be kept alive by the contract of push and pop scope
+        unsafe {
+            if ctx.suppress_secondary && pending_in(ctx.scopes) {
+                Disposition::Suppressed
+            } else {
+                deliver(ctx.scopes, &err.reported().with_ext(&ext))
+            }
+        }
+    });
+    #[cfg(feature = "paranoid")]
+    crate::id_state::delivered(id, disposition);
+    #[cfg(feature = "metadata")]
+    crate::metadata::raised(id.to_raw(), err.type_id(), err.type_name, None, disposition, eager);
+    if disposition == Disposition::Unhandled {
+        crate::fatal::unhandled(id.to_raw(), err.type_name, None);
+    }
+    if disposition.is_captured() {
+        // Safety: the value has been moved out, only the storage remains
+        unsafe { err.deallocate() };
+        std::mem::forget(err);
+    }
+    (id, error_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{current_epoch, HandlingScopes, ScopeNode, SingleErrorStorage, CONTEXTS, COUNTER_BITS, TAKEN_ID};
+
+    #[test]
+    fn taken_id_is_skipped() {
+        let mut scopes = HandlingScopes::new();
+        scopes.epoch = u32::MAX;
+        scopes.error_id = u32::MAX - 2;
+        assert_eq!(scopes.next_id(None), TAKEN_ID.to_raw() - 1);
+        assert_eq!(scopes.next_id(None), u64::from(u32::MAX) << COUNTER_BITS);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn source_never_produces_taken_id() {
+        let mut next = u64::from(u32::MAX) - 2;
+        let _ids = crate::sim::install_id_source(move || {
+            next += 1;
+            next
+        });
+        let mut scopes = HandlingScopes::new();
+        scopes.epoch = u32::MAX;
+        let epoch = u64::from(u32::MAX) << COUNTER_BITS;
+        assert_eq!(scopes.next_id(super::id_candidate()), TAKEN_ID.to_raw() - 1);
+        // Wraps like the counter instead of producing the reserved counter
+        assert_eq!(scopes.next_id(super::id_candidate()), epoch);
+        assert_eq!(scopes.next_id(super::id_candidate()), epoch + 1);
+    }
+
+    #[test]
+    fn stale_across_the_epoch_wrap() {
+        CONTEXTS.with(|contexts| contexts.borrow_mut().epoch = u32::MAX - 1);
+        let old: crate::Result<()> = crate::Result::new_error(1u8);
+        let mut storage = SingleErrorStorage::<u8>::default();
+        for _ in 0..3 {
+            let scope = std::pin::pin!(ScopeNode::new(&mut storage));
+            drop(unsafe { scope.register() });
+        }
+        assert_eq!(current_epoch(), 1);
+        assert!(old.is_stale());
+        let new: crate::Result<()> = crate::Result::new_error(2u8);
+        assert!(!new.is_stale());
+    }
+
+    #[test]
+    fn registered_scope() {
+        let mut storage = SingleErrorStorage::<u8>::default();
+        let old: crate::Result<()> = crate::Result::new_error(1u8);
+        let (first, second, third) = {
+            let mut scope = std::pin::pin!(ScopeNode::new(&mut storage));
+            let guard = unsafe { scope.as_mut().register() };
+            assert_eq!(guard.epoch(), current_epoch());
+            let first: crate::Result<()> = crate::Result::new_error(2u8);
+            assert!(!guard.predates(first.error_id().unwrap()));
+            drop(guard);
+
+            // Registered again once the guard is dropped
+            let guard = unsafe { scope.as_mut().register() };
+            assert!(guard.predates(old.error_id().unwrap()));
+            let second: crate::Result<()> = crate::Result::new_error(3u8);
+            drop(guard);
+            (first, second, crate::Result::<()>::new_error(4u8))
+        };
+
+        let (id, value) = storage.into_inner().unwrap();
+        assert_eq!((id, value), (second.error_id().unwrap(), 3));
+        assert!(first.is_error() && third.is_error());
+    }
 }
@@ -0,0 +1,252 @@
+//! Debugging aids, enabled by the `diagnostics` and `paranoid` features.
+//!
+//! With `diagnostics` every thread keeps a ring buffer with the most recent raises, which can be
+//! inspected with `recent_raises` or printed when a panic happens, see `dump_on_panic`.
+//!
+//! With `paranoid` the state of every error ID is tracked, see `id_state`. Capturing an error
+//! twice, or handling it twice, panics with the history of its ID.
+
+#[cfg(feature = "diagnostics")]
+use crate::context::Disposition;
+#[cfg(feature = "diagnostics")]
+use std::cell::RefCell;
+#[cfg(feature = "diagnostics")]
+use std::panic::Location;
+#[cfg(feature = "diagnostics")]
+use std::sync::Once;
+
+#[cfg(feature = "paranoid")]
+pub use crate::id_state::{id_state, IdState};
+
+#[cfg(feature = "diagnostics")]
+/// The number of raises kept per thread unless changed with [`set_capacity`].
+pub const DEFAULT_CAPACITY: usize = 32;
+
+#[cfg(feature = "diagnostics")]
+/// A single entry in the recent raises buffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RaiseRecord {
+    /// The ID of the raised error
+    pub id: crate::ErrorId,
+    /// The name of the type of the raised error
+    pub type_name: &'static str,
+    /// Where the error was raised, if known
+    pub location: Option<&'static Location<'static>>,
+    /// What happened to the error
+    pub disposition: Disposition,
+}
+
+#[cfg(feature = "diagnostics")]
+struct Ring {
+    records: Vec<RaiseRecord>,
+    capacity: usize,
+    next: usize,
+}
+
+#[cfg(feature = "diagnostics")]
+impl Ring {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            records: Vec::with_capacity(capacity),
+            capacity,
+            next: 0,
+        }
+    }
+
+    fn push(&mut self, record: RaiseRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        // The vector never grows past its initial capacity, so this never allocates
+        if self.records.len() < self.capacity {
+            self.records.push(record);
+        } else {
+            self.records[self.next] = record;
+        }
+        self.next = (self.next + 1) % self.capacity;
+    }
+
+    fn to_vec(&self) -> Vec<RaiseRecord> {
+        if self.records.len() < self.capacity {
+            self.records.clone()
+        } else {
+            let (newest, oldest) = self.records.split_at(self.next);
+            oldest.iter().chain(newest).copied().collect()
+        }
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+thread_local! {
+    static RECENT: RefCell<Ring> = RefCell::new(Ring::with_capacity(DEFAULT_CAPACITY));
+}
+
+#[cfg(feature = "diagnostics")]
+pub(crate) fn record(
+    id: u64,
+    type_name: &'static str,
+    location: Option<&'static Location<'static>>,
+    disposition: Disposition,
+) {
+    let _ = RECENT.try_with(|recent| {
+        if let Ok(mut recent) = recent.try_borrow_mut() {
+            recent.push(RaiseRecord {
+                id: crate::ErrorId::from_raw(id),
+                type_name,
+                location,
+                disposition,
+            });
+        }
+    });
+}
+
+#[cfg(feature = "diagnostics")]
+pub(crate) fn superseded(id: u64) {
+    let _ = RECENT.try_with(|recent| {
+        if let Ok(mut recent) = recent.try_borrow_mut() {
+            if let Some(record) = recent.records.iter_mut().find(|record| record.id.to_raw() == id) {
+                record.disposition = Disposition::Superseded;
+            }
+        }
+    });
+}
+
+#[cfg(feature = "diagnostics")]
+/// Set the number of raises kept for the current thread.
+///
+/// This clears the buffer. A capacity of 0 disables recording.
+pub fn set_capacity(capacity: usize) {
+    RECENT.with(|recent| *recent.borrow_mut() = Ring::with_capacity(capacity));
+}
+
+#[cfg(feature = "diagnostics")]
+/// Remove all recorded raises for the current thread.
+pub fn clear() {
+    RECENT.with(|recent| {
+        let capacity = recent.borrow().capacity;
+        *recent.borrow_mut() = Ring::with_capacity(capacity);
+    });
+}
+
+#[cfg(feature = "diagnostics")]
+/// The most recent raises on the current thread, oldest first.
+///
+/// # Examples
+///
+/// ```
+/// let _: xcept::Result<()> = xcept::Result::new_error(1);
+/// let recent = xcept::debug::recent_raises();
+/// assert_eq!(recent.last().unwrap().type_name, "i32");
+/// ```
+pub fn recent_raises() -> Vec<RaiseRecord> {
+    RECENT.with(|recent| recent.borrow().to_vec())
+}
+
+#[cfg(feature = "diagnostics")]
+/// Install a panic hook printing the recent raises of the panicking thread to stderr.
+///
+/// The previously installed hook runs after the raises are printed. Calling this more than once
+/// has no further effect.
+pub fn dump_on_panic() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let recent = RECENT
+                .try_with(|recent| recent.try_borrow().map(|r| r.to_vec()).unwrap_or_default())
+                .unwrap_or_default();
+            if !recent.is_empty() {
+                eprintln!("recent xcept raises on this thread (oldest first):");
+                for record in recent {
+                    match record.location {
+                        Some(location) => eprintln!(
+                            "  #{} {} at {} ({:?})",
+                            record.id, record.type_name, location, record.disposition
+                        ),
+                        None => eprintln!(
+                            "  #{} {} ({:?})",
+                            record.id, record.type_name, record.disposition
+                        ),
+                    }
+                }
+            }
+            previous(info);
+        }));
+    });
+}
+
+#[cfg(all(test, feature = "diagnostics"))]
+mod tests {
+    use crate::context::Disposition;
+
+    #[test]
+    fn records_raises() {
+        super::clear();
+        let line = line!() + 1;
+        let res: crate::Result<()> = crate::Result::new_error(1u8);
+        let _ = crate::try_or_handle_one(
+            || crate::Result::<()>::new_error("handled"),
+            |_: &str| crate::Result::new(()),
+        );
+
+        let recent = super::recent_raises();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(Some(recent[0].id), res.error_id());
+        assert_eq!(recent[0].type_name, "u8");
+        assert_eq!(recent[0].disposition, Disposition::Unhandled);
+        let location = recent[0].location.unwrap();
+        assert_eq!(location.file(), file!());
+        assert_eq!(location.line(), line);
+
+        assert_eq!(recent[1].type_name, "&str");
+        assert_eq!(recent[1].disposition, Disposition::Captured);
+    }
+
+    #[test]
+    fn evicts_oldest() {
+        super::set_capacity(3);
+        let ids: Vec<_> = (0..5)
+            .map(|i| crate::Result::<()>::new_error(i).error_id().unwrap())
+            .collect();
+
+        let recent: Vec<_> = super::recent_raises().iter().map(|r| r.id).collect();
+        assert_eq!(recent, ids[2..]);
+
+        super::set_capacity(0);
+        let _: crate::Result<()> = crate::Result::new_error(1);
+        assert!(super::recent_raises().is_empty());
+    }
+
+    #[test]
+    fn dump_on_panic_keeps_previous_hook() {
+        super::dump_on_panic();
+        super::dump_on_panic();
+        let _: crate::Result<()> = crate::Result::new_error("before panic");
+        let res = std::panic::catch_unwind(|| panic!("boom"));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn records_available() {
+        super::set_capacity(100);
+        let ids: Vec<_> = (0..80)
+            .map(|i| crate::Result::<()>::new_error(i).error_id().unwrap())
+            .collect();
+        let recent: Vec<_> = super::recent_raises().iter().map(|r| r.id).collect();
+        assert_eq!(recent, ids);
+
+        // Recorded before the handler runs
+        super::clear();
+        let seen = crate::try_or_handle_one(
+            || crate::Result::new_error(2u8),
+            |_: u8| crate::Result::new(super::RECENT.with(|recent| recent.borrow().to_vec())),
+        );
+        assert_eq!(seen.unwrap()[0].type_name, "u8");
+
+        let _: crate::Result<()> = crate::Result::new_error(3u16);
+        crate::flush_enrichment();
+        let last = super::RECENT.with(|recent| recent.borrow().to_vec()).pop().unwrap();
+        assert_eq!(last.type_name, "u16");
+        super::set_capacity(super::DEFAULT_CAPACITY);
+    }
+}
@@ -0,0 +1,122 @@
+//! Fallback values substituted at the raise site.
+//!
+//! [`with_default`] registers a value for the duration of a function. Raise points written with
+//! [`lookup_or_raise`] first look for a registered value, and continue with a clone of it instead
+//! of raising. Only when nothing is registered is the error created and raised.
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+
+struct Entry {
+    error: TypeId,
+    value: TypeId,
+    default: Box<dyn Any>,
+}
+
+thread_local! {
+    static DEFAULTS: RefCell<Vec<Entry>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Unregisters the defaults pushed after it, also when unwinding.
+struct Restore(usize);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let _ = DEFAULTS.try_with(|defaults| defaults.borrow_mut().truncate(self.0));
+    }
+}
+
+/// Execute `func` with `default` registered for errors of type `E` wanting a value of type `V`.
+///
+/// Nested registrations for the same pair override the outer ones until they end.
+///
+/// # Examples
+///
+/// ```
+/// struct MissingKey(&'static str);
+///
+/// fn port() -> xcept::Result<u16> {
+///     xcept::defaults::lookup_or_raise::<MissingKey, u16>(|| MissingKey("port"))
+/// }
+///
+/// assert_eq!(xcept::defaults::with_default::<MissingKey, u16, _>(8080, port).unwrap(), 8080);
+/// assert!(port().is_error());
+/// ```
+pub fn with_default<E, V, T>(default: V, func: impl FnOnce() -> T) -> T
+where
+    E: crate::Error,
+    V: Clone + 'static,
+{
+    let restore = DEFAULTS.with(|defaults| {
+        let mut defaults = defaults.borrow_mut();
+        defaults.push(Entry {
+            error: TypeId::of::<E>(),
+            value: TypeId::of::<V>(),
+            default: Box::new(default),
+        });
+        Restore(defaults.len() - 1)
+    });
+    let res = func();
+    drop(restore);
+    res
+}
+
+/// Continue with the innermost default registered for `E` and `V`, or raise the error.
+///
+/// `make_error` is only called if no default is registered.
+#[cfg_attr(feature = "metadata", track_caller)]
+pub fn lookup_or_raise<E, V>(make_error: impl FnOnce() -> E) -> crate::Result<V>
+where
+    E: crate::Error,
+    V: Clone + 'static,
+{
+    let default = DEFAULTS.with(|defaults| {
+        defaults
+            .borrow()
+            .iter()
+            .rev()
+            .find(|entry| entry.error == TypeId::of::<E>() && entry.value == TypeId::of::<V>())
+            .and_then(|entry| entry.default.downcast_ref::<V>())
+            .cloned()
+    });
+    match default {
+        Some(value) => crate::Result::new(value),
+        None => crate::Result::new_error(make_error()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lookup_or_raise, with_default};
+
+    struct MissingKey(&'static str);
+
+    fn host() -> crate::Result<String> {
+        lookup_or_raise::<MissingKey, String>(|| MissingKey("db.host"))
+    }
+
+    #[test]
+    fn innermost_wins() {
+        let hosts = with_default::<MissingKey, String, _>("outer".into(), || {
+            let outer = host().unwrap();
+            let inner = with_default::<MissingKey, String, _>("inner".into(), || host().unwrap());
+            // A default for another value type doesn't apply
+            let other = with_default::<MissingKey, u16, _>(1, || host().unwrap());
+            (outer, inner, other, host().unwrap())
+        });
+        assert_eq!(hosts, ("outer".into(), "inner".into(), "outer".into(), "outer".into()));
+    }
+
+    #[test]
+    fn unregistered_raises() {
+        let res = crate::try_or_handle_one(host, |MissingKey(key)| crate::Result::new(format!("no {key}")));
+        assert_eq!(res.unwrap(), "no db.host");
+
+        // Registrations end with their scope, also when unwinding
+        let unwound = std::panic::catch_unwind(|| {
+            with_default::<MissingKey, String, ()>("lost".into(), || std::panic::resume_unwind(Box::new(())))
+        });
+        assert!(unwound.is_err());
+        assert!(host().is_error());
+    }
+}
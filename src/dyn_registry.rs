@@ -0,0 +1,587 @@
+//! Handlers registered by type name, for scripting and configuration layers.
+//!
+//! Code that only knows error types by name, like an embedded script, can't name `E` in
+//! [`DynBuilder::handle`]. The types it may refer to are registered under a name with
+//! [`register_type`], shared by all threads, and [`handle_by_name`] adds a handler receiving an
+//! [`ErasedErrorInfo`] for the type with that name.
+//!
+//! Types can also be grouped in equivalence classes, for errors that are the same kind of failure
+//! but wrapped in different types, like the `io::Error` newtypes of several crates. Types join a
+//! class with [`register_equivalence`] or [`register_equivalence_with`], and [`handle_class`] and
+//! [`handle_class_as`] add a handler for every type of a class.
+
+use crate::context::{ErasedError, ReportedError, TrySetErrorResult};
+use crate::dynamic::{DynBuilder, ErasedEntry};
+use crate::manifest::ManifestEntry;
+use crate::redact::RedactedSummary;
+use std::any::{Any, TypeId};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+struct Registration {
+    name: String,
+    type_id: TypeId,
+    type_name: &'static str,
+}
+
+static TYPES: RwLock<Vec<Registration>> = RwLock::new(Vec::new());
+
+/// Register `E` under `name`, replacing any type registered under the same name before.
+pub fn register_type<E: crate::Error>(name: impl Into<String>) {
+    let registration = Registration {
+        name: name.into(),
+        type_id: TypeId::of::<E>(),
+        type_name: std::any::type_name::<E>(),
+    };
+    let mut types = TYPES.write().unwrap_or_else(|e| e.into_inner());
+    match types.iter_mut().find(|entry| entry.name == registration.name) {
+        Some(entry) => *entry = registration,
+        None => types.push(registration),
+    }
+}
+
+/// The names of all registered types, in registration order.
+pub fn registered_names() -> Vec<String> {
+    let types = TYPES.read().unwrap_or_else(|e| e.into_inner());
+    types.iter().map(|entry| entry.name.clone()).collect()
+}
+
+fn lookup(name: &str) -> Result<(TypeId, &'static str), UnknownTypeName> {
+    let types = TYPES.read().unwrap_or_else(|e| e.into_inner());
+    if let Some(entry) = types.iter().find(|entry| entry.name == name) {
+        return Ok((entry.type_id, entry.type_name));
+    }
+    Err(UnknownTypeName::new(name, types.iter().map(|entry| entry.name.as_str())))
+}
+
+type Extractor = Arc<dyn Fn(Box<dyn Any>) -> Box<dyn Any> + Send + Sync>;
+
+#[derive(Clone)]
+struct Member {
+    type_id: TypeId,
+    type_name: &'static str,
+    /// The type the extractor produces, and the extractor
+    extractor: Option<(TypeId, Extractor)>,
+}
+
+struct Equivalence {
+    class: String,
+    member: Member,
+}
+
+static CLASSES: RwLock<Vec<Equivalence>> = RwLock::new(Vec::new());
+
+fn register_member<E: crate::Error>(class: String, extractor: Option<(TypeId, Extractor)>) {
+    let equivalence = Equivalence {
+        class,
+        member: Member {
+            type_id: TypeId::of::<E>(),
+            type_name: std::any::type_name::<E>(),
+            extractor,
+        },
+    };
+    let mut classes = CLASSES.write().unwrap_or_else(|e| e.into_inner());
+    match classes
+        .iter_mut()
+        .find(|entry| entry.class == equivalence.class && entry.member.type_id == equivalence.member.type_id)
+    {
+        Some(entry) => *entry = equivalence,
+        None => classes.push(equivalence),
+    }
+}
+
+/// Add `E` to the equivalence class `class`, replacing any extractor registered for it before.
+///
+/// A type can be in several classes.
+pub fn register_equivalence<E: crate::Error>(class: impl Into<String>) {
+    register_member::<E>(class.into(), None);
+}
+
+/// Add `E` to the equivalence class `class`, with `extract` taking out the value handlers added by
+/// [`handle_class_as`] receive.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::dyn_registry::{handle_class_as, register_equivalence_with};
+///
+/// struct IoErr(std::io::Error);
+/// register_equivalence_with("io", |err: IoErr| err.0);
+///
+/// let mut builder = xcept::dynamic::DynBuilder::new();
+/// handle_class_as(&mut builder, "io", |err: std::io::Error| xcept::Result::new(err.kind())).unwrap();
+///
+/// let res = xcept::try_or_handle(
+///     || xcept::Result::new_error(IoErr(std::io::ErrorKind::NotFound.into())),
+///     builder.build(),
+/// );
+/// assert_eq!(res.unwrap(), std::io::ErrorKind::NotFound);
+/// ```
+pub fn register_equivalence_with<E: crate::Error, I: 'static>(class: impl Into<String>, extract: fn(E) -> I) {
+    let extractor: Extractor = Arc::new(move |value: Box<dyn Any>| {
+        let value = value.downcast::<E>().expect("the extractor is only called for its own type");
+        Box::new(extract(*value)) as Box<dyn Any>
+    });
+    register_member::<E>(class.into(), Some((TypeId::of::<I>(), extractor)));
+}
+
+/// The names of all equivalence classes, in registration order.
+pub fn registered_classes() -> Vec<String> {
+    let classes = CLASSES.read().unwrap_or_else(|e| e.into_inner());
+    let mut names: Vec<String> = Vec::new();
+    for entry in classes.iter() {
+        if !names.contains(&entry.class) {
+            names.push(entry.class.clone());
+        }
+    }
+    names
+}
+
+fn lookup_class(class: &str) -> Result<Vec<Member>, UnknownTypeName> {
+    let classes = CLASSES.read().unwrap_or_else(|e| e.into_inner());
+    let members: Vec<Member> = classes
+        .iter()
+        .filter(|entry| entry.class == class)
+        .map(|entry| entry.member.clone())
+        .collect();
+    if members.is_empty() {
+        return Err(UnknownTypeName::new(class, classes.iter().map(|entry| entry.class.as_str())));
+    }
+    Ok(members)
+}
+
+/// No type is registered under the name passed to [`handle_by_name`], or no type is in the class
+/// passed to [`handle_class`] or [`handle_class_as`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownTypeName {
+    name: String,
+    near_matches: Vec<String>,
+}
+
+impl UnknownTypeName {
+    fn new<'a>(name: &str, registered: impl Iterator<Item = &'a str>) -> Self {
+        let lowercase = name.to_lowercase();
+        let mut near_matches: Vec<String> = Vec::new();
+        for candidate in registered {
+            let candidate_lowercase = candidate.to_lowercase();
+            let near = !lowercase.is_empty()
+                && (candidate_lowercase.starts_with(&lowercase) || lowercase.starts_with(&candidate_lowercase));
+            if near && !near_matches.iter().any(|existing| existing == candidate) {
+                near_matches.push(candidate.to_owned());
+            }
+        }
+        Self {
+            name: name.to_owned(),
+            near_matches,
+        }
+    }
+
+    /// The name that was looked up.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Registered names that are a prefix of the name, or that the name is a prefix of, ignoring
+    /// case.
+    pub fn near_matches(&self) -> &[String] {
+        &self.near_matches
+    }
+}
+
+impl fmt::Display for UnknownTypeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no error type is registered as `{}`", self.name)?;
+        if !self.near_matches.is_empty() {
+            write!(f, ", did you mean `{}`?", self.near_matches.join("`, `"))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnknownTypeName {}
+
+/// An error passed to a handler added by [`handle_by_name`].
+pub struct ErasedErrorInfo {
+    id: crate::ErrorId,
+    type_name: &'static str,
+    summary: RedactedSummary,
+    value: Box<dyn Any>,
+}
+
+impl ErasedErrorInfo {
+    /// The ID of the error.
+    pub fn id(&self) -> crate::ErrorId {
+        self.id
+    }
+
+    /// The name of the Rust type of the error.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// The error rendered through its redactor, see [`crate::redact`].
+    pub fn summary(&self) -> &RedactedSummary {
+        &self.summary
+    }
+
+    /// The error value, for downcasting.
+    pub fn value(&self) -> &dyn Any {
+        &*self.value
+    }
+
+    /// Take the error value, for downcasting.
+    pub fn into_value(self) -> Box<dyn Any> {
+        self.value
+    }
+}
+
+struct NamedEntry<F> {
+    type_id: TypeId,
+    type_name: &'static str,
+    storage: Option<ErasedError>,
+    callback: F,
+    location: &'static std::panic::Location<'static>,
+}
+
+impl<F, V> ErasedEntry<V> for NamedEntry<F>
+where
+    F: FnMut(ErasedErrorInfo) -> crate::Result<V>,
+{
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        if self.type_id == error.type_id() && !crate::runtime::is_suppressed(error.type_id()) {
+            self.storage = Some(ErasedError::take(error));
+            TrySetErrorResult::NeedForget
+        } else {
+            TrySetErrorResult::NotHandled
+        }
+    }
+
+    fn try_handle(&mut self, error_id: crate::ErrorId) -> Option<crate::Result<V>> {
+        let err = self.storage.take().filter(|err| err.id() == error_id)?;
+        let info = ErasedErrorInfo {
+            id: err.id(),
+            type_name: err.type_name(),
+            summary: err.summary(),
+            value: err.into_box(),
+        };
+        Some((self.callback)(info))
+    }
+
+    fn describe(&self, entries: &mut Vec<ManifestEntry>) {
+        entries.push(ManifestEntry {
+            error_type: Some(self.type_name),
+            handler: std::any::type_name::<F>(),
+            location: Some(self.location),
+        });
+    }
+}
+
+/// Add a handler to `chain` for the type registered as `type_name`, see [`register_type`].
+///
+/// The handler is tried in the order it was added, like [`DynBuilder::handle`]. Fails without
+/// changing `chain` if no type is registered under the name.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::dyn_registry::{handle_by_name, register_type};
+///
+/// struct Timeout(u32);
+/// register_type::<Timeout>("Timeout");
+///
+/// let mut builder = xcept::dynamic::DynBuilder::new();
+/// handle_by_name(&mut builder, "Timeout", |info| {
+///     let timeout = info.value().downcast_ref::<Timeout>().unwrap();
+///     xcept::Result::new(format!("timed out after {}s", timeout.0))
+/// })
+/// .unwrap();
+///
+/// let err = handle_by_name(&mut builder, "Timeot", |_| xcept::Result::new(String::new())).unwrap_err();
+/// assert!(err.near_matches().is_empty());
+///
+/// let res = xcept::try_or_handle(|| xcept::Result::new_error(Timeout(3)), builder.build());
+/// assert_eq!(res.unwrap(), "timed out after 3s");
+/// ```
+#[track_caller]
+pub fn handle_by_name<V: 'static>(
+    chain: &mut DynBuilder<V>,
+    type_name: &str,
+    callback: impl FnMut(ErasedErrorInfo) -> crate::Result<V> + 'static,
+) -> Result<(), UnknownTypeName> {
+    let location = std::panic::Location::caller();
+    let (type_id, type_name) = lookup(type_name)?;
+    chain.push_entry(Box::new(NamedEntry {
+        type_id,
+        type_name,
+        storage: None,
+        callback,
+        location,
+    }));
+    Ok(())
+}
+
+struct ClassEntry<F> {
+    members: Vec<Member>,
+    /// Pass the value produced by the extractor of the member instead of the error itself
+    extract: bool,
+    storage: Option<ErasedError>,
+    callback: F,
+    location: &'static std::panic::Location<'static>,
+}
+
+impl<F, V> ErasedEntry<V> for ClassEntry<F>
+where
+    F: FnMut(ErasedErrorInfo) -> crate::Result<V>,
+{
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        let is_member = self.members.iter().any(|member| member.type_id == error.type_id());
+        if is_member && !crate::runtime::is_suppressed(error.type_id()) {
+            self.storage = Some(ErasedError::take(error));
+            TrySetErrorResult::NeedForget
+        } else {
+            TrySetErrorResult::NotHandled
+        }
+    }
+
+    fn try_handle(&mut self, error_id: crate::ErrorId) -> Option<crate::Result<V>> {
+        let err = self.storage.take().filter(|err| err.id() == error_id)?;
+        let member = self.members.iter().find(|member| member.type_id == err.type_id())?;
+        let (id, type_name, summary) = (err.id(), err.type_name(), err.summary());
+        let mut value = err.into_box();
+        if self.extract {
+            let (_, extractor) = member.extractor.as_ref()?;
+            value = extractor(value);
+        }
+        let info = ErasedErrorInfo {
+            id,
+            type_name,
+            summary,
+            value,
+        };
+        Some((self.callback)(info))
+    }
+
+    fn describe(&self, entries: &mut Vec<ManifestEntry>) {
+        for member in &self.members {
+            entries.push(ManifestEntry {
+                error_type: Some(member.type_name),
+                handler: std::any::type_name::<F>(),
+                location: Some(self.location),
+            });
+        }
+    }
+}
+
+/// Add a handler to `chain` for every type in the equivalence class `class`, see
+/// [`register_equivalence`].
+///
+/// The handler receives the error itself through [`ErasedErrorInfo::value`]. The types are the
+/// ones in the class when the handler is added. Fails without changing `chain` if the class has no
+/// types.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::dyn_registry::{handle_class, register_equivalence};
+///
+/// struct ReadFailed(std::io::Error);
+/// struct WriteFailed(std::io::Error);
+/// register_equivalence::<ReadFailed>("io");
+/// register_equivalence::<WriteFailed>("io");
+///
+/// let mut builder = xcept::dynamic::DynBuilder::new();
+/// handle_class(&mut builder, "io", |info| xcept::Result::new(info.type_name())).unwrap();
+/// let mut handlers = builder.build();
+///
+/// let err = std::io::Error::other("disk on fire");
+/// let res = xcept::try_or_handle(|| xcept::Result::new_error(WriteFailed(err)), &mut handlers);
+/// assert!(res.unwrap().ends_with("WriteFailed"));
+/// ```
+#[track_caller]
+pub fn handle_class<V: 'static>(
+    chain: &mut DynBuilder<V>,
+    class: &str,
+    callback: impl FnMut(ErasedErrorInfo) -> crate::Result<V> + 'static,
+) -> Result<(), UnknownTypeName> {
+    let location = std::panic::Location::caller();
+    let members = lookup_class(class)?;
+    chain.push_entry(Box::new(ClassEntry {
+        members,
+        extract: false,
+        storage: None,
+        callback,
+        location,
+    }));
+    Ok(())
+}
+
+/// Add a handler to `chain` for the types in the equivalence class `class` registered with an
+/// extractor producing `I`, see [`register_equivalence_with`].
+///
+/// The handler receives the value taken out of the error by the extractor. Types of the class
+/// without such an extractor are not handled. Fails without changing `chain` if no type of the
+/// class has one.
+#[track_caller]
+pub fn handle_class_as<I: 'static, V: 'static>(
+    chain: &mut DynBuilder<V>,
+    class: &str,
+    mut callback: impl FnMut(I) -> crate::Result<V> + 'static,
+) -> Result<(), UnknownTypeName> {
+    let location = std::panic::Location::caller();
+    let members: Vec<Member> = lookup_class(class)?
+        .into_iter()
+        .filter(|member| matches!(member.extractor, Some((target, _)) if target == TypeId::of::<I>()))
+        .collect();
+    if members.is_empty() {
+        return Err(UnknownTypeName::new(class, std::iter::empty()));
+    }
+    chain.push_entry(Box::new(ClassEntry {
+        members,
+        extract: true,
+        storage: None,
+        callback: move |info: ErasedErrorInfo| {
+            let value = info.into_value().downcast::<I>().expect("the extractor produces `I`");
+            callback(*value)
+        },
+        location,
+    }));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        handle_by_name, handle_class, handle_class_as, register_equivalence, register_equivalence_with, register_type,
+        registered_classes, registered_names, ErasedErrorInfo,
+    };
+    use crate::dynamic::DynBuilder;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct DiskFull {
+        free: u64,
+    }
+    struct DiskQuota;
+
+    #[test]
+    fn script_callback() {
+        register_type::<DiskFull>("DiskFull");
+        register_type::<DiskQuota>("DiskQuota");
+        crate::redact::register(|err: &DiskFull| crate::redact::RedactedSummary::new(format!("{} bytes free", err.free)));
+        assert!(registered_names().contains(&"DiskFull".to_owned()));
+
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = seen.clone();
+        let mut builder = DynBuilder::new();
+        handle_by_name(&mut builder, "DiskFull", move |info: ErasedErrorInfo| {
+            log.borrow_mut().push((info.id(), info.summary().to_string()));
+            let err = info.into_value().downcast::<DiskFull>().unwrap();
+            crate::Result::new(err.free)
+        })
+        .unwrap();
+        let mut handlers = builder.build();
+
+        let mut raised = None;
+        let res = crate::try_or_handle(
+            || {
+                let res = crate::Result::new_error(DiskFull { free: 12 });
+                raised = res.error_id();
+                res
+            },
+            &mut handlers,
+        );
+        assert_eq!(res.unwrap(), 12);
+        let seen = seen.borrow();
+        assert_eq!(seen[0].0, raised.unwrap());
+        assert!(seen[0].1.ends_with("12 bytes free"), "{}", seen[0].1);
+
+        // Types registered under other names pass through
+        assert!(crate::try_or_handle(|| crate::Result::new_error(DiskQuota), &mut handlers).is_error());
+    }
+
+    #[test]
+    fn unknown_name() {
+        register_type::<DiskFull>("DiskFull");
+        register_type::<DiskQuota>("DiskQuota");
+
+        let mut builder = DynBuilder::<()>::new();
+        let err = handle_by_name(&mut builder, "disk", |_| crate::Result::new(())).unwrap_err();
+        assert_eq!(err.name(), "disk");
+        assert_eq!(err.near_matches(), ["DiskFull", "DiskQuota"]);
+        assert!(err.to_string().contains("did you mean `DiskFull`, `DiskQuota`?"));
+
+        let err = handle_by_name(&mut builder, "NetworkDown", |_| crate::Result::new(())).unwrap_err();
+        assert!(err.near_matches().is_empty());
+        assert_eq!(builder.build().len(), 0);
+    }
+
+    mod crate_a {
+        pub struct IoErr(#[allow(dead_code)] pub std::io::Error);
+    }
+
+    mod crate_b {
+        pub enum Error {
+            Io(std::io::Error),
+            #[allow(dead_code)]
+            Parse(String),
+        }
+    }
+
+    struct NotIo;
+
+    fn b_io(err: crate_b::Error) -> std::io::Error {
+        match err {
+            crate_b::Error::Io(err) => err,
+            crate_b::Error::Parse(msg) => std::io::Error::new(std::io::ErrorKind::InvalidData, msg),
+        }
+    }
+
+    #[test]
+    fn equivalence_class() {
+        crate::register_equivalence::<crate_a::IoErr>("io");
+        register_equivalence_with("io", b_io);
+        assert!(registered_classes().contains(&"io".to_owned()));
+
+        let mut builder = DynBuilder::new();
+        handle_class(&mut builder, "io", |info: ErasedErrorInfo| crate::Result::new(info.type_name().to_owned())).unwrap();
+        let mut handlers = builder.build();
+        let res = crate::try_or_handle(
+            || crate::Result::new_error(crate_a::IoErr(std::io::ErrorKind::NotFound.into())),
+            &mut handlers,
+        );
+        assert!(res.unwrap().ends_with("crate_a::IoErr"));
+        let res = crate::try_or_handle(
+            || crate::Result::new_error(crate_b::Error::Io(std::io::ErrorKind::TimedOut.into())),
+            &mut handlers,
+        );
+        assert!(res.unwrap().ends_with("crate_b::Error"));
+        assert!(crate::try_or_handle(|| crate::Result::new_error(NotIo), &mut handlers).is_error());
+
+        let err = handle_class(&mut DynBuilder::<()>::new(), "i", |_| crate::Result::new(())).unwrap_err();
+        assert!(err.near_matches().contains(&"io".to_owned()), "{err}");
+    }
+
+    #[test]
+    fn equivalence_class_extractor() {
+        register_equivalence::<crate_a::IoErr>("io-extract");
+        register_equivalence_with("io-extract", b_io);
+
+        let mut builder = DynBuilder::new();
+        handle_class_as(&mut builder, "io-extract", |err: std::io::Error| crate::Result::new(err.kind())).unwrap();
+        let mut handlers = builder.build();
+        let res = crate::try_or_handle(
+            || crate::Result::new_error(crate_b::Error::Io(std::io::ErrorKind::TimedOut.into())),
+            &mut handlers,
+        );
+        assert_eq!(res.unwrap(), std::io::ErrorKind::TimedOut);
+        // Registered without an extractor, so not handled
+        let res = crate::try_or_handle(
+            || crate::Result::new_error(crate_a::IoErr(std::io::ErrorKind::NotFound.into())),
+            &mut handlers,
+        );
+        assert!(res.is_error());
+
+        let res = handle_class_as(&mut DynBuilder::<()>::new(), "io-extract", |_: String| crate::Result::new(()));
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,471 @@
+//! Handler chains built at runtime.
+//!
+//! The static [`Builder`] changes type with every added handler, so it can't be grown in a loop.
+//! [`DynBuilder`] erases the handler types instead, at the cost of one allocation per handler
+//! and dynamic dispatch when an error is raised.
+//!
+//! Handlers can also come from plugins that don't know the concrete error types at compile time,
+//! by implementing [`PluginErrorHandler`].
+//!
+//! [`Builder`]: crate::multihandler::Builder
+
+use crate::context::{ErasedError, ErrorHandlingContext, ReportedError, TrySetErrorResult};
+use crate::manifest::{HandlerManifest, ManifestEntry};
+use crate::multihandler::{FlatChain, TryHandle, TryHandleMut};
+use std::any::{Any, TypeId};
+
+pub(crate) trait ErasedEntry<V> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult;
+    fn try_handle(&mut self, error_id: crate::ErrorId) -> Option<crate::Result<V>>;
+    fn describe(&self, entries: &mut Vec<ManifestEntry>);
+}
+
+struct HandlerEntry<E, F> {
+    storage: Option<(crate::ErrorId, E)>,
+    handler: F,
+    location: &'static std::panic::Location<'static>,
+}
+
+impl<E, F, V> ErasedEntry<V> for HandlerEntry<E, F>
+where
+    E: crate::Error,
+    F: FnMut(E) -> crate::Result<V>,
+{
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        if TypeId::of::<E>() == error.type_id() && !crate::runtime::is_suppressed(error.type_id()) {
+            self.storage = Some((error.id(), (error.value_ptr() as *mut E).read()));
+            TrySetErrorResult::NeedForget
+        } else {
+            TrySetErrorResult::NotHandled
+        }
+    }
+
+    fn try_handle(&mut self, error_id: crate::ErrorId) -> Option<crate::Result<V>> {
+        match self.storage.take() {
+            Some((id, err)) if id == error_id => Some((self.handler)(err)),
+            _ => None,
+        }
+    }
+
+    fn describe(&self, entries: &mut Vec<ManifestEntry>) {
+        entries.push(ManifestEntry {
+            error_type: Some(std::any::type_name::<E>()),
+            handler: std::any::type_name::<F>(),
+            location: Some(self.location),
+        });
+    }
+}
+
+/// A static chain erased into a single entry.
+///
+/// A fresh clone of the chain is used every time the handlers are used.
+struct StaticEntry<T> {
+    template: T,
+    active: Option<T>,
+}
+
+impl<T, V> ErasedEntry<V> for StaticEntry<T>
+where
+    T: FlatChain<Value = V> + Clone,
+{
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        let template = &self.template;
+        self.active
+            .get_or_insert_with(|| template.clone())
+            .try_set_error(error)
+    }
+
+    fn try_handle(&mut self, error_id: crate::ErrorId) -> Option<crate::Result<V>> {
+        self.active.take()?.try_handle(error_id)
+    }
+
+    fn describe(&self, entries: &mut Vec<ManifestEntry>) {
+        self.template.describe(entries)
+    }
+}
+
+/// What a [`PluginErrorHandler`] did with an error.
+pub enum PluginOutcome<V> {
+    /// The error was handled, producing a value.
+    Handled(V),
+    /// The error was declined and is raised again to the enclosing scopes, keeping its ID.
+    ///
+    /// The box should be the one passed to the plugin. If it holds a value of another type, the
+    /// box itself is raised as a new `Box<dyn Any>` error.
+    Reraise(Box<dyn Any>),
+}
+
+/// An error handler working on type-erased errors, as provided by plugins.
+///
+/// Plugins implement this without knowing the handler chain they are added to, see
+/// [`DynBuilder::handle_plugins`] and [`from_plugins`](crate::multihandler::from_plugins).
+pub trait PluginErrorHandler<V> {
+    /// The type of errors this plugin handles.
+    fn error_type(&self) -> TypeId;
+
+    /// Handle an error of the type returned by [`error_type`](Self::error_type).
+    fn handle_erased(&mut self, error: Box<dyn Any>) -> PluginOutcome<V>;
+}
+
+struct PluginEntry<V> {
+    storage: Option<ErasedError>,
+    plugin: Box<dyn PluginErrorHandler<V>>,
+}
+
+impl<V> ErasedEntry<V> for PluginEntry<V> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        if self.plugin.error_type() == error.type_id() && !crate::runtime::is_suppressed(error.type_id()) {
+            self.storage = Some(ErasedError::take(error));
+            TrySetErrorResult::NeedForget
+        } else {
+            TrySetErrorResult::NotHandled
+        }
+    }
+
+    fn try_handle(&mut self, error_id: crate::ErrorId) -> Option<crate::Result<V>> {
+        let err = self.storage.take().filter(|err| err.id() == error_id)?;
+        let (boxed, shape) = err.into_box_with_shape();
+        Some(match self.plugin.handle_erased(boxed) {
+            PluginOutcome::Handled(value) => crate::Result::new(value),
+            PluginOutcome::Reraise(boxed) => match shape.restore(boxed) {
+                Ok(err) => {
+                    let (id, error_type) = crate::context::push_erased(err);
+                    crate::Result::new_with_error_type(id, error_type)
+                }
+                Err(boxed) => crate::Result::new_error(boxed),
+            },
+        })
+    }
+
+    fn describe(&self, entries: &mut Vec<ManifestEntry>) {
+        // Plugins only reveal the `TypeId` of their error type
+        entries.push(ManifestEntry::opaque("dyn PluginErrorHandler"));
+    }
+}
+
+/// Incrementally builds [`DynHandlers`].
+///
+/// # Examples
+///
+/// ```
+/// let mut builder = xcept::dynamic::DynBuilder::new();
+/// for offset in [1, 2] {
+///     builder.handle(move |x: i32| xcept::Result::new(x + offset));
+/// }
+/// let mut handlers = builder.build();
+///
+/// // The first handler accepting the error wins
+/// let res = xcept::try_or_handle(|| xcept::Result::new_error(10), &mut handlers);
+/// assert_eq!(res.unwrap(), 11);
+/// ```
+pub struct DynBuilder<V> {
+    entries: Vec<Box<dyn ErasedEntry<V>>>,
+}
+
+impl<V> Default for DynBuilder<V> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<V: 'static> DynBuilder<V> {
+    /// Create a builder without any handlers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a new error handler.
+    ///
+    /// Handlers are tried in the order they were added.
+    #[track_caller]
+    pub fn handle<E, F>(&mut self, handler: F) -> &mut Self
+    where
+        E: crate::Error,
+        F: FnMut(E) -> crate::Result<V> + 'static,
+    {
+        self.entries.push(Box::new(HandlerEntry {
+            storage: None,
+            handler,
+            location: std::panic::Location::caller(),
+        }));
+        self
+    }
+
+    /// Add handlers provided by plugins.
+    ///
+    /// Each plugin is an entry of its own, tried in order after the handlers added so far.
+    pub fn handle_plugins<I>(&mut self, plugins: I) -> &mut Self
+    where
+        I: IntoIterator<Item = Box<dyn PluginErrorHandler<V>>>,
+    {
+        self.entries.extend(plugins.into_iter().map(|plugin| {
+            Box::new(PluginEntry {
+                storage: None,
+                plugin,
+            }) as Box<dyn ErasedEntry<V>>
+        }));
+        self
+    }
+
+    /// Add an entry implemented elsewhere in the crate.
+    pub(crate) fn push_entry(&mut self, entry: Box<dyn ErasedEntry<V>>) {
+        self.entries.push(entry);
+    }
+
+    /// Add a static handler chain as a single entry.
+    pub(crate) fn push_static<T>(&mut self, chain: T)
+    where
+        T: FlatChain<Value = V> + Clone + 'static,
+    {
+        self.entries.push(Box::new(StaticEntry {
+            template: chain,
+            active: None,
+        }));
+    }
+
+    /// Convert the builder to a handling context.
+    pub fn build(self) -> DynHandlers<V> {
+        DynHandlers {
+            entries: self.entries,
+        }
+    }
+}
+
+/// A handler chain with the handler types erased.
+///
+/// It can be passed by value, or by mutable reference to reuse it, to [`try_or_handle`]. It can
+/// also start a static chain, see [`compose`].
+///
+/// [`try_or_handle`]: crate::try_or_handle
+/// [`compose`]: crate::multihandler::compose
+pub struct DynHandlers<V> {
+    entries: Vec<Box<dyn ErasedEntry<V>>>,
+}
+
+impl<V> DynHandlers<V> {
+    /// The number of handlers in the chain.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Test if the chain has no handlers.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Describe the entries of the chain, in the order they are asked.
+    ///
+    /// Static chains added with [`Builder::into_dyn`](crate::multihandler::Builder::into_dyn)
+    /// are described entry by entry.
+    pub fn manifest(&self) -> HandlerManifest {
+        let mut entries = Vec::with_capacity(self.entries.len());
+        for entry in &self.entries {
+            entry.describe(&mut entries);
+        }
+        HandlerManifest::new(entries)
+    }
+
+    unsafe fn set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        for entry in &mut self.entries {
+            match entry.try_set_error(error) {
+                TrySetErrorResult::NotHandled => {}
+                x => return x,
+            }
+        }
+        TrySetErrorResult::NotHandled
+    }
+
+    fn handle(&mut self, error_id: crate::ErrorId) -> Option<crate::Result<V>> {
+        // Every entry is visited to release stale captures
+        let mut result = None;
+        for entry in &mut self.entries {
+            let handled = entry.try_handle(error_id);
+            if result.is_none() {
+                result = handled;
+            }
+        }
+        result
+    }
+}
+
+impl<V> ErrorHandlingContext for DynHandlers<V> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        self.set_error(error)
+    }
+}
+
+impl<V> TryHandle for DynHandlers<V> {
+    type Value = V;
+    fn try_handle(mut self, error_id: crate::ErrorId) -> Option<crate::Result<V>> {
+        self.handle(error_id)
+    }
+}
+
+impl<V> TryHandleMut for DynHandlers<V> {
+    type Value = V;
+    fn try_handle_mut(&mut self, error_id: crate::ErrorId) -> Option<crate::Result<V>> {
+        self.handle(error_id)
+    }
+}
+
+crate::multihandler::opaque_flat_chain! {
+    impl [V] for DynHandlers<V>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DynBuilder, DynHandlers, PluginErrorHandler, PluginOutcome};
+    use std::any::{Any, TypeId};
+
+    struct PluginError<const N: usize>;
+
+    trait Plugin {
+        fn register(&self, builder: &mut DynBuilder<String>);
+    }
+
+    struct NumberedPlugin<const N: usize>;
+
+    impl<const N: usize> Plugin for NumberedPlugin<N> {
+        fn register(&self, builder: &mut DynBuilder<String>) {
+            builder.handle(|_: PluginError<N>| crate::Result::new(format!("plugin {N}")));
+        }
+    }
+
+    fn plugin_handlers() -> DynHandlers<String> {
+        let plugins: Vec<Box<dyn Plugin>> = vec![
+            Box::new(NumberedPlugin::<0>),
+            Box::new(NumberedPlugin::<1>),
+            Box::new(NumberedPlugin::<2>),
+            Box::new(NumberedPlugin::<3>),
+            Box::new(NumberedPlugin::<4>),
+        ];
+
+        let mut builder = DynBuilder::new();
+        for plugin in &plugins {
+            plugin.register(&mut builder);
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn plugins_in_loop() {
+        let mut handlers = plugin_handlers();
+        assert_eq!(handlers.len(), 5);
+
+        let res = crate::try_or_handle(|| crate::Result::new_error(PluginError::<2>), &mut handlers);
+        assert_eq!(res.unwrap(), "plugin 2");
+
+        // Reused after handling
+        let res = crate::try_or_handle(|| crate::Result::new_error(PluginError::<4>), &mut handlers);
+        assert_eq!(res.unwrap(), "plugin 4");
+
+        let res = crate::try_or_handle(|| crate::Result::new_error(PluginError::<5>), handlers);
+        assert!(res.is_error());
+    }
+
+    #[test]
+    fn static_chain_into_dyn() {
+        let mut builder = crate::builder(|x: i32| crate::Result::new(x.to_string()))
+            .handle(|b: bool| crate::Result::new(b.to_string()))
+            .into_dyn();
+        builder.handle(|s: &'static str| crate::Result::new(s.to_uppercase()));
+        let mut handlers = builder.build();
+        assert_eq!(handlers.len(), 2);
+
+        for _ in 0..2 {
+            let res = crate::try_or_handle(|| crate::Result::new_error(true), &mut handlers);
+            assert_eq!(res.unwrap(), "true");
+        }
+        let res = crate::try_or_handle(|| crate::Result::new_error("abc"), &mut handlers);
+        assert_eq!(res.unwrap(), "ABC");
+    }
+
+    #[test]
+    fn compose_dyn_first() {
+        let handlers = crate::multihandler::compose(plugin_handlers())
+            .handle(|x: i32| crate::Result::new(x.to_string()))
+            .build();
+        let res = crate::try_or_handle(|| crate::Result::new_error(PluginError::<0>), handlers);
+        assert_eq!(res.unwrap(), "plugin 0");
+
+        let handlers = crate::multihandler::compose(plugin_handlers())
+            .handle(|x: i32| crate::Result::new(x.to_string()))
+            .build();
+        let res = crate::try_or_handle(|| crate::Result::new_error(7), handlers);
+        assert_eq!(res.unwrap(), "7");
+    }
+
+    struct Timeout(u32);
+
+    struct TimeoutPlugin;
+
+    impl PluginErrorHandler<String> for TimeoutPlugin {
+        fn error_type(&self) -> TypeId {
+            TypeId::of::<Timeout>()
+        }
+
+        fn handle_erased(&mut self, error: Box<dyn Any>) -> PluginOutcome<String> {
+            let timeout = error.downcast::<Timeout>().unwrap();
+            PluginOutcome::Handled(format!("timeout after {}s", timeout.0))
+        }
+    }
+
+    /// Handles short messages, declining the others.
+    struct MessagePlugin {
+        seen: usize,
+    }
+
+    impl PluginErrorHandler<String> for MessagePlugin {
+        fn error_type(&self) -> TypeId {
+            TypeId::of::<String>()
+        }
+
+        fn handle_erased(&mut self, error: Box<dyn Any>) -> PluginOutcome<String> {
+            self.seen += 1;
+            match error.downcast_ref::<String>() {
+                Some(msg) if msg.len() < 8 => PluginOutcome::Handled(format!("message {msg}")),
+                _ => PluginOutcome::Reraise(error),
+            }
+        }
+    }
+
+    #[test]
+    fn erased_plugin_handlers() {
+        let plugins: Vec<Box<dyn PluginErrorHandler<String>>> =
+            vec![Box::new(TimeoutPlugin), Box::new(MessagePlugin { seen: 0 })];
+        let mut handlers = crate::multihandler::from_plugins(plugins);
+        assert_eq!(handlers.len(), 2);
+
+        let res = crate::try_or_handle(|| crate::Result::new_error(Timeout(3)), &mut handlers);
+        assert_eq!(res.unwrap(), "timeout after 3s");
+        let res = crate::try_or_handle(|| crate::Result::new_error("short".to_string()), &mut handlers);
+        assert_eq!(res.unwrap(), "message short");
+        let res = crate::try_or_handle(|| crate::Result::new_error(5u8), &mut handlers);
+        assert!(res.is_error());
+    }
+
+    #[test]
+    fn plugin_reraises_to_outer_scope() {
+        let mut builder = DynBuilder::new();
+        builder.handle_plugins([Box::new(MessagePlugin { seen: 0 }) as Box<dyn PluginErrorHandler<String>>]);
+        let mut handlers = builder.build();
+
+        let mut inner_id = None;
+        let res = crate::try_or_handle_one(
+            || {
+                let res = crate::try_or_handle(
+                    || crate::Result::new_error("a long message".to_string()),
+                    &mut handlers,
+                );
+                assert!(res.error_is::<String>());
+                inner_id = res.error_id();
+                res
+            },
+            |msg: String| crate::Result::new(format!("outer {msg}")),
+        );
+        assert_eq!(res.unwrap(), "outer a long message");
+        assert!(inner_id.is_some());
+    }
+}
@@ -0,0 +1,277 @@
+//! The top-level scope of binaries and tests, installed by `#[xcept::main]` and `#[xcept::test]`.
+//!
+//! The attributes (requires the `macros` feature) expand to a call of [`run_main`] or
+//! [`run_test`]. Both run the body inside an outermost scope taking every error that no other
+//! scope handles, so an unhandled error can be reported with its type, its raise location and its
+//! content as rendered by the redactor registered for the type, see [`redact`](crate::redact).
+//!
+//! ```no_run
+//! # #[cfg(feature = "macros")]
+//! #[xcept::main(exit_code = 2)]
+//! fn main() -> xcept::Result<()> {
+//!     xcept::Result::new_error("no input file")
+//! }
+//! # #[cfg(not(feature = "macros"))]
+//! # fn main() {}
+//! ```
+
+use crate::context::{
+    Acceptance, ContextExt, ErasedError, ErrorHandlingContext, ReportedError, ReportedErrorDescriptor, ScopeNode,
+    TrySetErrorResult,
+};
+use std::any::TypeId;
+use std::backtrace::Backtrace;
+use std::panic::Location;
+use std::pin::pin;
+use std::process::{ExitCode, Termination};
+
+/// How [`run_main`] and [`run_test`] report an unhandled error.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EntryOptions {
+    backtrace: bool,
+    exit_code: u8,
+}
+
+impl EntryOptions {
+    /// No backtraces, exiting with code 1 on an error.
+    pub const fn new() -> Self {
+        Self {
+            backtrace: false,
+            exit_code: 1,
+        }
+    }
+
+    /// Capture a backtrace when an unhandled error is raised, and add it to the report.
+    pub const fn backtrace(mut self, backtrace: bool) -> Self {
+        self.backtrace = backtrace;
+        self
+    }
+
+    /// The code [`run_main`] exits with on an error.
+    pub const fn exit_code(mut self, exit_code: u8) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+}
+
+impl Default for EntryOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Unhandled {
+    error: ErasedError,
+    location: Option<&'static Location<'static>>,
+    backtrace: Option<Backtrace>,
+}
+
+/// The outermost scope, keeping the most recent error reaching it.
+struct Reporter {
+    backtrace: bool,
+    count: usize,
+    last: Option<Unhandled>,
+}
+
+impl Reporter {
+    fn new(options: EntryOptions) -> Self {
+        Self {
+            backtrace: options.backtrace,
+            count: 0,
+            last: None,
+        }
+    }
+
+    /// Run `body` with the reporter as the outermost scope.
+    fn run<T>(&mut self, body: impl FnOnce() -> crate::Result<T>) -> crate::Result<T> {
+        let scope = pin!(ScopeNode::new(self));
+        // Safety: the guard is dropped before returning
+        let _guard = unsafe { scope.register() };
+        body()
+    }
+
+    /// Describe the unhandled error with ID `error_id`.
+    fn describe(&self, error_id: crate::ErrorId) -> String {
+        let id = error_id.to_raw();
+        let Some(unhandled) = self.last.as_ref().filter(|unhandled| unhandled.error.id() == error_id) else {
+            return crate::describe_unhandled(error_id);
+        };
+        let mut text = format!("unhandled error #{id} of type `{}`", unhandled.error.type_name());
+        if let Some(location) = unhandled.location {
+            text += &format!(", raised at {location}");
+        }
+        if let Some(content) = unhandled.error.summary().content() {
+            text += &format!(": {content}");
+        }
+        if let Some(backtrace) = &unhandled.backtrace {
+            text += &format!("\n\nStack backtrace:\n{backtrace}");
+        }
+        text
+    }
+}
+
+impl ErrorHandlingContext for Reporter {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        #[cfg(feature = "paranoid")]
+        crate::id_state::captured(error.id());
+        self.count += 1;
+        self.last = Some(Unhandled {
+            error: ErasedError::take(error),
+            location: error.location(),
+            // Captured while raising, so the backtrace shows the raiser
+            backtrace: self.backtrace.then(Backtrace::force_capture),
+        });
+        TrySetErrorResult::NeedForget
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        Some(self)
+    }
+}
+
+impl ContextExt for Reporter {
+    fn accepts(&mut self, _type_id: TypeId) -> Option<bool> {
+        Some(true)
+    }
+
+    fn would_accept(&mut self, _descriptor: &ReportedErrorDescriptor) -> Acceptance {
+        Acceptance::Accept
+    }
+
+    // The last error is only kept for the report, it's never handled. Reporting it as pending
+    // would make every later raise look secondary, see `Result::new_error_if_clear`.
+
+    fn debug_name(&self) -> &'static str {
+        "entry::Reporter"
+    }
+}
+
+/// Run the body of `main` in the top-level scope, converting its result to an exit code.
+///
+/// A value is reported with its [`Termination`] implementation. An error is reported on stderr,
+/// like the [`Termination`] implementation of [`Result`](crate::Result) does, with the content of
+/// the error added when it has a registered redactor, and the process exits with the
+/// [`exit_code`](EntryOptions::exit_code) of `options`. Errors the body raises and doesn't return
+/// don't change the outcome.
+///
+/// # Examples
+///
+/// ```
+/// use std::process::ExitCode;
+/// use xcept::entry::{run_main, EntryOptions};
+///
+/// let code = run_main(EntryOptions::new().exit_code(3), || xcept::Result::<()>::new_error("missing"));
+/// assert_eq!(code, ExitCode::from(3));
+/// assert_eq!(run_main(EntryOptions::new(), || xcept::Result::new(())), ExitCode::SUCCESS);
+/// ```
+pub fn run_main<T: Termination>(options: EntryOptions, body: impl FnOnce() -> crate::Result<T>) -> ExitCode {
+    let mut reporter = Reporter::new(options);
+    let res = reporter.run(body);
+    match res.into_std() {
+        Ok(value) => value.report(),
+        Err(id) => {
+            eprintln!("Error: {}", reporter.describe(id));
+            ExitCode::from(options.exit_code)
+        }
+    }
+}
+
+/// Run the body of a test in the top-level scope, panicking if it raised an unhandled error.
+///
+/// The test fails if the body returns an error, or if any error it raised reached the top-level
+/// scope, even when the result holding it was ignored. The panic message holds the report of the
+/// error, see [`run_main`].
+///
+/// # Examples
+///
+/// ```
+/// use xcept::entry::{run_test, EntryOptions};
+///
+/// run_test(EntryOptions::new(), || {
+///     let handled = xcept::try_or_handle_one(|| xcept::Result::new_error(3u8), |x: u8| xcept::Result::new(x));
+///     assert_eq!(handled.unwrap(), 3);
+///     xcept::Result::new(())
+/// });
+///
+/// let failed = std::panic::catch_unwind(|| {
+///     run_test(EntryOptions::new(), || {
+///         let _ignored: xcept::Result<()> = xcept::Result::new_error("lost");
+///         xcept::Result::new(())
+///     })
+/// });
+/// assert!(failed.is_err());
+/// ```
+#[track_caller]
+pub fn run_test<T>(options: EntryOptions, body: impl FnOnce() -> crate::Result<T>) -> T {
+    let mut reporter = Reporter::new(options);
+    let res = reporter.run(body);
+    match res.into_std() {
+        Err(id) => panic!("test returned an {}", reporter.describe(id)),
+        Ok(_) if reporter.count != 0 => {
+            let id = reporter.last.as_ref().map_or(crate::ErrorId::from_raw(0), |unhandled| unhandled.error.id());
+            panic!(
+                "test raised {} error(s) not handled by any scope, the last one is an {}",
+                reporter.count,
+                reporter.describe(id)
+            )
+        }
+        Ok(value) => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_main, run_test, EntryOptions};
+    use std::process::ExitCode;
+
+    struct Invalid(&'static str);
+
+    fn panic_message(func: impl FnOnce() + std::panic::UnwindSafe) -> String {
+        let payload = std::panic::catch_unwind(func).unwrap_err();
+        payload.downcast::<String>().map(|message| *message).unwrap_or_default()
+    }
+
+    #[test]
+    fn main_exit_codes() {
+        assert_eq!(run_main(EntryOptions::new(), || crate::Result::new(ExitCode::from(4))), ExitCode::from(4));
+        assert_eq!(run_main(EntryOptions::new(), || crate::Result::<()>::new_error(1u8)), ExitCode::FAILURE);
+        let handled = run_main(EntryOptions::new().exit_code(9), || {
+            let _: crate::Result<()> = crate::Result::new_error(1u8);
+            crate::try_or_handle_one(|| crate::Result::new_error(2u8), |_: u8| crate::Result::new(()))
+        });
+        assert_eq!(handled, ExitCode::SUCCESS);
+
+        // An ignored error doesn't stay pending
+        let code = run_main(EntryOptions::new(), || {
+            let _: crate::Result<()> = crate::Result::new_error("ignored");
+            assert!(!crate::context::scopes_pending());
+            crate::Result::<()>::new_error_if_clear("real")
+        });
+        assert_eq!(code, ExitCode::FAILURE);
+    }
+
+    #[test]
+    fn test_reports() {
+        crate::redact::register(|err: &Invalid| crate::redact::RedactedSummary::new(err.0));
+        assert_eq!(run_test(EntryOptions::new(), || crate::Result::new(5)), 5);
+
+        let message = panic_message(|| {
+            run_test(EntryOptions::new(), || crate::Result::<()>::new_error(Invalid("bad header")));
+        });
+        assert!(message.starts_with("test returned an unhandled error #"), "{message}");
+        assert!(message.contains("of type `xcept::entry::tests::Invalid`"), "{message}");
+        assert!(message.ends_with(": bad header"), "{message}");
+        assert!(!message.contains("Stack backtrace"), "{message}");
+
+        let message = panic_message(|| {
+            run_test(EntryOptions::new().backtrace(true), || {
+                let _: crate::Result<()> = crate::Result::new_error(1u8);
+                let _: crate::Result<()> = crate::Result::new_error(Invalid("bad body"));
+                crate::Result::new(())
+            });
+        });
+        assert!(message.starts_with("test raised 2 error(s) not handled by any scope"), "{message}");
+        assert!(message.contains(": bad body\n\nStack backtrace:\n"), "{message}");
+    }
+}
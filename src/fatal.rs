@@ -0,0 +1,176 @@
+//! Exiting the process on errors no scope handles, after an ordered shutdown.
+//!
+//! Daemons that treat any unhandled error as fatal call [`install`] at startup. From then on a
+//! raise that no active scope accepts, and optionally an error [abandoned] by a panic, ends the
+//! process: the error is reported on stderr, the raise metadata and the logs are flushed, the
+//! shutdown callbacks of the [`FatalConfig`] run in order, and the process exits with the
+//! configured code.
+//!
+//! The callbacks run on the thread that raised the error. A watchdog thread exits the process
+//! anyway if they don't finish within the flush timeout, so a hanging callback can't keep a
+//! broken process alive. Errors raised while shutting down are not fatal again.
+//!
+//! [abandoned]: crate::context::on_abandoned
+
+use std::panic::Location;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+type ShutdownCallback = Box<dyn FnOnce() + Send>;
+
+/// What [`install`] does with a fatal error.
+pub struct FatalConfig {
+    callbacks: Vec<ShutdownCallback>,
+    flush_timeout: Duration,
+    exit_code: i32,
+    abandoned: bool,
+}
+
+impl FatalConfig {
+    /// No shutdown callbacks, a flush timeout of 5 seconds, exiting with code 1 on unhandled
+    /// errors only.
+    pub fn new() -> Self {
+        Self {
+            callbacks: Vec::new(),
+            flush_timeout: Duration::from_secs(5),
+            exit_code: 1,
+            abandoned: false,
+        }
+    }
+
+    /// Run `callback` before exiting, after the callbacks added before it.
+    pub fn on_shutdown(mut self, callback: impl FnOnce() + Send + 'static) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// How long the shutdown callbacks may run before the process exits without them.
+    pub fn flush_timeout(mut self, flush_timeout: Duration) -> Self {
+        self.flush_timeout = flush_timeout;
+        self
+    }
+
+    /// The code the process exits with, also when the flush timeout expires.
+    pub fn exit_code(mut self, exit_code: i32) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+
+    /// Also exit when a panic abandons a captured error, see
+    /// [`on_abandoned`](crate::context::on_abandoned).
+    pub fn abandoned(mut self, abandoned: bool) -> Self {
+        self.abandoned = abandoned;
+        self
+    }
+}
+
+impl Default for FatalConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for FatalConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FatalConfig")
+            .field("callbacks", &self.callbacks.len())
+            .field("flush_timeout", &self.flush_timeout)
+            .field("exit_code", &self.exit_code)
+            .field("abandoned", &self.abandoned)
+            .finish()
+    }
+}
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static ABANDONED: AtomicBool = AtomicBool::new(false);
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+static CONFIG: Mutex<Option<FatalConfig>> = Mutex::new(None);
+
+/// Make errors no scope handles end the process, as described by `config`.
+///
+/// Replaces the configuration of an earlier call. Applies to every thread of the process.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use xcept::fatal::FatalConfig;
+///
+/// xcept::fatal::install(
+///     FatalConfig::new()
+///         .on_shutdown(|| eprintln!("flushing metrics"))
+///         .flush_timeout(Duration::from_secs(2))
+///         .exit_code(70),
+/// );
+/// // Not handled by any scope, so the process exits with code 70
+/// let _: xcept::Result<()> = xcept::Result::new_error("corrupt state");
+/// ```
+pub fn install(config: FatalConfig) {
+    ABANDONED.store(config.abandoned, Ordering::Relaxed);
+    *CONFIG.lock().unwrap_or_else(|e| e.into_inner()) = Some(config);
+    INSTALLED.store(true, Ordering::Release);
+}
+
+/// Test if [`install`] was called.
+pub fn is_installed() -> bool {
+    INSTALLED.load(Ordering::Acquire)
+}
+
+/// Called for every raise no scope accepted.
+#[inline]
+pub(crate) fn unhandled(id: u64, type_name: &'static str, location: Option<&'static Location<'static>>) {
+    if is_installed() {
+        let report = match location {
+            Some(location) => format!("unhandled error #{id} of type `{type_name}`, raised at {location}"),
+            None => format!("unhandled error #{id} of type `{type_name}`"),
+        };
+        shut_down(&report);
+    }
+}
+
+/// Called for every error abandoned by a panic.
+pub(crate) fn abandoned(abandoned: &crate::context::Abandoned) {
+    if is_installed() && ABANDONED.load(Ordering::Relaxed) {
+        let report = format!(
+            "abandoned error of type `{}` in `{}`",
+            abandoned.type_name.unwrap_or("<unknown>"),
+            abandoned.context
+        );
+        shut_down(&report);
+    }
+}
+
+/// Report the error, run the shutdown callbacks and exit, unless already shutting down.
+#[cold]
+fn shut_down(report: &str) {
+    if SHUTTING_DOWN.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let config = CONFIG.lock().unwrap_or_else(|e| e.into_inner()).take();
+    let Some(config) = config else {
+        return;
+    };
+    eprintln!("fatal: {report}");
+
+    let exit_code = config.exit_code;
+    let flush_timeout = config.flush_timeout;
+    let watchdog = std::thread::Builder::new().name("xcept-fatal".into()).spawn(move || {
+        std::thread::sleep(flush_timeout);
+        eprintln!("fatal: shutdown callbacks did not finish within {flush_timeout:?}");
+        std::process::exit(exit_code);
+    });
+    if watchdog.is_err() {
+        // Without a watchdog a hanging callback would keep the process alive
+        std::process::exit(exit_code);
+    }
+
+    crate::flush_enrichment();
+    #[cfg(feature = "log")]
+    ::log::logger().flush();
+    for callback in config.callbacks {
+        // A panicking callback must not stop the others, or the exit
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(callback));
+    }
+    std::process::exit(exit_code);
+}
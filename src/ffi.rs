@@ -0,0 +1,125 @@
+//! Recovery from foreign code skipping Rust frames.
+//!
+//! Some C libraries, like GUI toolkits and scripting VMs, leave a callback with `longjmp` instead
+//! of returning. The scope guards of the skipped frames never run, so the scopes of the thread
+//! still refer to dead stack frames, and the next raise would write through dangling pointers.
+//!
+//! Take a [`FrameMark`] with [`mark_frame`] before calling such a library, and pass it to
+//! [`recover_to`] when control comes back through a `longjmp`, before raising anything.
+
+use crate::context::{restore_scopes, snapshot_scopes, ScopesSnapshot};
+use std::marker::PhantomData;
+
+/// The scope state of a thread, taken by [`mark_frame`].
+#[must_use = "a mark is only useful when passed to `recover_to`"]
+pub struct FrameMark {
+    snapshot: ScopesSnapshot,
+    _not_send: PhantomData<*mut ()>,
+}
+
+/// Remember the active scopes of the current thread, to return to them with [`recover_to`].
+pub fn mark_frame() -> FrameMark {
+    FrameMark {
+        snapshot: snapshot_scopes(),
+        _not_send: PhantomData,
+    }
+}
+
+/// Forget every scope pushed after `mark` was taken, restoring the state of the thread at the
+/// time of the mark.
+///
+/// Errors raised afterwards are delivered to the scopes that were active when `mark` was taken.
+/// The active operation, see [`enter_operation`](crate::context::enter_operation), and secondary
+/// error suppression are restored as well.
+///
+/// Errors captured by the forgotten scopes but not handled yet are leaked. Their contexts lived
+/// in the skipped frames, so they can't be inspected, and no
+/// [`on_abandoned`](crate::context::on_abandoned) hook runs for them.
+///
+/// # Safety
+///
+/// Every scope pushed since `mark` was taken must belong to a frame that was skipped, none of
+/// their guards may be dropped afterwards. The scopes active when `mark` was taken must still
+/// be active. With the `paranoid` feature, dropping one of the skipped guards panics.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::context::{ScopeNode, SingleErrorStorage};
+///
+/// let res = xcept::try_or_handle_one(
+///     || {
+///         let mark = xcept::ffi::mark_frame();
+///         // Stands in for a callback left through `longjmp`, its scope is never popped
+///         let storage = Box::leak(Box::new(SingleErrorStorage::<i32>::default()));
+///         let scope = std::pin::Pin::static_mut(Box::leak(Box::new(ScopeNode::new(storage))));
+///         std::mem::forget(unsafe { scope.register() });
+///
+///         unsafe { xcept::ffi::recover_to(mark) };
+///         xcept::Result::new_error(5)
+///     },
+///     |err: i32| xcept::Result::new(err),
+/// );
+/// assert_eq!(res.unwrap(), 5);
+/// ```
+pub unsafe fn recover_to(mark: FrameMark) {
+    restore_scopes(mark.snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mark_frame, recover_to};
+    use crate::context::{push_scope, ScopeNode, SingleErrorStorage};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    struct Counted(u32, Rc<Cell<u32>>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.1.set(self.1.get() + 1);
+        }
+    }
+
+    /// Pushes `depth` scopes without ever popping them, like frames skipped by `longjmp`.
+    ///
+    /// The scopes and contexts are leaked instead of left on a dead stack frame, so a missing
+    /// recovery fails the test instead of being undefined behavior.
+    fn skipped_frames(depth: usize, drops: &Rc<Cell<u32>>) {
+        std::mem::forget(crate::context::enter_operation());
+        for _ in 0..depth {
+            let storage = Box::leak(Box::new(SingleErrorStorage::<Counted>::default()));
+            let scope = Box::leak(Box::new(ScopeNode::raw(storage)));
+            std::mem::forget(unsafe { push_scope(scope) });
+        }
+        // Captured by the innermost skipped scope, and leaked with it
+        let _: crate::Result<()> = crate::Result::new_error(Counted(0, drops.clone()));
+    }
+
+    #[test]
+    fn raises_reach_surviving_scope() {
+        let drops = Rc::new(Cell::new(0));
+        // Only accepts errors of its own operation, which the skipped frames left
+        let res = crate::multihandler::try_or_handle_isolated(
+            || {
+                let mark = mark_frame();
+                skipped_frames(3, &drops);
+                unsafe { recover_to(mark) };
+                crate::Result::new_error(Counted(7, drops.clone()))
+            },
+            crate::builder(|err: Counted| crate::Result::new(err.0)).build(),
+        );
+        assert_eq!(res.unwrap(), 7);
+        // Only the handled error was dropped, the captured one was leaked
+        assert_eq!(drops.get(), 1);
+        assert_eq!(crate::context::scopes_accept::<Counted>(), Some(false));
+    }
+
+    #[test]
+    fn nothing_skipped() {
+        let mark = mark_frame();
+        let epoch = crate::context::current_epoch();
+        unsafe { recover_to(mark) };
+        assert_eq!(crate::context::current_epoch(), epoch);
+    }
+}
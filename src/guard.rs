@@ -0,0 +1,160 @@
+//! Checks of invariants that raise errors instead of panicking.
+
+use std::fmt;
+use std::panic::Location;
+
+/// The comparison a [`GuardFailure`] failed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GuardKind {
+    /// The operands were expected to be equal, see [`guard_eq`]
+    Eq,
+    /// The operands were expected to differ, see [`guard_ne`]
+    Ne,
+}
+
+/// The error raised by [`guard_eq`] and [`guard_ne`], holding both operands.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GuardFailure<A, B> {
+    /// The left operand
+    pub left: A,
+    /// The right operand
+    pub right: B,
+    /// The comparison that failed
+    pub kind: GuardKind,
+    /// Where the guard was called
+    pub location: &'static Location<'static>,
+}
+
+impl<A: fmt::Debug, B: fmt::Debug> fmt::Display for GuardFailure<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.kind {
+            GuardKind::Eq => "==",
+            GuardKind::Ne => "!=",
+        };
+        write!(
+            f,
+            "guard `left {op} right` failed at {}\n  left: {:?}\n right: {:?}",
+            self.location, self.left, self.right
+        )
+    }
+}
+
+impl<A: fmt::Debug, B: fmt::Debug> std::error::Error for GuardFailure<A, B> {}
+
+/// Raise the error made by `err` unless `cond` holds.
+///
+/// # Examples
+///
+/// ```
+/// struct Empty;
+///
+/// fn first(items: &[i32]) -> xcept::Result<i32> {
+///     if let Err(id) = xcept::guard(!items.is_empty(), || Empty).try_unwrap() {
+///         return xcept::Result::new_with_error_id(id);
+///     }
+///     xcept::Result::new(items[0])
+/// }
+///
+/// let res = xcept::try_or_handle_one(|| first(&[]), |_: Empty| xcept::Result::new(-1));
+/// assert_eq!(res.unwrap(), -1);
+/// ```
+#[track_caller]
+#[inline]
+pub fn guard<E: crate::Error>(cond: bool, err: impl FnOnce() -> E) -> crate::Result<()> {
+    if cond {
+        crate::Result::new(())
+    } else {
+        crate::Result::new_error(err())
+    }
+}
+
+/// Raise a [`GuardFailure`] holding both operands unless `left == right`.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::GuardFailure;
+///
+/// let res = xcept::try_or_handle_one(
+///     || xcept::guard_eq(2 + 2, 5),
+///     |err: GuardFailure<i32, i32>| {
+///         assert_eq!((err.left, err.right), (4, 5));
+///         xcept::Result::new(())
+///     },
+/// );
+/// assert!(res.is_ok());
+/// ```
+#[track_caller]
+#[inline]
+pub fn guard_eq<A, B>(left: A, right: B) -> crate::Result<()>
+where
+    A: PartialEq<B> + fmt::Debug + 'static,
+    B: fmt::Debug + 'static,
+{
+    if left == right {
+        crate::Result::new(())
+    } else {
+        fail(left, right, GuardKind::Eq)
+    }
+}
+
+/// Raise a [`GuardFailure`] holding both operands unless `left != right`.
+#[track_caller]
+#[inline]
+pub fn guard_ne<A, B>(left: A, right: B) -> crate::Result<()>
+where
+    A: PartialEq<B> + fmt::Debug + 'static,
+    B: fmt::Debug + 'static,
+{
+    if left != right {
+        crate::Result::new(())
+    } else {
+        fail(left, right, GuardKind::Ne)
+    }
+}
+
+#[track_caller]
+#[cold]
+fn fail<A: 'static, B: 'static>(left: A, right: B, kind: GuardKind) -> crate::Result<()> {
+    crate::Result::new_error(GuardFailure {
+        left,
+        right,
+        kind,
+        location: Location::caller(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{guard_eq, guard_ne, GuardFailure, GuardKind};
+
+    #[test]
+    fn captures_operands() {
+        let line = line!() + 2;
+        let res = crate::try_or_handle_one(
+            || guard_eq(3, 1 + 1).and(crate::Result::new(None)),
+            |err: GuardFailure<i32, i32>| crate::Result::new(Some(err)),
+        );
+        let err = res.unwrap().unwrap();
+        assert_eq!((err.left, err.right, err.kind), (3, 2, GuardKind::Eq));
+        assert_eq!((err.location.file(), err.location.line()), (file!(), line));
+        assert!(err.to_string().contains("left: 3"));
+
+        let res = crate::try_or_handle_one(
+            || guard_ne("a", "a").and(crate::Result::new(false)),
+            |err: GuardFailure<&str, &str>| crate::Result::new(err.kind == GuardKind::Ne),
+        );
+        assert!(res.unwrap());
+        assert!(guard_eq(String::from("x"), "x").is_ok());
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn raise_location_is_caller() {
+        let line = line!() + 1;
+        let _ = guard_eq(1, 2);
+        let recent = crate::debug::recent_raises();
+        assert_eq!(recent.last().unwrap().location.unwrap().line(), line);
+    }
+}
@@ -0,0 +1,213 @@
+//! Tracks what happened to each error ID, enabled by the `paranoid` feature.
+//!
+//! Every raised ID must be captured by at most one storage and handled at most once. Each step
+//! of an ID is recorded per thread, and a step not allowed after the previous one panics with the
+//! history of the ID. See [`id_state`](crate::debug::id_state).
+
+use crate::context::Disposition;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// The number of IDs tracked per thread before the finished ones are forgotten.
+const CAPACITY: usize = 4096;
+/// The number of most recent steps kept per ID.
+const HISTORY_LEN: usize = 8;
+
+/// A step in the life of an error ID.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IdState {
+    /// The error was raised and is being offered to the scopes
+    Raised,
+    /// A scope accepted the error
+    Captured,
+    /// A handler ran for the error
+    Handled,
+    /// No scope accepted the error and its value was dropped
+    Dropped,
+}
+
+impl IdState {
+    fn allows(self, next: IdState) -> bool {
+        use IdState::*;
+        matches!(
+            (self, next),
+            (Raised, Captured) | (Raised, Dropped) | (Captured, Handled) | (Captured, Raised)
+        )
+    }
+
+    fn is_final(self) -> bool {
+        matches!(self, IdState::Handled | IdState::Dropped)
+    }
+}
+
+/// The most recent steps of an ID, stored inline so tracking doesn't allocate per raise.
+#[derive(Copy, Clone)]
+struct History {
+    steps: [IdState; HISTORY_LEN],
+    len: usize,
+}
+
+impl History {
+    fn new() -> Self {
+        Self {
+            steps: [IdState::Raised; HISTORY_LEN],
+            len: 1,
+        }
+    }
+
+    fn last(&self) -> IdState {
+        self.steps[self.len - 1]
+    }
+
+    fn push(&mut self, step: IdState) {
+        if self.len == HISTORY_LEN {
+            self.steps.copy_within(1.., 0);
+            self.len -= 1;
+        }
+        self.steps[self.len] = step;
+        self.len += 1;
+    }
+
+    fn steps(&self) -> &[IdState] {
+        &self.steps[..self.len]
+    }
+}
+
+thread_local! {
+    // Allocated once per thread, inserting never grows it
+    static HISTORIES: RefCell<HashMap<crate::ErrorId, History>> = RefCell::new(HashMap::with_capacity(CAPACITY));
+}
+
+fn with_histories(f: impl FnOnce(&mut HashMap<crate::ErrorId, History>)) {
+    let _ = HISTORIES.try_with(|histories| {
+        if let Ok(mut histories) = histories.try_borrow_mut() {
+            f(&mut histories)
+        }
+    });
+}
+
+fn step(id: crate::ErrorId, next: IdState) {
+    let mut illegal = None;
+    with_histories(|histories| {
+        // Steps of IDs that were never raised, like those made up by tests of contexts, are ignored
+        if let Some(history) = histories.get_mut(&id) {
+            let current = history.last();
+            history.push(next);
+            if !current.allows(next) {
+                illegal = Some((current, *history));
+            }
+        }
+    });
+    if let Some((current, history)) = illegal {
+        panic!("error {id} went from {current:?} to {next:?}, history: {:?}", history.steps());
+    }
+}
+
+/// A new error was raised with `id`, replacing the history of an earlier error with the same ID.
+pub(crate) fn raised(id: crate::ErrorId) {
+    with_histories(|histories| {
+        if histories.len() >= CAPACITY {
+            histories.retain(|_, history| !history.last().is_final());
+            if histories.len() >= CAPACITY {
+                histories.clear();
+            }
+        }
+        histories.insert(id, History::new());
+    });
+}
+
+/// A captured error was raised again, keeping its ID.
+pub(crate) fn reraised(id: crate::ErrorId) {
+    step(id, IdState::Raised);
+}
+
+/// A storage took the value of the error.
+pub(crate) fn captured(id: crate::ErrorId) {
+    step(id, IdState::Captured);
+}
+
+/// The raise of the error finished, with `disposition`.
+///
+/// Marks the error as captured if it was accepted by a scope that doesn't record it itself.
+pub(crate) fn delivered(id: crate::ErrorId, disposition: Disposition) {
+    let accepted = matches!(disposition, Disposition::Captured | Disposition::Dropped);
+    let current = HISTORIES
+        .try_with(|histories| Some(histories.try_borrow().ok()?.get(&id)?.last()))
+        .ok()
+        .flatten();
+    match current {
+        Some(IdState::Raised) if accepted => step(id, IdState::Captured),
+        Some(_) if accepted => {}
+        _ => step(id, IdState::Dropped),
+    }
+}
+
+/// A handler is run for the error.
+pub(crate) fn handled(id: crate::ErrorId) {
+    step(id, IdState::Handled);
+}
+
+/// The current state of the error `id` on this thread, `None` if it isn't tracked.
+///
+/// Only the IDs of errors raised on this thread are tracked, and the ones that were handled or
+/// dropped are forgotten after a few thousand raises.
+pub fn id_state(id: crate::ErrorId) -> Option<IdState> {
+    HISTORIES
+        .try_with(|histories| Some(histories.borrow().get(&id)?.last()))
+        .ok()
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{id_state, IdState};
+    use crate::context::{ErrorHandlingContext, ReportedError, SingleErrorStorage, TrySetErrorResult};
+    use crate::multihandler::TryHandle;
+
+    #[test]
+    fn lifecycle() {
+        let mut inner = None;
+        let res = crate::try_or_handle_one(
+            || {
+                let res = crate::Result::<u32>::new_error(3u8);
+                inner = res.error_id();
+                assert_eq!(id_state(inner.unwrap()), Some(IdState::Captured));
+                res
+            },
+            |err: u8| crate::Result::new(u32::from(err)),
+        );
+        assert_eq!(res.unwrap(), 3);
+        assert_eq!(id_state(inner.unwrap()), Some(IdState::Handled));
+
+        let unhandled = crate::Result::<()>::new_error(4u8).error_id().unwrap();
+        assert_eq!(id_state(unhandled), Some(IdState::Dropped));
+    }
+
+    /// Offers every error to two storages, which both take the value.
+    #[derive(Default)]
+    struct DoubleCapture {
+        first: SingleErrorStorage<u32>,
+        second: SingleErrorStorage<u32>,
+    }
+
+    impl ErrorHandlingContext for DoubleCapture {
+        unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+            self.first.try_set_error(error);
+            self.second.try_set_error(error)
+        }
+    }
+
+    impl TryHandle for DoubleCapture {
+        type Value = u32;
+        fn try_handle(self, _: crate::ErrorId) -> Option<crate::Result<u32>> {
+            None
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "went from Captured to Captured, history: [Raised, Captured, Captured]")]
+    fn double_capture_detected() {
+        // `u32` has no drop glue, so reading it twice is only a logic error
+        let _ = crate::test_support::drive(DoubleCapture::default(), 1u32);
+    }
+}
@@ -0,0 +1,178 @@
+//! Helpers for code bases moving between `std::result::Result` and xcept.
+//!
+//! [`StdResultExt`] raises the error of a std result, optionally wrapping it first. The handler
+//! adaptors [`forward_error`] and [`handle_io_kind`] pass errors a handler is not interested in on
+//! to the enclosing scopes. [`fallible`] makes `?` usable on xcept results inside a closure.
+
+/// Raise the error held by a `std::result::Result`.
+pub trait StdResultExt<T, E> {
+    /// Convert to an xcept [`Result`](crate::Result), raising the error if there is one.
+    ///
+    /// Same as the `From` conversion, but usable where the target type can't be inferred.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xcept::interop::StdResultExt;
+    ///
+    /// let res = xcept::try_or_handle_one(
+    ///     || "x".parse::<i32>().raise(),
+    ///     |_: std::num::ParseIntError| xcept::Result::new(0),
+    /// );
+    /// assert_eq!(res.unwrap(), 0);
+    /// ```
+    fn raise(self) -> crate::Result<T>;
+
+    /// Convert to an xcept [`Result`](crate::Result), raising the error returned by `wrap` if
+    /// there is an error.
+    ///
+    /// Used to add context, such as a line number, the same way `map_err` is used with std
+    /// results.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xcept::interop::StdResultExt;
+    ///
+    /// struct BadLine(i32);
+    ///
+    /// let res = xcept::try_or_handle_one(
+    ///     || "x".parse::<i32>().raise_with(|_| BadLine(3)),
+    ///     |err: BadLine| xcept::Result::new(err.0),
+    /// );
+    /// assert_eq!(res.unwrap(), 3);
+    /// ```
+    fn raise_with<F: crate::Error>(self, wrap: impl FnOnce(E) -> F) -> crate::Result<T>;
+}
+
+impl<T, E: crate::Error> StdResultExt<T, E> for std::result::Result<T, E> {
+    #[cfg_attr(feature = "metadata", track_caller)]
+    #[inline]
+    fn raise(self) -> crate::Result<T> {
+        match self {
+            Ok(v) => crate::Result::new(v),
+            Err(e) => crate::Result::new_error(e),
+        }
+    }
+
+    #[cfg_attr(feature = "metadata", track_caller)]
+    #[inline]
+    fn raise_with<F: crate::Error>(self, wrap: impl FnOnce(E) -> F) -> crate::Result<T> {
+        match self {
+            Ok(v) => crate::Result::new(v),
+            Err(e) => crate::Result::new_error(wrap(e)),
+        }
+    }
+}
+
+/// A handler passing the error on to the enclosing scopes.
+///
+/// The error is raised again from the handler, so it gets a new error ID.
+///
+/// # Examples
+///
+/// ```
+/// let res = xcept::try_or_handle_one(
+///     || xcept::try_or_handle_one(|| xcept::Result::<i32>::new_error(5u8), xcept::interop::forward_error::<u8, _>),
+///     |x: u8| xcept::Result::new(i32::from(x)),
+/// );
+/// assert_eq!(res.unwrap(), 5);
+/// ```
+#[cfg_attr(feature = "metadata", track_caller)]
+pub fn forward_error<E: crate::Error, V>(err: E) -> crate::Result<V> {
+    crate::Result::new_error(err)
+}
+
+/// Wrap an `std::io::Error` handler so it only handles errors of the given kind.
+///
+/// Errors of other kinds are forwarded to the enclosing scopes, see [`forward_error`].
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{Error, ErrorKind};
+///
+/// let open = |kind: ErrorKind| {
+///     xcept::try_or_handle_one(
+///         || xcept::try_or_handle_one(
+///             || xcept::Result::new_error(Error::from(kind)),
+///             xcept::interop::handle_io_kind(ErrorKind::NotFound, |_| xcept::Result::new("missing")),
+///         ),
+///         |_: Error| xcept::Result::new("other"),
+///     )
+/// };
+/// assert_eq!(open(ErrorKind::NotFound).unwrap(), "missing");
+/// assert_eq!(open(ErrorKind::PermissionDenied).unwrap(), "other");
+/// ```
+pub fn handle_io_kind<V>(
+    kind: std::io::ErrorKind,
+    handler: impl FnOnce(std::io::Error) -> crate::Result<V>,
+) -> impl FnOnce(std::io::Error) -> crate::Result<V> {
+    move |err| {
+        if err.kind() == kind {
+            handler(err)
+        } else {
+            forward_error(err)
+        }
+    }
+}
+
+/// The ID of an error being propagated out of a [`fallible`] closure with `?`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Propagate(crate::ErrorId);
+
+impl Propagate {
+    /// The ID of the propagated error.
+    pub fn id(self) -> crate::ErrorId {
+        self.0
+    }
+}
+
+/// Passed to the closure of [`fallible`], converts xcept results for use with `?`.
+pub struct Fallible {
+    _not_send: std::marker::PhantomData<*mut ()>,
+}
+
+impl Fallible {
+    /// Run `func`, turning an error it returns into a [`Propagate`] for `?`.
+    ///
+    /// The error was already delivered to the handling scopes when it was raised, only its ID
+    /// is propagated.
+    pub fn run<T>(&mut self, func: impl FnOnce() -> crate::Result<T>) -> Result<T, Propagate> {
+        func().try_unwrap().map_err(Propagate)
+    }
+}
+
+/// Convert a closure using `?` on [`Fallible::run`] into a closure returning an xcept
+/// [`Result`](crate::Result), keeping the ID of a propagated error.
+///
+/// This bridges the missing `Try` support of xcept results, the steps after a failing one don't
+/// run.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::interop::StdResultExt;
+///
+/// let parse = |s: &'static str| move || s.parse::<i32>().raise_with(|_| "not a number");
+/// let res = xcept::try_or_handle_one(
+///     xcept::fallible(|ctx| {
+///         let a = ctx.run(parse("1"))?;
+///         let b = ctx.run(parse("x"))?;
+///         Ok(a + b)
+///     }),
+///     |_: &'static str| xcept::Result::new(0),
+/// );
+/// assert_eq!(res.unwrap(), 0);
+/// ```
+pub fn fallible<T>(func: impl FnOnce(&mut Fallible) -> Result<T, Propagate>) -> impl FnOnce() -> crate::Result<T> {
+    move || {
+        let mut ctx = Fallible {
+            _not_send: std::marker::PhantomData,
+        };
+        match func(&mut ctx) {
+            Ok(value) => crate::Result::new(value),
+            Err(propagate) => crate::Result::new_with_error_id(propagate.0),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Iterator adaptors for iterators over [`Result`](crate::Result)s.
+
+use crate::context::SingleErrorStorage;
+
+/// Extension trait for iterators over [`Result`](crate::Result)s.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::iter::FallibleIteratorExt;
+///
+/// type ParseError = <i32 as std::str::FromStr>::Err;
+///
+/// let parsed = ["1", "x", "3"]
+///     .into_iter()
+///     .map(|s| xcept::Result::from(s.parse::<i32>()))
+///     .handle_each(|_: ParseError| Some(0))
+///     .map(|res| res.unwrap())
+///     .collect::<Vec<_>>();
+/// assert_eq!(parsed, [1, 0, 3]);
+/// ```
+pub trait FallibleIteratorExt<T>: Iterator<Item = crate::Result<T>> + Sized {
+    /// Yield the values up to the first error.
+    ///
+    /// The ID of the error is available from [`OkValues::error_id`] afterwards.
+    fn ok_values(self) -> OkValues<Self> {
+        OkValues {
+            iter: self,
+            error_id: None,
+        }
+    }
+
+    /// Handle errors of type `E` raised while producing each item.
+    ///
+    /// Every call to `next` of the underlying iterator runs in its own handling scope. An error
+    /// of type `E` is passed to `f`, which either substitutes a value or returns `None` to skip
+    /// the item. Errors of other types are yielded as they are.
+    fn handle_each<E, F>(self, f: F) -> HandleEach<Self, E, F>
+    where
+        E: crate::Error,
+        F: FnMut(E) -> Option<T>,
+    {
+        HandleEach {
+            iter: self,
+            handler: f,
+            _error: std::marker::PhantomData,
+        }
+    }
+
+    /// Collect all values, or return the first error.
+    ///
+    /// Iteration stops at the first error.
+    fn raise_first(self) -> crate::Result<Vec<T>> {
+        let mut values = Vec::new();
+        for item in self {
+            match item.try_unwrap() {
+                Ok(value) => values.push(value),
+                Err(id) => return crate::Result::new_with_error_id(id),
+            }
+        }
+        crate::Result::new(values)
+    }
+}
+
+impl<T, I: Iterator<Item = crate::Result<T>>> FallibleIteratorExt<T> for I {}
+
+/// Iterator returned by [`FallibleIteratorExt::ok_values`].
+pub struct OkValues<I> {
+    iter: I,
+    error_id: Option<crate::ErrorId>,
+}
+
+impl<I> OkValues<I> {
+    /// The ID of the error that stopped the iteration, if any.
+    pub fn error_id(&self) -> Option<crate::ErrorId> {
+        self.error_id
+    }
+}
+
+impl<T, I: Iterator<Item = crate::Result<T>>> Iterator for OkValues<I> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.error_id.is_some() {
+            return None;
+        }
+        match self.iter.next()?.try_unwrap() {
+            Ok(value) => Some(value),
+            Err(id) => {
+                self.error_id = Some(id);
+                None
+            }
+        }
+    }
+}
+
+/// Iterator returned by [`FallibleIteratorExt::handle_each`].
+pub struct HandleEach<I, E, F> {
+    iter: I,
+    handler: F,
+    _error: std::marker::PhantomData<fn(E)>,
+}
+
+impl<T, I, E, F> Iterator for HandleEach<I, E, F>
+where
+    I: Iterator<Item = crate::Result<T>>,
+    E: crate::Error,
+    F: FnMut(E) -> Option<T>,
+{
+    type Item = crate::Result<T>;
+
+    fn next(&mut self) -> Option<crate::Result<T>> {
+        loop {
+            let mut storage = SingleErrorStorage::<E>::default();
+            let item = {
+                let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut storage));
+                // Safety: the guard is dropped at the end of the block
+                let _guard = unsafe { scope.register() };
+                self.iter.next()
+            };
+
+            let item = item?;
+            match (item.error_id(), storage.into_inner()) {
+                (Some(id), Some((captured, err))) if id == captured => {
+                    if let Some(value) = (self.handler)(err) {
+                        return Some(crate::Result::new(value));
+                    }
+                }
+                _ => return Some(item),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FallibleIteratorExt;
+
+    type ParseError = <i32 as std::str::FromStr>::Err;
+
+    const INPUT: [&str; 10] = ["1", "2", "x", "4", "5", "6", "y", "8", "9", "10"];
+
+    fn parse(s: &str) -> crate::Result<i32> {
+        s.parse().into()
+    }
+
+    #[test]
+    fn substitute() {
+        let values: Vec<_> = INPUT
+            .iter()
+            .map(|s| parse(s))
+            .handle_each(|_: ParseError| Some(-1))
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(values, [1, 2, -1, 4, 5, 6, -1, 8, 9, 10]);
+    }
+
+    #[test]
+    fn skip() {
+        let mut failures = 0;
+        let values: Vec<_> = INPUT
+            .iter()
+            .map(|s| parse(s))
+            .handle_each(|_: ParseError| {
+                failures += 1;
+                None
+            })
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(values, [1, 2, 4, 5, 6, 8, 9, 10]);
+        assert_eq!(failures, 2);
+    }
+
+    #[test]
+    fn other_errors_pass_through() {
+        let items: Vec<_> = INPUT
+            .iter()
+            .map(|s| parse(s))
+            .handle_each(|_: bool| Some(0))
+            .collect();
+        assert_eq!(items.iter().filter(|r| r.is_error()).count(), 2);
+    }
+
+    #[test]
+    fn ok_values_and_raise_first() {
+        let mut iter = INPUT.iter().map(|s| parse(s)).ok_values();
+        assert_eq!(iter.by_ref().collect::<Vec<_>>(), [1, 2]);
+        assert!(iter.error_id().is_some());
+
+        let res = INPUT.iter().map(|s| parse(s)).raise_first();
+        assert!(res.is_error());
+        let res = INPUT[..2].iter().map(|s| parse(s)).raise_first();
+        assert_eq!(res.unwrap(), [1, 2]);
+    }
+}
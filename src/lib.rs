@@ -1,12 +1,88 @@
+#![cfg_attr(feature = "nightly", feature(try_trait_v2, try_trait_v2_residual))]
+
 use crate::context::SingleErrorStorage;
+use std::any::TypeId;
 use std::hint::unreachable_unchecked;
 use std::marker::PhantomData;
 
+/// `assert!` with the `paranoid` feature, `debug_assert!` otherwise.
+macro_rules! paranoid_assert {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "paranoid")]
+        assert!($($arg)*);
+        #[cfg(not(feature = "paranoid"))]
+        debug_assert!($($arg)*);
+    };
+}
+
+pub mod augment;
+pub mod batch;
+pub mod blocking;
+pub mod capture;
+pub mod compat;
+pub mod conformance;
 pub mod context;
+#[cfg(any(feature = "diagnostics", feature = "paranoid"))]
+pub mod debug;
+pub mod defaults;
+pub mod dyn_registry;
+pub mod dynamic;
+pub mod entry;
+pub mod fatal;
+pub mod ffi;
+pub mod guard;
+#[cfg(feature = "paranoid")]
+mod id_state;
+pub mod interop;
+pub mod iter;
+#[cfg(feature = "log")]
+pub mod log;
+pub mod manifest;
+#[cfg(feature = "metadata")]
+mod metadata;
 pub mod multihandler;
+pub mod policy;
+pub mod redact;
+pub mod retry;
+pub mod runtime;
+pub mod scoped;
+pub mod signal;
+#[cfg(feature = "simulation")]
+pub mod sim;
+pub mod stable_id;
+#[cfg(feature = "futures")]
+pub mod stream;
+pub mod supervisor;
+pub mod test_support;
+pub mod watchdog;
 
+pub use augment::augment_scope;
+pub use batch::{metadata_for, BatchError};
+pub use capture::capture_set;
+pub use context::on_raise;
+pub use context::{clear_pending_alarm, pending_count, set_pending_alarm};
+pub use context::panic_on_raise;
+pub use context::suppressing_secondary;
+pub use dyn_registry::register_equivalence;
+pub use guard::{guard, guard_eq, guard_ne, GuardFailure};
+pub use interop::fallible;
 pub use multihandler::builder;
 pub use multihandler::try_or_handle;
+pub use retry::retry_backoff;
+pub use scoped::scoped;
+
+/// Install the top-level scope and reporter of [`entry`] around `main`.
+///
+/// Applied to `fn main() -> xcept::Result<T>` or a plain `fn main()`, requires the `macros`
+/// feature. Takes the optional arguments `backtrace`, `exit_code = N` and `handlers = f`, a
+/// function returning a handler chain wrapped around the body like [`try_or_handle`].
+#[cfg(feature = "macros")]
+pub use xcept_macros::main;
+/// Run a test in the top-level scope of [`entry`], failing it on any unhandled error.
+///
+/// Used instead of `#[test]`, takes the same arguments as [`main`] except `exit_code`.
+#[cfg(feature = "macros")]
+pub use xcept_macros::test;
 
 /// Marker trait for error compatible types
 ///
@@ -15,16 +91,526 @@ pub trait Error: 'static {}
 
 impl<T: 'static> Error for T {}
 
+/// An error type with a field recording where it is raised.
+///
+/// Errors raised with [`Result::new_located_error`] get the field filled in automatically.
+/// Usually derived with `#[derive(LocatedError)]` (requires the `macros` feature), which fills
+/// the field marked `#[xcept(location)]`, or else the field named `location`. The field can be
+/// a `&'static Location<'static>` or an `Option` of one.
+pub trait LocatedError: Error {
+    /// Record the location the error is raised from.
+    fn set_location(&mut self, location: &'static std::panic::Location<'static>);
+}
+
+#[cfg(feature = "macros")]
+pub use xcept_macros::LocatedError;
+
+/// Conversion of a possibly borrowing value into an owned, `'static` error type.
+///
+/// Errors must be `'static`, so borrowed errors are converted with [`Result::raise_owned`]
+/// before being raised, and handlers then take the owned type. Implemented for common borrowed
+/// types and, as the identity, for common owned types. Structs whose fields all implement it can
+/// derive it with `#[derive(ToStatic)]` (requires the `macros` feature), which generates an
+/// owned `{Name}Owned` struct with the converted fields.
+pub trait ToStatic {
+    /// The owned type.
+    type Owned: 'static;
+
+    /// Convert to the owned type.
+    fn to_static(self) -> Self::Owned;
+}
+
+#[cfg(feature = "macros")]
+pub use xcept_macros::ToStatic;
+
+impl ToStatic for &str {
+    type Owned = String;
+    fn to_static(self) -> String {
+        self.to_owned()
+    }
+}
+
+impl ToStatic for &[u8] {
+    type Owned = Vec<u8>;
+    fn to_static(self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+impl<B> ToStatic for std::borrow::Cow<'_, B>
+where
+    B: ToOwned + ?Sized,
+    B::Owned: 'static,
+{
+    type Owned = B::Owned;
+    fn to_static(self) -> B::Owned {
+        self.into_owned()
+    }
+}
+
+impl ToStatic for std::fmt::Arguments<'_> {
+    type Owned = String;
+    fn to_static(self) -> String {
+        std::fmt::format(self)
+    }
+}
+
+impl<T: ToStatic> ToStatic for Option<T> {
+    type Owned = Option<T::Owned>;
+    fn to_static(self) -> Self::Owned {
+        self.map(T::to_static)
+    }
+}
+
+macro_rules! to_static_identity {
+    ($($ty:ty),*) => {
+        $(
+            impl ToStatic for $ty {
+                type Owned = Self;
+                #[inline]
+                fn to_static(self) -> Self {
+                    self
+                }
+            }
+        )*
+    };
+}
+
+to_static_identity!(
+    bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, String,
+    Vec<u8>, std::path::PathBuf, std::io::Error
+);
+
+/// Run the deferred metadata work of every raise on the current thread so far.
+///
+/// The metadata of a raise, like the entries of `debug::recent_raises` and the records of the
+/// `log` feature, is currently produced on the raise path, so there is nothing to run yet.
+/// Call this where the metadata must be complete, like before exiting, so that consumers moved
+/// off the raise path later keep working.
+#[inline]
+pub fn flush_enrichment() {}
+
+/// A zero-sized error type, distinguished only by its marker type `M`.
+///
+/// Useful for errors that carry no information besides having happened. Raising a `Flag` never
+/// allocates and never needs to be dropped, and [`Builder::handle_flag`] handles it without taking
+/// a value. Use [`flag!`] to declare a standalone unit error type instead.
+///
+/// [`Builder::handle_flag`]: crate::multihandler::Builder::handle_flag
+///
+/// # Examples
+///
+/// ```
+/// enum Cancelled {}
+///
+/// let handlers = xcept::builder(|_: std::io::Error| xcept::Result::new(1))
+///     .handle_flag::<xcept::Flag<Cancelled>>(|| xcept::Result::new(2))
+///     .build();
+/// let res = xcept::try_or_handle(|| xcept::Result::new_error(xcept::Flag::<Cancelled>::new()), handlers);
+/// assert_eq!(res.unwrap(), 2);
+/// ```
+pub struct Flag<M: ?Sized>(PhantomData<fn() -> M>);
+
+impl<M: ?Sized> Flag<M> {
+    /// Create the flag.
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: ?Sized> Default for Flag<M> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M: ?Sized> Clone for Flag<M> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M: ?Sized> Copy for Flag<M> {}
+
+impl<M: ?Sized> std::fmt::Debug for Flag<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Flag<{}>", std::any::type_name::<M>())
+    }
+}
+
+impl<M: ?Sized> std::fmt::Display for Flag<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "flag {}", std::any::type_name::<M>())
+    }
+}
+
+impl<M: ?Sized> std::error::Error for Flag<M> {}
+
+/// Declare unit error types carrying no data.
+///
+/// Each name becomes a distinct zero-sized struct deriving the common traits, suitable for
+/// [`Builder::handle_flag`]. Attributes and a visibility may precede each name.
+///
+/// [`Builder::handle_flag`]: crate::multihandler::Builder::handle_flag
+///
+/// # Examples
+///
+/// ```
+/// xcept::flag!(Timeout, pub Cancelled);
+///
+/// let handlers = xcept::builder(|_: Timeout| xcept::Result::new("timeout"))
+///     .handle_flag::<Cancelled>(|| xcept::Result::new("cancelled"))
+///     .build();
+/// let res = xcept::try_or_handle(|| xcept::Result::new_error(Cancelled), handlers);
+/// assert_eq!(res.unwrap(), "cancelled");
+/// ```
+#[macro_export]
+macro_rules! flag {
+    ($($(#[$attr:meta])* $vis:vis $name:ident),+ $(,)?) => {
+        $(
+            $(#[$attr])*
+            #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+            $vis struct $name;
+        )+
+    };
+}
+
+/// Yield the value of a [`Result`], or return its error from the enclosing function.
+///
+/// The stable counterpart of `?`. Only the error ID is returned, the error was delivered to the
+/// scopes when it was raised, so the enclosing function may return a `Result` of another value
+/// type. The second form applies `map` to the value.
+///
+/// # Examples
+///
+/// ```
+/// fn parse(s: &str) -> xcept::Result<u32> {
+///     xcept::Result::from(s.parse::<u32>())
+/// }
+///
+/// fn describe(a: &str, b: &str) -> xcept::Result<String> {
+///     let sum = xcept::check!(parse(a)) + xcept::check!(parse(b));
+///     let doubled = xcept::check!(parse(a), |v| v * 2);
+///     xcept::Result::new(format!("{sum} {doubled}"))
+/// }
+///
+/// assert_eq!(describe("1", "2").unwrap(), "3 2");
+/// let res = xcept::try_or_handle_one(|| describe("1", "x"), |_: std::num::ParseIntError| xcept::Result::new("bad".to_string()));
+/// assert_eq!(res.unwrap(), "bad");
+/// ```
+#[macro_export]
+macro_rules! check {
+    ($expr:expr $(,)?) => {
+        match $crate::Result::__propagate($expr) {
+            ::core::result::Result::Ok(value) => value,
+            ::core::result::Result::Err(err) => return err,
+        }
+    };
+    ($expr:expr, $map:expr $(,)?) => {
+        ($map)($crate::check!($expr))
+    };
+}
+
+/// Raise an error and return it from the enclosing function.
+///
+/// The enclosing function may return a [`Result`] of any value type.
+///
+///   * `throw!(err)` raises `err`, like [`Result::new_error`].
+///   * `throw!("format {}", args)` raises a [`Message`] formatted like `format!`, also without
+///     arguments, so `throw!("no {path}")` captures `path` and `{{` escapes a brace. Any literal is
+///     a format string, raise a literal error like `42` or a `&'static str` with
+///     [`Result::new_error`] or `throw!(@bare ...)`.
+///   * `throw!(@bare err)` raises `err` like [`Result::new_error_bare`].
+///
+/// # Examples
+///
+/// ```
+/// fn open(path: &str) -> xcept::Result<u32> {
+///     if path.is_empty() {
+///         xcept::throw!(std::io::Error::other("empty path"));
+///     }
+///     xcept::throw!("failed to open {path}")
+/// }
+///
+/// let handlers = || {
+///     xcept::builder(|msg: xcept::Message| xcept::Result::new(msg.to_string()))
+///         .handle(|err: std::io::Error| xcept::Result::new(err.to_string()))
+///         .build()
+/// };
+/// let res = xcept::try_or_handle(|| open("a.txt").map(|fd| fd.to_string()), handlers());
+/// assert_eq!(res.unwrap(), "failed to open a.txt");
+/// let res = xcept::try_or_handle(|| open("").map(|fd| fd.to_string()), handlers());
+/// assert_eq!(res.unwrap(), "empty path");
+/// ```
+#[macro_export]
+macro_rules! throw {
+    (@bare $err:expr $(,)?) => {
+        return $crate::Result::new_error_bare($err)
+    };
+    ($fmt:literal $(, $($arg:tt)*)?) => {
+        return $crate::Result::new_error($crate::Message::new(::std::fmt::format(::std::format_args!(
+            $fmt $(, $($arg)*)?
+        ))))
+    };
+    ($err:expr $(,)?) => {
+        return $crate::Result::new_error($err)
+    };
+}
+
+/// Raise an error and return it from the enclosing function unless `cond` holds.
+///
+/// The error is given like to [`throw!`], either as an expression or as a format string with
+/// optional arguments, raising a [`Message`]. Any literal is a format string, so
+/// `ensure!(x > 0, "got {x}")` captures `x`. Nothing is evaluated besides `cond` when it holds.
+///
+/// # Examples
+///
+/// ```
+/// #[derive(Debug)]
+/// struct TooLarge(u32);
+///
+/// fn validate(x: i32, limit: u32) -> xcept::Result<u32> {
+///     xcept::ensure!(x > 0, "x must be positive, got {x}");
+///     xcept::ensure!(x as u32 <= limit, TooLarge(x as u32));
+///     xcept::Result::new(x as u32)
+/// }
+///
+/// let handlers = || {
+///     xcept::builder(|msg: xcept::Message| xcept::Result::new(msg.into_string()))
+///         .handle(|err: TooLarge| xcept::Result::new(format!("{err:?}")))
+///         .build()
+/// };
+/// let res = xcept::try_or_handle(|| validate(-1, 10).map(|x| x.to_string()), handlers());
+/// assert_eq!(res.unwrap(), "x must be positive, got -1");
+/// let res = xcept::try_or_handle(|| validate(11, 10).map(|x| x.to_string()), handlers());
+/// assert_eq!(res.unwrap(), "TooLarge(11)");
+/// ```
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr, $fmt:literal $(, $($arg:tt)*)?) => {
+        if !$cond {
+            $crate::throw!($fmt $(, $($arg)*)?);
+        }
+    };
+    ($cond:expr, $err:expr $(,)?) => {
+        if !$cond {
+            $crate::throw!($err);
+        }
+    };
+}
+
+/// A formatted error message, raised by the formatting form of [`throw!`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Message(String);
+
+impl Message {
+    /// Create a message error.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+
+    /// The message.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Convert into the message.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl std::fmt::Display for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Message {}
+
+/// The error raised when converting `None` into a [`Result`].
+///
+/// # Examples
+///
+/// ```
+/// let handlers = xcept::builder(|_: xcept::NoneError| xcept::Result::new("missing"))
+///     .handle(|_: std::io::Error| xcept::Result::new("io"))
+///     .build();
+/// let res = xcept::try_or_handle(|| xcept::Result::from(None::<&str>), handlers);
+/// assert_eq!(res.unwrap(), "missing");
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NoneError;
+
+impl std::fmt::Display for NoneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("value was `None`")
+    }
+}
+
+impl std::error::Error for NoneError {}
+
+/// The ID of an error, held by a [`Result`] in place of the error value.
+///
+/// The error value is held by the handling scopes, only its ID is carried. The representation is
+/// opaque, convert from and to the raw `u64` with [`ErrorId::from_raw`] and [`ErrorId::to_raw`]
+/// only where an integer is needed, like at an FFI boundary.
+///
+/// # Migrating from raw IDs
+///
+/// Error IDs used to be plain `u32` values. [`Result::error_id`], [`Result::new_with_error_id`],
+/// `TryHandle::try_handle`, the `push_error` functions of [`context`], the `id` and `into_inner`
+/// methods of the reported errors and storages, `ContextExt::on_disposition` and
+/// `debug::id_state` now take or return an `ErrorId`. Contexts store the `ErrorId` of
+/// [`ReportedError::id`](context::ReportedError::id) and compare it with the one passed to
+/// `TryHandle::try_handle` directly. The deprecated [`Result::error_id_raw`],
+/// [`Result::new_with_raw_error_id`] and the public fields of `ReportedError` keep the raw
+/// representation for code that hasn't migrated yet.
+///
+/// # Examples
+///
+/// ```
+/// let res: xcept::Result<()> = xcept::Result::new_error("failed");
+/// let id = res.error_id().unwrap();
+/// assert_eq!(xcept::ErrorId::from_raw(id.to_raw()), id);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ErrorId(u64);
+
+impl ErrorId {
+    /// The error ID with the raw representation `raw`.
+    #[inline]
+    pub const fn from_raw(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    /// The raw representation of the error ID.
+    #[inline]
+    pub const fn to_raw(self) -> u64 {
+        self.0
+    }
+
+    /// The raw representation of the error ID.
+    #[deprecated(note = "use `ErrorId::to_raw`")]
+    #[inline]
+    pub fn id(self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for ErrorId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error #{}", self.0)
+    }
+}
+
+impl std::error::Error for ErrorId {}
+
 /// The main result type
 ///
 /// Unlike `std::result::Result` this `Result` can only hold a value, or an error flag. The error,
 /// if one has occurred will be set directly at the handling scope.
 ///
+/// # Compared to `std::result::Result`
+///
+/// The error value is never held by the `Result`, only its ID, so combinators needing the error
+/// value can't be provided. The error ID is preserved by every combinator unless noted.
+///
+/// | std                  | xcept                     | Notes                                        |
+/// |----------------------|---------------------------|----------------------------------------------|
+/// | `ok`                 | [`Result::ok`]            |                                              |
+/// | `err`                | [`Result::err`]           | Returns the error ID                         |
+/// | `expect`             | [`Result::expect`]        | The panic message includes the error ID      |
+/// | `unwrap_or`, `unwrap_or_default` | [`Result::unwrap_or`], [`Result::unwrap_or_default`] |      |
+/// | `unwrap_or_else`     | [`Result::unwrap_or_else`] | The closure doesn't get the error           |
+/// | `map`                | [`Result::map`]           |                                              |
+/// | `and_then`           | [`Result::and_then`]      |                                              |
+/// | `map_or`             | [`Result::map_or`]        |                                              |
+/// | `map_or_else`        | [`Result::map_or_else`]   | The default closure doesn't get the error    |
+/// | `inspect`            | [`Result::inspect`]       |                                              |
+/// | `iter`, `iter_mut`   | [`Result::iter`], [`Result::iter_mut`] |                                 |
+/// | `into_iter`          | `IntoIterator`            |                                              |
+/// | `collect`            | `FromIterator`            | Collect lazily, see the `FromIterator` impl  |
+/// | `as_ref`, `as_mut`   | [`Result::as_ref`], [`Result::as_mut`] |                                 |
+/// | `as_deref`           | [`Result::as_deref`]      |                                              |
+/// | `take` (`Option`)    | [`Result::take`]          | Leaves the reserved `context::TAKEN_ID`      |
+/// | `get_or_insert_with` (`Option`) | [`Result::get_or_insert_with_replacing_error`] | The error ID is discarded |
+/// | `and`                | [`Result::and`]           | An error held by the argument is discarded   |
+/// | `or`                 | [`Result::or`]            | The error ID of `self` is discarded          |
+/// | `or_else`            | [`Result::or_try`], [`Result::or_raise`] | Supersede the error of `self` |
+/// | `cloned`, `copied`   | [`Result::cloned`], [`Result::copied`] |                                 |
+/// | `unzip` (`Option`)   | [`Result::unzip`]         | Both results get the same error ID           |
+/// | `flatten`            | [`Result::flatten`]       |                                              |
+/// | `map_err`            | [`Result::map_raise`]     | Raises a new error, the ID changes           |
+/// | `?`                  | `?`, [`check!`]           | `?` needs the `nightly` feature              |
+/// | `xor` (`Option`)     | -                         | Needs an error when both are ok              |
+/// | `unwrap_err`, `expect_err`, `iter_err` | -        | Need the error value                         |
+///
 pub struct Result<T> {
-    value: core::result::Result<T, u32>,
+    value: core::result::Result<T, Raised>,
     _not_send: PhantomData<*mut ()>,
 }
 
+/// The type of a raised error, kept by the `Result` the raise returned.
+pub(crate) struct ErrorType {
+    type_id: fn() -> TypeId,
+    type_name: fn() -> &'static str,
+}
+
+pub(crate) trait HasErrorType {
+    const ERROR_TYPE: ErrorType;
+}
+
+impl<E: 'static> HasErrorType for E {
+    const ERROR_TYPE: ErrorType = ErrorType {
+        type_id: TypeId::of::<E>,
+        type_name: core::any::type_name::<E>,
+    };
+}
+
+/// The error branch of [`Result`].
+#[derive(Copy, Clone)]
+struct Raised {
+    id: u64,
+    /// `None` if the `Result` was created from an ID
+    error_type: Option<&'static ErrorType>,
+}
+
+impl Raised {
+    const fn from_id(id: u64) -> Self {
+        Self { id, error_type: None }
+    }
+
+    fn of<E: Error>(id: u64) -> Self {
+        Self {
+            id,
+            error_type: Some(&<E as HasErrorType>::ERROR_TYPE),
+        }
+    }
+}
+
+// Results compare and print by error ID only, the type is recorded for queries
+impl PartialEq for Raised {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Raised {}
+
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn unwrap_failed(msg: &str, raised: Raised) -> ! {
+    match raised.error_type {
+        Some(error_type) => panic!("{msg}: error ID {}, type `{}`", raised.id, (error_type.type_name)()),
+        None => panic!("{msg}: error ID {}", raised.id),
+    }
+}
+
 impl<T> Result<T> {
     /// Create a new `Result` holding `value`
     ///
@@ -39,9 +625,13 @@ impl<T> Result<T> {
     /// ```
     /// let x = xcept::Result::new(10);
     /// assert_eq!(x.unwrap(), 10);
+    ///
+    /// // Usable in constants, but not in statics since `Result` isn't `Sync`
+    /// const DEFAULT: xcept::Result<u32> = xcept::Result::new(0);
+    /// assert!(DEFAULT.is_ok());
     /// ```
     #[inline]
-    pub fn new(value: T) -> Self {
+    pub const fn new(value: T) -> Self {
         Self {
             value: Ok(value),
             _not_send: PhantomData,
@@ -62,14 +652,42 @@ impl<T> Result<T> {
     /// # Examples
     ///
     /// ```
-    /// let res = xcept::Result::new_with_error_id(1);
-    /// assert_eq!(res.error_id().unwrap(), 1);
+    /// let id = xcept::ErrorId::from_raw(1);
+    /// let res: xcept::Result<i32> = xcept::Result::new_with_error_id(id);
+    /// assert_eq!(res.error_id(), Some(id));
     /// ```
     ///
     #[inline]
-    pub fn new_with_error_id(id: u32) -> Self {
+    pub const fn new_with_error_id(id: ErrorId) -> Self {
+        Self {
+            value: Err(Raised::from_id(id.0)),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Create a `Result` holding the error `id` of the type `error_type`, for errors raised
+    /// again after their type was erased.
+    pub(crate) const fn new_with_error_type(id: ErrorId, error_type: &'static ErrorType) -> Self {
+        Self {
+            value: Err(Raised {
+                id: id.0,
+                error_type: Some(error_type),
+            }),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Create a `Result` holding the error with the raw ID `id`.
+    #[deprecated(note = "use `Result::new_with_error_id` with `ErrorId::from_raw`")]
+    #[inline]
+    pub const fn new_with_raw_error_id(id: u64) -> Self {
+        Self::new_with_error_id(ErrorId(id))
+    }
+
+    #[inline]
+    const fn from_raised(raised: Raised) -> Self {
         Self {
-            value: Err(id),
+            value: Err(raised),
             _not_send: PhantomData,
         }
     }
@@ -77,7 +695,9 @@ impl<T> Result<T> {
     /// Create a new `Result` with an error indication.
     ///
     /// The error is not held within `Result`, but is directly assigned to the nearest handler,
-    /// if one is found. If no handler is found the error is dropped.
+    /// if one is found. If no handler is found the error is dropped. Unlike [`Result::new`] and
+    /// [`Result::new_with_error_id`] this isn't a `const fn`, raising needs the scopes of the
+    /// current thread.
     ///
     /// # Arguments
     ///
@@ -92,244 +712,3795 @@ impl<T> Result<T> {
     /// assert!(!err.is_ok());
     /// assert!(err.is_error());
     /// ```
+    #[cfg_attr(feature = "metadata", track_caller)]
     #[inline]
     pub fn new_error<E: Error>(err: E) -> Self {
         let id = context::push_error(err);
         Self {
-            value: Err(id),
+            value: Err(Raised::of::<E>(id.0)),
             _not_send: PhantomData,
         }
     }
 
-    /// Test if a `Result` contains a value.
+    /// Create a new `Result` with an error indication, only creating the error if a scope may
+    /// handle it.
+    ///
+    /// For error types that are expensive to build. The active scopes are asked whether they
+    /// accept an `E` first, see [`context::scopes_accept`], and `make` is only called if one does
+    /// or can't tell. Otherwise the result just holds a fresh error ID, like an error raised and
+    /// not handled by any scope.
     ///
     /// # Examples
     ///
     /// ```
-    /// let err: xcept::Result<i32> = xcept::Result::new_error("Error");
-    /// assert!(!err.is_ok());
-    /// assert!(err.is_error());
+    /// struct Snapshot(Vec<u8>);
+    ///
+    /// // No scope handles `Snapshot`, so it's never built
+    /// let res: xcept::Result<()> = xcept::Result::new_error_with(|| -> Snapshot { unreachable!() });
+    /// assert!(res.error_is::<Snapshot>());
+    ///
+    /// let res = xcept::try_or_handle_one(
+    ///     || xcept::Result::new_error_with(|| Snapshot(vec![0; 4096])),
+    ///     |snapshot: Snapshot| xcept::Result::new(snapshot.0.len()),
+    /// );
+    /// assert_eq!(res.unwrap(), 4096);
     /// ```
+    #[cfg_attr(feature = "metadata", track_caller)]
     #[inline]
-    pub fn is_ok(&self) -> bool {
-        self.value.is_ok()
-    }
-
-    /// Convert the `Result` to an `Option<T>`
-    #[inline]
-    pub fn ok(self) -> Option<T> {
-        self.value.ok()
+    pub fn new_error_with<E: Error>(make: impl FnOnce() -> E) -> Self {
+        let id = context::push_error_lazy(make);
+        Self {
+            value: Err(Raised::of::<E>(id.0)),
+            _not_send: PhantomData,
+        }
     }
 
-    /// Test if a `Result` contains an error.
+    /// Create a new `Result` with an error indication, only delivering the error to the scopes.
+    ///
+    /// Same as [`new_error`](Self::new_error), except nothing else is recorded about the raise:
+    /// probes registered with [`context::on_raise`] aren't run, and neither the location nor the
+    /// metadata of the raise is kept. Meant for hot paths raising often, like a parser raising and
+    /// handling an error per rejected token. See [`context::RaiseFlags::BARE`].
     ///
     /// # Examples
     ///
     /// ```
-    /// let err: xcept::Result<i32> = xcept::Result::new_error("Error");
-    /// assert!(!err.is_ok());
-    /// assert!(err.is_error());
+    /// let res = xcept::try_or_handle_one(
+    ///     || xcept::Result::<u8>::new_error_bare(1u8),
+    ///     |err: u8| xcept::Result::new(err + 1),
+    /// );
+    /// assert_eq!(res.unwrap(), 2);
     /// ```
     #[inline]
-    pub fn is_error(&self) -> bool {
-        self.value.is_err()
+    pub fn new_error_bare<E: Error>(err: E) -> Self {
+        let id = context::push_error_with(err, context::RaiseFlags::BARE);
+        Self {
+            value: Err(Raised::of::<E>(id.0)),
+            _not_send: PhantomData,
+        }
     }
 
-    /// Unwrap the `Result` to a value, panicking if `Result` holds an error.
-    ///
-    /// # Panics
+    /// Create a new `Result` with an error indication, letting contexts that only observe the
+    /// raise keep a copy of the error.
     ///
-    /// If the `Result` doesn't contain a value we panic instead.
+    /// The scope that captures the error still receives the original value, and handles it once.
+    /// See [`context::ReportedError::clone_value`].
+    #[cfg_attr(feature = "metadata", track_caller)]
     #[inline]
-    pub fn unwrap(self) -> T {
-        self.value.unwrap()
+    pub fn new_error_cloneable<E: Error + Clone>(err: E) -> Self {
+        let id = context::push_error_cloneable(err);
+        Self {
+            value: Err(Raised::of::<E>(id.0)),
+            _not_send: PhantomData,
+        }
     }
 
-    /// Unchecked unwrap
-    ///
-    /// # Safety
-    ///
-    /// If `result.is_ok()` returns `false` this will result in *undefined behaviour*.
+    /// Create a new `Result` with an error indication, recording everything about the raise the
+    /// enabled features record. See [`context::RaiseFlags::TRACED`].
+    #[cfg_attr(feature = "metadata", track_caller)]
     #[inline]
-    pub unsafe fn unwrap_unchecked(self) -> T {
-        self.value.unwrap_unchecked()
+    pub fn new_error_traced<E: Error>(err: E) -> Self {
+        let id = context::push_error_with(err, context::RaiseFlags::TRACED);
+        Self {
+            value: Err(Raised::of::<E>(id.0)),
+            _not_send: PhantomData,
+        }
     }
 
-    /// Get the ID of the error that was set when `Result` was created.
+    /// Create a new `Result` with an error indication, raising the portable representation of
+    /// `err` tagged with its stable ID.
+    ///
+    /// Only handlers added with
+    /// [`Builder::handle_stable`](multihandler::Builder::handle_stable), or handlers for the
+    /// portable type itself, see the error. See [`stable_id`].
+    #[cfg_attr(feature = "metadata", track_caller)]
     #[inline]
-    pub fn error_id(&self) -> Option<u32> {
-        match &self.value {
-            Ok(_) => None,
-            Err(x) => Some(*x),
+    pub fn new_error_stable<E: stable_id::StableError>(err: E) -> Self {
+        let id = context::push_stable_error(err.into_portable(), E::STABLE_ID);
+        Self {
+            value: Err(Raised::of::<E::Portable>(id)),
+            _not_send: PhantomData,
         }
     }
 
-    /// Unchecked getter of the ID of the error that was set when `Result` was created.
+    /// Create a new `Result` with an error indication, running `probe` on the error first.
     ///
-    /// # Safety
+    /// The probe runs before the error is offered to any handling scope, so it sees the error
+    /// even if no scope accepts it. Delivery is the same as for [`Result::new_error`].
     ///
-    /// If `result.is_error()` returns `false` this will result in *undefined behaviour*.
+    /// # Examples
+    ///
+    /// ```
+    /// let res: xcept::Result<i32> =
+    ///     xcept::Result::new_error_inspected("Error", |err| eprintln!("raising {}", err));
+    /// assert!(res.is_error());
+    /// ```
+    #[cfg_attr(feature = "metadata", track_caller)]
     #[inline]
-    pub unsafe fn unchecked_error_id(&self) -> u32 {
-        match &self.value {
-            Err(x) => *x,
-            _ => unreachable_unchecked(),
-        }
+    pub fn new_error_inspected<E: Error>(err: E, probe: impl FnOnce(&E)) -> Self {
+        probe(&err);
+        Self::new_error(err)
     }
-}
 
-impl<T> From<T> for Result<T> {
+    /// Create a new `Result` with an error indication, recording the caller in the error.
+    ///
+    /// Call it from a `#[track_caller]` function to record the location of that function's
+    /// caller instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::panic::Location;
+    ///
+    /// struct Timeout {
+    ///     location: Option<&'static Location<'static>>,
+    /// }
+    ///
+    /// impl xcept::LocatedError for Timeout {
+    ///     fn set_location(&mut self, location: &'static Location<'static>) {
+    ///         self.location = Some(location);
+    ///     }
+    /// }
+    ///
+    /// let res = xcept::try_or_handle_one(
+    ///     || xcept::Result::new_located_error(Timeout { location: None }),
+    ///     |err: Timeout| xcept::Result::new(err.location.unwrap().line()),
+    /// );
+    /// assert_eq!(res.unwrap(), line!() - 3);
+    /// ```
+    #[track_caller]
     #[inline]
-    fn from(v: T) -> Self {
-        Self::new(v)
+    pub fn new_located_error<E: LocatedError>(mut err: E) -> Self {
+        err.set_location(std::panic::Location::caller());
+        Self::new_error(err)
+    }
+
+    /// Create a new `Result` with an error indication, converting `err` to its owned type first.
+    ///
+    /// Handlers take the owned type, see [`ToStatic`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// fn lookup(key: &str) -> xcept::Result<u32> {
+    ///     xcept::Result::raise_owned(format_args!("missing key {key}"))
+    /// }
+    ///
+    /// let res = xcept::try_or_handle_one(|| lookup("db.host"), |err: String| xcept::Result::new(err.len() as u32));
+    /// assert_eq!(res.unwrap(), 19);
+    /// ```
+    #[cfg_attr(feature = "metadata", track_caller)]
+    #[inline]
+    pub fn raise_owned(err: impl ToStatic) -> Self {
+        Self::new_error(err.to_static())
+    }
+
+    /// Test if a `Result` contains a value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let err: xcept::Result<i32> = xcept::Result::new_error("Error");
+    /// assert!(!err.is_ok());
+    /// assert!(err.is_error());
+    /// ```
+    #[inline]
+    pub const fn is_ok(&self) -> bool {
+        self.value.is_ok()
+    }
+
+    /// Run `f` with a reference to the value if the `Result` holds one.
+    ///
+    /// The error ID is kept unchanged if the `Result` holds an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res = xcept::try_or_handle_one(
+    ///     || xcept::Result::new_error("missing"),
+    ///     |_: &'static str| xcept::Result::new(7),
+    /// )
+    /// .inspect(|value| println!("resolved to {}", value));
+    /// assert_eq!(res.unwrap(), 7);
+    /// ```
+    #[inline]
+    pub fn inspect(self, f: impl FnOnce(&T)) -> Self {
+        if let Ok(value) = &self.value {
+            f(value);
+        }
+        self
+    }
+
+    /// Run `probe` with the error ID if the `Result` holds an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res: xcept::Result<i32> = xcept::Result::new_error("Error")
+    ///     .tap_error(|id| eprintln!("error {} passed through", id));
+    /// assert!(res.is_error());
+    /// ```
+    #[inline]
+    pub fn tap_error(self, probe: impl FnOnce(ErrorId)) -> Self {
+        if let Err(raised) = self.value {
+            probe(ErrorId(raised.id));
+        }
+        self
+    }
+
+    /// Convert the `Result` to an `Option<T>`
+    #[inline]
+    pub fn ok(self) -> Option<T> {
+        self.value.ok()
+    }
+
+    /// Test if a `Result` contains an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let err: xcept::Result<i32> = xcept::Result::new_error("Error");
+    /// assert!(!err.is_ok());
+    /// assert!(err.is_error());
+    /// ```
+    #[inline]
+    pub const fn is_error(&self) -> bool {
+        self.value.is_err()
+    }
+
+    /// Unwrap the `Result` to a value, panicking if `Result` holds an error.
+    ///
+    /// # Panics
+    ///
+    /// If the `Result` doesn't contain a value we panic instead, with the ID of the error in the
+    /// message, and the type of the error if it's known, see [`Result::error_type_name`].
+    #[track_caller]
+    #[inline]
+    pub fn unwrap(self) -> T {
+        match self.value {
+            Ok(value) => value,
+            Err(raised) => unwrap_failed("called `Result::unwrap()` on an error", raised),
+        }
+    }
+
+    /// Unwrap the `Result` to a value, panicking with `msg` if `Result` holds an error.
+    ///
+    /// # Panics
+    ///
+    /// If the `Result` doesn't contain a value we panic with `msg`, the ID of the error and its
+    /// type if it's known. The error itself was delivered to the handling scopes when it was
+    /// raised, so the ID is what relates the panic to it, for instance in logs.
+    ///
+    /// # Examples
+    ///
+    /// ```should_panic
+    /// let res: xcept::Result<i32> = xcept::Result::new_error("Error");
+    /// res.expect("config must be loaded");
+    /// ```
+    #[track_caller]
+    #[inline]
+    pub fn expect(self, msg: &str) -> T {
+        match self.value {
+            Ok(value) => value,
+            Err(raised) => unwrap_failed(msg, raised),
+        }
+    }
+
+    /// Unwrap the `Result` to a value, or `default` if it holds an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res: xcept::Result<i32> = xcept::Result::new_error("Error");
+    /// assert_eq!(res.unwrap_or(3), 3);
+    /// ```
+    #[inline]
+    pub fn unwrap_or(self, default: T) -> T {
+        self.value.unwrap_or(default)
+    }
+
+    /// Unwrap the `Result` to a value, or the value returned by `f` if it holds an error.
+    ///
+    /// Unlike [`std::result::Result::unwrap_or_else`], `f` doesn't receive the error: it was
+    /// delivered to the handling scopes when it was raised, the `Result` only holds its ID. Use
+    /// [`try_unwrap`](Self::try_unwrap) to get the ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res: xcept::Result<String> = xcept::Result::new_error("Error");
+    /// assert_eq!(res.unwrap_or_else(|| "fallback".to_string()), "fallback");
+    /// ```
+    #[inline]
+    pub fn unwrap_or_else<F: FnOnce() -> T>(self, f: F) -> T {
+        self.value.unwrap_or_else(|_| f())
+    }
+
+    /// Unwrap the `Result` to a value, or the default value of `T` if it holds an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res: xcept::Result<Vec<u8>> = xcept::Result::new_error("Error");
+    /// assert!(res.unwrap_or_default().is_empty());
+    /// ```
+    #[inline]
+    pub fn unwrap_or_default(self) -> T
+    where
+        T: Default,
+    {
+        self.value.unwrap_or_default()
+    }
+
+    /// Unwrap the `Result` to a value, or the ID of the error it holds.
+    ///
+    /// This is the safe alternative to [`Result::unwrap_unchecked`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let id = xcept::ErrorId::from_raw(3);
+    /// let res: xcept::Result<i32> = xcept::Result::new_with_error_id(id);
+    /// assert_eq!(res.try_unwrap(), Err(id));
+    /// assert_eq!(xcept::Result::new(1).try_unwrap(), Ok(1));
+    /// ```
+    #[inline]
+    pub fn try_unwrap(self) -> core::result::Result<T, ErrorId> {
+        self.value.map_err(|raised| ErrorId(raised.id))
+    }
+
+    /// Unchecked unwrap
+    ///
+    /// The precondition is checked with a debug assertion.
+    ///
+    /// Not available with the `deny-unchecked` feature.
+    ///
+    /// # Safety
+    ///
+    /// If `result.is_ok()` returns `false` this will result in *undefined behaviour*.
+    #[cfg(not(feature = "deny-unchecked"))]
+    #[inline]
+    pub unsafe fn unwrap_unchecked(self) -> T {
+        self.value_unchecked()
+    }
+
+    /// Crate-private version of `unwrap_unchecked`, available regardless of features.
+    ///
+    /// # Safety
+    ///
+    /// `self.is_ok()` must return `true`.
+    #[cfg_attr(feature = "deny-unchecked", allow(dead_code))]
+    #[inline]
+    pub(crate) unsafe fn value_unchecked(self) -> T {
+        paranoid_assert!(self.is_ok(), "called `unwrap_unchecked` on an error `Result`");
+        self.value.unwrap_unchecked()
+    }
+
+    /// Get the ID of the error that was set when `Result` was created.
+    #[inline]
+    pub const fn error_id(&self) -> Option<ErrorId> {
+        match &self.value {
+            Ok(_) => None,
+            Err(raised) => Some(ErrorId(raised.id)),
+        }
+    }
+
+    /// Get the raw ID of the error that was set when `Result` was created.
+    #[deprecated(note = "use `Result::error_id` with `ErrorId::to_raw`")]
+    #[inline]
+    pub const fn error_id_raw(&self) -> Option<u64> {
+        match &self.value {
+            Ok(_) => None,
+            Err(raised) => Some(raised.id),
+        }
+    }
+
+    /// Test if the error was raised as an `E`.
+    ///
+    /// The type is recorded by the raising functions, like [`Result::new_error`], and kept when
+    /// the error passes through combinators, `?` and [`check!`]. It's unknown for a `Result`
+    /// created from an ID with [`Result::new_with_error_id`], so this returns `false` then, and
+    /// always for a value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res = xcept::try_or_handle_one(
+    ///     || xcept::Result::<()>::new_error("not a number"),
+    ///     |_: std::io::Error| xcept::Result::new(()),
+    /// );
+    /// assert!(res.error_is::<&str>());
+    /// assert!(!res.error_is::<std::io::Error>());
+    /// ```
+    pub fn error_is<E: Error>(&self) -> bool {
+        match &self.value {
+            Err(Raised {
+                error_type: Some(error_type),
+                ..
+            }) => (error_type.type_id)() == TypeId::of::<E>(),
+            _ => false,
+        }
+    }
+
+    /// The name of the type the error was raised as, `None` for a value or if the type is
+    /// unknown, see [`Result::error_is`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res: xcept::Result<()> = xcept::Result::new_error(4u8);
+    /// assert_eq!(res.error_type_name(), Some("u8"));
+    /// assert_eq!(xcept::Result::<()>::new_with_error_id(res.error_id().unwrap()).error_type_name(), None);
+    /// ```
+    pub fn error_type_name(&self) -> Option<&'static str> {
+        match &self.value {
+            Err(raised) => raised.error_type.map(|error_type| (error_type.type_name)()),
+            Ok(_) => None,
+        }
+    }
+
+    /// Test if the error was raised before the innermost active scope was entered.
+    ///
+    /// Such an error was never offered to that scope, so returning it from the scope passes on
+    /// a `Result` that outlived the scope its error was raised in. With no active scope, tests
+    /// if a scope has been left since the error was raised. Always `false` for a value, and for
+    /// errors raised since the innermost scope was entered.
+    ///
+    /// This compares the epoch stored in the ID, see [`context::current_epoch`], so errors
+    /// raised before the scope but with no scope left in between aren't detected. The epoch
+    /// has 32 bits and advances on every scope pop, including the one of every
+    /// `try_or_handle` call, so errors raised 2^31 or more pops ago wrap around and aren't
+    /// detected either.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let old: xcept::Result<()> = xcept::try_or_handle_one(|| xcept::Result::new_error(1), |_: u8| xcept::Result::new(()));
+    /// let res = xcept::try_or_handle_one(
+    ///     || {
+    ///         assert!(old.is_stale());
+    ///         assert!(!xcept::Result::<()>::new_error(2).is_stale());
+    ///         old
+    ///     },
+    ///     |_: u8| xcept::Result::new(()),
+    /// );
+    /// assert!(res.is_error());
+    /// ```
+    pub fn is_stale(&self) -> bool {
+        self.error_id().is_some_and(|id| context::is_stale_id(id.0))
+    }
+
+    /// Unchecked getter of the ID of the error that was set when `Result` was created.
+    ///
+    /// The precondition is checked with a debug assertion. Use [`Result::error_id`] for a safe
+    /// alternative.
+    ///
+    /// Not available with the `deny-unchecked` feature.
+    ///
+    /// # Safety
+    ///
+    /// If `result.is_error()` returns `false` this will result in *undefined behaviour*.
+    #[cfg(not(feature = "deny-unchecked"))]
+    #[inline]
+    pub unsafe fn unchecked_error_id(&self) -> ErrorId {
+        ErrorId(self.error_id_unchecked())
+    }
+
+    /// Crate-private version of `unchecked_error_id`, available regardless of features.
+    ///
+    /// # Safety
+    ///
+    /// `self.is_error()` must return `true`.
+    #[inline]
+    pub(crate) unsafe fn error_id_unchecked(&self) -> u64 {
+        paranoid_assert!(self.is_error(), "called `unchecked_error_id` on an ok `Result`");
+        match &self.value {
+            Err(raised) => raised.id,
+            _ => unreachable_unchecked(),
+        }
+    }
+}
+
+impl<T> Result<T> {
+    /// The ID of the error, consuming the `Result`.
+    ///
+    /// This mirrors `std::result::Result::err`, but the error value itself is held by the handling
+    /// scopes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let id = xcept::ErrorId::from_raw(3);
+    /// let res: xcept::Result<i32> = xcept::Result::new_with_error_id(id);
+    /// assert_eq!(res.err(), Some(id));
+    /// assert_eq!(xcept::Result::new(1).err(), None);
+    /// ```
+    #[inline]
+    pub fn err(self) -> Option<ErrorId> {
+        self.value.err().map(|raised| ErrorId(raised.id))
+    }
+
+    /// Convert into a `std::result::Result`, with the [`ErrorId`] of an error.
+    ///
+    /// Lets `?` propagate an error a handling scope declined out of a function returning a
+    /// `std::result::Result`. Converting back with `From` raises the `ErrorId` as a new error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// fn parse(s: &str) -> Result<i32, Box<dyn std::error::Error>> {
+    ///     let res = xcept::Result::<i32>::from(s.parse::<i32>());
+    ///     Ok(res.into_std()? * 2)
+    /// }
+    ///
+    /// assert_eq!(parse("4").unwrap(), 8);
+    /// assert!(parse("x").unwrap_err().to_string().starts_with("error #"));
+    /// ```
+    #[inline]
+    pub fn into_std(self) -> std::result::Result<T, ErrorId> {
+        self.value.map_err(|raised| ErrorId(raised.id))
+    }
+
+    /// The value, or the error as a `Result` of any type, used by [`check!`] to keep the type
+    /// of the error.
+    #[doc(hidden)]
+    #[inline]
+    pub fn __propagate<U>(self) -> std::result::Result<T, Result<U>> {
+        self.value.map_err(Result::from_raised)
+    }
+
+    /// An iterator over the value, yielding nothing for an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res = xcept::Result::new(4);
+    /// assert_eq!(res.iter().next(), Some(&4));
+    /// ```
+    #[inline]
+    pub fn iter(&self) -> core::option::IntoIter<&T> {
+        self.value.as_ref().ok().into_iter()
+    }
+
+    /// An iterator over the value, mutably, yielding nothing for an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut res = xcept::Result::new(4);
+    /// for value in &mut res {
+    ///     *value += 1;
+    /// }
+    /// assert_eq!(res.unwrap(), 5);
+    /// ```
+    #[inline]
+    pub fn iter_mut(&mut self) -> core::option::IntoIter<&mut T> {
+        self.value.as_mut().ok().into_iter()
+    }
+
+    /// Borrow the value, keeping the error ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res = xcept::Result::new(vec![1, 2]);
+    /// assert_eq!(res.as_ref().map(|v| v.len()).unwrap(), 2);
+    /// assert_eq!(res.unwrap(), [1, 2]);
+    /// ```
+    #[inline]
+    pub fn as_ref(&self) -> Result<&T> {
+        Result {
+            value: self.value.as_ref().map_err(|raised| *raised),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Mutably borrow the value, keeping the error ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut res = xcept::Result::new(vec![1, 2]);
+    /// res.as_mut().map(|v| v.push(3));
+    /// assert_eq!(res.unwrap(), [1, 2, 3]);
+    /// ```
+    #[inline]
+    pub fn as_mut(&mut self) -> Result<&mut T> {
+        Result {
+            value: self.value.as_mut().map_err(|raised| *raised),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Modify the value in place with `f`, doing nothing if the `Result` holds an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut res = xcept::Result::new(String::from("abc"));
+    /// res.map_value_in_place(|s| s.push('d'));
+    /// assert_eq!(res.unwrap(), "abcd");
+    /// ```
+    #[inline]
+    pub fn map_value_in_place(&mut self, f: impl FnOnce(&mut T)) {
+        if let Ok(value) = &mut self.value {
+            f(value);
+        }
+    }
+
+    /// Borrow the value mutably, replacing an error with the value returned by `f` first.
+    ///
+    /// The error ID is discarded, so the error can no longer be matched by the handler of the
+    /// scope it was delivered to. Use [`Result::or_try`] to also supersede the error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut res: xcept::Result<Vec<u8>> = xcept::Result::new_error("no buffer");
+    /// res.get_or_insert_with_replacing_error(Vec::new).push(1);
+    /// assert_eq!(res.unwrap(), [1]);
+    /// ```
+    #[inline]
+    pub fn get_or_insert_with_replacing_error(&mut self, f: impl FnOnce() -> T) -> &mut T {
+        if self.value.is_err() {
+            self.value = Ok(f());
+        }
+        match &mut self.value {
+            Ok(value) => value,
+            // Safety: replaced with a value above
+            Err(_) => unsafe { unreachable_unchecked() },
+        }
+    }
+
+    /// Move the value or error ID out, leaving [`TAKEN_ID`](context::TAKEN_ID) behind.
+    ///
+    /// The taken ID is never allocated to a raise, so no handler matches what is left behind,
+    /// see [`Result::is_taken`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut res = xcept::Result::new(3);
+    /// assert_eq!(res.take().unwrap(), 3);
+    /// assert!(res.is_taken());
+    /// assert_eq!(res.error_id(), Some(xcept::context::TAKEN_ID));
+    /// ```
+    #[inline]
+    pub fn take(&mut self) -> Result<T> {
+        std::mem::replace(self, Result::new_with_error_id(context::TAKEN_ID))
+    }
+
+    /// Test if the value or error of the `Result` was moved out with [`Result::take`].
+    #[inline]
+    pub fn is_taken(&self) -> bool {
+        self.error_id() == Some(context::TAKEN_ID)
+    }
+
+    /// Borrow the value through `Deref`, keeping the error ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res = xcept::Result::new("abc".to_string());
+    /// let borrowed: xcept::Result<&str> = res.as_deref();
+    /// assert_eq!(borrowed.unwrap(), "abc");
+    /// ```
+    #[inline]
+    pub fn as_deref(&self) -> Result<&T::Target>
+    where
+        T: core::ops::Deref,
+    {
+        Result {
+            value: self.value.as_deref().map_err(|raised| *raised),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Transform the value with `f`, keeping the error ID.
+    ///
+    /// The error was delivered to the handling scopes when it was raised, so an error is passed
+    /// through without involving them. An outer handler still matches it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res = xcept::Result::new(4).map(|x| x.to_string());
+    /// assert_eq!(res.unwrap(), "4");
+    ///
+    /// let res = xcept::try_or_handle_one(
+    ///     || xcept::Result::<i32>::new_error(7u8).map(|x| x.to_string()),
+    ///     |err: u8| xcept::Result::new(format!("error {err}")),
+    /// );
+    /// assert_eq!(res.unwrap(), "error 7");
+    /// ```
+    #[inline]
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Result<U> {
+        Result {
+            value: self.value.map(f),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Run `f` on the value, or return `default` if the `Result` holds an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let parse = |s: &str| -> xcept::Result<i32> { s.parse().map_err(|_| "not a number").into() };
+    /// assert_eq!(parse("4").map_or(0, |x| x * 2), 8);
+    /// assert_eq!(parse("x").map_or(0, |x| x * 2), 0);
+    /// ```
+    #[inline]
+    pub fn map_or<U, F: FnOnce(T) -> U>(self, default: U, f: F) -> U {
+        self.value.map_or(default, f)
+    }
+
+    /// Run `f` on the value, or return the value of `default` if the `Result` holds an error.
+    ///
+    /// Unlike [`std::result::Result::map_or_else`], `default` doesn't receive the error: it was
+    /// delivered to the handling scopes when it was raised, the `Result` only holds its ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res: xcept::Result<i32> = xcept::Result::new_error("Error");
+    /// assert_eq!(res.map_or_else(|| "failed".to_string(), |x| x.to_string()), "failed");
+    /// ```
+    #[inline]
+    pub fn map_or_else<U, D: FnOnce() -> U, F: FnOnce(T) -> U>(self, default: D, f: F) -> U {
+        self.value.map_or_else(|_| default(), f)
+    }
+
+    /// Run `f` on the value, or pass the error on with its ID.
+    ///
+    /// If `f` raises a new error, the `Result` it returns, referring to the new error, is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// fn half(x: i32) -> xcept::Result<i32> {
+    ///     if x % 2 == 0 { xcept::Result::new(x / 2) } else { xcept::Result::new_error("odd") }
+    /// }
+    ///
+    /// assert_eq!(xcept::Result::new(8).and_then(half).and_then(half).unwrap(), 2);
+    ///
+    /// let res = xcept::try_or_handle_one(
+    ///     || xcept::Result::new(6).and_then(half).and_then(half),
+    ///     |err: &str| xcept::Result::new(err.len() as i32),
+    /// );
+    /// assert_eq!(res.unwrap(), 3);
+    /// ```
+    #[inline]
+    pub fn and_then<U, F: FnOnce(T) -> Result<U>>(self, f: F) -> Result<U> {
+        match self.value {
+            Ok(value) => f(value),
+            Err(raised) => Result::from_raised(raised),
+        }
+    }
+
+    /// Return `res` if `self` holds a value, otherwise the error of `self`.
+    ///
+    /// If `self` is an error, the error ID held by `res` is discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let id = xcept::ErrorId::from_raw(1);
+    /// let err: xcept::Result<i32> = xcept::Result::new_with_error_id(id);
+    /// assert_eq!(err.and(xcept::Result::new("x")).error_id(), Some(id));
+    /// assert_eq!(xcept::Result::new(1).and(xcept::Result::new("x")).unwrap(), "x");
+    /// ```
+    #[inline]
+    pub fn and<U>(self, res: Result<U>) -> Result<U> {
+        match self.value {
+            Ok(_) => res,
+            Err(raised) => Result::from_raised(raised),
+        }
+    }
+
+    /// Return `self` if it holds a value, otherwise `res`.
+    ///
+    /// The error ID of `self` is discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let err: xcept::Result<i32> = xcept::Result::new_with_error_id(xcept::ErrorId::from_raw(1));
+    /// assert_eq!(err.or(xcept::Result::new(2)).unwrap(), 2);
+    /// ```
+    #[inline]
+    pub fn or(self, res: Result<T>) -> Result<T> {
+        match self.value {
+            Ok(_) => self,
+            Err(_) => res,
+        }
+    }
+
+    /// Return `self` if it holds a value, otherwise try the alternative returned by `f`.
+    ///
+    /// The error of `self` is [superseded](context::supersede) before `f` runs: the scopes drop it
+    /// if they still hold it, so only the error of the last alternative is live and reaches a
+    /// handler.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #[derive(Debug)]
+    /// struct Missing(&'static str);
+    ///
+    /// fn env() -> xcept::Result<u16> { xcept::Result::new_error(Missing("env")) }
+    /// fn file() -> xcept::Result<u16> { xcept::Result::new_error(Missing("file")) }
+    /// fn defaults() -> xcept::Result<u16> { xcept::Result::new(8080) }
+    ///
+    /// let port = xcept::try_or_handle_one(
+    ///     || env().or_try(file).or_try(defaults),
+    ///     |err: Missing| panic!("{err:?} superseded by the defaults"),
+    /// );
+    /// assert_eq!(port.unwrap(), 8080);
+    ///
+    /// let source = xcept::try_or_handle_one(
+    ///     || env().or_try(file).map(|_| ""),
+    ///     |Missing(source)| xcept::Result::new(source),
+    /// );
+    /// assert_eq!(source.unwrap(), "file");
+    /// ```
+    #[inline]
+    pub fn or_try(self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        match self.value {
+            Ok(_) => self,
+            Err(raised) => {
+                context::supersede(ErrorId(raised.id));
+                f()
+            }
+        }
+    }
+
+    /// Return `self` if it holds a value, otherwise raise `err` in place of its error.
+    ///
+    /// The error of `self` is superseded like with [`Result::or_try`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// struct MissingConfig;
+    ///
+    /// let res = xcept::try_or_handle_one(
+    ///     || xcept::Result::<u16>::new_error("no env").or_raise(MissingConfig),
+    ///     |_: MissingConfig| xcept::Result::new(0),
+    /// );
+    /// assert_eq!(res.unwrap(), 0);
+    /// ```
+    #[cfg_attr(feature = "metadata", track_caller)]
+    #[inline]
+    pub fn or_raise<E: Error>(self, err: E) -> Result<T> {
+        match self.value {
+            Ok(_) => self,
+            Err(raised) => {
+                context::supersede(ErrorId(raised.id));
+                Result::new_error(err)
+            }
+        }
+    }
+
+    /// Raise a new error created by `factory` if `self` is an error.
+    ///
+    /// This is the closest to `std::result::Result::map_err`. The factory can't see the original
+    /// error, which is already held by a handling scope or dropped. The returned `Result` refers
+    /// to the new error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res = xcept::try_or_handle_one(
+    ///     || xcept::Result::<i32>::new_error(1u8).map_raise(|| "wrapped"),
+    ///     |msg: &str| xcept::Result::new(msg.len() as i32),
+    /// );
+    /// assert_eq!(res.unwrap(), 7);
+    /// ```
+    #[cfg_attr(feature = "metadata", track_caller)]
+    #[inline]
+    pub fn map_raise<E: Error>(self, factory: impl FnOnce() -> E) -> Self {
+        match self.value {
+            Ok(_) => self,
+            Err(_) => Self::new_error(factory()),
+        }
+    }
+}
+
+impl Result<()> {
+    /// Raise `err` only if no active scope has a pending error.
+    ///
+    /// If a scope already captured an error that hasn't been handled, `err` is dropped and an ok
+    /// `Result` is returned, so a secondary error doesn't mask the root cause. See also
+    /// [`suppressing_secondary`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let res: xcept::Result<()> = xcept::Result::new_error_if_clear("nothing pending");
+    /// assert!(res.is_error());
+    /// ```
+    #[cfg_attr(feature = "metadata", track_caller)]
+    pub fn new_error_if_clear<E: Error>(err: E) -> Self {
+        if context::scopes_pending() {
+            Self::new(())
+        } else {
+            Self::new_error(err)
+        }
+    }
+}
+
+impl<T: Clone> Result<&T> {
+    /// Clone the borrowed value, keeping the error ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let value = vec![1];
+    /// let res = xcept::Result::new(&value);
+    /// assert_eq!(res.cloned().unwrap(), vec![1]);
+    /// ```
+    #[inline]
+    pub fn cloned(self) -> Result<T> {
+        Result {
+            value: self.value.cloned(),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<T: Copy> Result<&T> {
+    /// Copy the borrowed value, keeping the error ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let value = 5;
+    /// let res = xcept::Result::new(&value);
+    /// assert_eq!(res.copied().unwrap(), 5);
+    /// ```
+    #[inline]
+    pub fn copied(self) -> Result<T> {
+        Result {
+            value: self.value.copied(),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<A, B> Result<(A, B)> {
+    /// Split a `Result` holding a pair into a pair of results.
+    ///
+    /// For an error both results get the error ID of `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let (a, b) = xcept::Result::new((1, "one")).unzip();
+    /// assert_eq!(a.unwrap(), 1);
+    /// assert_eq!(b.unwrap(), "one");
+    /// ```
+    #[inline]
+    pub fn unzip(self) -> (Result<A>, Result<B>) {
+        match self.value {
+            Ok((a, b)) => (Result::new(a), Result::new(b)),
+            Err(raised) => (Result::from_raised(raised), Result::from_raised(raised)),
+        }
+    }
+}
+
+impl<T> Result<Result<T>> {
+    /// Remove one level of nesting.
+    ///
+    /// An error of the outer `Result` keeps its error ID, otherwise the inner `Result` is returned
+    /// unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let nested = xcept::Result::new(xcept::Result::new(1));
+    /// assert_eq!(nested.flatten().unwrap(), 1);
+    ///
+    /// let id = xcept::ErrorId::from_raw(7);
+    /// let outer: xcept::Result<xcept::Result<i32>> = xcept::Result::new_with_error_id(id);
+    /// assert_eq!(outer.flatten().error_id(), Some(id));
+    /// ```
+    #[inline]
+    pub fn flatten(self) -> Result<T> {
+        match self.value {
+            Ok(inner) => inner,
+            Err(raised) => Result::from_raised(raised),
+        }
+    }
+}
+
+/// Yields the value, or nothing for an error.
+///
+/// # Examples
+///
+/// ```
+/// let results = vec![xcept::Result::new(1), xcept::Result::new_error("failed"), xcept::Result::new(3)];
+/// let values: Vec<i32> = results.into_iter().flatten().collect();
+/// assert_eq!(values, [1, 3]);
+/// ```
+impl<T> IntoIterator for Result<T> {
+    type Item = T;
+    type IntoIter = core::option::IntoIter<T>;
+
+    /// An iterator yielding the value, or nothing for an error.
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.value.ok().into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Result<T> {
+    type Item = &'a T;
+    type IntoIter = core::option::IntoIter<&'a T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Result<T> {
+    type Item = &'a mut T;
+    type IntoIter = core::option::IntoIter<&'a mut T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Clones the value, or the error ID.
+///
+/// Cloning a result holding an error doesn't raise anything. Both results refer to the same error,
+/// which is still stored once, in the scope that captured it, and handled at most once.
+///
+/// # Examples
+///
+/// ```
+/// let res = xcept::try_or_handle_one(
+///     || {
+///         let err = xcept::Result::<i32>::new_error(3u8);
+///         let copy = err.clone();
+///         assert_eq!(copy, err);
+///         copy
+///     },
+///     |err: u8| xcept::Result::new(i32::from(err)),
+/// );
+/// assert_eq!(res, xcept::Result::new(3));
+/// ```
+impl<T: Clone> Clone for Result<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+/// Compares the values, or the error IDs. A value never equals an error.
+impl<T: PartialEq> PartialEq for Result<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: Eq> Eq for Result<T> {}
+
+/// Collects the values until the first error, which is returned with its ID.
+///
+/// Elements after the first error aren't evaluated. Every error reaches the scopes when it is
+/// raised, not when it is collected, so collect lazily, for instance from a `map` over the
+/// inputs. When the results were all created before collecting, later errors were raised too,
+/// and may have replaced the first one in the storage of the scope, so the returned ID is no
+/// longer handled by it.
+///
+/// # Examples
+///
+/// ```
+/// fn parse(s: &str) -> xcept::Result<i32> {
+///     s.parse().into()
+/// }
+///
+/// let res = xcept::try_or_handle_one(
+///     || ["1", "2", "x", "y"].into_iter().map(parse).collect::<xcept::Result<Vec<_>>>(),
+///     |_: std::num::ParseIntError| xcept::Result::new(Vec::new()),
+/// );
+/// assert!(res.unwrap().is_empty());
+/// ```
+impl<T, C: FromIterator<T>> FromIterator<Result<T>> for Result<C> {
+    fn from_iter<I: IntoIterator<Item = Result<T>>>(iter: I) -> Self {
+        let mut error = None;
+        let collected = iter
+            .into_iter()
+            .map_while(|res| match res.value {
+                Ok(v) => Some(v),
+                Err(raised) => {
+                    error = Some(raised);
+                    None
+                }
+            })
+            .collect();
+        match error {
+            Some(raised) => Self::from_raised(raised),
+            None => Self::new(collected),
+        }
+    }
+}
+
+/// Prints `Ok(value)`, or `Error(id)` with the error ID.
+impl<T: std::fmt::Debug> std::fmt::Debug for Result<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Ok(v) => f.debug_tuple("Ok").field(v).finish(),
+            Err(raised) => f.debug_tuple("Error").field(&raised.id).finish(),
+        }
+    }
+}
+
+/// Prints the value, or `error #id` with the error ID.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(xcept::Result::new(4).to_string(), "4");
+/// let err = xcept::Result::<i32>::new_error("failed");
+/// assert_eq!(err.to_string(), err.error_id().unwrap().to_string());
+/// ```
+impl<T: std::fmt::Display> std::fmt::Display for Result<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Ok(v) => v.fmt(f),
+            Err(raised) => write!(f, "error #{}", raised.id),
+        }
+    }
+}
+
+impl<T> From<T> for Result<T> {
+    #[inline]
+    fn from(v: T) -> Self {
+        Self::new(v)
+    }
+}
+
+impl<T, E: Error> From<std::result::Result<T, E>> for Result<T> {
+    #[cfg_attr(feature = "metadata", track_caller)]
+    #[inline]
+    fn from(val: std::result::Result<T, E>) -> Self {
+        match val {
+            Ok(v) => Self::new(v),
+            Err(e) => Self::new_error(e),
+        }
+    }
+}
+
+/// Lets `main` return a [`Result`], exiting with a failure code on an error.
+///
+/// An error is reported on stderr as `Error: unhandled error #id`. The error value was delivered
+/// to the scopes active when it was raised, or dropped if there were none, so only its ID is
+/// known here. With the `diagnostics` feature the type name and location of the raise are added
+/// if the raise is still among the [`debug::recent_raises`].
+///
+/// # Examples
+///
+/// ```no_run
+/// fn main() -> xcept::Result<()> {
+///     xcept::Result::new_error("no input file")
+/// }
+/// ```
+impl<T: std::process::Termination> std::process::Termination for Result<T> {
+    fn report(self) -> std::process::ExitCode {
+        match self.value {
+            Ok(v) => v.report(),
+            Err(raised) => {
+                eprintln!("Error: {}", describe_unhandled(ErrorId(raised.id)));
+                std::process::ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+/// Lets `?` propagate an error to the caller, enabled by the `nightly` feature.
+///
+/// The error was delivered to the scopes when it was raised, so only its ID is propagated and
+/// nothing is reported again. A `try_or_handle` around the caller still matches the stored error.
+///
+/// # Examples
+///
+/// ```
+/// fn parse(s: &str) -> xcept::Result<u32> {
+///     xcept::Result::from(s.parse::<u32>())
+/// }
+///
+/// fn sum(a: &str, b: &str) -> xcept::Result<u32> {
+///     xcept::Result::new(parse(a)? + parse(b)?)
+/// }
+///
+/// assert_eq!(sum("1", "2").unwrap(), 3);
+/// let res = xcept::try_or_handle_one(|| sum("1", "x"), |_: std::num::ParseIntError| xcept::Result::new(0));
+/// assert_eq!(res.unwrap(), 0);
+/// ```
+#[cfg(feature = "nightly")]
+impl<T> core::ops::Try for Result<T> {
+    type Output = T;
+    type Residual = Result<core::convert::Infallible>;
+
+    #[inline]
+    fn from_output(output: T) -> Self {
+        Result::new(output)
+    }
+
+    #[inline]
+    fn branch(self) -> core::ops::ControlFlow<Self::Residual, T> {
+        match self.value {
+            Ok(v) => core::ops::ControlFlow::Continue(v),
+            Err(raised) => core::ops::ControlFlow::Break(Result::from_raised(raised)),
+        }
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<T> core::ops::Residual<T> for Result<core::convert::Infallible> {
+    type TryType = Result<T>;
+}
+
+#[cfg(feature = "nightly")]
+impl<T> core::ops::FromResidual<Result<core::convert::Infallible>> for Result<T> {
+    #[inline]
+    fn from_residual(residual: Result<core::convert::Infallible>) -> Self {
+        match residual.value {
+            Err(raised) => Result::from_raised(raised),
+        }
+    }
+}
+
+pub(crate) fn describe_unhandled(error_id: ErrorId) -> String {
+    let id = error_id.0;
+    if error_id == context::TAKEN_ID {
+        return format!("unhandled error #{id}, left behind by `Result::take`");
+    }
+    #[cfg(feature = "diagnostics")]
+    if let Some(record) = debug::recent_raises().into_iter().rev().find(|record| record.id == error_id) {
+        return match record.location {
+            Some(location) => format!("unhandled error #{id} of type `{}`, raised at {location}", record.type_name),
+            None => format!("unhandled error #{id} of type `{}`", record.type_name),
+        };
+    }
+    format!("unhandled error #{id}")
+}
+
+/// Raises [`NoneError`] for `None`.
+impl<T> From<Option<T>> for Result<T> {
+    #[cfg_attr(feature = "metadata", track_caller)]
+    #[inline]
+    fn from(val: Option<T>) -> Self {
+        match val {
+            Some(v) => Self::new(v),
+            None => Self::new_error(NoneError),
+        }
+    }
+}
+
+/// Try to execute a function, and try to handle an error, if one occurs.
+///
+/// Use [`try_or_handle`] to handle multiple error types.
+///
+/// # Arguments
+///
+/// * `func`: The function to execute
+/// * `handler`: Handle a single error
+///
+/// returns: Result<T>
+///
+/// # Examples
+///
+/// ```
+/// fn to_int(string: &str) -> xcept::Result<i32> {
+///     string.parse().into()
+/// }
+///
+/// type ErrorT = <i32 as std::str::FromStr>::Err;
+/// let res = xcept::try_or_handle_one(|| to_int("abc"), |_err: ErrorT| xcept::Result::new(-1));
+/// assert_eq!(res.unwrap(), -1);
+///
+/// let res = xcept::try_or_handle_one(|| to_int("10"), |_err: ErrorT| xcept::Result::new(-1));
+/// assert_eq!(res.unwrap(), 10);
+/// ```
+#[inline]
+pub fn try_or_handle_one<F, H, T, E>(func: F, handler: H) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+    H: FnOnce(E) -> Result<T>,
+    E: Error,
+{
+    let mut error_storage: crate::context::SingleErrorStorage<E> = SingleErrorStorage::default();
+    let res = {
+        let scope = std::pin::pin!(context::ScopeNode::new(&mut error_storage));
+        // Safety: the guard is dropped at the end of the block
+        let _guard = unsafe { scope.register() };
+        func()
+    };
+    if res.is_error() {
+        handle_one_error(error_storage, res, handler)
+    } else {
+        res
+    }
+}
+
+/// The error branch of [`try_or_handle_one`], kept out of line so the ok path is small.
+#[cold]
+#[inline(never)]
+fn handle_one_error<T, E: Error>(
+    storage: SingleErrorStorage<E>,
+    res: Result<T>,
+    handler: impl FnOnce(E) -> Result<T>,
+) -> Result<T> {
+    // Safety: only called with error results
+    unsafe { storage.try_handle_unchecked(res, handler) }
+}
+
+/// Same as [`try_or_handle_one`], with a trait object body and a function pointer handler.
+///
+/// Instantiated once per `T` and `E` instead of once per body and handler type, for code bases
+/// with many call sites passing plain `fn` items, where the generic version costs compile time.
+/// Behaves exactly like [`try_or_handle_one`]. Each body still gets a small vtable, so the
+/// binary isn't necessarily smaller, see `tests/code_size.rs`.
+///
+/// # Examples
+///
+/// ```
+/// fn fallback(_: std::num::ParseIntError) -> xcept::Result<i32> {
+///     xcept::Result::new(-1)
+/// }
+///
+/// let res = xcept::try_or_handle_fn(&mut || "abc".parse().into(), fallback);
+/// assert_eq!(res.unwrap(), -1);
+/// ```
+#[inline(never)]
+pub fn try_or_handle_fn<T, E: Error>(func: &mut dyn FnMut() -> Result<T>, handler: fn(E) -> Result<T>) -> Result<T> {
+    let mut error_storage: crate::context::SingleErrorStorage<E> = SingleErrorStorage::default();
+    let res = {
+        let scope = std::pin::pin!(context::ScopeNode::new(&mut error_storage));
+        // Safety: the guard is dropped at the end of the block
+        let _guard = unsafe { scope.register() };
+        func()
+    };
+    if res.is_error() {
+        handle_one_error(error_storage, res, handler)
+    } else {
+        res
+    }
+}
+
+/// Method syntax for [`try_or_handle_one`] and [`try_or_handle`].
+///
+/// Implemented for every closure returning a [`Result`]. The closure has to be wrapped in
+/// parentheses to call a method on it.
+///
+/// The error type of a handler is inferred from its argument, so closures need a type annotation
+/// like `|err: MyError| ...`. Function items like `error_handler` below already name the type.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::TryExt;
+///
+/// #[derive(Debug)]
+/// struct CustomError(i32);
+///
+/// fn hello_world(x: i32) -> xcept::Result<&'static str> {
+///     if x % 2 == 0 {
+///         "Even".into()
+///     } else if x == 1 {
+///         xcept::Result::new_error(CustomError(x))
+///     } else {
+///         xcept::Result::new_error(x)
+///     }
+/// }
+///
+/// fn error_handler(x: i32) -> xcept::Result<&'static str> {
+///     println!("x: {x}");
+///     "Odd".into()
+/// }
+///
+/// assert_eq!((|| hello_world(0)).or_handle(error_handler).unwrap(), "Even");
+/// assert_eq!((|| hello_world(3)).or_handle(error_handler).unwrap(), "Odd");
+///
+/// let one = (|| hello_world(1)).or_else_value(|err: CustomError| {
+///     println!("{err:?}");
+///     "One"
+/// });
+/// assert_eq!(one, "One");
+///
+/// let handlers = xcept::builder(|_: CustomError| "One".into())
+///     .handle(error_handler)
+///     .build();
+/// assert_eq!((|| hello_world(1)).or_handle_with(handlers).unwrap(), "One");
+/// assert_eq!((|| hello_world(5)).or_value("Unknown"), "Unknown");
+/// ```
+pub trait TryExt<T>: FnOnce() -> Result<T> + Sized {
+    /// Run the closure and handle errors of type `E`, see [`try_or_handle_one`].
+    fn or_handle<E, H>(self, handler: H) -> Result<T>
+    where
+        E: Error,
+        H: FnOnce(E) -> Result<T>,
+    {
+        try_or_handle_one(self, handler)
+    }
+
+    /// Run the closure with a handler chain, see [`try_or_handle`].
+    fn or_handle_with<H>(self, handlers: H) -> Result<T>
+    where
+        H: multihandler::TryHandle<Value = T> + context::ErrorHandlingContext,
+    {
+        try_or_handle(self, handlers)
+    }
+
+    /// Run the closure, returning `value` if it returns an error.
+    ///
+    /// Errors are still delivered to the scopes active when they were raised, only the error
+    /// indication of the returned `Result` is replaced.
+    fn or_value(self, value: T) -> T {
+        self().ok().unwrap_or(value)
+    }
+
+    /// Run the closure, converting an error of type `E` to a value with `f`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the closure returns an error that isn't of type `E`.
+    fn or_else_value<E: Error>(self, f: impl FnOnce(E) -> T) -> T {
+        try_or_handle_one(self, |err| Result::new(f(err))).unwrap()
+    }
+}
+
+impl<T, F: FnOnce() -> Result<T>> TryExt<T> for F {}
+
+/// Execute a function where only the first error matters.
+///
+/// The first error raised by `func`, of any type, is captured, and every error raised after it
+/// is dropped immediately without being offered to any outer scope. When `func` returns an
+/// error the first error is raised again to the outer scopes, keeping its ID, and the returned
+/// `Result` refers to it.
+///
+/// Errors captured by scopes within `func` are not affected.
+///
+/// # Examples
+///
+/// ```
+/// fn storm() -> xcept::Result<()> {
+///     for i in 0..5 {
+///         let _ = xcept::Result::<()>::new_error(i);
+///     }
+///     xcept::Result::new_error("follow-on error")
+/// }
+///
+/// let res = xcept::try_or_handle_one(|| xcept::first_error_scope(storm), |first: i32| {
+///     assert_eq!(first, 0);
+///     xcept::Result::new(())
+/// });
+/// assert!(res.is_ok());
+/// ```
+#[inline]
+pub fn first_error_scope<F, T>(func: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+{
+    let mut context = context::FirstErrorContext::default();
+    let res = {
+        let scope = std::pin::pin!(context::ScopeNode::new(&mut context));
+        // Safety: the guard is dropped at the end of the block
+        let _guard = unsafe { scope.register() };
+        func()
+    };
+    match context.into_inner() {
+        Some(first) if res.is_error() => {
+            let (id, error_type) = context::push_erased(first);
+            Result::new_with_error_type(id, error_type)
+        }
+        _ => res,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    #[test]
+    fn try_or_handle_one() {
+        fn func() -> crate::Result<i32> {
+            crate::Result::new_error(true)
+        }
+
+        let x = crate::try_or_handle_one(func, |_e: bool| 1.into()).unwrap();
+        assert_eq!(x, 1);
+
+        fn two_errors() -> crate::Result<i32> {
+            crate::Result::<i32>::new_error(true);
+            crate::Result::new_error(10)
+        }
+
+        let mut called = false;
+        let x = crate::try_or_handle_one(two_errors, |_: bool| {
+            called = true;
+            2.into()
+        });
+
+        assert!(!called);
+        assert!(x.is_error(), "{x:?}");
+    }
+
+    #[test]
+    fn error_without_scopes() {
+        let res: crate::Result<i32> = crate::Result::new_error(true);
+        assert!(res.is_error(), "{res:?}");
+    }
+
+    #[test]
+    fn multi_handlers() {
+        fn handler1(e: i32) -> crate::Result<i32> {
+            (e * 2).into()
+        }
+        fn handler2(_e: &str) -> crate::Result<i32> {
+            (-1).into()
+        }
+        fn handler3(_e: bool) -> crate::Result<i32> {
+            crate::Result::new_error("Bool not supported!")
+        }
+        let handlers = crate::multihandler::builder(handler1)
+            .handle(handler2)
+            .handle(handler3)
+            .build();
+
+        let res = crate::try_or_handle(|| 10.into(), handlers);
+
+        assert_eq!(res.unwrap(), 10);
+
+        let res = crate::try_or_handle(|| crate::Result::new_error(15), handlers);
+
+        assert_eq!(res.unwrap(), 30);
+
+        let res = crate::try_or_handle(
+            || crate::Result::new_error("should be -1"),
+            handlers,
+        );
+
+        assert_eq!(res.unwrap(), -1);
+
+        let res = crate::try_or_handle(|| crate::Result::new_error(false), handlers);
+
+        assert!(res.is_error(), "{res:?}");
+    }
+
+    #[test]
+    fn multi_handlers_with_refs() {
+        let which = RefCell::new(0);
+        let handlers = crate::multihandler::builder(|_: i32| {
+            *which.borrow_mut() = 1;
+            crate::Result::new(1)
+        })
+        .handle(|_: &str| {
+            *which.borrow_mut() = 2;
+            crate::Result::new(2)
+        })
+        .build();
+
+        let res = crate::try_or_handle(|| crate::Result::new_error(0), handlers);
+        assert_eq!(res.unwrap(), 1);
+        assert_eq!(*which.borrow(), 1);
+
+        let res = crate::try_or_handle(|| crate::Result::new_error("hello"), handlers);
+        assert_eq!(res.unwrap(), 2);
+        assert_eq!(*which.borrow(), 2);
+    }
+
+    #[test]
+    fn handler_array_eight_entries() {
+        // Code size note: with `Sequence` every entry adds its own `try_set_error` and
+        // `try_handle` instantiation (16 symbols for 8 handlers), while the array shares a single
+        // instantiation of each, only adding one small erased reader function per entry.
+        struct E0;
+        struct E1;
+        struct E2;
+        struct E3;
+        struct E4;
+        struct E5;
+        struct E6;
+        struct E7;
+        struct Unknown;
+
+        let handlers = crate::handler_array![
+            E0 => |_| crate::Result::new(0),
+            E1 => |_| crate::Result::new(1),
+            E2 => |_| crate::Result::new(2),
+            E3 => |_| crate::Result::new(3),
+            E4 => |_| crate::Result::new(4),
+            E5 => |_| crate::Result::new(5),
+            E6 => |_| crate::Result::new(6),
+            E7 => |_| crate::Result::new(7),
+        ];
+
+        let raise: [fn() -> crate::Result<i32>; 8] = [
+            || crate::Result::new_error(E0),
+            || crate::Result::new_error(E1),
+            || crate::Result::new_error(E2),
+            || crate::Result::new_error(E3),
+            || crate::Result::new_error(E4),
+            || crate::Result::new_error(E5),
+            || crate::Result::new_error(E6),
+            || crate::Result::new_error(E7),
+        ];
+
+        for (expected, func) in raise.into_iter().enumerate() {
+            let res = crate::try_or_handle(func, handlers);
+            assert_eq!(res.unwrap(), expected as i32);
+        }
+
+        let res = crate::try_or_handle(|| 100.into(), handlers);
+        assert_eq!(res.unwrap(), 100);
+
+        let res = crate::try_or_handle(|| crate::Result::new_error(Unknown), handlers);
+        assert!(res.is_error(), "{res:?}");
+    }
+
+    #[test]
+    fn handler_array_drops_once() {
+        use std::cell::Cell;
+
+        thread_local! {
+            static DROPS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        struct Counted;
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.with(|d| d.set(d.get() + 1));
+            }
+        }
+
+        let handlers = crate::handler_array![Counted => |_| crate::Result::new(1)];
+        let res = crate::try_or_handle(|| crate::Result::new_error(Counted), handlers);
+        assert_eq!(res.unwrap(), 1);
+        assert_eq!(DROPS.with(|d| d.get()), 1);
+    }
+
+    #[test]
+    fn first_error_scope_coalesces() {
+        use std::cell::Cell;
+
+        thread_local! {
+            static DROPS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        struct Storm(i32);
+        impl Drop for Storm {
+            fn drop(&mut self) {
+                DROPS.with(|d| d.set(d.get() + 1));
+            }
+        }
+
+        fn five_errors() -> crate::Result<i32> {
+            for i in 0..4 {
+                let _ = crate::Result::<i32>::new_error(Storm(i));
+            }
+            crate::Result::new_error(Storm(4))
+        }
+
+        let mut handled = Vec::new();
+        let res = crate::try_or_handle_one(
+            || crate::first_error_scope(five_errors),
+            |e: Storm| {
+                handled.push(e.0);
+                crate::Result::new(-1)
+            },
+        );
+
+        assert_eq!(res.unwrap(), -1);
+        assert_eq!(handled, vec![0]);
+        assert_eq!(DROPS.with(|d| d.get()), 5);
+    }
+
+    #[test]
+    fn first_error_scope_ok_drops_first() {
+        let mut called = false;
+        let res = crate::try_or_handle_one(
+            || {
+                crate::first_error_scope(|| {
+                    let _ = crate::Result::<i32>::new_error(String::from("ignored"));
+                    crate::Result::new(1)
+                })
+            },
+            |_: String| {
+                called = true;
+                crate::Result::new(2)
+            },
+        );
+        assert_eq!(res.unwrap(), 1);
+        assert!(!called);
+    }
+
+    #[test]
+    fn first_error_scope_keeps_type() {
+        struct A;
+        let res = crate::try_or_handle_one(
+            || {
+                let res = crate::first_error_scope(|| crate::Result::<()>::new_error(A));
+                assert!(res.error_is::<A>());
+                assert_eq!(res.error_type_name(), Some(std::any::type_name::<A>()));
+                res
+            },
+            |_: A| crate::Result::new(()),
+        );
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn handlers_with_deps() {
+        struct Deps {
+            log: RefCell<Vec<String>>,
+        }
+
+        fn on_int(e: i32, deps: &&Deps) -> crate::Result<i32> {
+            deps.log.borrow_mut().push(format!("int {e}"));
+            crate::Result::new(1)
+        }
+
+        fn on_str(e: &'static str, deps: &&Deps) -> crate::Result<i32> {
+            deps.log.borrow_mut().push(format!("str {e}"));
+            crate::Result::new(2)
+        }
+
+        let deps = Deps {
+            log: RefCell::new(Vec::new()),
+        };
+        let handlers = crate::multihandler::with_deps(&deps)
+            .handle(on_int)
+            .handle(on_str)
+            .build();
+
+        let res = crate::multihandler::try_or_handle_deps(|| crate::Result::new_error(10), handlers);
+        assert_eq!(res.unwrap(), 1);
+        let res = crate::multihandler::try_or_handle_deps(|| crate::Result::new_error("x"), handlers);
+        assert_eq!(res.unwrap(), 2);
+        let res = crate::multihandler::try_or_handle_deps(|| crate::Result::new(3), handlers);
+        assert_eq!(res.unwrap(), 3);
+
+        assert_eq!(*deps.log.borrow(), vec!["int 10".to_string(), "str x".to_string()]);
+    }
+
+    #[test]
+    fn try_unwrap() {
+        assert_eq!(crate::Result::new(5).try_unwrap(), Ok(5));
+        let res: crate::Result<i32> = crate::Result::new_with_error_id(crate::ErrorId::from_raw(7));
+        assert_eq!(res.try_unwrap(), Err(crate::ErrorId::from_raw(7)));
+    }
+
+    // Safe shims that violate the preconditions, only sound because the debug assertions fire
+    // before any undefined behaviour can happen.
+    #[cfg(debug_assertions)]
+    fn unwrap_error_shim() -> i32 {
+        unsafe { crate::Result::<i32>::new_with_error_id(crate::ErrorId::from_raw(1)).value_unchecked() }
+    }
+
+    #[cfg(debug_assertions)]
+    fn error_id_of_ok_shim() -> u64 {
+        unsafe { crate::Result::new(1).error_id_unchecked() }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "called `unwrap_unchecked` on an error `Result`")]
+    fn unwrap_unchecked_asserts() {
+        unwrap_error_shim();
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "called `unchecked_error_id` on an ok `Result`")]
+    fn unchecked_error_id_asserts() {
+        error_id_of_ok_shim();
+    }
+    // A context written against the public fields of `ReportedError`
+    struct LegacyContext(Option<(u64, u16)>);
+
+    impl crate::context::ErrorHandlingContext for LegacyContext {
+        #[allow(deprecated)]
+        unsafe fn try_set_error(
+            &mut self,
+            error: &crate::context::ReportedError,
+        ) -> crate::context::TrySetErrorResult {
+            if error.type_id == std::any::TypeId::of::<u16>() {
+                self.0 = Some((error.id, (error.value as *mut u16).read()));
+                crate::context::TrySetErrorResult::NeedForget
+            } else {
+                crate::context::TrySetErrorResult::NotHandled
+            }
+        }
+    }
+
+    #[test]
+    fn deprecated_field_access() {
+        let mut ctx = LegacyContext(None);
+        let (res, other): (crate::Result<()>, crate::Result<()>) = {
+            let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut ctx));
+            let _guard = unsafe { scope.register() };
+            (crate::Result::new_error(12u16), crate::Result::new_error(12u32))
+        };
+
+        assert!(other.is_error(), "{other:?}");
+        assert_eq!(ctx.0, Some((res.error_id().unwrap().to_raw(), 12)));
+    }
+
+    #[test]
+    fn reported_error_accessors() {
+        let mut value = 5u8;
+        let ext = crate::context::ReportExt::default();
+        let error = crate::context::ReportedError::new(crate::ErrorId(3), &mut value);
+        assert!(error.ext().is_none());
+        let error = error.with_ext(&ext);
+        assert_eq!(error.id(), crate::ErrorId(3));
+        assert_eq!(error.type_id(), std::any::TypeId::of::<u8>());
+        assert_eq!(error.type_name(), "u8");
+        assert_eq!(unsafe { *(error.value_ptr() as *const u8) }, 5);
+        assert!(error.location().is_none());
+    }
+    #[test]
+    fn observed_outcomes() {
+        use crate::multihandler::{try_or_handle_observed, ScopeOutcome};
+
+        fn run(body: impl FnOnce() -> crate::Result<i32>) -> (crate::Result<i32>, ScopeOutcome) {
+            let handlers = crate::builder(|x: u8| crate::Result::new(x as i32))
+                .handle(|_: &str| crate::Result::new(-1))
+                .build();
+            let mut outcome = None;
+            let res = try_or_handle_observed(body, handlers, |o| outcome = Some(o));
+            (res, outcome.unwrap())
+        }
+
+        let (res, outcome) = run(|| crate::Result::new(1));
+        assert_eq!(res.unwrap(), 1);
+        assert_eq!(outcome, ScopeOutcome::Ok);
+
+        let mut raised = None;
+        let (res, outcome) = run(|| {
+            let res = crate::Result::new_error(4u8);
+            raised = res.error_id();
+            res
+        });
+        assert_eq!(res.unwrap(), 4);
+        assert_eq!(
+            outcome,
+            ScopeOutcome::Handled {
+                type_name: "u8",
+                error_id: raised.unwrap()
+            }
+        );
+
+        let (res, outcome) = run(|| crate::Result::new_error(4u16));
+        assert_eq!(
+            outcome,
+            ScopeOutcome::Propagated {
+                error_id: res.error_id().unwrap()
+            }
+        );
+    }
+    #[test]
+    fn probes_run_before_capture() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let handlers = {
+            let log = log.clone();
+            crate::builder(move |x: i32| {
+                log.borrow_mut().push(format!("handler {x}"));
+                crate::Result::new(x)
+            })
+            .build()
+        };
+
+        let probe = {
+            let log = log.clone();
+            crate::on_raise(move |x: &i32| log.borrow_mut().push(format!("on_raise {x}")))
+        };
+        let res = crate::try_or_handle(
+            || {
+                crate::Result::new_error_inspected(1, |x| {
+                    log.borrow_mut().push(format!("inspected {x}"))
+                })
+            },
+            handlers,
+        );
+        assert_eq!(res.unwrap(), 1);
+
+        // Not probed: another type, and after unregistering
+        let _: crate::Result<()> = crate::Result::new_error(2u8);
+        drop(probe);
+        let _: crate::Result<()> = crate::Result::new_error(3);
+
+        assert_eq!(
+            *log.borrow(),
+            vec!["inspected 1", "on_raise 1", "handler 1"]
+        );
+    }
+
+    #[test]
+    fn tap_error_once() {
+        let mut seen = Vec::new();
+        let res: crate::Result<()> = crate::Result::new_error(1).tap_error(|id| seen.push(id));
+        let _ = crate::Result::new(1).tap_error(|id| seen.push(id));
+        assert_eq!(seen, vec![res.error_id().unwrap()]);
+    }
+    #[derive(Copy, Clone)]
+    struct Numbered<const N: usize>;
+
+    macro_rules! numbered_chain {
+        ($($n:literal),+) => {
+            crate::builder(|_: Numbered<0>| crate::Result::new(0))
+                $(.handle(|_: Numbered<$n>| crate::Result::new($n)))+
+        };
+    }
+
+    fn raise_numbered(n: usize) -> crate::Result<usize> {
+        match n {
+            0 => crate::Result::new_error(Numbered::<0>),
+            3 => crate::Result::new_error(Numbered::<3>),
+            11 => crate::Result::new_error(Numbered::<11>),
+            17 => crate::Result::new_error(Numbered::<17>),
+            _ => crate::Result::new_error(n),
+        }
+    }
+
+    #[test]
+    fn flat_matches_sequence() {
+        let flat = numbered_chain!(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11)
+            .handle(|n: usize| crate::Result::new(100 + n))
+            .build_flat();
+        let sequence = flat.into_inner();
+        for n in [0, 3, 11, 5] {
+            let a = crate::try_or_handle(|| raise_numbered(n), flat);
+            let b = crate::try_or_handle(|| raise_numbered(n), sequence);
+            assert_eq!(a.unwrap(), b.unwrap());
+        }
+
+        // Longer than the table, walked like a sequence
+        let long = numbered_chain!(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17).build_flat();
+        assert_eq!(crate::try_or_handle(|| raise_numbered(17), long).unwrap(), 17);
+        assert_eq!(crate::try_or_handle(|| raise_numbered(3), long).unwrap(), 3);
+        assert!(crate::try_or_handle(|| raise_numbered(5), long).is_error());
+    }
+
+    #[test]
+    fn flat_keeps_order() {
+        let mut builder = crate::dynamic::DynBuilder::new();
+        builder.handle(|x: i32| crate::Result::new(format!("dyn {x}")));
+        let handlers = crate::multihandler::compose(builder.build())
+            .handle(|x: i32| crate::Result::new(format!("static {x}")))
+            .handle(|x: u8| crate::Result::new(format!("first {x}")))
+            .handle(|x: u8| crate::Result::new(format!("second {x}")))
+            .build();
+        assert_eq!(
+            crate::try_or_handle(|| crate::Result::new_error(1i32), handlers).unwrap(),
+            "dyn 1"
+        );
+
+        let handlers = crate::builder(|x: u8| crate::Result::new(format!("first {x}")))
+            .handle(|x: u8| crate::Result::new(format!("second {x}")))
+            .build();
+        assert_eq!(
+            crate::try_or_handle(|| crate::Result::new_error(2u8), handlers).unwrap(),
+            "first 2"
+        );
+    }
+    #[test]
+    fn try_ext_methods() {
+        use crate::TryExt;
+
+        let res = (|| crate::Result::<i32>::new_error(2u8)).or_handle(|x: u8| crate::Result::new(x as i32));
+        assert_eq!(res.unwrap(), 2);
+        let res = (|| crate::Result::<i32>::new_error(2u16)).or_handle(|x: u8| crate::Result::new(x as i32));
+        assert!(res.is_error(), "{res:?}");
+
+        assert_eq!((|| crate::Result::new_error("e")).or_value(5), 5);
+        assert_eq!((|| crate::Result::new(1)).or_else_value(|_: &str| 5), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn try_ext_or_else_value_unhandled() {
+        use crate::TryExt;
+        (|| crate::Result::new_error(1u8)).or_else_value(|_: &str| 5);
+    }
+    #[test]
+    fn isolated_ignores_reentrant_raises() {
+        use crate::multihandler::try_or_handle_isolated;
+        use std::cell::Cell;
+
+        // A callback registered earlier, running in its own isolated scope when it fires
+        let callback = || {
+            try_or_handle_isolated(
+                || crate::Result::<i32>::new_error(99),
+                crate::builder(|_: &str| crate::Result::new(0)).build(),
+            )
+        };
+
+        let handled = Cell::new(None);
+        let outer = crate::builder(|x: i32| {
+            handled.set(Some(x));
+            crate::Result::new(x)
+        })
+        .build();
+        let mut callback_res = None;
+        let res = try_or_handle_isolated(
+            || {
+                callback_res = Some(callback());
+                crate::Result::new_error(1)
+            },
+            outer,
+        );
+
+        assert_eq!(res.unwrap(), 1);
+        assert_eq!(handled.get(), Some(1));
+        assert!(callback_res.unwrap().is_error());
+    }
+
+    #[test]
+    fn isolated_passes_foreign_raises_outwards() {
+        use crate::multihandler::try_or_handle_isolated;
+
+        let res = crate::try_or_handle(
+            || {
+                try_or_handle_isolated(
+                    || {
+                        try_or_handle_isolated(
+                            || crate::Result::<i32>::new_error(5),
+                            crate::builder(|_: u8| crate::Result::new(0)).build(),
+                        )
+                    },
+                    crate::builder(|_: i32| crate::Result::new(-1)).build(),
+                )
+            },
+            crate::builder(|x: i32| crate::Result::new(x)).build(),
+        );
+        assert_eq!(res.unwrap(), 5);
+    }
+
+    #[test]
+    fn operation_tokens_nest() {
+        let outer = crate::context::enter_operation();
+        let inner = crate::context::enter_operation();
+        assert!(inner.token() > outer.token());
+        drop(inner);
+        let next = crate::context::enter_operation();
+        assert!(next.token() > outer.token());
+    }
+    #[test]
+    fn combinators_match_std() {
+        fn ours(res: &std::result::Result<i32, crate::ErrorId>) -> crate::Result<i32> {
+            match res {
+                Ok(v) => crate::Result::new(*v),
+                Err(id) => crate::Result::new_with_error_id(*id),
+            }
+        }
+        let cases = [Ok(1), Err(crate::ErrorId::from_raw(7))];
+        for a in &cases {
+            for b in &cases {
+                assert_eq!(ours(a).and(ours(b)).try_unwrap(), a.and(*b));
+                assert_eq!(ours(a).or(ours(b)).try_unwrap(), a.or(*b));
+            }
+            assert_eq!(ours(a).err(), a.err());
+            assert_eq!(ours(a).iter().next(), a.iter().next());
+            assert_eq!(ours(a).into_iter().collect::<Vec<_>>(), (*a).into_iter().collect::<Vec<_>>());
+            let (x, y) = ours(a).and(crate::Result::new(((), 2))).unzip();
+            assert_eq!((x.error_id(), y.try_unwrap()), (a.err(), a.map(|_| 2)));
+        }
+
+        let boxed = crate::Result::new(Box::new(3));
+        assert_eq!(*boxed.as_deref().unwrap(), 3);
+        let copied = crate::Result::<&i32>::new_with_error_id(crate::ErrorId::from_raw(4)).copied();
+        assert_eq!(copied.error_id(), Some(crate::ErrorId::from_raw(4)));
+    }
+
+    #[test]
+    fn map_raise_replaces_error() {
+        let res: crate::Result<i32> = crate::Result::new(1).map_raise(|| "unused");
+        assert_eq!(res.unwrap(), 1);
+
+        let mut ctx = crate::context::CatchAllContext { inner: None };
+        let res = {
+            let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut ctx));
+            let _guard = unsafe { scope.register() };
+            let first: crate::Result<i32> = crate::Result::new_with_error_id(crate::ErrorId::from_raw(1));
+            first.map_raise(|| 5u8)
+        };
+        assert_eq!(ctx.inner, Some((res.error_id().unwrap(), std::any::TypeId::of::<u8>())));
+    }
+    #[test]
+    fn base_only_context_works_everywhere() {
+        use crate::context::{scopes_accept, ErrorHandlingContext, ReportedError, TrySetErrorResult};
+
+        // Implements only the frozen base trait
+        #[derive(Clone)]
+        struct Plain(Option<crate::ErrorId>);
+
+        impl ErrorHandlingContext for Plain {
+            unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+                if error.type_id() == std::any::TypeId::of::<u64>() {
+                    self.0 = Some(error.id());
+                    TrySetErrorResult::NeedDrop
+                } else {
+                    TrySetErrorResult::NotHandled
+                }
+            }
+        }
+
+        impl crate::multihandler::TryHandle for Plain {
+            type Value = u64;
+            fn try_handle(self, error_id: crate::ErrorId) -> Option<crate::Result<u64>> {
+                (self.0 == Some(error_id)).then(|| crate::Result::new(64))
+            }
+        }
+
+        let handlers = crate::multihandler::compose(Plain(None))
+            .handle(|x: u8| crate::Result::new(x as u64))
+            .build();
+        let res = crate::try_or_handle(|| crate::Result::new_error(1u64), handlers.clone());
+        assert_eq!(res.unwrap(), 64);
+        let res = crate::try_or_handle(|| crate::Result::new_error(2u8), handlers.clone());
+        assert_eq!(res.unwrap(), 2);
+
+        // The plain context can't tell, unless a known accepting entry comes first
+        let mut answers = None;
+        let _ = crate::try_or_handle(
+            || {
+                answers = Some((scopes_accept::<u16>(), scopes_accept::<u8>()));
+                crate::Result::new(0)
+            },
+            handlers,
+        );
+        assert_eq!(answers, Some((None, Some(true))));
+    }
+
+    #[test]
+    fn ext_reports_pending_and_dispositions() {
+        use crate::context::{
+            scopes_pending, ContextExt, Disposition, ErrorHandlingContext, ReportedError,
+            TrySetErrorResult,
+        };
+
+        #[derive(Default)]
+        struct Recording(Vec<(crate::ErrorId, Disposition)>);
+
+        impl ErrorHandlingContext for Recording {
+            unsafe fn try_set_error(&mut self, _error: &ReportedError) -> TrySetErrorResult {
+                TrySetErrorResult::NeedDrop
+            }
+
+            fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+                Some(self)
+            }
+        }
+
+        impl ContextExt for Recording {
+            fn on_disposition(&mut self, error_id: crate::ErrorId, disposition: Disposition) {
+                self.0.push((error_id, disposition));
+            }
+        }
+
+        let mut recording = Recording::default();
+        let res: crate::Result<()> = {
+            let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut recording));
+            let _guard = unsafe { scope.register() };
+            crate::Result::new_error("x")
+        };
+        assert_eq!(recording.0, vec![(res.error_id().unwrap(), Disposition::Dropped)]);
+
+        assert!(!scopes_pending());
+        let pending = crate::try_or_handle_one(
+            || {
+                let _: crate::Result<()> = crate::Result::new_error(1u8);
+                crate::try_or_handle_one(|| crate::Result::new(scopes_pending()), |_: u16| crate::Result::new(false))
+            },
+            |_: u8| crate::Result::new(false),
+        );
+        assert!(pending.unwrap());
+    }
+
+    #[cfg(feature = "paranoid")]
+    #[test]
+    #[should_panic(expected = "not the innermost")]
+    fn paranoid_pop_non_head() {
+        let mut outer = crate::context::SingleErrorStorage::<i32>::default();
+        let mut inner = crate::context::SingleErrorStorage::<bool>::default();
+        let mut outer_scope = crate::context::ScopeNode::raw(&mut outer);
+        let mut inner_scope = crate::context::ScopeNode::raw(&mut inner);
+        let outer_guard = unsafe { crate::context::push_scope(&mut outer_scope) };
+        let _inner_guard = unsafe { crate::context::push_scope(&mut inner_scope) };
+        drop(outer_guard);
+    }
+
+    #[cfg(feature = "paranoid")]
+    #[test]
+    #[should_panic(expected = "dropped while still registered")]
+    fn paranoid_scope_dropped_while_registered() {
+        let mut storage = crate::context::SingleErrorStorage::<i32>::default();
+        let mut scope = crate::context::ScopeNode::raw(&mut storage);
+        std::mem::forget(unsafe { crate::context::push_scope(&mut scope) });
+        drop(scope);
+    }
+
+    #[cfg(feature = "paranoid")]
+    #[test]
+    #[should_panic(expected = "with an ok `Result`")]
+    fn paranoid_unchecked_try_handle_ok() {
+        let storage = crate::context::SingleErrorStorage::<i32>::default();
+        let _ = unsafe { storage.try_handle_unchecked(crate::Result::new(1), |_| crate::Result::new(2)) };
+    }
+
+    #[cfg(feature = "paranoid")]
+    #[test]
+    #[should_panic(expected = "dropped twice")]
+    fn paranoid_guard_dropped_twice() {
+        let mut storage = crate::context::SingleErrorStorage::<i32>::default();
+        let mut scope = crate::context::ScopeNode::raw(&mut storage);
+        let guard = unsafe { crate::context::push_scope(&mut scope) };
+        let duplicate = unsafe { std::ptr::read(&guard) };
+        drop(guard);
+        drop(duplicate);
+    }
+
+    #[test]
+    fn dedup_by_value_runs_once_per_value() {
+        #[derive(Clone, PartialEq, Eq, Hash)]
+        struct MissingKey(&'static str);
+
+        let invocations = std::cell::Cell::new(0);
+        let mut handler = crate::multihandler::dedup_by_value(|MissingKey(key)| {
+            invocations.set(invocations.get() + 1);
+            crate::Result::new(key.len())
+        });
+
+        let mut handled = 0;
+        for key in ["db.host", "db.host", "db.port", "db.host"] {
+            let res = crate::try_or_handle(|| crate::Result::new_error(MissingKey(key)), &mut handler);
+            assert_eq!(res.unwrap(), 7);
+            handled += 1;
+        }
+        assert_eq!(handled, 4);
+        assert_eq!(invocations.get(), 2);
+        assert_eq!(handler.seen(), 2);
+
+        handler.reset();
+        assert_eq!(handler.seen(), 0);
+        let res = crate::try_or_handle(|| crate::Result::new_error(MissingKey("db.host")), &mut handler);
+        assert!(res.is_ok());
+        assert_eq!(invocations.get(), 3);
+    }
+
+    #[test]
+    fn secondary_errors_when_pending() {
+        #[derive(Debug, PartialEq)]
+        struct Cleanup(u8);
+
+        let cleanup_caught = std::cell::Cell::new(None);
+        let outcome = std::cell::Cell::new(None);
+        let run = |fail: bool| {
+            crate::try_or_handle(
+                || {
+                    let primary: crate::Result<()> = if fail {
+                        crate::Result::new_error("primary")
+                    } else {
+                        crate::Result::new(())
+                    };
+                    let cleanup = crate::try_or_handle_one(
+                        || {
+                            let first = crate::Result::new_error_if_clear(Cleanup(1));
+                            let second: crate::Result<()> =
+                                crate::suppressing_secondary(|| crate::Result::new_error(Cleanup(2)));
+                            outcome.set(Some((first.is_ok(), second.is_ok())));
+                            first
+                        },
+                        |err: Cleanup| {
+                            cleanup_caught.set(Some(err.0));
+                            crate::Result::new(())
+                        },
+                    );
+                    primary.and(cleanup).and(crate::Result::new("clear"))
+                },
+                crate::builder(|err: &'static str| crate::Result::new(err)).build(),
+            )
+        };
+
+        // The primary error is pending in the outer scope, both cleanup errors are dropped
+        assert_eq!(run(true).unwrap(), "primary");
+        assert_eq!(outcome.get(), Some((true, false)));
+        assert_eq!(cleanup_caught.get(), None);
+
+        // The first cleanup error is raised, and is then pending in the inner scope
+        assert_eq!(run(false).unwrap(), "clear");
+        assert_eq!(outcome.get(), Some((false, false)));
+        assert_eq!(cleanup_caught.get(), Some(1));
+
+        // Nothing pending without any scope, raises normally
+        let res: crate::Result<()> = crate::suppressing_secondary(|| crate::Result::new_error(Cleanup(3)));
+        assert!(res.is_error(), "{res:?}");
+    }
+
+    #[test]
+    fn raise_owned_converts() {
+        fn parse(input: &str) -> crate::Result<u32> {
+            match input.parse() {
+                Ok(x) => crate::Result::new(x),
+                Err(_) if input.is_empty() => crate::Result::raise_owned(std::borrow::Cow::Borrowed("empty")),
+                Err(_) => crate::Result::raise_owned(format_args!("not a number: {input}")),
+            }
+        }
+
+        let handled = RefCell::new(Vec::new());
+        for input in ["", "x1", "12"] {
+            let res = crate::try_or_handle_one(
+                || parse(input),
+                |err: String| {
+                    handled.borrow_mut().push(err);
+                    crate::Result::new(0)
+                },
+            );
+            assert!(res.is_ok());
+        }
+        assert_eq!(*handled.borrow(), ["empty", "not a number: x1"]);
+
+        let bytes: crate::Result<()> = crate::try_or_handle_one(
+            || crate::Result::raise_owned(&b"\x00\x01"[..]),
+            |err: Vec<u8>| {
+                assert_eq!(err, [0, 1]);
+                crate::Result::new(())
+            },
+        );
+        assert!(bytes.is_ok());
+    }
+
+    #[test]
+    fn abandoned_on_unwind() {
+        #[derive(Debug)]
+        struct Lost;
+
+        let abandoned = std::rc::Rc::new(RefCell::new(Vec::new()));
+        let hook = {
+            let abandoned = abandoned.clone();
+            crate::context::on_abandoned(move |lost| abandoned.borrow_mut().push(lost.type_name))
+        };
+
+        let outer = crate::builder(|_: &'static str| crate::Result::new(0)).build();
+        let res = std::panic::catch_unwind(|| {
+            crate::try_or_handle(
+                || {
+                    let inner = crate::builder(|_: Lost| crate::Result::new(1))
+                        .handle(|_: bool| crate::Result::new(2))
+                        .build();
+                    crate::try_or_handle(
+                        || {
+                            let _: crate::Result<()> = crate::Result::new_error(Lost);
+                            std::panic::resume_unwind(Box::new("cancelled"))
+                        },
+                        inner,
+                    )
+                },
+                outer,
+            )
+        });
+        assert!(res.is_err());
+        assert_eq!(*abandoned.borrow(), [Some(std::any::type_name::<Lost>())]);
+
+        // Scopes left without a pending error, or normally, don't report anything
+        let res = std::panic::catch_unwind(|| {
+            crate::try_or_handle_one(|| -> crate::Result<()> { std::panic::resume_unwind(Box::new(1)) }, |_: Lost| {
+                crate::Result::new(())
+            })
+        });
+        assert!(res.is_err());
+        let res = crate::try_or_handle_one(|| crate::Result::new_error(Lost), |_: Lost| crate::Result::new(()));
+        assert!(res.is_ok());
+        drop(hook);
+        assert_eq!(abandoned.borrow().len(), 1);
+    }
+
+    #[test]
+    fn boxing_catch_all_downcasts() {
+        fn read_config(missing: bool) -> crate::Result<String> {
+            if missing {
+                crate::Result::new_error(std::io::Error::new(std::io::ErrorKind::NotFound, "config.toml"))
+            } else {
+                crate::Result::new_error(7u8)
+            }
+        }
+
+        for missing in [true, false] {
+            let mut catch_all = crate::context::BoxingCatchAll::default();
+            let res = {
+                let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut catch_all));
+                let _guard = unsafe { scope.register() };
+                read_config(missing)
+            };
+
+            let res = catch_all.try_handle(res, |err| {
+                let message = match err.downcast::<std::io::Error>() {
+                    Ok(err) => format!("{:?}: {}", err.kind(), err),
+                    Err(err) => format!("other: {}", err.downcast::<u8>().unwrap()),
+                };
+                crate::Result::new(message)
+            });
+            let expected = if missing { "NotFound: config.toml" } else { "other: 7" };
+            assert_eq!(res.unwrap(), expected);
+        }
+
+        // Erased errors can be boxed too
+        let mut first = crate::context::FirstErrorContext::default();
+        let _: crate::Result<()> = {
+            let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut first));
+            let _guard = unsafe { scope.register() };
+            crate::Result::new_error(String::from("boxed"))
+        };
+        let boxed = first.into_inner().unwrap().into_box();
+        assert_eq!(*boxed.downcast::<String>().unwrap(), "boxed");
+    }
+
+    #[test]
+    fn epoch_wraparound() {
+        use crate::context::{current_epoch, epoch_of, epoch_precedes};
+        let half = 1u32 << 31;
+
+        for a in [0, 1, 127, 128, 255, 256, half - 1, half, u32::MAX - 1, u32::MAX] {
+            assert!(!epoch_precedes(a, a));
+            for ahead in [1, 127, 128, 129, 255, 256, 257, half - 1] {
+                let b = a.wrapping_add(ahead);
+                assert!(epoch_precedes(a, b), "{a} before {b}");
+                assert!(!epoch_precedes(b, a), "{b} not before {a}");
+            }
+            // Half the range or more ahead is taken as behind
+            assert!(!epoch_precedes(a, a.wrapping_add(half)));
+            assert!(epoch_precedes(a.wrapping_add(half + 1), a));
+        }
+
+        let scope = |ctx: &mut crate::context::SingleErrorStorage<u8>| {
+            let scope = std::pin::pin!(crate::context::ScopeNode::new(ctx));
+            drop(unsafe { scope.register() });
+        };
+        let start = current_epoch();
+        let first: crate::Result<()> = crate::Result::new_error(1);
+        let mut ctx = crate::context::SingleErrorStorage::<u8>::default();
+        for _ in 0..127 {
+            scope(&mut ctx);
+        }
+        let second: crate::Result<()> = crate::Result::new_error(2);
+        assert_eq!(epoch_of(second.error_id().unwrap().to_raw()), current_epoch());
+        assert!(first.is_stale());
+        assert!(!second.is_stale());
+
+        // Still detected past 128 and 256 pops, where an 8 bit epoch wrapped
+        for pops in 128..=300 {
+            scope(&mut ctx);
+            assert!(first.is_stale(), "after {pops} pops");
+            assert!(second.is_stale(), "after {pops} pops");
+        }
+        assert_eq!(current_epoch(), start.wrapping_add(300));
+        assert!(crate::context::TAKEN_ID.to_raw() > second.error_id().unwrap().to_raw());
+    }
+
+    #[test]
+    fn epoch_nested_scopes() {
+        let mut outer_ctx = crate::context::SingleErrorStorage::<u8>::default();
+        let outer_scope = std::pin::pin!(crate::context::ScopeNode::new(&mut outer_ctx));
+        let outer = unsafe { outer_scope.register() };
+        let before: crate::Result<()> = crate::Result::new_error(1u8);
+
+        let mut inner_ctx = crate::context::SingleErrorStorage::<u8>::default();
+        let inner_scope = std::pin::pin!(crate::context::ScopeNode::new(&mut inner_ctx));
+        let inner = unsafe { inner_scope.register() };
+        // Pushing doesn't change the epoch, so an error raised just before isn't detected
+        assert_eq!(inner.epoch(), outer.epoch());
+        assert!(!before.is_stale());
+
+        {
+            let mut ctx = crate::context::SingleErrorStorage::<u8>::default();
+            let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut ctx));
+            drop(unsafe { scope.register() });
+        }
+        let after: crate::Result<()> = crate::Result::new_error(2u8);
+        assert!(!after.is_stale());
+        assert!(!inner.predates(after.error_id().unwrap()));
+        drop(inner);
+
+        // Popping `inner` advanced the epoch, so a scope pushed now predates `after`
+        let mut ctx = crate::context::SingleErrorStorage::<u8>::default();
+        let late_scope = std::pin::pin!(crate::context::ScopeNode::new(&mut ctx));
+        let late = unsafe { late_scope.register() };
+        assert!(after.is_stale());
+        assert!(late.predates(after.error_id().unwrap()));
+        assert!(!outer.predates(after.error_id().unwrap()));
+        drop(late);
+        drop(outer);
+    }
+
+    #[test]
+    fn epoch_ids_across_epochs() {
+        use crate::context::{current_epoch, epoch_of};
+        let mut ids = Vec::new();
+        for _ in 0..4 {
+            let epoch = current_epoch();
+            let res: crate::Result<()> = crate::try_or_handle_one(|| crate::Result::new_error(1i32), |_: u8| crate::Result::new(()));
+            let id = res.error_id().unwrap().to_raw();
+            assert_eq!(epoch_of(id), epoch);
+            ids.push(id);
+        }
+        // Every `try_or_handle_one` pops a scope, so the IDs differ in their epoch
+        for pair in ids.windows(2) {
+            assert_ne!(pair[0], pair[1]);
+            assert!(crate::context::epoch_precedes(epoch_of(pair[0]), epoch_of(pair[1])));
+        }
+    }
+
+    #[test]
+    fn panic_on_raise_region() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        struct Counted(Rc<Cell<u32>>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        // Errors handled inside the region don't panic
+        let res = crate::panic_on_raise(|| {
+            crate::try_or_handle_one(|| crate::Result::new_error(3u8), |x: u8| crate::Result::new(x))
+        });
+        assert_eq!(res.unwrap(), 3);
+
+        let drops = Rc::new(Cell::new(0));
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            crate::panic_on_raise(|| -> crate::Result<()> { crate::Result::new_error(Counted(drops.clone())) })
+        }));
+        let message = res.err().unwrap().downcast::<String>().unwrap();
+        assert!(message.contains(std::any::type_name::<Counted>()), "{message}");
+        if cfg!(feature = "diagnostics") {
+            assert!(message.contains(file!()), "{message}");
+        }
+        // Leaked while unwinding, never dropped twice
+        assert_eq!(drops.get(), 0);
+
+        // The region's scope is gone
+        assert_eq!(crate::context::scopes_accept::<Counted>(), Some(false));
+        let res: crate::Result<()> = crate::Result::new_error(Counted(drops.clone()));
+        assert!(res.is_error(), "{res:?}");
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn map_captured_keeps_id() {
+        use crate::multihandler::TryHandle;
+
+        let mut storage = crate::context::SingleErrorStorage::<i32>::default();
+        assert!(!storage.replace_with(|_| unreachable!()));
+
+        let mut handlers = crate::builder(|x: u8| crate::Result::new(i64::from(x)))
+            .handle(|x: i32| crate::Result::new(i64::from(x) * 10))
+            .build_flat();
+        assert!(!handlers.map_captured(|x: i32| x));
+        let res: crate::Result<()> = {
+            let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut handlers));
+            let _guard = unsafe { scope.register() };
+            crate::Result::new_error(4i32)
+        };
+
+        let id = res.error_id().unwrap();
+        assert!(!handlers.map_captured(|x: u8| x));
+        assert!(handlers.map_captured(|x: i32| -x));
+        assert_eq!(handlers.try_handle(id).unwrap().unwrap(), -40);
+    }
+
+    #[test]
+    fn map_keeps_error_id() {
+        let mut raised = None;
+        let res = crate::try_or_handle_one(
+            || {
+                let res: crate::Result<i32> = crate::Result::new_error(3u8);
+                raised = res.error_id();
+                let mapped = res.map(|x| x.to_string());
+                assert_eq!(mapped.error_id(), raised);
+                mapped
+            },
+            |err: u8| crate::Result::new(format!("handled {err}")),
+        );
+        assert!(raised.is_some());
+        assert_eq!(res.unwrap(), "handled 3");
+
+        let res = crate::try_or_handle_one(|| crate::Result::new(2).map(|x| x * 10), |_: u8| crate::Result::new(0));
+        assert_eq!(res.unwrap(), 20);
+    }
+
+    #[test]
+    fn and_then_chain() {
+        struct Parse(&'static str);
+        struct Lookup(u32);
+
+        fn parse(input: &'static str) -> crate::Result<u32> {
+            input.parse().map_err(|_| Parse(input)).into()
+        }
+        fn lookup(key: u32) -> crate::Result<&'static str> {
+            if key == 1 {
+                crate::Result::new("one")
+            } else {
+                crate::Result::new_error(Lookup(key))
+            }
+        }
+        fn render(name: &'static str) -> crate::Result<String> {
+            crate::Result::new(name.to_uppercase())
+        }
+
+        let run = |input| {
+            let handlers = crate::builder(|err: Parse| crate::Result::new(format!("parse {}", err.0)))
+                .handle(|err: Lookup| crate::Result::new(format!("lookup {}", err.0)))
+                .build();
+            let mut inner_id = None;
+            let res = crate::try_or_handle(
+                || {
+                    let res = parse(input).and_then(|key| {
+                        let res = lookup(key);
+                        inner_id = res.error_id();
+                        res
+                    });
+                    // An error raised by the middle step is the one passed on
+                    if inner_id.is_some() {
+                        assert_eq!(res.error_id(), inner_id);
+                    }
+                    res.and_then(render)
+                },
+                handlers,
+            );
+            res.unwrap()
+        };
+        assert_eq!(run("1"), "ONE");
+        assert_eq!(run("2"), "lookup 2");
+        assert_eq!(run("x"), "parse x");
+    }
+
+    #[test]
+    fn bare_raise_records_nothing() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let probed = Rc::new(Cell::new(0));
+        let counter = probed.clone();
+        let _probe = crate::context::on_raise(move |_: &u16| counter.set(counter.get() + 1));
+        #[cfg(feature = "diagnostics")]
+        crate::debug::clear();
+
+        let res = crate::try_or_handle_one(|| crate::Result::<u16>::new_error_bare(7u16), |err: u16| crate::Result::new(err));
+        assert_eq!(res.unwrap(), 7);
+        assert_eq!(probed.get(), 0);
+        #[cfg(feature = "diagnostics")]
+        assert!(crate::debug::recent_raises().is_empty());
+
+        let _: crate::Result<()> = crate::Result::new_error_traced(8u16);
+        assert_eq!(probed.get(), 1);
+        #[cfg(feature = "diagnostics")]
+        assert_eq!(crate::debug::recent_raises().len(), 1);
+    }
+
+    #[test]
+    fn unwrap_or_variants() {
+        assert_eq!(crate::Result::new(1).unwrap_or(2), 1);
+        assert_eq!(crate::Result::new(1).unwrap_or_else(|| unreachable!()), 1);
+        assert_eq!(crate::Result::new(1).unwrap_or_default(), 1);
+
+        // Raised without any scope, the error is dropped and only the ID is left
+        let unhandled = || crate::Result::<i32>::new_error("unhandled");
+        assert_eq!(unhandled().unwrap_or(2), 2);
+        assert_eq!(unhandled().unwrap_or_else(|| 3), 3);
+        assert_eq!(unhandled().unwrap_or_default(), 0);
+
+        // Inside a scope the error reaches the handler, and the fallback is still used
+        let res = crate::try_or_handle_one(
+            || crate::Result::new(crate::Result::<i32>::new_error(5u8).unwrap_or(4)),
+            |_: u8| crate::Result::new(0),
+        );
+        assert_eq!(res.unwrap(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "config must be loaded: error ID 17")]
+    fn expect_message() {
+        assert_eq!(crate::Result::new(1).expect("unused"), 1);
+        crate::Result::<i32>::new_with_error_id(crate::ErrorId::from_raw(17)).expect("config must be loaded");
+    }
+
+    #[test]
+    #[should_panic(expected = "called `Result::unwrap()` on an error: error ID 18")]
+    fn unwrap_message() {
+        crate::Result::<i32>::new_with_error_id(crate::ErrorId::from_raw(18)).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = ", type `xcept::tests::unwrap_message_with_type::Unparsable`")]
+    fn unwrap_message_with_type() {
+        struct Unparsable;
+        crate::Result::<i32>::new_error(Unparsable).unwrap();
+    }
+
+    #[test]
+    fn expect_message_with_type() {
+        let res: crate::Result<i32> = crate::Result::new_error(std::io::Error::other("gone"));
+        let id = res.error_id().unwrap().to_raw();
+        let payload = std::panic::catch_unwind(|| res.expect("config must be loaded")).unwrap_err();
+        let message = payload.downcast::<String>().unwrap();
+        let type_name = std::any::type_name::<std::io::Error>();
+        assert_eq!(*message, format!("config must be loaded: error ID {id}, type `{type_name}`"));
+    }
+
+    #[test]
+    fn fallible_steps() {
+        struct StepFailed(u32);
+        let ran = std::cell::RefCell::new(Vec::new());
+        let step = |n: u32, fail: bool| {
+            let ran = &ran;
+            move || {
+                ran.borrow_mut().push(n);
+                if fail {
+                    crate::Result::new_error(StepFailed(n))
+                } else {
+                    crate::Result::new(n)
+                }
+            }
+        };
+
+        let mut raised = None;
+        let res = crate::try_or_handle_one(
+            crate::fallible(|ctx| {
+                let a = ctx.run(step(1, false))?;
+                let b = ctx.run(step(2, true)).inspect_err(|p| raised = Some(p.id()))?;
+                let c = ctx.run(step(3, false))?;
+                Ok(a + b + c)
+            }),
+            |err: StepFailed| crate::Result::new(100 + err.0),
+        );
+        assert_eq!(res.unwrap(), 102);
+        assert_eq!(*ran.borrow(), [1, 2]);
+        assert!(raised.is_some());
+
+        ran.borrow_mut().clear();
+        let res = crate::fallible(|ctx| {
+            let a = ctx.run(step(1, false))?;
+            let b = ctx.run(step(2, false))?;
+            Ok(a + b + ctx.run(step(3, false))?)
+        })();
+        assert_eq!(res.unwrap(), 6);
+        assert_eq!(*ran.borrow(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn inspect_value() {
+        let mut seen = None;
+        let res = crate::Result::new(String::from("abc")).inspect(|value| seen = Some(value.len()));
+        assert_eq!(res.unwrap(), "abc");
+        assert_eq!(seen, Some(3));
+
+        let res = crate::Result::<String>::new_with_error_id(crate::ErrorId::from_raw(21)).inspect(|_| unreachable!());
+        assert_eq!(res.error_id(), Some(crate::ErrorId::from_raw(21)));
+    }
+
+    #[test]
+    fn map_or_variants() {
+        assert_eq!(crate::Result::new(2).map_or(0, |x| x * 3), 6);
+        assert_eq!(crate::Result::new(2).map_or_else(|| unreachable!(), |x| x * 3), 6);
+
+        // Raised without any scope, only the default is left
+        let unhandled = || crate::Result::<i32>::new_error("unhandled");
+        assert_eq!(unhandled().map_or(0, |x| x * 3), 0);
+        assert_eq!(unhandled().map_or_else(|| -1, |_| unreachable!()), -1);
+    }
+
+    #[test]
+    fn as_ref_and_as_mut() {
+        struct Connection {
+            requests: u32,
+        }
+
+        let mut conn = crate::Result::new(Connection { requests: 0 });
+        for _ in 0..2 {
+            if let Ok(conn) = conn.as_mut().try_unwrap() {
+                conn.requests += 1;
+            }
+        }
+        assert_eq!(conn.as_ref().map(|conn| conn.requests).unwrap(), 2);
+        assert_eq!(conn.unwrap().requests, 2);
+
+        let mut failed = crate::Result::<Connection>::new_error("refused");
+        let id = failed.error_id();
+        assert!(id.is_some());
+        assert_eq!(failed.as_ref().error_id(), id);
+        assert_eq!(failed.as_mut().error_id(), id);
+        assert_eq!(failed.error_id(), id);
+    }
+
+    #[test]
+    fn debug_and_display() {
+        let ok = crate::Result::new(vec![1, 2]);
+        assert_eq!(format!("{ok:?}"), "Ok([1, 2])");
+        assert_eq!(format!("{:#?}", crate::Result::new(3)), "Ok(\n    3,\n)");
+        assert_eq!(crate::Result::new("text").to_string(), "text");
+        assert_eq!(format!("{:>6}", crate::Result::new(7)), "     7");
+
+        let err = crate::Result::<i32>::new_error(5u8);
+        let id = err.error_id().unwrap().to_raw();
+        assert_eq!(format!("{err:?}"), format!("Error({id})"));
+        assert_eq!(err.to_string(), format!("error #{id}"));
+    }
+
+    #[test]
+    fn clone_and_eq() {
+        let calls = std::cell::Cell::new(0);
+        let mut copy = None;
+        let res = crate::try_or_handle(
+            || {
+                let err = crate::Result::<u32>::new_error(9u8);
+                copy = Some(err.clone());
+                assert_eq!(copy, Some(err.clone()));
+                err
+            },
+            crate::builder(|err: u8| {
+                calls.set(calls.get() + 1);
+                crate::Result::new(u32::from(err))
+            })
+            .build(),
+        );
+        assert_eq!(res, crate::Result::new(9));
+        assert_eq!(calls.get(), 1);
+
+        // Returning the clone after the scope doesn't handle the error again
+        let copy = copy.unwrap();
+        assert!(copy.is_error(), "{copy:?}");
+        assert_ne!(copy, crate::Result::new(9));
+        assert_ne!(copy, crate::Result::new_error(9u8));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn audit_scope_keeps_copy() {
+        use crate::context::{ErrorHandlingContext, ReportedError, ScopeNode, TrySetErrorResult};
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        struct Tracked {
+            copy: bool,
+            drops: Rc<Cell<(u32, u32)>>,
+        }
+
+        impl Clone for Tracked {
+            fn clone(&self) -> Self {
+                Self {
+                    copy: true,
+                    drops: self.drops.clone(),
+                }
+            }
+        }
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                let (original, copy) = self.drops.get();
+                self.drops.set(if self.copy { (original, copy + 1) } else { (original + 1, copy) });
+            }
+        }
+
+        #[derive(Default)]
+        struct Audit {
+            seen: u32,
+            copies: Vec<Tracked>,
+        }
+
+        impl ErrorHandlingContext for Audit {
+            unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+                self.seen += 1;
+                self.copies.extend(error.clone_value::<Tracked>());
+                TrySetErrorResult::NotHandled
+            }
+        }
+
+        let drops = Rc::new(Cell::new((0, 0)));
+        let tracked = || Tracked {
+            copy: false,
+            drops: drops.clone(),
+        };
+        let mut audit = Audit::default();
+        let mut handled = 0;
+        let res = crate::try_or_handle_one(
+            || {
+                let scope = std::pin::pin!(ScopeNode::new(&mut audit));
+                let _guard = unsafe { scope.register() };
+                let _ = crate::Result::<u32>::new_error(tracked());
+                crate::Result::<u32>::new_error_cloneable(tracked())
+            },
+            |err: Tracked| {
+                handled += 1;
+                crate::Result::new(u32::from(err.copy))
+            },
+        );
+        // The handler got the original of the returned error
+        assert_eq!(res.unwrap(), 0);
+        assert_eq!(handled, 1);
+        // Both raises were observed, only the cloneable one was copied
+        assert_eq!(audit.seen, 2);
+        assert_eq!(audit.copies.len(), 1);
+        assert_eq!(drops.get(), (2, 0));
+        drop(audit);
+        assert_eq!(drops.get(), (2, 1));
     }
-}
 
-impl<T, E: Error> From<std::result::Result<T, E>> for Result<T> {
-    #[inline]
-    fn from(val: std::result::Result<T, E>) -> Self {
-        match val {
-            Ok(v) => Self::new(v),
-            Err(e) => Self::new_error(e),
+    #[test]
+    fn from_option() {
+        let handlers = || {
+            crate::builder(|_: crate::NoneError| crate::Result::new(0))
+                .handle(|_: std::io::Error| crate::Result::new(1))
+                .build()
+        };
+        let mut values = vec![4, 5];
+        let res = crate::try_or_handle(|| crate::Result::from(values.pop()), handlers());
+        assert_eq!(res, crate::Result::new(5));
+
+        values.clear();
+        let res = crate::try_or_handle(|| crate::Result::from(values.pop()), handlers());
+        assert_eq!(res, crate::Result::new(0));
+
+        // Unhandled like any other error
+        let res: crate::Result<i32> = None.into();
+        assert!(res.is_error(), "{res:?}");
+    }
+
+    #[test]
+    fn try_or_handle_fn_matches_generic() {
+        use std::cell::RefCell;
+
+        thread_local! {
+            static CALLS: RefCell<Vec<u32>> = const { RefCell::new(Vec::new()) };
+        }
+
+        fn handler(err: u32) -> crate::Result<i64> {
+            CALLS.with(|calls| calls.borrow_mut().push(err));
+            if err == 99 {
+                crate::Result::new_error(err + 1)
+            } else {
+                crate::Result::new(i64::from(err) * 2)
+            }
+        }
+
+        type Body = fn() -> crate::Result<i64>;
+        let bodies: [Body; 6] = [
+            || crate::Result::new(1),
+            || crate::Result::new_error(3u32),
+            // Declined, another type
+            || crate::Result::new_error(3u8),
+            // Two errors, only the returned one is handled
+            || {
+                let _ = crate::Result::<i64>::new_error(4u32);
+                crate::Result::new_error(5u32)
+            },
+            // A captured error that isn't returned
+            || {
+                let _ = crate::Result::<i64>::new_error(6u32);
+                crate::Result::new_error(7u8)
+            },
+            // The handler raises again
+            || crate::Result::new_error(99u32),
+        ];
+
+        let run = |body: Body, generic: bool| {
+            CALLS.with(|calls| calls.borrow_mut().clear());
+            let res = crate::try_or_handle_one(
+                || {
+                    if generic {
+                        crate::try_or_handle_one(body, handler)
+                    } else {
+                        crate::try_or_handle_fn(&mut { body }, handler)
+                    }
+                },
+                |err: u8| crate::Result::new(-i64::from(err)),
+            );
+            (res.ok(), CALLS.with(|calls| calls.take()))
+        };
+        for (index, body) in bodies.into_iter().enumerate() {
+            assert_eq!(run(body, true), run(body, false), "body {index}");
         }
+        assert_eq!(run(bodies[3], false), (Some(10), vec![5]));
+        assert_eq!(run(bodies[5], false), (None, vec![99]));
     }
-}
 
-/// Try to execute a function, and try to handle an error, if one occurs.
-///
-/// Use [`try_or_handle`] to handle multiple error types.
-///
-/// # Arguments
-///
-/// * `func`: The function to execute
-/// * `handler`: Handle a single error
-///
-/// returns: Result<T>
-///
-/// # Examples
-///
-/// ```
-/// fn to_int(string: &str) -> xcept::Result<i32> {
-///     string.parse().into()
-/// }
-///
-/// type ErrorT = <i32 as std::str::FromStr>::Err;
-/// let res = xcept::try_or_handle_one(|| to_int("abc"), |_err: ErrorT| xcept::Result::new(-1));
-/// assert_eq!(res.unwrap(), -1);
-///
-/// let res = xcept::try_or_handle_one(|| to_int("10"), |_err: ErrorT| xcept::Result::new(-1));
-/// assert_eq!(res.unwrap(), 10);
-/// ```
-#[inline]
-pub fn try_or_handle_one<F, H, T, E>(func: F, handler: H) -> Result<T>
-where
-    F: FnOnce() -> Result<T>,
-    H: FnOnce(E) -> Result<T>,
-    E: Error,
-{
-    let mut error_storage: crate::context::SingleErrorStorage<E> = SingleErrorStorage::default();
-    let mut scope = context::ScopeNode::new(&mut error_storage);
-    // Safety: scope is kept alive, guard is dropped before `scope` is used again
-    let guard = unsafe { context::push_handling_scope(&mut scope) };
-    let res = func();
-    drop(guard);
-    if res.is_error() {
-        // Safety: res.is_error() is true
-        unsafe { error_storage.unchecked_try_handle(res, handler) }
-    } else {
-        res
+    #[test]
+    fn into_std_and_back() {
+        assert_eq!(crate::Result::new(3).into_std(), Ok(3));
+
+        let res = crate::Result::<i32>::new_error(3u8);
+        let id = res.error_id().unwrap();
+        let lowered = res.into_std();
+        assert_eq!(lowered, Err(id));
+        assert_eq!(lowered.unwrap_err().to_string(), format!("error #{}", id.to_raw()));
+
+        // Raising the lowered error again gives a new `ErrorId` error carrying the first ID
+        let seen = crate::try_or_handle_one(
+            || crate::Result::from(lowered),
+            |err: crate::ErrorId| crate::Result::new(err.to_raw() as i32),
+        );
+        assert_eq!(seen.unwrap(), id.to_raw() as i32);
+
+        let round_trip: crate::Result<u32> = std::result::Result::<u32, crate::ErrorId>::Ok(5).into();
+        assert_eq!(round_trip.into_std(), Ok(5));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::cell::RefCell;
+    #[test]
+    fn collect_results() {
+        let ok: crate::Result<Vec<u32>> = (1..4).map(crate::Result::new).collect();
+        assert_eq!(ok, crate::Result::new(vec![1, 2, 3]));
+
+        let mut evaluated = Vec::new();
+        let mut first = None;
+        let res = crate::try_or_handle(
+            || {
+                let res = (0..6u32)
+                    .map(|i| {
+                        evaluated.push(i);
+                        if i % 2 == 1 {
+                            crate::Result::new_error(i)
+                        } else {
+                            crate::Result::new(i)
+                        }
+                    })
+                    .collect::<crate::Result<Vec<u32>>>();
+                first = res.error_id();
+                res
+            },
+            crate::builder(|err: u32| crate::Result::new(vec![err])).build(),
+        );
+        // The handler got the error of the first failing element, the rest was never evaluated
+        assert_eq!(res.unwrap(), [1]);
+        assert_eq!(evaluated, [0, 1]);
+        assert!(first.is_some());
+
+        // Created up front, the last error replaced the first in the storage
+        let res = crate::try_or_handle_one(
+            || {
+                let results: Vec<crate::Result<u32>> = (0..4).map(|i| if i % 2 == 1 { crate::Result::new_error(i) } else { crate::Result::new(i) }).collect();
+                results.into_iter().collect::<crate::Result<Vec<u32>>>()
+            },
+            |err: u32| crate::Result::new(vec![err]),
+        );
+        assert!(res.is_error(), "{res:?}");
+    }
 
     #[test]
-    fn try_or_handle_one() {
-        fn func() -> crate::Result<i32> {
-            crate::Result::new_error(true)
+    fn pending_alarm_per_crossing() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let alarms = Rc::new(RefCell::new(Vec::new()));
+        let log = alarms.clone();
+        crate::set_pending_alarm(3, move |count| log.borrow_mut().push(count));
+
+        let mut storage = crate::batch::VecErrorStorage::<u32>::default();
+        let pending = storage.run(|| {
+            for i in 0..5u32 {
+                let _ = crate::Result::<()>::new_error(i);
+            }
+            crate::pending_count()
+        });
+        // Crossed once while capturing 5 errors
+        assert_eq!(pending, 5);
+        assert_eq!(*alarms.borrow(), [3]);
+        assert_eq!(storage.into_inner().len(), 5);
+
+        // Draining and capturing below the threshold arms it again
+        let mut storage = crate::batch::VecErrorStorage::<u32>::default();
+        storage.run(|| {
+            let _ = crate::Result::<()>::new_error(0u32);
+            assert_eq!(crate::pending_count(), 1);
+            let _ = crate::Result::<()>::new_error(1u32);
+            let _ = crate::Result::<()>::new_error(2u32);
+        });
+        assert_eq!(*alarms.borrow(), [3, 3]);
+
+        // Summed over nested scopes
+        let res = crate::try_or_handle_one(
+            || {
+                let _ = crate::Result::<()>::new_error(7u8);
+                let mut storage = crate::batch::VecErrorStorage::<u32>::default();
+                storage.run(|| {
+                    let _ = crate::Result::<()>::new_error(0u32);
+                    let _ = crate::Result::<()>::new_error(1u32);
+                    crate::Result::new(crate::pending_count())
+                })
+            },
+            |_: u8| crate::Result::new(0),
+        );
+        assert_eq!(res.unwrap(), 3);
+        assert_eq!(*alarms.borrow(), [3, 3, 3]);
+        crate::clear_pending_alarm();
+        assert_eq!(crate::pending_count(), 0);
+    }
+
+    #[test]
+    fn iterating() {
+        let mut results = vec![crate::Result::new(1), crate::Result::new_error(2u8), crate::Result::new(3)];
+        for res in &mut results {
+            for value in res.iter_mut() {
+                *value *= 10;
+            }
         }
+        let borrowed: Vec<&i32> = results.iter().flatten().collect();
+        assert_eq!(borrowed, [&10, &30]);
 
-        let x = crate::try_or_handle_one(func, |_e: bool| 1.into()).unwrap();
-        assert_eq!(x, 1);
+        // Iterating an error doesn't raise or handle anything
+        let epoch = crate::context::current_epoch();
+        let pending = crate::pending_count();
+        let err = crate::Result::<i32>::new_with_error_id(crate::ErrorId::from_raw(7));
+        assert_eq!((&err).into_iter().count(), 0);
+        assert_eq!(err.into_iter().count(), 0);
+        assert_eq!(crate::context::current_epoch(), epoch);
+        assert_eq!(crate::pending_count(), pending);
+    }
 
-        fn two_errors() -> crate::Result<i32> {
-            crate::Result::<i32>::new_error(true);
-            crate::Result::new_error(10)
+    #[test]
+    fn termination() {
+        use std::process::{ExitCode, Termination};
+
+        assert_eq!(crate::Result::new(()).report(), ExitCode::SUCCESS);
+        assert_eq!(crate::Result::new(ExitCode::from(3)).report(), ExitCode::from(3));
+        assert_eq!(crate::Result::<()>::new_error(1u8).report(), ExitCode::FAILURE);
+
+        let id = crate::Result::<()>::new_error(1u8).error_id().unwrap();
+        let described = super::describe_unhandled(id);
+        assert!(described.starts_with(&format!("unhandled error #{}", id.to_raw())), "{described}");
+        #[cfg(feature = "diagnostics")]
+        assert!(described.contains("of type `u8`, raised at"), "{described}");
+    }
+
+    #[test]
+    fn const_constructors() {
+        const TABLE: [crate::Result<u32>; 3] = [crate::Result::new(1), crate::Result::new_with_error_id(crate::ErrorId::from_raw(7)), crate::Result::new(3)];
+        const FAILED: usize = {
+            let mut failed = 0;
+            let mut i = 0;
+            while i < TABLE.len() {
+                if TABLE[i].is_error() {
+                    failed += 1;
+                }
+                i += 1;
+            }
+            failed
+        };
+        const ID: Option<crate::ErrorId> = TABLE[1].error_id();
+
+        assert_eq!(FAILED, 1);
+        assert_eq!(ID, Some(crate::ErrorId::from_raw(7)));
+        assert!(TABLE[0].is_ok());
+        assert_eq!(TABLE.into_iter().flatten().sum::<u32>(), 4);
+    }
+
+    #[test]
+    fn shared_handler_chains() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        #[derive(Clone, PartialEq, Eq, Hash)]
+        struct MissingKey(&'static str);
+
+        let mut builder = crate::dynamic::DynBuilder::new();
+        builder.handle(|_: u8| crate::Result::new(1));
+        builder.handle(|MissingKey(key)| crate::Result::new(key.len()));
+        let mut chain = builder.build();
+        let res = crate::try_or_handle(|| crate::Result::new_error(MissingKey("db.host")), &mut chain);
+        assert_eq!(res.unwrap(), 7);
+
+        let chain = RefCell::new(chain);
+        let res = crate::try_or_handle(|| crate::Result::new_error(5u8), &chain);
+        assert_eq!(res.unwrap(), 1);
+        let res = crate::try_or_handle(|| crate::Result::new_error(MissingKey("db")), &chain);
+        assert_eq!(res.unwrap(), 2);
+
+        let chain = Rc::new(chain);
+        let res = crate::try_or_handle(|| crate::Result::new_error(MissingKey("db.port")), &chain);
+        assert_eq!(res.unwrap(), 7);
+        let res = crate::try_or_handle(|| crate::Result::new_error(5u8), Rc::clone(&chain));
+        assert_eq!(res.unwrap(), 1);
+        assert_eq!(Rc::strong_count(&chain), 1);
+
+        let dedup = Rc::new(RefCell::new(crate::multihandler::dedup_by_value(|MissingKey(key)| crate::Result::new(key.len()))));
+        for key in ["db.host", "db.host", "db"] {
+            assert!(crate::try_or_handle(|| crate::Result::new_error(MissingKey(key)), &dedup).is_ok());
         }
+        assert_eq!(dedup.borrow().seen(), 2);
 
-        let mut called = false;
-        let x = crate::try_or_handle_one(two_errors, |_: bool| {
-            called = true;
-            2.into()
+        // Not an accepted type, propagated as usual
+        let res = crate::try_or_handle_one(|| crate::try_or_handle(|| crate::Result::new_error("other"), &dedup), |_: &str| crate::Result::new(0));
+        assert_eq!(res.unwrap(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "re-entered a scope handled by the same chain")]
+    fn shared_handler_chain_reentered() {
+        let chain = std::rc::Rc::new_cyclic(|chain: &std::rc::Weak<std::cell::RefCell<_>>| {
+            let chain = chain.clone();
+            let mut builder = crate::dynamic::DynBuilder::<i32>::new();
+            builder.handle(move |_: u8| crate::try_or_handle(|| crate::Result::new_error(1u8), chain.upgrade().unwrap()));
+            std::cell::RefCell::new(builder.build())
         });
+        let _ = crate::try_or_handle(|| crate::Result::new_error(0u8), &chain);
+    }
 
-        assert!(!called);
-        assert!(x.is_error());
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn question_mark_keeps_error_id() {
+        let mut raised = None;
+        let mut inner = || -> crate::Result<u8> {
+            let res: crate::Result<u8> = crate::Result::new_error("bad");
+            raised = res.error_id();
+            let v = res?;
+            crate::Result::new(v + 1)
+        };
+        let mut seen = None;
+        let res = crate::try_or_handle_one(
+            || {
+                let res = inner();
+                seen = res.error_id();
+                res
+            },
+            |_: &str| crate::Result::new(7),
+        );
+        assert_eq!(res.unwrap(), 7);
+        assert!(raised.is_some());
+        assert_eq!(seen, raised);
+
+        let ok = || -> crate::Result<u8> { crate::Result::new(crate::Result::new(1u8)? + 1) };
+        assert_eq!(ok().unwrap(), 2);
     }
 
     #[test]
-    fn error_without_scopes() {
-        let res: crate::Result<i32> = crate::Result::new_error(true);
-        assert!(res.is_error());
+    fn check_propagates() {
+        #[derive(Debug)]
+        struct Invalid(u32);
+
+        fn read(v: u32) -> crate::Result<u32> {
+            if v == 0 {
+                crate::Result::new_error(Invalid(v))
+            } else {
+                crate::Result::new(v)
+            }
+        }
+        fn scaled(v: u32) -> crate::Result<u64> {
+            crate::Result::new(crate::check!(read(v), u64::from) * 10)
+        }
+        fn label(v: u32) -> crate::Result<String> {
+            crate::Result::new(format!("#{}", crate::check!(scaled(v))))
+        }
+
+        let mut inner_id = None;
+        let mut run = |v| {
+            crate::try_or_handle_one(
+                || {
+                    let res = label(v);
+                    inner_id = res.error_id();
+                    crate::Result::new(crate::check!(res) + "!")
+                },
+                |err: Invalid| crate::Result::new(format!("invalid {}", err.0)),
+            )
+        };
+        assert_eq!(run(3).unwrap(), "#30!");
+        assert_eq!(run(0).unwrap(), "invalid 0");
+        assert!(inner_id.is_some());
     }
 
     #[test]
-    fn multi_handlers() {
-        fn handler1(e: i32) -> crate::Result<i32> {
-            (e * 2).into()
+    fn throw_returns_early() {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Missing(&'static str);
+
+        fn lookup(key: &'static str) -> crate::Result<u16> {
+            match key {
+                "" => crate::throw!(@bare Missing(key)),
+                "port" => crate::Result::new(8080),
+                "ssh" => crate::Result::new(22),
+                "host" => crate::throw!(Missing(key)),
+                _ => crate::throw!("unknown key `{}` ({} bytes)", key, key.len()),
+            }
         }
-        fn handler2(_e: &str) -> crate::Result<i32> {
-            (-1).into()
+        fn describe(key: &'static str) -> crate::Result<String> {
+            let port = crate::check!(lookup(key));
+            if port < 1024 {
+                crate::throw!("port {port} is privileged");
+            }
+            crate::Result::new(format!(":{port}"))
         }
-        fn handler3(_e: bool) -> crate::Result<i32> {
-            crate::Result::new_error("Bool not supported!")
+
+        let handlers = crate::multihandler::builder(|msg: crate::Message| crate::Result::new(msg.into_string()))
+            .handle(|Missing(key)| crate::Result::new(format!("missing {key:?}")))
+            .build();
+        let run = |key| crate::try_or_handle(|| describe(key), handlers.clone());
+        assert_eq!(run("port").unwrap(), ":8080");
+        assert_eq!(run("host").unwrap(), "missing \"host\"");
+        assert_eq!(run("").unwrap(), "missing \"\"");
+        assert_eq!(run("user").unwrap(), "unknown key `user` (4 bytes)");
+        assert_eq!(run("ssh").unwrap(), "port 22 is privileged");
+    }
+
+    #[test]
+    fn or_try_supersedes() {
+        use crate::context::{ContextExt, Disposition, ErrorHandlingContext, ReportedError, TrySetErrorResult};
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Missing(&'static str);
+
+        /// Accepts nothing, records the dispositions it is told about.
+        #[derive(Default)]
+        struct Observer(Vec<(crate::ErrorId, Disposition)>);
+
+        impl ErrorHandlingContext for Observer {
+            unsafe fn try_set_error(&mut self, _error: &ReportedError) -> TrySetErrorResult {
+                TrySetErrorResult::NotHandled
+            }
+
+            fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+                Some(self)
+            }
         }
-        let handlers = crate::multihandler::builder(handler1)
-            .handle(handler2)
-            .handle(handler3)
+
+        impl ContextExt for Observer {
+            fn on_disposition(&mut self, error_id: crate::ErrorId, disposition: Disposition) {
+                self.0.push((error_id, disposition));
+            }
+        }
+
+        let ids = std::cell::RefCell::new(Vec::new());
+        let source = |name: &'static str, value: Option<u16>| {
+            let ids = &ids;
+            move || {
+                let res = match value {
+                    Some(v) => crate::Result::new(v),
+                    None => crate::Result::new_error(Missing(name)),
+                };
+                ids.borrow_mut().extend(res.error_id());
+                res
+            }
+        };
+
+        // The second source succeeds, the third isn't tried
+        let mut observer = Observer::default();
+        let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut observer));
+        let guard = unsafe { scope.register() };
+        let handled = std::cell::Cell::new(0);
+        let res = crate::try_or_handle_one(
+            || {
+                let res = source("env", None)().or_try(source("file", Some(1))).or_try(source("defaults", Some(2)));
+                assert_eq!(crate::pending_count(), 0);
+                res
+            },
+            |_: Missing| {
+                handled.set(handled.get() + 1);
+                crate::Result::new(0)
+            },
+        );
+        drop(guard);
+        assert_eq!(res.unwrap(), 1);
+        assert_eq!(handled.get(), 0);
+        let first = ids.borrow()[0];
+        assert_eq!(observer.0, [(first, Disposition::Superseded)]);
+        #[cfg(feature = "diagnostics")]
+        {
+            let record = crate::debug::recent_raises().into_iter().find(|r| r.id == first).unwrap();
+            assert_eq!(record.disposition, Disposition::Superseded);
+        }
+
+        // Every source fails, only the last error is handled
+        ids.borrow_mut().clear();
+        let mut seen = Vec::new();
+        let res = crate::try_or_handle_one(
+            || source("env", None)().or_try(source("file", None)).or_try(source("defaults", None)),
+            |err: Missing| {
+                seen.push(err.0);
+                crate::Result::new(0)
+            },
+        );
+        assert_eq!(res.unwrap(), 0);
+        assert_eq!(seen, ["defaults"]);
+        assert_eq!(ids.borrow().len(), 3);
+
+        let res = crate::try_or_handle_one(
+            || source("env", None)().or_raise(Missing("config")),
+            |err: Missing| crate::Result::new(err.0.len() as u16),
+        );
+        assert_eq!(res.unwrap(), 6);
+    }
+
+    #[test]
+    fn ensure_checks_conditions() {
+        #[derive(Debug)]
+        struct Negative(i32);
+
+        fn checked(x: i32) -> crate::Result<i32> {
+            crate::ensure!(x >= 0, Negative(x));
+            crate::ensure!(x % 2 == 0, "{x} is odd");
+            crate::Result::new(x / 2)
+        }
+
+        let raised = std::rc::Rc::new(std::cell::Cell::new(0));
+        let _spy = crate::on_raise({
+            let raised = raised.clone();
+            move |_: &Negative| raised.set(raised.get() + 1)
+        });
+        let _message_spy = crate::on_raise({
+            let raised = raised.clone();
+            move |_: &crate::Message| raised.set(raised.get() + 1)
+        });
+        let run = |x| {
+            crate::try_or_handle(
+                || checked(x).map(|x| x.to_string()),
+                crate::multihandler::builder(|err: Negative| crate::Result::new(format!("negative {}", err.0)))
+                    .handle(|msg: crate::Message| crate::Result::new(msg.into_string()))
+                    .build(),
+            )
+        };
+
+        assert_eq!(run(4).unwrap(), "2");
+        assert_eq!(raised.get(), 0);
+        assert_eq!(run(-3).unwrap(), "negative -3");
+        assert_eq!(run(3).unwrap(), "3 is odd");
+        assert_eq!(raised.get(), 2);
+    }
+
+    #[test]
+    fn flatten_nested() {
+        let both_ok = crate::Result::new(crate::Result::new(3));
+        assert_eq!(both_ok.flatten().unwrap(), 3);
+
+        let inner: crate::Result<crate::Result<u8>> = crate::Result::new(crate::Result::new_error("inner"));
+        let inner_id = inner.as_ref().unwrap().error_id();
+        assert!(inner_id.is_some());
+        assert_eq!(inner.flatten().error_id(), inner_id);
+
+        let outer: crate::Result<crate::Result<u8>> = crate::Result::new_error("outer");
+        let outer_id = outer.error_id();
+        assert!(outer_id.is_some());
+        assert_eq!(outer.flatten().error_id(), outer_id);
+
+        let res = crate::try_or_handle_one(
+            || crate::Result::new(3u8).map(|x| if x > 2 { crate::Result::new_error("too large") } else { crate::Result::new(x) }).flatten(),
+            |msg: &str| crate::Result::new(msg.len() as u8),
+        );
+        assert_eq!(res.unwrap(), 9);
+    }
+    #[test]
+    fn in_place_accessors() {
+        let mut ok = crate::Result::new(vec![1]);
+        ok.map_value_in_place(|v| v.push(2));
+        ok.get_or_insert_with_replacing_error(|| panic!("holds a value")).push(3);
+        assert_eq!(ok.as_ref().ok().map(Vec::len), Some(3));
+        assert!(!ok.is_taken());
+
+        let mut err: crate::Result<Vec<i32>> = crate::Result::new_error(1u8);
+        let id = err.error_id().unwrap();
+        err.map_value_in_place(|_| panic!("holds an error"));
+        assert_eq!(err.error_id(), Some(id));
+        assert!(err.as_mut().ok().is_none());
+        err.get_or_insert_with_replacing_error(Vec::new).push(4);
+        assert_eq!(err.unwrap(), [4]);
+    }
+
+    #[test]
+    fn take_leaves_taken_id() {
+        let mut ok = crate::Result::new(1);
+        assert_eq!(ok.take().unwrap(), 1);
+        assert!(ok.is_taken());
+        assert_eq!(ok.take().error_id(), Some(crate::context::TAKEN_ID));
+
+        // The taken result is never matched, while the taken error still is
+        let res = crate::try_or_handle_one(
+            || {
+                let mut err: crate::Result<u32> = crate::Result::new_error(2u8);
+                let taken = err.take();
+                assert!(err.is_taken() && !taken.is_taken());
+                taken
+            },
+            |x: u8| crate::Result::new(u32::from(x)),
+        );
+        assert_eq!(res.unwrap(), 2);
+        let res = crate::try_or_handle_one(
+            || {
+                let mut err: crate::Result<u32> = crate::Result::new_error(2u8);
+                let _ = err.take();
+                err
+            },
+            |x: u8| crate::Result::new(u32::from(x)),
+        );
+        assert!(res.is_taken());
+        assert!(crate::describe_unhandled(crate::context::TAKEN_ID).contains("`Result::take`"));
+    }
+
+    #[test]
+    fn error_type_is_recorded() {
+        struct Declined;
+        fn inner() -> crate::Result<u8> {
+            crate::Result::new_error(Declined)
+        }
+        fn outer() -> crate::Result<String> {
+            let value = crate::check!(inner());
+            crate::Result::new(value.to_string())
+        }
+
+        // Declined by the handlers, through `check!` and a combinator
+        let res = crate::try_or_handle_one(|| outer().map(|s| s.len()), |_: u8| crate::Result::new(0));
+        assert!(res.error_is::<Declined>());
+        assert!(!res.error_is::<u8>());
+        assert!(res.error_type_name().unwrap().ends_with("::Declined"));
+
+        // No scopes installed
+        let res: crate::Result<()> = crate::Result::new_error(std::io::Error::other("lost"));
+        assert!(res.error_is::<std::io::Error>());
+        assert_eq!(res.error_type_name(), Some(std::any::type_name::<std::io::Error>()));
+        let (a, b) = res.map(|()| (1, 2)).unzip();
+        assert!(a.error_is::<std::io::Error>() && b.error_is::<std::io::Error>());
+
+        let ok = crate::Result::new(1);
+        assert!(!ok.error_is::<i32>());
+        assert_eq!(ok.error_type_name(), None);
+        let from_id: crate::Result<()> = crate::Result::new_with_error_id(crate::ErrorId::from_raw(3));
+        assert!(!from_id.error_is::<Declined>());
+        assert_eq!(from_id.error_type_name(), None);
+        assert_eq!(from_id, crate::Result::new_with_error_id(crate::ErrorId::from_raw(3)));
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn raise_location_reaches_handlers() {
+        use crate::context::{ErrorHandlingContext, ErrorInfo, ReportedError, ScopeNode, TrySetErrorResult};
+
+        struct Observer(Vec<ErrorInfo>);
+        impl ErrorHandlingContext for Observer {
+            unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+                self.0.push(error.info());
+                TrySetErrorResult::NotHandled
+            }
+        }
+
+        let mut observer = Observer(Vec::new());
+        let mut lines = Vec::new();
+        let handlers = crate::builder(|_: u8| crate::Result::new((String::new(), 0)))
+            .handle_with_info(|err: std::num::ParseIntError, info| {
+                let location = info.location().unwrap();
+                assert_eq!(info.type_name(), std::any::type_name::<std::num::ParseIntError>());
+                crate::Result::new((format!("{err} at {}", location.file()), location.line()))
+            })
             .build();
+        let res = crate::try_or_handle(
+            || {
+                let scope = std::pin::pin!(ScopeNode::new(&mut observer));
+                let _guard = unsafe { scope.register() };
+                lines.push(line!() + 1);
+                let _: crate::Result<()> = crate::Result::new_error(1u8);
+                lines.push(line!() + 1);
+                crate::Result::<i32>::from("x".parse::<i32>()).map(|_| (String::new(), 0))
+            },
+            handlers,
+        );
+        let (message, line) = res.unwrap();
+        assert_eq!(message, format!("invalid digit found in string at {}", file!()));
+        assert_eq!(line, lines[1]);
 
-        let res = crate::try_or_handle(|| 10.into(), handlers.clone());
+        // Hooks see the location too, and the ID matches the one of the `Result`
+        let locations: Vec<_> = observer.0.iter().map(|info| info.location().unwrap()).collect();
+        assert_eq!(locations.iter().map(|l| l.file()).collect::<Vec<_>>(), [file!(), file!()]);
+        assert_eq!(locations.iter().map(|l| l.line()).collect::<Vec<_>>(), lines);
 
-        assert_eq!(res.unwrap(), 10);
+        // Bare raises record no location
+        let mut storage = crate::SingleErrorStorage::<u16>::default();
+        {
+            let scope = std::pin::pin!(ScopeNode::new(&mut storage));
+            let _guard = unsafe { scope.register() };
+            let _: crate::Result<()> = crate::Result::new_error_bare(2u16);
+        }
+        let (_, info) = storage.into_inner_with_info().unwrap();
+        assert_eq!(info.location(), None);
+    }
+
+    #[test]
+    fn renamed_errors_share_a_handler() {
+        mod old {
+            pub struct ParseError(pub u32);
+        }
+        mod new {
+            pub struct ParseError {
+                pub line: u32,
+            }
+        }
+        struct Legacy(&'static str);
 
-        let res = crate::try_or_handle(|| crate::Result::new_error(15), handlers.clone());
+        let run = |raise_old: bool| {
+            crate::try_or_handle(
+                move || {
+                    if raise_old {
+                        crate::Result::new_error(old::ParseError(3))
+                    } else {
+                        crate::Result::new_error(new::ParseError { line: 4 })
+                    }
+                },
+                crate::builder(|_: u8| crate::Result::new(0))
+                    .handle_either(|err: old::ParseError| err.0, |err: new::ParseError| err.line, |line| {
+                        crate::Result::new(line * 10)
+                    })
+                    .build(),
+            )
+        };
+        assert_eq!(run(true).unwrap(), 30);
+        assert_eq!(run(false).unwrap(), 40);
 
-        assert_eq!(res.unwrap(), 30);
+        for (raised, expected) in [(0, 5), (1, 6), (2, 7)] {
+            let res = crate::try_or_handle(
+                move || match raised {
+                    0 => crate::Result::new_error(old::ParseError(5)),
+                    1 => crate::Result::new_error(new::ParseError { line: 6 }),
+                    _ => crate::Result::new_error(Legacy("7")),
+                },
+                crate::handle_any_of!(
+                    crate::builder(|_: u8| crate::Result::new(0)),
+                    [
+                        old::ParseError => |err| err.0,
+                        new::ParseError => |err| err.line,
+                        Legacy => |err| err.0.parse().unwrap(),
+                    ],
+                    crate::Result::new,
+                )
+                .build(),
+            );
+            assert_eq!(res.unwrap(), expected);
+        }
+
+        // Other types still propagate
+        let res: crate::Result<u32> = crate::try_or_handle(
+            || crate::Result::new_error(1u16),
+            crate::builder(|_: u8| crate::Result::new(0))
+                .handle_either(|err: old::ParseError| err.0, |err: new::ParseError| err.line, crate::Result::new)
+                .build(),
+        );
+        assert!(res.error_is::<u16>());
+    }
+
+    #[test]
+    fn lazy_error_only_built_when_accepted() {
+        struct Expensive(#[allow(dead_code)] Vec<u8>);
+        let built = std::cell::Cell::new(0);
+        let make = || {
+            built.set(built.get() + 1);
+            Expensive(vec![0; 1024])
+        };
 
         let res = crate::try_or_handle(
-            || crate::Result::new_error("should be -1"),
-            handlers.clone(),
+            || crate::Result::<u8>::new_error_with(make),
+            crate::builder(|_: u8| crate::Result::new(1)).handle(|_: std::io::Error| crate::Result::new(2)).build(),
         );
+        assert_eq!(built.get(), 0);
+        assert!(res.error_is::<Expensive>());
+        let first = res.error_id().unwrap();
+        let second = crate::Result::<u8>::new_error_with(make).error_id().unwrap();
+        assert_eq!(built.get(), 0);
+        assert_ne!(first, second);
 
-        assert_eq!(res.unwrap(), -1);
+        let res = crate::try_or_handle(
+            || crate::Result::<u8>::new_error_with(make),
+            crate::builder(|_: u8| crate::Result::new(1)).handle(|_: Expensive| crate::Result::new(3)).build(),
+        );
+        assert_eq!(res.unwrap(), 3);
+        assert_eq!(built.get(), 1);
 
-        let res = crate::try_or_handle(|| crate::Result::new_error(false), handlers.clone());
+        // Not created, but still counted and recorded
+        let raises = crate::context::raise_count();
+        let res = crate::Result::<u8>::new_error_with(make);
+        assert!(res.is_error());
+        assert_eq!(built.get(), 1);
+        assert_eq!(crate::context::raise_count(), raises + 1);
+        #[cfg(feature = "diagnostics")]
+        {
+            let last = crate::debug::recent_raises().pop().unwrap();
+            assert_eq!(last.id, res.error_id().unwrap());
+            assert_eq!(last.disposition, crate::context::Disposition::Unhandled);
+            assert!(last.type_name.ends_with("Expensive"));
+        }
 
-        assert_eq!(res.is_error(), true);
+        // Probes need the value
+        let probed = std::rc::Rc::new(std::cell::Cell::new(0));
+        let probe = crate::on_raise({
+            let probed = probed.clone();
+            move |_: &Expensive| probed.set(probed.get() + 1)
+        });
+        let _ = crate::Result::<u8>::new_error_with(make);
+        assert_eq!((built.get(), probed.get()), (2, 1));
+        drop(probe);
     }
 
     #[test]
-    fn multi_handlers_with_refs() {
-        let which = RefCell::new(0);
-        let handlers = crate::multihandler::builder(|_: i32| {
-            *which.borrow_mut() = 1;
-            crate::Result::new(1)
-        })
-        .handle(|_: &str| {
-            *which.borrow_mut() = 2;
-            crate::Result::new(2)
-        })
-        .build();
+    fn throw_lone_literals() {
+        fn number(fail: bool) -> crate::Result<()> {
+            if fail {
+                return crate::Result::new_error(42);
+            }
+            crate::throw!(@bare "plain")
+        }
+        fn message(x: i32) -> crate::Result<()> {
+            match x {
+                0 => crate::throw!("plain"),
+                1 => crate::throw!("{{x}} is {x}"),
+                _ => crate::throw!("{x} of {}", 2,),
+            }
+        }
+        fn positive(x: i32) -> crate::Result<i32> {
+            crate::ensure!(x > 0, x - 1);
+            crate::ensure!(x < 100, char::from(b'x'));
+            crate::ensure!(x != 50, "{{x}} was {x}");
+            crate::Result::new(x)
+        }
 
-        let res = crate::try_or_handle(|| crate::Result::new_error(0), handlers.clone());
-        assert_eq!(res.unwrap(), 1);
-        assert_eq!(*which.borrow(), 1);
+        let handlers = || {
+            crate::builder(|err: i32| crate::Result::new(err.to_string()))
+                .handle(|err: &'static str| crate::Result::new(err.to_string()))
+                .handle(|err: char| crate::Result::new(err.to_string()))
+                .handle(|msg: crate::Message| crate::Result::new(msg.into_string()))
+                .build()
+        };
+        assert_eq!(crate::try_or_handle(|| number(true).map(|_| String::new()), handlers()).unwrap(), "42");
+        assert_eq!(crate::try_or_handle(|| number(false).map(|_| String::new()), handlers()).unwrap(), "plain");
+        let run = |x| {
+            crate::try_or_handle_one(|| message(x).map(|_| String::new()), |msg: crate::Message| {
+                crate::Result::new(msg.into_string())
+            })
+        };
+        assert_eq!(run(0).unwrap(), "plain");
+        assert_eq!(run(1).unwrap(), "{x} is 1");
+        assert_eq!(run(2).unwrap(), "2 of 2");
+        assert_eq!(crate::try_or_handle(|| positive(0).map(|x| x.to_string()), handlers()).unwrap(), "-1");
+        assert_eq!(crate::try_or_handle(|| positive(100).map(|x| x.to_string()), handlers()).unwrap(), "x");
+        assert_eq!(crate::try_or_handle(|| positive(5).map(|x| x.to_string()), handlers()).unwrap(), "5");
+        assert_eq!(crate::try_or_handle(|| positive(50).map(|x| x.to_string()), handlers()).unwrap(), "{x} was 50");
+    }
 
-        let res = crate::try_or_handle(|| crate::Result::new_error("hello"), handlers.clone());
-        assert_eq!(res.unwrap(), 2);
-        assert_eq!(*which.borrow(), 2);
+    #[test]
+    #[allow(deprecated)]
+    fn raw_error_id_shims() {
+        let res: crate::Result<()> = crate::Result::new_error("failed");
+        let raw = res.error_id_raw().unwrap();
+        assert_eq!(raw, res.error_id().unwrap().to_raw());
+        let copy: crate::Result<()> = crate::Result::new_with_raw_error_id(raw);
+        assert_eq!(copy.error_id(), res.error_id());
+        assert_eq!(crate::Result::new(1).error_id_raw(), None);
     }
 }
@@ -0,0 +1,267 @@
+//! Logging raises with the `log` crate, enabled by the `log` feature.
+//!
+//! Every raise is logged once what happened to it is known: captured by a scope, handled by a
+//! scope dropping it, suppressed as a secondary error, or not handled by any scope. The record
+//! has the `xcept` target and holds the error ID, type name and raise location. The content of the
+//! error is only included if a redactor is registered for its type, see [`redact`](crate::redact).
+//!
+//! The level of the record is chosen by the [`Severity`] registered for the error type, or
+//! [`Severity::Warning`], mapped to a `log::Level` by the function set with [`set_level_map`].
+//!
+//! Records are written on the raise path, once the disposition is known. A type with a redactor
+//! has its redactor run and its content allocated for the record. Types without a redactor cost
+//! the raise path an atomic load, plus a read lock once any redactor is registered.
+
+use crate::context::{Disposition, ErasedError};
+use std::any::TypeId;
+use std::panic::Location;
+use std::sync::RwLock;
+
+/// How serious an error type is, deciding the level its raises are logged at.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Expected errors, only interesting when debugging
+    Debug,
+    /// Errors that are part of normal operation
+    Info,
+    /// Errors worth looking at, the default
+    Warning,
+    /// Errors that made an operation fail
+    Error,
+    /// Errors threatening the whole program
+    Critical,
+}
+
+/// The level map used until [`set_level_map`] is called.
+///
+/// [`Severity::Critical`] is logged at `Error`, the others at the level of the same name.
+pub fn default_level(severity: Severity) -> ::log::Level {
+    match severity {
+        Severity::Debug => ::log::Level::Debug,
+        Severity::Info => ::log::Level::Info,
+        Severity::Warning => ::log::Level::Warn,
+        Severity::Error | Severity::Critical => ::log::Level::Error,
+    }
+}
+
+static SEVERITIES: RwLock<Vec<(TypeId, Severity)>> = RwLock::new(Vec::new());
+static LEVEL_MAP: RwLock<fn(Severity) -> ::log::Level> = RwLock::new(default_level);
+
+/// Register the severity of errors of type `E`, replacing any earlier one.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::log::Severity;
+///
+/// struct CacheMiss;
+///
+/// xcept::log::register_severity::<CacheMiss>(Severity::Debug);
+/// assert_eq!(xcept::log::severity::<CacheMiss>(), Severity::Debug);
+/// assert_eq!(xcept::log::severity::<std::io::Error>(), Severity::Warning);
+/// ```
+pub fn register_severity<E: crate::Error>(severity: Severity) {
+    let mut severities = SEVERITIES.write().unwrap_or_else(|e| e.into_inner());
+    match severities.iter_mut().find(|(type_id, _)| *type_id == TypeId::of::<E>()) {
+        Some(entry) => entry.1 = severity,
+        None => severities.push((TypeId::of::<E>(), severity)),
+    }
+}
+
+/// The severity registered for `E`, [`Severity::Warning`] if none is.
+pub fn severity<E: crate::Error>() -> Severity {
+    severity_of(TypeId::of::<E>())
+}
+
+fn severity_of(type_id: TypeId) -> Severity {
+    let severities = SEVERITIES.read().unwrap_or_else(|e| e.into_inner());
+    severities
+        .iter()
+        .find(|(id, _)| *id == type_id)
+        .map_or(Severity::Warning, |(_, severity)| *severity)
+}
+
+/// Replace the function mapping a [`Severity`] to the level records are logged at.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::log::Severity;
+///
+/// xcept::log::set_level_map(|severity| match severity {
+///     Severity::Debug | Severity::Info => log::Level::Trace,
+///     _ => xcept::log::default_level(severity),
+/// });
+/// ```
+pub fn set_level_map(map: fn(Severity) -> ::log::Level) {
+    *LEVEL_MAP.write().unwrap_or_else(|e| e.into_inner()) = map;
+}
+
+fn level(type_id: TypeId) -> ::log::Level {
+    let map = *LEVEL_MAP.read().unwrap_or_else(|e| e.into_inner());
+    map(severity_of(type_id))
+}
+
+/// The redacted content of `err`, if anything may be logged at all.
+///
+/// Runs on the raise path. Without a redactor for `E` this is an atomic load of the log level and,
+/// once any redactor is registered, a read lock of the redactors, but no allocation.
+pub(crate) fn content<E: crate::Error>(err: &E) -> Option<String> {
+    if ::log::max_level() == ::log::LevelFilter::Off {
+        return None;
+    }
+    crate::redact::summarize_value(err).into_content()
+}
+
+pub(crate) fn erased_content(err: &ErasedError) -> Option<String> {
+    if ::log::max_level() == ::log::LevelFilter::Off {
+        return None;
+    }
+    err.summary().into_content()
+}
+
+/// Log one raise, called when the raise metadata is flushed.
+pub(crate) fn record(
+    id: u64,
+    type_id: TypeId,
+    type_name: &'static str,
+    location: Option<&'static Location<'static>>,
+    disposition: Disposition,
+    content: Option<&str>,
+) {
+    if ::log::max_level() == ::log::LevelFilter::Off {
+        return;
+    }
+    let level = level(type_id);
+    let outcome = match disposition {
+        Disposition::Captured => "was captured by a scope",
+        Disposition::Dropped => "was handled by a scope",
+        Disposition::Suppressed => "was suppressed as a secondary error",
+        Disposition::Unhandled => "was not handled by any scope",
+        Disposition::Superseded => "was superseded by a later alternative",
+    };
+    let content = content.map(|content| format!(": {content}")).unwrap_or_default();
+    match location {
+        Some(location) => ::log::log!(
+            target: "xcept",
+            level,
+            "error #{id} of type `{type_name}` raised at {location} {outcome}{content}"
+        ),
+        None => ::log::log!(target: "xcept", level, "error #{id} of type `{type_name}` {outcome}{content}"),
+    }
+}
+
+/// Log that an error was superseded, at `Debug` since it no longer needs attention.
+pub(crate) fn superseded(id: u64) {
+    ::log::debug!(target: "xcept", "error #{id} was superseded by a later alternative");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{register_severity, set_level_map, Severity};
+    use crate::context::{CatchAllContext, ScopeNode};
+    use ::log::Level;
+    use std::cell::RefCell;
+
+    struct Capture;
+
+    thread_local! {
+        static RECORDS: RefCell<Vec<(Level, String)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    impl ::log::Log for Capture {
+        fn enabled(&self, _: &::log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &::log::Record) {
+            if record.target() == "xcept" {
+                RECORDS.with(|records| records.borrow_mut().push((record.level(), record.args().to_string())));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn records() -> Vec<(Level, String)> {
+        crate::flush_enrichment();
+        RECORDS.with(|records| records.take())
+    }
+
+    struct Invalid {
+        reason: &'static str,
+        #[allow(dead_code)]
+        email: &'static str,
+    }
+    struct Corrupt;
+    struct Minor;
+
+    // The level map is process wide, so all logging tests run as one test
+    #[test]
+    fn one_record_per_disposition() {
+        let _ = ::log::set_logger(&Capture);
+        ::log::set_max_level(::log::LevelFilter::Trace);
+        crate::redact::register(|err: &Invalid| crate::redact::RedactedSummary::new(err.reason));
+        register_severity::<Corrupt>(Severity::Critical);
+        register_severity::<Minor>(Severity::Debug);
+        records();
+
+        // Captured
+        let line = line!() + 2;
+        let res = crate::try_or_handle_one(
+            || crate::Result::<()>::new_error(Invalid { reason: "email is taken", email: "someone@example.com" }),
+            |_: Invalid| crate::Result::new(()),
+        );
+        assert!(res.is_ok());
+        let records = records();
+        assert_eq!(records.len(), 1, "{records:?}");
+        assert_eq!(records[0].0, Level::Warn);
+        assert!(records[0].1.starts_with("error #"), "{}", records[0].1);
+        assert!(records[0].1.contains("of type `xcept::log::tests::Invalid`"), "{}", records[0].1);
+        assert!(records[0].1.contains(&format!("raised at {}:{line}:", file!())), "{}", records[0].1);
+        assert!(records[0].1.ends_with("was captured by a scope: email is taken"), "{}", records[0].1);
+        assert!(!records[0].1.contains("someone@example.com"));
+
+        // Handled by dropping
+        let mut catch_all = CatchAllContext { inner: None };
+        let res: crate::Result<()> = {
+            let scope = std::pin::pin!(ScopeNode::new(&mut catch_all));
+            let _guard = unsafe { scope.register() };
+            crate::Result::new_error(Corrupt)
+        };
+        let records = self::records();
+        assert_eq!(records.len(), 1, "{records:?}");
+        assert_eq!(records[0].0, Level::Error);
+        let id = res.error_id().unwrap().to_raw();
+        assert!(records[0].1.starts_with(&format!("error #{id} of type `xcept::log::tests::Corrupt` raised at ")));
+        assert!(records[0].1.ends_with(" was handled by a scope"), "{}", records[0].1);
+
+        // Suppressed
+        let _ = crate::try_or_handle_one(
+            || {
+                let primary: crate::Result<()> = crate::Result::new_error(Corrupt);
+                crate::suppressing_secondary(|| {
+                    let _: crate::Result<()> = crate::Result::new_error(Minor);
+                });
+                primary
+            },
+            |_: Corrupt| crate::Result::new(()),
+        );
+        let records = self::records();
+        assert_eq!(records.len(), 2, "{records:?}");
+        assert_eq!(records[1].0, Level::Debug);
+        assert!(records[1].1.ends_with(" was suppressed as a secondary error"), "{}", records[1].1);
+
+        // Unhandled, with a custom level map
+        set_level_map(|severity| match severity {
+            Severity::Debug => Level::Trace,
+            _ => Level::Info,
+        });
+        let _: crate::Result<()> = crate::Result::new_error(Minor);
+        let _: crate::Result<()> = crate::Result::new_error(Corrupt);
+        let records = self::records();
+        assert_eq!(records.iter().map(|r| r.0).collect::<Vec<_>>(), [Level::Trace, Level::Info]);
+        assert!(records.iter().all(|r| r.1.ends_with(" was not handled by any scope")), "{records:?}");
+        set_level_map(super::default_level);
+    }
+}
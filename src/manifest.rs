@@ -0,0 +1,297 @@
+//! Descriptions of handler chains, for auditing the effective chain at startup.
+//!
+//! [`FlatHandlers::manifest`](crate::multihandler::FlatHandlers::manifest) and
+//! [`DynHandlers::manifest`](crate::dynamic::DynHandlers::manifest) list the entries of a chain
+//! in the order they are asked, with where each handler was registered. With the `serde`
+//! feature a [`HandlerManifest`] can be serialized, for instance for golden-file tests.
+//!
+//! [`FlatHandlers::dry_run`](crate::multihandler::FlatHandlers::dry_run) finds the entry that
+//! would handle an error of a given type, without running any handler.
+
+use std::fmt;
+use std::panic::Location;
+
+/// A single entry of a [`HandlerManifest`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// The name of the error type handled by the entry, `None` if the entry is asked for every
+    /// error and decides itself, like a catch-all or a handler for stable errors
+    pub error_type: Option<&'static str>,
+    /// The name of the type of the handler, or of the entry if it has no single handler
+    pub handler: &'static str,
+    /// Where the handler was registered, if known
+    pub location: Option<&'static Location<'static>>,
+}
+
+impl ManifestEntry {
+    /// An entry that is asked for every error, described by its type name only.
+    pub(crate) fn opaque(handler: &'static str) -> Self {
+        Self {
+            error_type: None,
+            handler,
+            location: None,
+        }
+    }
+}
+
+/// Which entry of a chain would handle an error, found by
+/// [`FlatHandlers::dry_run`](crate::multihandler::FlatHandlers::dry_run).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DispatchDecision {
+    /// The entry at `index` would handle the error
+    Match {
+        /// The position of the entry in the chain
+        index: usize,
+        /// The description of the entry
+        entry: ManifestEntry,
+    },
+    /// The entry at `index` needs the value of the error to decide, so the outcome can't be
+    /// known from the type alone
+    Indeterminate {
+        /// The position of the entry in the chain
+        index: usize,
+        /// The description of the entry
+        entry: ManifestEntry,
+    },
+    /// No entry would handle the error, it propagates to the enclosing scope
+    NoMatch,
+}
+
+/// The entries of a handler chain, in the order they are asked.
+///
+/// `Display` renders an aligned table with one row per entry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HandlerManifest {
+    entries: Vec<ManifestEntry>,
+}
+
+impl HandlerManifest {
+    pub(crate) fn new(entries: Vec<ManifestEntry>) -> Self {
+        Self { entries }
+    }
+
+    /// The entries, in the order they are asked.
+    pub fn entries(&self) -> &[ManifestEntry] {
+        &self.entries
+    }
+}
+
+impl fmt::Display for HandlerManifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<[String; 4]> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                [
+                    index.to_string(),
+                    entry.error_type.unwrap_or("*").to_owned(),
+                    entry.handler.to_owned(),
+                    entry.location.map_or_else(|| "-".to_owned(), ToString::to_string),
+                ]
+            })
+            .collect();
+        let header = ["#", "error type", "handler", "registered at"].map(str::to_owned);
+        let mut widths = [0; 4];
+        for row in std::iter::once(&header).chain(&rows) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        for row in std::iter::once(&header).chain(&rows) {
+            let [index, error_type, handler, location] = row;
+            writeln!(
+                f,
+                "{index:<0$}  {error_type:<1$}  {handler:<2$}  {location}",
+                widths[0], widths[1], widths[2]
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ManifestEntry {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        struct SerLocation(&'static Location<'static>);
+
+        impl serde::Serialize for SerLocation {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut location = serializer.serialize_struct("Location", 3)?;
+                location.serialize_field("file", self.0.file())?;
+                location.serialize_field("line", &self.0.line())?;
+                location.serialize_field("column", &self.0.column())?;
+                location.end()
+            }
+        }
+
+        let mut entry = serializer.serialize_struct("ManifestEntry", 3)?;
+        entry.serialize_field("error_type", &self.error_type)?;
+        entry.serialize_field("handler", self.handler)?;
+        entry.serialize_field("location", &self.location.map(SerLocation))?;
+        entry.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for HandlerManifest {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flag;
+
+    flag!(Shutdown);
+
+    fn five_handlers() -> (crate::multihandler::FlatHandlers<impl crate::multihandler::FlatChain<Value = i32>>, u32) {
+        let line = line!() + 1;
+        let handlers = crate::builder(|_: std::io::Error| crate::Result::new(1))
+            .handle(|_: std::num::ParseIntError| crate::Result::new(2))
+            .handle(|_: &'static str| crate::Result::new(3))
+            .handle_flag::<Shutdown>(|| crate::Result::new(4))
+            .handle(|_: u8| crate::Result::new(5))
+            .build_flat();
+        (handlers, line)
+    }
+
+    #[test]
+    fn five_handler_chain() {
+        let (handlers, line) = five_handlers();
+        let manifest = handlers.manifest();
+        let entries = manifest.entries();
+
+        let types: Vec<_> = entries.iter().map(|entry| entry.error_type.unwrap()).collect();
+        assert_eq!(
+            types,
+            [
+                "std::io::error::Error",
+                "core::num::error::ParseIntError",
+                "&str",
+                std::any::type_name::<Shutdown>(),
+                "u8"
+            ]
+        );
+        for (offset, entry) in entries.iter().enumerate() {
+            let location = entry.location.unwrap();
+            assert_eq!(location.file(), file!());
+            assert_eq!(location.line(), line + offset as u32);
+            assert!(entry.handler.contains("{{closure}}"), "{}", entry.handler);
+        }
+
+        let rendered = manifest.to_string();
+        let rows: Vec<_> = rendered.lines().collect();
+        assert_eq!(rows.len(), 6);
+        assert!(rows[0].starts_with("#  error type"), "{rendered}");
+        assert!(rows[5].starts_with("4  u8 "), "{rendered}");
+        assert!(rows[5].ends_with(&format!("{}:{}:14", file!(), line + 4)), "{rendered}");
+    }
+
+    #[test]
+    fn dyn_chain() {
+        let line = line!() + 1;
+        let mut builder = crate::builder(|_: u16| crate::Result::new(0))
+            .handle(|_: u8| crate::Result::new(1))
+            .into_dyn();
+        builder.handle(|_: u32| crate::Result::new(2));
+        let manifest = builder.build().manifest();
+        let entries = manifest.entries();
+
+        // The static chain is described entry by entry
+        let types: Vec<_> = entries.iter().map(|entry| entry.error_type.unwrap()).collect();
+        assert_eq!(types, ["u16", "u8", "u32"]);
+        let lines: Vec<_> = entries.iter().map(|entry| entry.location.unwrap().line()).collect();
+        assert_eq!(lines, [line, line + 1, line + 3]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialized() {
+        let (handlers, line) = five_handlers();
+        let json = serde_json::to_value(handlers.manifest()).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 5);
+        assert_eq!(json[4]["error_type"], "u8");
+        assert_eq!(json[4]["location"]["file"], file!());
+        assert_eq!(json[4]["location"]["line"], line + 4);
+    }
+
+    /// Accepts even codes only, so it needs the value to decide
+    struct EvenCodes {
+        inner: Option<(crate::ErrorId, Code)>,
+    }
+    struct Code(u32);
+
+    impl crate::context::ErrorHandlingContext for EvenCodes {
+        unsafe fn try_set_error(&mut self, error: &crate::context::ReportedError) -> crate::context::TrySetErrorResult {
+            if error.type_id() != std::any::TypeId::of::<Code>() || !(*(error.value_ptr() as *const Code)).0.is_multiple_of(2) {
+                return crate::context::TrySetErrorResult::NotHandled;
+            }
+            self.inner = Some((error.id(), (error.value_ptr() as *mut Code).read()));
+            crate::context::TrySetErrorResult::NeedForget
+        }
+
+        fn ext(&mut self) -> Option<&mut dyn crate::context::ContextExt> {
+            Some(self)
+        }
+    }
+
+    impl crate::context::ContextExt for EvenCodes {
+        fn accepts(&mut self, type_id: std::any::TypeId) -> Option<bool> {
+            (type_id != std::any::TypeId::of::<Code>()).then_some(false)
+        }
+    }
+
+    impl crate::multihandler::TryHandle for EvenCodes {
+        type Value = u32;
+        fn try_handle(self, error_id: crate::ErrorId) -> Option<crate::Result<u32>> {
+            match self.inner {
+                Some((id, code)) if id == error_id => Some(crate::Result::new(code.0 * 100)),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn dry_run_matches_dispatch() {
+        use crate::context::ReportedErrorDescriptor;
+        use crate::manifest::DispatchDecision;
+
+        let line = line!() + 2;
+        let handlers = || {
+            crate::multihandler::compose(EvenCodes { inner: None })
+                .handle(|_: u8| crate::Result::new(1))
+                .handle(|code: Code| crate::Result::new(code.0))
+                .handle(|_: &'static str| crate::Result::new(3))
+                .build_flat()
+        };
+        let mut candidate = handlers();
+
+        // Typed entries decide from the type alone, and match what dispatch does
+        let decision = candidate.dry_run(&ReportedErrorDescriptor::of::<u8>());
+        let DispatchDecision::Match { index, entry } = decision else { panic!("{decision:?}") };
+        assert_eq!((index, entry.error_type, entry.location.unwrap().line()), (1, Some("u8"), line + 1));
+        assert_eq!(crate::try_or_handle(|| crate::Result::new_error(7u8), handlers()).unwrap(), 1);
+
+        let decision = candidate.dry_run(&ReportedErrorDescriptor::of::<&'static str>());
+        assert!(matches!(decision, DispatchDecision::Match { index: 3, .. }), "{decision:?}");
+        assert_eq!(crate::try_or_handle(|| crate::Result::new_error("bad"), handlers()).unwrap(), 3);
+
+        let decision = candidate.dry_run(&ReportedErrorDescriptor::of::<u16>());
+        assert_eq!(decision, DispatchDecision::NoMatch);
+        let res = crate::try_or_handle(|| crate::Result::new_error(7u16), handlers());
+        assert!(res.error_is::<u16>());
+
+        // The predicate needs the value, and dispatch indeed depends on it
+        let decision = candidate.dry_run(&ReportedErrorDescriptor::of::<Code>());
+        assert!(matches!(decision, DispatchDecision::Indeterminate { index: 0, .. }), "{decision:?}");
+        assert_eq!(crate::try_or_handle(|| crate::Result::new_error(Code(4)), handlers()).unwrap(), 400);
+        assert_eq!(crate::try_or_handle(|| crate::Result::new_error(Code(5)), handlers()).unwrap(), 5);
+
+        // The dry runs captured nothing, so the candidate still works
+        assert_eq!(crate::try_or_handle(|| crate::Result::new_error(9u8), candidate).unwrap(), 1);
+    }
+}
@@ -0,0 +1,82 @@
+//! Raise metadata shared by the features consuming it.
+//!
+//! Any feature that needs to know about raises enables the internal `metadata` feature and
+//! registers itself in [`raised`], instead of adding its own hook to the raise path. This keeps
+//! the raise path identical no matter which combination of features is enabled.
+//!
+//! # Eager vs lazy
+//!
+//! The consumers run on the raise path, once the disposition of the raise is known. Deferring
+//! them behind a queue cost more than the consumers themselves, the recent raises ring and the
+//! log records, so nothing is deferred until a consumer is expensive enough to pay for it, see
+//! [`flush_enrichment`](crate::flush_enrichment).
+//!
+//! Everything needing the error value, like the probes of [`on_raise`](crate::on_raise) or the
+//! redacted content kept by [`Eager`], is recorded before the value is delivered, since it may
+//! be dropped, moved into a handler or forgotten afterwards.
+//!
+//! With the `log` feature [`Eager`] costs every raise an atomic load of the log level. If the
+//! level isn't `Off` and a redactor is registered for any type, the redactors are looked up under
+//! a read lock, and for a type with a redactor, the redactor runs and its content `String` is
+//! kept until the record is written.
+
+use crate::context::Disposition;
+use std::any::TypeId;
+use std::panic::Location;
+
+/// What the raise path records from the error value, for the consumers needing it.
+#[derive(Default)]
+pub(crate) struct Eager {
+    #[cfg(feature = "log")]
+    content: Option<String>,
+}
+
+impl Eager {
+    #[inline]
+    pub(crate) fn capture<E: crate::Error>(err: &E) -> Self {
+        #[cfg(not(feature = "log"))]
+        let _ = err;
+        Self {
+            #[cfg(feature = "log")]
+            content: crate::log::content(err),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn capture_erased(err: &crate::context::ErasedError) -> Self {
+        #[cfg(not(feature = "log"))]
+        let _ = err;
+        Self {
+            #[cfg(feature = "log")]
+            content: crate::log::erased_content(err),
+        }
+    }
+}
+
+/// Called once for every raise, after the error has been delivered to the handling scopes.
+#[inline]
+pub(crate) fn raised(
+    id: u64,
+    type_id: TypeId,
+    type_name: &'static str,
+    location: Option<&'static Location<'static>>,
+    disposition: Disposition,
+    eager: Eager,
+) {
+    #[cfg(feature = "diagnostics")]
+    crate::debug::record(id, type_name, location, disposition);
+    #[cfg(feature = "log")]
+    crate::log::record(id, type_id, type_name, location, disposition, eager.content.as_deref());
+    #[cfg(not(feature = "log"))]
+    let _ = (id, type_id, type_name, location, disposition, eager);
+}
+
+/// Called when an error is superseded, after its raise.
+pub(crate) fn superseded(id: u64) {
+    #[cfg(feature = "diagnostics")]
+    crate::debug::superseded(id);
+    #[cfg(feature = "log")]
+    crate::log::superseded(id);
+    #[cfg(not(any(feature = "diagnostics", feature = "log")))]
+    let _ = id;
+}
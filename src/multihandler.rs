@@ -1,23 +1,132 @@
-use crate::context::{ErrorHandlingContext, ReportedError, TrySetErrorResult};
-use crate::SingleErrorStorage;
+use std::any::TypeId;
+use std::cell::{RefCell, RefMut};
+use std::rc::Rc;
+
+#[cfg(feature = "diagnostics")]
+use crate::context::ErrorInfo;
+use crate::context::{
+    Acceptance, ContextExt, Disposition, ErrorHandlingContext, ReportedError, ReportedErrorDescriptor, TrySetErrorResult,
+};
+use crate::manifest::{DispatchDecision, HandlerManifest, ManifestEntry};
+use crate::stable_id::{StableError, StableHandler};
+use crate::{ErrorId, SingleErrorStorage};
 
 pub trait TryHandle
 {
     type Value;
-    fn try_handle(self, error_id: u32) -> Option<crate::Result<Self::Value>>;
+    fn try_handle(self, error_id: ErrorId) -> Option<crate::Result<Self::Value>>;
+}
+
+/// A handler chain that handles errors through a mutable reference, so it can be reused.
+///
+/// `&mut H`, `&RefCell<H>` and `Rc<RefCell<H>>` are handler chains for every `H` implementing
+/// this trait, and can be passed to [`try_or_handle`] directly.
+pub trait TryHandleMut: ErrorHandlingContext {
+    type Value;
+    fn try_handle_mut(&mut self, error_id: ErrorId) -> Option<crate::Result<Self::Value>>;
+}
+
+impl<H: TryHandleMut> ErrorHandlingContext for &mut H {
+    #[inline]
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        (**self).try_set_error(error)
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        (**self).ext()
+    }
+}
+
+impl<H: TryHandleMut> TryHandle for &mut H {
+    type Value = H::Value;
+    fn try_handle(self, error_id: ErrorId) -> Option<crate::Result<H::Value>> {
+        self.try_handle_mut(error_id)
+    }
+}
+
+/// Borrow a shared chain for the duration of one call into it.
+///
+/// The chain is only borrowed while an error is offered to it or handled, so this fails only if
+/// a handler of the chain raises into, or handles with, the same chain.
+#[track_caller]
+fn borrow_chain<H>(chain: &RefCell<H>) -> RefMut<'_, H> {
+    match chain.try_borrow_mut() {
+        Ok(chain) => chain,
+        Err(_) => panic!(
+            "handler chain in a `RefCell` is already borrowed: a handler of the chain re-entered a scope handled by the same chain"
+        ),
+    }
+}
+
+/// The extensions are not forwarded, they can't outlive the borrow of the chain.
+impl<H: TryHandleMut> ErrorHandlingContext for &RefCell<H> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        borrow_chain(self).try_set_error(error)
+    }
+}
+
+impl<H: TryHandleMut> TryHandle for &RefCell<H> {
+    type Value = H::Value;
+    fn try_handle(self, error_id: ErrorId) -> Option<crate::Result<H::Value>> {
+        borrow_chain(self).try_handle_mut(error_id)
+    }
+}
+
+/// The extensions are not forwarded, they can't outlive the borrow of the chain.
+impl<H: TryHandleMut> ErrorHandlingContext for Rc<RefCell<H>> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        borrow_chain(self).try_set_error(error)
+    }
+}
+
+impl<H: TryHandleMut> TryHandle for Rc<RefCell<H>> {
+    type Value = H::Value;
+    fn try_handle(self, error_id: ErrorId) -> Option<crate::Result<H::Value>> {
+        borrow_chain(&self).try_handle_mut(error_id)
+    }
+}
+
+/// Conversion into the handler chain used by [`try_or_handle`].
+///
+/// Every handler chain converts into itself. `&Rc<RefCell<H>>` converts into `&RefCell<H>`, so a
+/// shared chain can be passed without cloning the `Rc`.
+pub trait AsHandlerChain {
+    type Chain: TryHandle + ErrorHandlingContext;
+    fn into_handler_chain(self) -> Self::Chain;
+}
+
+impl<H: TryHandle + ErrorHandlingContext> AsHandlerChain for H {
+    type Chain = H;
+    #[inline]
+    fn into_handler_chain(self) -> H {
+        self
+    }
+}
+
+impl<'a, H: TryHandleMut> AsHandlerChain for &'a Rc<RefCell<H>> {
+    type Chain = &'a RefCell<H>;
+    #[inline]
+    fn into_handler_chain(self) -> &'a RefCell<H> {
+        self
+    }
 }
 
 #[derive(Copy, Clone)]
 pub struct BoundHandler<E, H> {
     storage: SingleErrorStorage<E>,
     handler: H,
+    location: &'static std::panic::Location<'static>,
 }
 
 impl<E, H> BoundHandler<E, H> {
+    /// Bind `handler`, registered at the caller's location.
+    #[track_caller]
     pub fn new(handler: H) -> Self {
         Self {
             storage: SingleErrorStorage::default(),
             handler,
+            location: std::panic::Location::caller(),
         }
     }
 }
@@ -26,9 +135,13 @@ impl<E, H, V> TryHandle for BoundHandler<E, H>
 where
     H: FnOnce(E) -> crate::Result<V> {
     type Value = V;
-    fn try_handle(self, error_id: u32) -> Option<crate::Result<V>> {
+    fn try_handle(self, error_id: ErrorId) -> Option<crate::Result<V>> {
         match self.storage.into_inner() {
-            Some((id, err)) if id == error_id => Some((self.handler)(err)),
+            Some((id, err)) if id == error_id => {
+                #[cfg(feature = "paranoid")]
+                crate::id_state::handled(id);
+                Some((self.handler)(err))
+            }
             _ => None,
         }
     }
@@ -41,6 +154,257 @@ where
     unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
         self.storage.try_set_error(error)
     }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        self.storage.ext()
+    }
+}
+
+/// A handler receiving the [`ErrorInfo`] of the error along with it, enabled by the
+/// `diagnostics` feature.
+///
+/// Created by [`Builder::handle_with_info`].
+#[cfg(feature = "diagnostics")]
+#[derive(Copy, Clone)]
+pub struct InfoHandler<E, H>(BoundHandler<E, H>);
+
+#[cfg(feature = "diagnostics")]
+impl<E, H, V> TryHandle for InfoHandler<E, H>
+where
+    E: crate::Error,
+    H: FnOnce(E, ErrorInfo) -> crate::Result<V>,
+{
+    type Value = V;
+    fn try_handle(self, error_id: ErrorId) -> Option<crate::Result<V>> {
+        match self.0.storage.into_inner_with_info() {
+            Some((err, info)) if info.id() == error_id => {
+                #[cfg(feature = "paranoid")]
+                crate::id_state::handled(error_id);
+                Some((self.0.handler)(err, info))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl<E, H> ErrorHandlingContext for InfoHandler<E, H>
+where
+    E: crate::Error,
+{
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        self.0.try_set_error(error)
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        self.0.ext()
+    }
+}
+
+/// Error types converted to a common representation `C`, see [`Builder::handle_any_of`].
+///
+/// Built as a list of [`Convert`] ending in [`NoConversion`], usually by [`handle_any_of!`].
+///
+/// [`handle_any_of!`]: crate::handle_any_of
+pub trait Conversions<C> {
+    /// Test if errors of type `type_id` are converted.
+    fn accepts(&self, type_id: TypeId) -> bool;
+
+    /// Take the value of `error` and convert it, if it's of one of the types.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`ErrorHandlingContext::try_set_error`]. The value has been moved
+    /// out if this returns `Some`.
+    unsafe fn convert(&self, error: &ReportedError) -> Option<C>;
+}
+
+/// The end of a list of [`Conversions`], converting nothing.
+#[derive(Copy, Clone, Default)]
+pub struct NoConversion;
+
+impl<C> Conversions<C> for NoConversion {
+    fn accepts(&self, _type_id: TypeId) -> bool {
+        false
+    }
+
+    unsafe fn convert(&self, _error: &ReportedError) -> Option<C> {
+        None
+    }
+}
+
+/// Converts errors of type `E` with a function, and the other types with `Next`.
+pub struct Convert<E, C, Next = NoConversion> {
+    convert: fn(E) -> C,
+    next: Next,
+}
+
+impl<E, C, Next> Convert<E, C, Next> {
+    /// Convert `E` with `convert`, and the other types with `next`.
+    pub fn new(convert: fn(E) -> C, next: Next) -> Self {
+        Self { convert, next }
+    }
+}
+
+impl<E, C, Next: Clone> Clone for Convert<E, C, Next> {
+    fn clone(&self) -> Self {
+        Self {
+            convert: self.convert,
+            next: self.next.clone(),
+        }
+    }
+}
+
+impl<E, C, Next: Copy> Copy for Convert<E, C, Next> {}
+
+impl<E: crate::Error, C, Next: Conversions<C>> Conversions<C> for Convert<E, C, Next> {
+    fn accepts(&self, type_id: TypeId) -> bool {
+        type_id == TypeId::of::<E>() || self.next.accepts(type_id)
+    }
+
+    unsafe fn convert(&self, error: &ReportedError) -> Option<C> {
+        if error.type_id() == TypeId::of::<E>() {
+            Some((self.convert)((error.value_ptr() as *mut E).read()))
+        } else {
+            self.next.convert(error)
+        }
+    }
+}
+
+/// Captures an error of any of the types of `K`, stored converted to `C`.
+#[derive(Copy, Clone)]
+pub struct AnyOfStorage<C, K> {
+    conversions: K,
+    inner: Option<(ErrorId, C)>,
+    captured_type: &'static str,
+}
+
+impl<C, K> AnyOfStorage<C, K> {
+    /// Capture errors converted by `conversions`.
+    pub fn new(conversions: K) -> Self {
+        Self {
+            conversions,
+            inner: None,
+            captured_type: "",
+        }
+    }
+
+    /// The ID of the captured error and its converted value, if one was captured.
+    pub fn into_inner(self) -> Option<(ErrorId, C)> {
+        self.inner
+    }
+}
+
+impl<C, K: Conversions<C>> ErrorHandlingContext for AnyOfStorage<C, K> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        if crate::runtime::is_suppressed(error.type_id()) {
+            return TrySetErrorResult::NotHandled;
+        }
+        match self.conversions.convert(error) {
+            Some(converted) => {
+                #[cfg(feature = "paranoid")]
+                crate::id_state::captured(error.id());
+                self.inner = Some((error.id(), converted));
+                self.captured_type = error.type_name();
+                TrySetErrorResult::NeedForget
+            }
+            None => TrySetErrorResult::NotHandled,
+        }
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        Some(self)
+    }
+}
+
+impl<C, K: Conversions<C>> ContextExt for AnyOfStorage<C, K> {
+    fn accepts(&mut self, type_id: TypeId) -> Option<bool> {
+        Some(self.conversions.accepts(type_id) && !crate::runtime::is_suppressed(type_id))
+    }
+
+    fn has_pending(&mut self) -> bool {
+        self.inner.is_some()
+    }
+
+    fn pending_type_name(&mut self) -> Option<&'static str> {
+        self.inner.as_ref().map(|_| self.captured_type)
+    }
+
+    fn on_disposition(&mut self, error_id: crate::ErrorId, disposition: Disposition) {
+        if disposition == Disposition::Superseded && self.inner.as_ref().is_some_and(|(id, _)| *id == error_id) {
+            self.inner = None;
+        }
+    }
+
+    fn debug_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// A handler for several error types converted to a common representation `C`.
+///
+/// Created by [`Builder::handle_either`] and [`Builder::handle_any_of`].
+#[derive(Copy, Clone)]
+pub struct AnyOfHandler<C, K, H> {
+    storage: AnyOfStorage<C, K>,
+    handler: H,
+    location: &'static std::panic::Location<'static>,
+}
+
+impl<C, K, H, V> TryHandle for AnyOfHandler<C, K, H>
+where
+    H: FnOnce(C) -> crate::Result<V>,
+{
+    type Value = V;
+    fn try_handle(self, error_id: ErrorId) -> Option<crate::Result<V>> {
+        match self.storage.into_inner() {
+            Some((id, converted)) if id == error_id => {
+                #[cfg(feature = "paranoid")]
+                crate::id_state::handled(id);
+                Some((self.handler)(converted))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<C, K: Conversions<C>, H> ErrorHandlingContext for AnyOfHandler<C, K, H> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        self.storage.try_set_error(error)
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        self.storage.ext()
+    }
+}
+
+impl<C, K: Conversions<C>, H> FlatChain for AnyOfHandler<C, K, H>
+where
+    Self: TryHandle,
+{
+    const LEN: usize = 1;
+
+    fn entry_types(table: &mut [Option<TypeId>]) {
+        // Accepts several types, so it's asked for every error
+        table[0] = None;
+    }
+
+    #[inline]
+    unsafe fn try_set_error_at(&mut self, _index: usize, error: &ReportedError) -> TrySetErrorResult {
+        self.try_set_error(error)
+    }
+
+    fn describe(&self, entries: &mut Vec<ManifestEntry>) {
+        entries.push(ManifestEntry {
+            error_type: None,
+            handler: std::any::type_name::<H>(),
+            location: Some(self.location),
+        });
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -60,6 +424,79 @@ where
             x => x,
         }
     }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        Some(self)
+    }
+}
+
+impl<Left, Right> ContextExt for Sequence<Left, Right>
+where
+    Left: ErrorHandlingContext,
+    Right: ErrorHandlingContext,
+{
+    fn accepts(&mut self, type_id: TypeId) -> Option<bool> {
+        let left = self.left.ext().and_then(|ext| ext.accepts(type_id));
+        let right = self.right.ext().and_then(|ext| ext.accepts(type_id));
+        match (left, right) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        }
+    }
+
+    fn would_accept(&mut self, descriptor: &ReportedErrorDescriptor) -> Acceptance {
+        let left = self.left.ext().map_or(Acceptance::Indeterminate, |ext| ext.would_accept(descriptor));
+        match left {
+            Acceptance::Reject => self.right.ext().map_or(Acceptance::Indeterminate, |ext| ext.would_accept(descriptor)),
+            decided => decided,
+        }
+    }
+
+    fn has_pending(&mut self) -> bool {
+        self.left.ext().is_some_and(|ext| ext.has_pending())
+            || self.right.ext().is_some_and(|ext| ext.has_pending())
+    }
+
+    fn pending_count(&mut self) -> usize {
+        self.left.ext().map_or(0, |ext| ext.pending_count()) + self.right.ext().map_or(0, |ext| ext.pending_count())
+    }
+
+    fn pending_type_name(&mut self) -> Option<&'static str> {
+        self.left
+            .ext()
+            .and_then(|ext| ext.pending_type_name())
+            .or_else(|| self.right.ext().and_then(|ext| ext.pending_type_name()))
+    }
+
+    fn pending_summary(&mut self) -> Option<crate::redact::RedactedSummary> {
+        self.left
+            .ext()
+            .and_then(|ext| ext.pending_summary())
+            .or_else(|| self.right.ext().and_then(|ext| ext.pending_summary()))
+    }
+
+    fn pending_storage(&mut self, type_id: TypeId) -> Option<&mut dyn std::any::Any> {
+        if self.left.ext().is_some_and(|ext| ext.pending_storage(type_id).is_some()) {
+            self.left.ext().and_then(|ext| ext.pending_storage(type_id))
+        } else {
+            self.right.ext().and_then(|ext| ext.pending_storage(type_id))
+        }
+    }
+
+    fn debug_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    fn on_disposition(&mut self, error_id: crate::ErrorId, disposition: Disposition) {
+        if let Some(ext) = self.left.ext() {
+            ext.on_disposition(error_id, disposition);
+        }
+        if let Some(ext) = self.right.ext() {
+            ext.on_disposition(error_id, disposition);
+        }
+    }
 }
 
 impl<Left, Right> TryHandle for Sequence<Left, Right>
@@ -68,7 +505,7 @@ where
     Right: TryHandle<Value = Left::Value>,
 {
     type Value = Left::Value;
-    fn try_handle(self, error_id: u32) -> Option<crate::Result<Self::Value>> {
+    fn try_handle(self, error_id: ErrorId) -> Option<crate::Result<Self::Value>> {
         match self.left.try_handle(error_id) {
             None => self.right.try_handle(error_id),
             x => x
@@ -76,83 +513,722 @@ where
     }
 }
 
-#[derive(Copy, Clone)]
-pub struct Builder<T>(T);
+/// The maximum chain length dispatched through the type table of [`FlatHandlers`].
+///
+/// Longer chains are walked entry by entry, the same way as a [`Sequence`].
+pub const MAX_FLAT_ENTRIES: usize = 16;
 
-impl<T> Builder<T>
-where
-    T: TryHandle + ErrorHandlingContext
-{
-    /// Add a new error handler to the builder.
-    ///
-    /// # Arguments
+/// A handler chain that can be flattened into a [`FlatHandlers`] table.
+///
+/// Every entry of the chain has an index, starting at 0 for the first entry.
+pub trait FlatChain: TryHandle + ErrorHandlingContext {
+    /// The number of entries in the chain.
+    const LEN: usize;
+
+    /// Write the error type accepted by each entry to `table`, which holds exactly `LEN` slots.
     ///
-    /// * `handler`: The error handler to add
+    /// Entries that can't be described by a single type write `None`, and are always offered
+    /// the error.
+    fn entry_types(table: &mut [Option<TypeId>]);
+
+    /// Offer an error to the entry at `index` only.
     ///
-    /// returns: [`Builder<Sequence<T, BoundHandler<E, H>>>`]
+    /// # Safety
     ///
-    /// # Examples
+    /// Same requirements as [`ErrorHandlingContext::try_set_error`].
+    unsafe fn try_set_error_at(&mut self, index: usize, error: &ReportedError) -> TrySetErrorResult;
+
+    /// Ask the entry at `index` only whether it would accept the error described by `descriptor`.
     ///
-    /// ```
-    /// let _handlers = xcept::builder(|_err: std::io::Error| -1.into())
-    ///     .handle(|_err: std::str::Utf8Error| -2.into())
-    ///     .build(); // A handler that can handle both std::io::Error and std::str::Utf8Error
-    /// ```
-    pub fn handle<H, E>(self, handler: H) -> Builder<Sequence<T, BoundHandler<E, H>>>
-    where
-        H: FnOnce(E) -> crate::Result<T::Value>
-    {
-        Builder(Sequence {
-            left: self.0,
-            right: BoundHandler::<E, H>::new(handler),
-        })
+    /// By default the whole chain is asked through [`ContextExt::would_accept`], which is right
+    /// for chains of a single entry.
+    fn would_accept_at(&mut self, index: usize, descriptor: &ReportedErrorDescriptor) -> Acceptance {
+        let _ = index;
+        self.ext().map_or(Acceptance::Indeterminate, |ext| ext.would_accept(descriptor))
     }
 
-    /// Convert the builder to a handling context.
-    ///
-    /// The handling context is suitable for usage by [`try_or_handle`].
-    ///
-    /// See [`try_or_handle`] for more information.
+    /// Append a description of each entry to `entries`, in order, see [`FlatHandlers::manifest`].
     ///
-    pub fn build(self) -> T {
-        self.0
+    /// By default the chain is described as a single entry asked for every error.
+    fn describe(&self, entries: &mut Vec<ManifestEntry>) {
+        entries.push(ManifestEntry::opaque(std::any::type_name::<Self>()));
     }
 }
 
-/// Create a builder that to build a handler for use with [`try_or_handle`]
-///
-/// [`try_or_handle`]: crate::try_or_handle
-///
-/// # Arguments
-///
-/// * `handler`: The first handler to add to the builder
-///
-/// returns: [`Builder<BoundHandler<E, T>>`]
-///
-/// # Examples
-///
-/// ```
-/// let _handlers = xcept::builder(|_err: std::io::Error| -1.into())
-///     .handle(|_err: std::str::Utf8Error| -2.into())
-///     .build(); // A handler that can handle both std::io::Error and std::str::Utf8Error
-/// ```
-pub fn builder<T, E, V>(handler: T) -> Builder<BoundHandler<E, T>>
+impl<E, H> FlatChain for BoundHandler<E, H>
 where
-    T: FnOnce(E) -> crate::Result<V>
+    E: crate::Error,
+    Self: TryHandle,
 {
-    Builder(BoundHandler::<E, T>::new(handler))
+    const LEN: usize = 1;
+
+    fn entry_types(table: &mut [Option<TypeId>]) {
+        table[0] = Some(TypeId::of::<E>());
+    }
+
+    #[inline]
+    unsafe fn try_set_error_at(&mut self, _index: usize, error: &ReportedError) -> TrySetErrorResult {
+        self.try_set_error(error)
+    }
+
+    fn describe(&self, entries: &mut Vec<ManifestEntry>) {
+        entries.push(ManifestEntry {
+            error_type: Some(std::any::type_name::<E>()),
+            handler: std::any::type_name::<H>(),
+            location: Some(self.location),
+        });
+    }
 }
 
-/// Try to execute a function, and try to handle any error that happens.
-///
-/// Unlike [`try_or_handle_one`] this function can handle multiple different error types, but
-/// the error handler must be built using a [builder].
-///
-/// [`try_or_handle_one`]: crate::try_or_handle_one
-/// [builder]: builder
-///
-/// # Examples
-///
+#[cfg(feature = "diagnostics")]
+impl<E, H> FlatChain for InfoHandler<E, H>
+where
+    E: crate::Error,
+    Self: TryHandle,
+{
+    const LEN: usize = 1;
+
+    fn entry_types(table: &mut [Option<TypeId>]) {
+        table[0] = Some(TypeId::of::<E>());
+    }
+
+    #[inline]
+    unsafe fn try_set_error_at(&mut self, _index: usize, error: &ReportedError) -> TrySetErrorResult {
+        self.try_set_error(error)
+    }
+
+    fn describe(&self, entries: &mut Vec<ManifestEntry>) {
+        entries.push(ManifestEntry {
+            error_type: Some(std::any::type_name::<E>()),
+            handler: std::any::type_name::<H>(),
+            location: Some(self.0.location),
+        });
+    }
+}
+
+impl<Left, Right> FlatChain for Sequence<Left, Right>
+where
+    Left: FlatChain,
+    Right: FlatChain<Value = Left::Value>,
+{
+    const LEN: usize = Left::LEN + Right::LEN;
+
+    fn entry_types(table: &mut [Option<TypeId>]) {
+        let (left, right) = table.split_at_mut(Left::LEN);
+        Left::entry_types(left);
+        Right::entry_types(right);
+    }
+
+    #[inline]
+    unsafe fn try_set_error_at(&mut self, index: usize, error: &ReportedError) -> TrySetErrorResult {
+        if index < Left::LEN {
+            self.left.try_set_error_at(index, error)
+        } else {
+            self.right.try_set_error_at(index - Left::LEN, error)
+        }
+    }
+
+    fn would_accept_at(&mut self, index: usize, descriptor: &ReportedErrorDescriptor) -> Acceptance {
+        if index < Left::LEN {
+            self.left.would_accept_at(index, descriptor)
+        } else {
+            self.right.would_accept_at(index - Left::LEN, descriptor)
+        }
+    }
+
+    fn describe(&self, entries: &mut Vec<ManifestEntry>) {
+        self.left.describe(entries);
+        self.right.describe(entries);
+    }
+}
+
+/// Implement [`FlatChain`] for a context that forms a single entry which is always asked.
+macro_rules! opaque_flat_chain {
+    ($(impl [$($param:tt)*] for $ty:ty;)*) => {
+        $(
+            impl<$($param)*> $crate::multihandler::FlatChain for $ty
+            where
+                Self: $crate::multihandler::TryHandle + $crate::context::ErrorHandlingContext,
+            {
+                const LEN: usize = 1;
+
+                fn entry_types(table: &mut [Option<::std::any::TypeId>]) {
+                    table[0] = None;
+                }
+
+                #[inline]
+                unsafe fn try_set_error_at(
+                    &mut self,
+                    _index: usize,
+                    error: &$crate::context::ReportedError,
+                ) -> $crate::context::TrySetErrorResult {
+                    $crate::context::ErrorHandlingContext::try_set_error(self, error)
+                }
+            }
+        )*
+    };
+}
+pub(crate) use opaque_flat_chain;
+
+opaque_flat_chain! {
+    impl [V, const N: usize] for HandlerArray<V, N>;
+    impl [T] for Composed<T>;
+}
+
+/// A handler chain dispatched through a table of the error types its entries accept.
+///
+/// Instead of asking every entry in turn, the table is scanned for entries accepting the
+/// reported type, and only those are asked. Entries are asked in the same order as a
+/// [`Sequence`] would, so the result is always the same.
+///
+/// Created by [`Builder::build_flat`].
+#[derive(Copy, Clone)]
+pub struct FlatHandlers<T> {
+    chain: T,
+    table: [u64; MAX_FLAT_ENTRIES],
+}
+
+/// Hash marking an entry that is always asked.
+const ASK_ALWAYS: u64 = 0;
+
+/// Hasher keeping the bits of the `TypeId` as they are, they are already a hash.
+#[derive(Default)]
+struct TypeIdHasher(u64);
+
+impl std::hash::Hasher for TypeIdHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.write_u64(u64::from_ne_bytes(word));
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 = self.0.rotate_left(5) ^ value;
+    }
+}
+
+/// Reduce a `TypeId` to a `u64` for the table scan.
+///
+/// Different types may share a hash, the entries themselves compare the full `TypeId` so this
+/// only costs an extra call.
+#[inline]
+fn type_hash(type_id: TypeId) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = TypeIdHasher::default();
+    type_id.hash(&mut hasher);
+    hasher.finish() | 1
+}
+
+impl<T: FlatChain> FlatHandlers<T> {
+    /// Build the type table for `chain`.
+    pub fn new(chain: T) -> Self {
+        let mut table = [ASK_ALWAYS; MAX_FLAT_ENTRIES];
+        if T::LEN <= MAX_FLAT_ENTRIES {
+            let mut types = [None; MAX_FLAT_ENTRIES];
+            T::entry_types(&mut types[..T::LEN]);
+            for (hash, type_id) in table.iter_mut().zip(types) {
+                *hash = type_id.map_or(ASK_ALWAYS, type_hash);
+            }
+        }
+        Self { chain, table }
+    }
+
+    /// The handler chain, without the table.
+    pub fn into_inner(self) -> T {
+        self.chain
+    }
+
+    /// Describe the entries of the chain, in the order they are asked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let handlers = xcept::builder(|_: std::io::Error| xcept::Result::new(1))
+    ///     .handle(|_: u8| xcept::Result::new(2))
+    ///     .build_flat();
+    /// let manifest = handlers.manifest();
+    /// assert_eq!(manifest.entries()[1].error_type, Some("u8"));
+    /// println!("{manifest}");
+    /// ```
+    pub fn manifest(&self) -> HandlerManifest {
+        let mut entries = Vec::with_capacity(T::LEN);
+        self.chain.describe(&mut entries);
+        HandlerManifest::new(entries)
+    }
+
+    /// Find the entry that would handle the error described by `probe`, without running any
+    /// handler.
+    ///
+    /// Entries are asked in dispatch order through [`FlatChain::would_accept_at`]. The first
+    /// entry accepting the error is the [`DispatchDecision::Match`], unless an entry before it
+    /// can only tell from the value of the error, like a predicate handler, which makes the
+    /// decision [`DispatchDecision::Indeterminate`]. Useful for replaying recorded error types
+    /// against a candidate chain before enabling it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xcept::context::ReportedErrorDescriptor;
+    /// use xcept::manifest::DispatchDecision;
+    ///
+    /// let mut handlers = xcept::builder(|_: std::io::Error| xcept::Result::new(1))
+    ///     .handle(|_: u8| xcept::Result::new(2))
+    ///     .build_flat();
+    /// match handlers.dry_run(&ReportedErrorDescriptor::named("u8")) {
+    ///     DispatchDecision::Match { index, .. } => assert_eq!(index, 1),
+    ///     decision => panic!("{decision:?}"),
+    /// }
+    /// assert_eq!(handlers.dry_run(&ReportedErrorDescriptor::of::<u16>()), DispatchDecision::NoMatch);
+    /// ```
+    pub fn dry_run(&mut self, probe: &ReportedErrorDescriptor) -> DispatchDecision {
+        let mut entries = Vec::with_capacity(T::LEN);
+        self.chain.describe(&mut entries);
+        for (index, entry) in entries.into_iter().enumerate().take(T::LEN) {
+            match self.chain.would_accept_at(index, probe) {
+                Acceptance::Reject => {}
+                Acceptance::Accept => return DispatchDecision::Match { index, entry },
+                Acceptance::Indeterminate => return DispatchDecision::Indeterminate { index, entry },
+            }
+        }
+        DispatchDecision::NoMatch
+    }
+
+    /// Replace the error captured for the entry handling `E` with the one returned by `f`,
+    /// keeping its ID.
+    ///
+    /// The entry is found through [`ContextExt::pending_storage`]. Returns `false`, without
+    /// calling `f`, if no entry has captured an `E`. See also
+    /// [`SingleErrorStorage::replace_with`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xcept::context::ScopeNode;
+    /// use xcept::multihandler::TryHandle;
+    ///
+    /// let mut handlers = xcept::builder(|err: std::io::Error| xcept::Result::new(err.to_string()))
+    ///     .handle(|_: u8| xcept::Result::new(String::new()))
+    ///     .build_flat();
+    /// let res: xcept::Result<()> = {
+    ///     let scope = std::pin::pin!(ScopeNode::new(&mut handlers));
+    ///     let _guard = unsafe { scope.register() };
+    ///     xcept::Result::new_error(std::io::Error::other("/srv/app/secret.key missing"))
+    /// };
+    ///
+    /// handlers.map_captured(|_: std::io::Error| std::io::Error::other("secret.key missing"));
+    /// let message = handlers.try_handle(res.error_id().unwrap()).unwrap();
+    /// assert_eq!(message.unwrap(), "secret.key missing");
+    /// ```
+    pub fn map_captured<E: crate::Error>(&mut self, f: impl FnOnce(E) -> E) -> bool {
+        self.chain
+            .ext()
+            .and_then(|ext| ext.pending_storage(TypeId::of::<E>()))
+            .and_then(|storage| storage.downcast_mut::<SingleErrorStorage<E>>())
+            .is_some_and(|storage| storage.replace_with(f))
+    }
+}
+
+impl<T: FlatChain> ErrorHandlingContext for FlatHandlers<T> {
+    #[inline]
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        if T::LEN > MAX_FLAT_ENTRIES {
+            return self.chain.try_set_error(error);
+        }
+        let hash = type_hash(error.type_id());
+        for (index, entry) in self.table[..T::LEN].iter().enumerate() {
+            if *entry != hash && *entry != ASK_ALWAYS {
+                continue;
+            }
+            match self.chain.try_set_error_at(index, error) {
+                TrySetErrorResult::NotHandled => {}
+                x => return x,
+            }
+        }
+        TrySetErrorResult::NotHandled
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        self.chain.ext()
+    }
+}
+
+impl<T: FlatChain> TryHandle for FlatHandlers<T> {
+    type Value = T::Value;
+    fn try_handle(self, error_id: ErrorId) -> Option<crate::Result<Self::Value>> {
+        self.chain.try_handle(error_id)
+    }
+}
+
+impl<T: FlatChain> FlatChain for FlatHandlers<T> {
+    const LEN: usize = T::LEN;
+
+    fn entry_types(table: &mut [Option<TypeId>]) {
+        T::entry_types(table)
+    }
+
+    #[inline]
+    unsafe fn try_set_error_at(&mut self, index: usize, error: &ReportedError) -> TrySetErrorResult {
+        self.chain.try_set_error_at(index, error)
+    }
+
+    fn describe(&self, entries: &mut Vec<ManifestEntry>) {
+        self.chain.describe(entries)
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Builder<T>(T);
+
+impl<T> Builder<T>
+where
+    T: TryHandle + ErrorHandlingContext
+{
+    /// Add a new error handler to the builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler`: The error handler to add
+    ///
+    /// returns: [`Builder<Sequence<T, BoundHandler<E, H>>>`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let _handlers = xcept::builder(|_err: std::io::Error| xcept::Result::new(-1))
+    ///     .handle(|_err: std::str::Utf8Error| xcept::Result::new(-2))
+    ///     .build(); // A handler that can handle both std::io::Error and std::str::Utf8Error
+    /// ```
+    #[track_caller]
+    pub fn handle<H, E>(self, handler: H) -> Builder<Sequence<T, BoundHandler<E, H>>>
+    where
+        H: FnOnce(E) -> crate::Result<T::Value>
+    {
+        Builder(Sequence {
+            left: self.0,
+            right: BoundHandler::<E, H>::new(handler),
+        })
+    }
+
+    /// Add a handler that also receives the [`ErrorInfo`] of the error, enabled by the
+    /// `diagnostics` feature.
+    ///
+    /// Matches errors like [`Builder::handle`]. The info holds where the error was raised, so the
+    /// handler can log it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let handlers = xcept::builder(|_: u8| xcept::Result::new(String::new()))
+    ///     .handle_with_info(|err: std::io::Error, info| {
+    ///         xcept::Result::new(format!("{err} at line {}", info.location().unwrap().line()))
+    ///     })
+    ///     .build();
+    /// let line = line!() + 1;
+    /// let res = xcept::try_or_handle(|| xcept::Result::new_error(std::io::Error::other("gone")), handlers);
+    /// assert_eq!(res.unwrap(), format!("gone at line {line}"));
+    /// ```
+    #[cfg(feature = "diagnostics")]
+    #[track_caller]
+    pub fn handle_with_info<H, E>(self, handler: H) -> Builder<Sequence<T, InfoHandler<E, H>>>
+    where
+        H: FnOnce(E, ErrorInfo) -> crate::Result<T::Value>,
+    {
+        Builder(Sequence {
+            left: self.0,
+            right: InfoHandler(BoundHandler::new(handler)),
+        })
+    }
+
+    /// Add one handler for two error types, converting each to the common representation `C`.
+    ///
+    /// Meant for refactors renaming an error type, where both the old and the new type are
+    /// raised for a while. Use [`handle_any_of!`] for more than two types.
+    ///
+    /// [`handle_any_of!`]: crate::handle_any_of
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// mod old {
+    ///     pub struct ParseError(pub String);
+    /// }
+    /// mod new {
+    ///     pub struct ParseError {
+    ///         pub message: String,
+    ///     }
+    /// }
+    ///
+    /// let handlers = xcept::builder(|_: std::io::Error| xcept::Result::new(String::new()))
+    ///     .handle_either(|err: old::ParseError| err.0, |err: new::ParseError| err.message, xcept::Result::new)
+    ///     .build();
+    /// let res = xcept::try_or_handle(|| xcept::Result::new_error(old::ParseError("bad digit".into())), handlers);
+    /// assert_eq!(res.unwrap(), "bad digit");
+    /// ```
+    #[allow(clippy::type_complexity)]
+    #[track_caller]
+    pub fn handle_either<E1, E2, C, H>(
+        self,
+        convert1: fn(E1) -> C,
+        convert2: fn(E2) -> C,
+        handler: H,
+    ) -> Builder<Sequence<T, AnyOfHandler<C, Convert<E1, C, Convert<E2, C>>, H>>>
+    where
+        E1: crate::Error,
+        E2: crate::Error,
+        H: FnOnce(C) -> crate::Result<T::Value>,
+    {
+        self.handle_any_of(Convert::new(convert1, Convert::new(convert2, NoConversion)), handler)
+    }
+
+    /// Add one handler for every error type of `conversions`, see [`Conversions`].
+    ///
+    /// Usually called through [`handle_any_of!`].
+    ///
+    /// [`handle_any_of!`]: crate::handle_any_of
+    #[track_caller]
+    pub fn handle_any_of<C, K, H>(self, conversions: K, handler: H) -> Builder<Sequence<T, AnyOfHandler<C, K, H>>>
+    where
+        K: Conversions<C>,
+        H: FnOnce(C) -> crate::Result<T::Value>,
+    {
+        Builder(Sequence {
+            left: self.0,
+            right: AnyOfHandler {
+                storage: AnyOfStorage::new(conversions),
+                handler,
+                location: std::panic::Location::caller(),
+            },
+        })
+    }
+
+    /// Add a handler for a [`StableError`], matched by its stable ID instead of its `TypeId`.
+    ///
+    /// Handles errors raised with [`Result::new_error_stable`] by any type sharing the stable ID
+    /// of `E`, including types from other compilation units. See [`crate::stable_id`].
+    ///
+    /// [`StableError`]: crate::stable_id::StableError
+    /// [`Result::new_error_stable`]: crate::Result::new_error_stable
+    pub fn handle_stable<H, E>(self, handler: H) -> Builder<Sequence<T, StableHandler<E, H>>>
+    where
+        E: StableError,
+        H: FnOnce(E) -> crate::Result<T::Value>,
+    {
+        Builder(Sequence {
+            left: self.0,
+            right: StableHandler::new(handler),
+        })
+    }
+
+    /// Add a handler for a data-less error type, such as a [`Flag`] or a type declared with
+    /// [`flag!`].
+    ///
+    /// The handler takes no argument. Unlike [`Builder::handle`] the resulting chain is not
+    /// `Copy`; use `handle(|_: F| ...)` for handlers that are reused.
+    ///
+    /// [`Flag`]: crate::Flag
+    /// [`flag!`]: crate::flag
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// xcept::flag!(NotReady);
+    ///
+    /// let handlers = xcept::builder(|_: std::io::Error| xcept::Result::new(false))
+    ///     .handle_flag::<NotReady>(|| xcept::Result::new(true))
+    ///     .build();
+    /// assert!(xcept::try_or_handle(|| xcept::Result::new_error(NotReady), handlers).unwrap());
+    /// ```
+    #[allow(clippy::type_complexity)]
+    #[track_caller]
+    pub fn handle_flag<F: crate::Error>(
+        self,
+        handler: impl FnOnce() -> crate::Result<T::Value>,
+    ) -> Builder<Sequence<T, BoundHandler<F, impl FnOnce(F) -> crate::Result<T::Value>>>> {
+        self.handle(move |_: F| handler())
+    }
+
+    /// Convert the builder to a handling context.
+    ///
+    /// The handling context is suitable for usage by [`try_or_handle`].
+    ///
+    /// See [`try_or_handle`] for more information.
+    ///
+    pub fn build(self) -> T {
+        self.0
+    }
+
+    /// Convert the builder to a handling context dispatching through a type table, see
+    /// [`FlatHandlers`].
+    ///
+    /// The table is opt-in: in the `dispatch` benchmark the inlined walk of the chain returned by
+    /// [`build`](Self::build) is faster, about 50 ns against 57 ns for twelve handlers with the
+    /// last one matching. [`FlatHandlers::dry_run`] and the manifest of the handlers need the
+    /// table.
+    pub fn build_flat(self) -> FlatHandlers<T>
+    where
+        T: FlatChain,
+    {
+        FlatHandlers::new(self.0)
+    }
+
+    /// Erase the handlers added so far, to continue adding handlers at runtime.
+    ///
+    /// The erased handlers form a single entry of the [`DynBuilder`], which uses a fresh clone of
+    /// them every time the built handlers are used.
+    ///
+    /// [`DynBuilder`]: crate::dynamic::DynBuilder
+    pub fn into_dyn(self) -> crate::dynamic::DynBuilder<T::Value>
+    where
+        T: FlatChain + Clone + 'static,
+        T::Value: 'static,
+    {
+        let mut builder = crate::dynamic::DynBuilder::new();
+        builder.push_static(self.0);
+        builder
+    }
+}
+
+/// Create runtime handlers from plugin provided handlers.
+///
+/// The plugins are tried in order. See [`PluginErrorHandler`] for how they handle or decline
+/// errors.
+///
+/// [`PluginErrorHandler`]: crate::dynamic::PluginErrorHandler
+///
+/// # Examples
+///
+/// ```
+/// use std::any::{Any, TypeId};
+/// use xcept::dynamic::{PluginErrorHandler, PluginOutcome};
+///
+/// struct Plugin;
+///
+/// impl PluginErrorHandler<i32> for Plugin {
+///     fn error_type(&self) -> TypeId {
+///         TypeId::of::<u8>()
+///     }
+///
+///     fn handle_erased(&mut self, error: Box<dyn Any>) -> PluginOutcome<i32> {
+///         PluginOutcome::Handled(i32::from(*error.downcast::<u8>().unwrap()))
+///     }
+/// }
+///
+/// let handlers = xcept::multihandler::from_plugins(vec![Box::new(Plugin)]);
+/// assert_eq!(xcept::try_or_handle(|| xcept::Result::new_error(4u8), handlers).unwrap(), 4);
+/// ```
+pub fn from_plugins<V: 'static>(
+    plugins: Vec<Box<dyn crate::dynamic::PluginErrorHandler<V>>>,
+) -> crate::dynamic::DynHandlers<V> {
+    let mut builder = crate::dynamic::DynBuilder::new();
+    builder.handle_plugins(plugins);
+    builder.build()
+}
+
+/// Start a builder from an existing handler chain.
+///
+/// This allows runtime built [`DynHandlers`] to be extended with static handlers.
+///
+/// The existing chain forms a single entry of the new chain, so any handling context can be
+/// used, not only those implementing [`FlatChain`].
+///
+/// [`DynHandlers`]: crate::dynamic::DynHandlers
+///
+/// # Examples
+///
+/// ```
+/// let mut dyn_builder = xcept::dynamic::DynBuilder::new();
+/// dyn_builder.handle(|_: bool| xcept::Result::new(0));
+///
+/// let handlers = xcept::multihandler::compose(dyn_builder.build())
+///     .handle(|x: i32| xcept::Result::new(x))
+///     .build();
+/// assert_eq!(xcept::try_or_handle(|| xcept::Result::new_error(3), handlers).unwrap(), 3);
+/// ```
+pub fn compose<T>(handlers: T) -> Builder<Composed<T>>
+where
+    T: TryHandle + ErrorHandlingContext
+{
+    Builder(Composed(handlers))
+}
+
+/// An existing handler chain used as a single entry, see [`compose`].
+#[derive(Copy, Clone)]
+pub struct Composed<T>(T);
+
+impl<T> Composed<T> {
+    /// The wrapped handler chain.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: ErrorHandlingContext> ErrorHandlingContext for Composed<T> {
+    #[inline]
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        self.0.try_set_error(error)
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        self.0.ext()
+    }
+}
+
+impl<T: TryHandle> TryHandle for Composed<T> {
+    type Value = T::Value;
+    #[inline]
+    fn try_handle(self, error_id: ErrorId) -> Option<crate::Result<Self::Value>> {
+        self.0.try_handle(error_id)
+    }
+}
+
+/// Create a builder that to build a handler for use with [`try_or_handle`]
+///
+/// [`try_or_handle`]: crate::try_or_handle
+///
+/// # Arguments
+///
+/// * `handler`: The first handler to add to the builder
+///
+/// returns: [`Builder<BoundHandler<E, T>>`]
+///
+/// # Examples
+///
+/// ```
+/// let _handlers = xcept::builder(|_err: std::io::Error| xcept::Result::new(-1))
+///     .handle(|_err: std::str::Utf8Error| xcept::Result::new(-2))
+///     .build(); // A handler that can handle both std::io::Error and std::str::Utf8Error
+/// ```
+#[track_caller]
+pub fn builder<T, E, V>(handler: T) -> Builder<BoundHandler<E, T>>
+where
+    T: FnOnce(E) -> crate::Result<V>
+{
+    Builder(BoundHandler::<E, T>::new(handler))
+}
+
+/// Try to execute a function, and try to handle any error that happens.
+///
+/// Unlike [`try_or_handle_one`] this function can handle multiple different error types, but
+/// the error handler must be built using a [builder].
+///
+/// Chains implementing [`TryHandleMut`] can also be passed as `&mut H`, `&RefCell<H>`,
+/// `Rc<RefCell<H>>` or `&Rc<RefCell<H>>`, see [`AsHandlerChain`]. A shared chain is borrowed only
+/// while an error is offered to it or handled, and panics if one of its handlers re-enters a scope
+/// handled by the same chain.
+///
+/// [`try_or_handle_one`]: crate::try_or_handle_one
+/// [builder]: builder
+///
+/// # Examples
+///
 /// ```
 /// fn to_int(string: &str) -> xcept::Result<i32> {
 ///     if string.is_empty() {
@@ -173,25 +1249,640 @@ where
 /// let res = xcept::try_or_handle(|| to_int("abc"), handlers.clone());
 /// assert_eq!(res.unwrap(), -1);
 ///
-/// let res = xcept::try_or_handle(|| to_int(""), handlers.clone());
+/// let res = xcept::try_or_handle(|| to_int(""), handlers);
 /// assert_eq!(res.unwrap(), -2);
 /// ```
 #[inline]
-pub fn try_or_handle<F, H, T>(func: F, mut handlers: H) -> crate::Result<T>
+pub fn try_or_handle<F, H, T>(func: F, handlers: H) -> crate::Result<T>
     where
         F: FnOnce() -> crate::Result<T>,
-        H: TryHandle<Value = T> + crate::context::ErrorHandlingContext,
+        H: AsHandlerChain,
+        H::Chain: TryHandle<Value = T>,
+{
+    let mut handlers = handlers.into_handler_chain();
+    let res = {
+        let scope = core::pin::pin!(crate::context::ScopeNode::new(&mut handlers));
+        // Safety: the guard is dropped at the end of the block
+        let _guard = unsafe { scope.register() };
+        func()
+    };
+    if res.is_error() {
+        handle_error(res, handlers)
+    } else {
+        res
+    }
+}
+
+/// The error branch of the `try_or_handle` functions, kept out of line so the ok path is small.
+#[cold]
+#[inline(never)]
+fn handle_error<H: TryHandle>(res: crate::Result<H::Value>, handlers: H) -> crate::Result<H::Value> {
+    // Safety: only called with error results
+    match handlers.try_handle(ErrorId::from_raw(unsafe { res.error_id_unchecked() })) {
+        None => res,
+        Some(x) => x,
+    }
+}
+
+/// Summary of a scope run by [`try_or_handle_observed`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScopeOutcome {
+    /// The scope returned a value
+    Ok,
+    /// An error was handled by the scope's handlers
+    Handled {
+        /// The name of the type of the handled error
+        type_name: &'static str,
+        /// The ID of the handled error
+        error_id: ErrorId,
+    },
+    /// An error was not handled and is propagated to the caller
+    Propagated {
+        /// The ID of the propagated error
+        error_id: ErrorId,
+    },
+}
+
+/// Wraps a handling context, remembering the type of the error it accepted.
+struct Observed<H> {
+    handlers: H,
+    accepted: Option<(crate::ErrorId, &'static str)>,
+}
+
+impl<H: ErrorHandlingContext> ErrorHandlingContext for Observed<H> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        let result = self.handlers.try_set_error(error);
+        if matches!(result, TrySetErrorResult::NeedForget | TrySetErrorResult::NeedDrop) {
+            self.accepted = Some((error.id(), error.type_name()));
+        }
+        result
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        self.handlers.ext()
+    }
+}
+
+/// Same as [`try_or_handle`] but calls `on_exit` with a summary of the scope.
+///
+/// `on_exit` is called after the handler, if any, has run and before returning.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::multihandler::ScopeOutcome;
+///
+/// let handlers = xcept::builder(|_: &str| xcept::Result::new(0)).build();
+/// let mut outcome = None;
+/// let res = xcept::multihandler::try_or_handle_observed(
+///     || xcept::Result::new_error("failed"),
+///     handlers,
+///     |o| outcome = Some(o),
+/// );
+/// assert_eq!(res.unwrap(), 0);
+/// assert!(matches!(outcome, Some(ScopeOutcome::Handled { type_name: "&str", .. })));
+/// ```
+pub fn try_or_handle_observed<F, H, T, O>(func: F, handlers: H, on_exit: O) -> crate::Result<T>
+where
+    F: FnOnce() -> crate::Result<T>,
+    H: TryHandle<Value = T> + ErrorHandlingContext,
+    O: FnOnce(ScopeOutcome),
+{
+    let mut observed = Observed {
+        handlers,
+        accepted: None,
+    };
+    let res = {
+        let scope = core::pin::pin!(crate::context::ScopeNode::new(&mut observed));
+        // Safety: the guard is dropped at the end of the block
+        let _guard = unsafe { scope.register() };
+        func()
+    };
+
+    let error_id = match res.error_id() {
+        None => {
+            on_exit(ScopeOutcome::Ok);
+            return res;
+        }
+        Some(id) => id,
+    };
+    let accepted = observed.accepted;
+    match observed.handlers.try_handle(error_id) {
+        Some(handled) => {
+            let type_name = match accepted {
+                Some((id, type_name)) if id == error_id => type_name,
+                _ => "",
+            };
+            on_exit(ScopeOutcome::Handled { type_name, error_id });
+            handled
+        }
+        None => {
+            on_exit(ScopeOutcome::Propagated { error_id });
+            res
+        }
+    }
+}
+
+/// Only accepts errors raised within its own isolated operation.
+struct Isolated<H> {
+    handlers: H,
+    operation: u64,
+}
+
+impl<H: ErrorHandlingContext> ErrorHandlingContext for Isolated<H> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        if error.operation() == self.operation {
+            self.handlers.try_set_error(error)
+        } else {
+            TrySetErrorResult::NotHandled
+        }
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        self.handlers.ext()
+    }
+}
+
+/// Same as [`try_or_handle`] but only handles errors raised by `func` itself.
+///
+/// `func` runs as a new isolated operation, see [`enter_operation`]. Errors raised within
+/// another isolated operation, like a re-entrant callback that runs in its own isolated scope,
+/// are passed on to the enclosing scopes instead of being handled here. This includes errors
+/// raised in nested isolated scopes that those scopes don't handle.
+///
+/// [`enter_operation`]: crate::context::enter_operation
+///
+/// # Examples
+///
+/// ```
+/// let handlers = xcept::builder(|x: i32| xcept::Result::new(x)).build();
+/// let res = xcept::multihandler::try_or_handle_isolated(|| xcept::Result::new_error(1), handlers);
+/// assert_eq!(res.unwrap(), 1);
+/// ```
+pub fn try_or_handle_isolated<F, H, T>(func: F, handlers: H) -> crate::Result<T>
+where
+    F: FnOnce() -> crate::Result<T>,
+    H: TryHandle<Value = T> + ErrorHandlingContext,
 {
-    let mut scope = crate::context::ScopeNode::new(&mut handlers);
-    let guard = unsafe { crate::context::push_handling_scope(&mut scope) };
-    let res = func();
-    drop(guard);
+    let operation = crate::context::enter_operation();
+    let mut isolated = Isolated {
+        handlers,
+        operation: operation.token(),
+    };
+    let res = {
+        let scope = core::pin::pin!(crate::context::ScopeNode::new(&mut isolated));
+        // Safety: the guard is dropped at the end of the block
+        let _guard = unsafe { scope.register() };
+        func()
+    };
+    drop(operation);
     if res.is_error() {
-        match handlers.try_handle(unsafe { res.unchecked_error_id() }) {
-            None => res,
-            Some(x) => x,
+        handle_error(res, isolated.handlers)
+    } else {
+        res
+    }
+}
+
+/// Type erased handler entry used by [`HandlerArray`].
+///
+/// The function reads the error value from the pointer and runs the actual handler on it.
+pub type ErasedHandler<V> = fn(*mut ()) -> crate::Result<V>;
+
+/// A flat table of handlers for zero-sized error types.
+///
+/// Unlike a chain of [`Sequence`]s every entry has the same type, so the table is scanned with
+/// a simple loop and no code is generated per entry. Create it with [`handler_array!`].
+///
+/// [`handler_array!`]: crate::handler_array
+pub struct HandlerArray<V, const N: usize> {
+    entries: [(TypeId, ErasedHandler<V>); N],
+    captured: Option<(crate::ErrorId, usize)>,
+}
+
+impl<V, const N: usize> Clone for HandlerArray<V, N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V, const N: usize> Copy for HandlerArray<V, N> {}
+
+impl<V, const N: usize> ErrorHandlingContext for HandlerArray<V, N> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        match self.entries.iter().position(|(type_id, _)| *type_id == error.type_id()) {
+            Some(index) => {
+                self.captured = Some((error.id(), index));
+                // All error types are zero-sized, so the value is recreated when handled
+                TrySetErrorResult::NeedForget
+            }
+            None => TrySetErrorResult::NotHandled,
+        }
+    }
+}
+
+impl<V, const N: usize> TryHandle for HandlerArray<V, N> {
+    type Value = V;
+    fn try_handle(self, error_id: ErrorId) -> Option<crate::Result<V>> {
+        match self.captured {
+            Some((id, index)) if id == error_id => {
+                Some((self.entries[index].1)(core::ptr::NonNull::<()>::dangling().as_ptr()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Create a [`HandlerArray`] from type erased entries.
+///
+/// Prefer the safe [`handler_array!`] macro.
+///
+/// [`handler_array!`]: crate::handler_array
+///
+/// # Safety
+///
+///   * Every `TypeId` must identify a zero-sized type.
+///   * Every function must only read a value of the type identified by its `TypeId` from the
+///     pointer, using `read_unaligned`. The pointer passed is non-null but dangling.
+pub unsafe fn array<V, const N: usize>(entries: [(TypeId, ErasedHandler<V>); N]) -> HandlerArray<V, N> {
+    HandlerArray {
+        entries,
+        captured: None,
+    }
+}
+
+/// Build a [`HandlerArray`] for zero-sized error types.
+///
+/// Each entry is written as `ErrorType => handler` where `handler` is a function or
+/// non-capturing closure taking `ErrorType` and returning a `Result<V>`. Using an error type
+/// that is not zero-sized is a compile time error.
+///
+/// [`HandlerArray`]: crate::multihandler::HandlerArray
+///
+/// # Examples
+///
+/// ```
+/// struct NotFound;
+/// struct Timeout;
+///
+/// let handlers = xcept::handler_array![
+///     NotFound => |_| xcept::Result::new(404),
+///     Timeout => |_| xcept::Result::new(408),
+/// ];
+///
+/// let res = xcept::try_or_handle(|| xcept::Result::new_error(Timeout), handlers);
+/// assert_eq!(res.unwrap(), 408);
+/// ```
+#[macro_export]
+macro_rules! handler_array {
+    ($($error:ty => $handler:expr),+ $(,)?) => {{
+        // The handlers are expanded outside any `unsafe` block, so unsafe code in them still
+        // needs its own
+        let entries = [$(
+            (
+                ::core::any::TypeId::of::<$error>(),
+                (|ptr: *mut ()| {
+                    const { assert!(::core::mem::size_of::<$error>() == 0, "handler_array! only supports zero-sized error types") };
+                    // Safety: the entry is only called with a pointer for `$error`, which is zero-sized
+                    let error = unsafe { (ptr as *mut $error).read_unaligned() };
+                    ($handler)(error)
+                }) as $crate::multihandler::ErasedHandler<_>,
+            )
+        ),+];
+        // Safety: every entry is zero-sized and reads its own type
+        unsafe { $crate::multihandler::array(entries) }
+    }};
+}
+
+/// Add one handler for several error types to a [`Builder`], converting each to a common
+/// representation.
+///
+/// Written as `handle_any_of!(builder, [ErrorType => convert, ...], handler)`, where `convert` is
+/// a function or non-capturing closure converting `ErrorType`, and `handler` takes the converted
+/// value. See [`Builder::handle_either`] for two types.
+///
+/// [`Builder`]: crate::multihandler::Builder
+/// [`Builder::handle_either`]: crate::multihandler::Builder::handle_either
+///
+/// # Examples
+///
+/// ```
+/// struct V1(u32);
+/// struct V2(u64);
+/// struct V3 {
+///     code: u64,
+/// }
+///
+/// let handlers = xcept::handle_any_of!(
+///     xcept::builder(|_: std::io::Error| xcept::Result::new(0)),
+///     [V1 => |err| u64::from(err.0), V2 => |err| err.0, V3 => |err| err.code],
+///     |code: u64| xcept::Result::new(code),
+/// )
+/// .build();
+/// let res = xcept::try_or_handle(|| xcept::Result::new_error(V3 { code: 7 }), handlers);
+/// assert_eq!(res.unwrap(), 7);
+/// ```
+#[macro_export]
+macro_rules! handle_any_of {
+    ($builder:expr, [$($error:ty => $convert:expr),+ $(,)?], $handler:expr $(,)?) => {
+        $builder.handle_any_of($crate::handle_any_of!(@convert $($error => $convert),+), $handler)
+    };
+    (@convert $error:ty => $convert:expr $(, $rest:ty => $rest_convert:expr)*) => {
+        $crate::multihandler::Convert::<$error, _, _>::new(
+            $convert,
+            $crate::handle_any_of!(@convert $($rest => $rest_convert),*),
+        )
+    };
+    (@convert) => {
+        $crate::multihandler::NoConversion
+    };
+}
+
+/// Like [`TryHandle`], but the handlers also receive a reference to shared dependencies.
+pub trait TryHandleWithDeps<D>
+{
+    type Value;
+    fn try_handle_with_deps(self, error_id: ErrorId, deps: &D) -> Option<crate::Result<Self::Value>>;
+}
+
+impl<D, E, H, V> TryHandleWithDeps<D> for BoundHandler<E, H>
+where
+    H: FnOnce(E, &D) -> crate::Result<V> {
+    type Value = V;
+    fn try_handle_with_deps(self, error_id: ErrorId, deps: &D) -> Option<crate::Result<V>> {
+        match self.storage.into_inner() {
+            Some((id, err)) if id == error_id => Some((self.handler)(err, deps)),
+            _ => None,
+        }
+    }
+}
+
+impl<D, Left, Right> TryHandleWithDeps<D> for Sequence<Left, Right>
+where
+    Left: TryHandleWithDeps<D>,
+    Right: TryHandleWithDeps<D, Value = Left::Value>,
+{
+    type Value = Left::Value;
+    fn try_handle_with_deps(self, error_id: ErrorId, deps: &D) -> Option<crate::Result<Self::Value>> {
+        match self.left.try_handle_with_deps(error_id, deps) {
+            None => self.right.try_handle_with_deps(error_id, deps),
+            x => x
+        }
+    }
+}
+
+/// A chain of handlers sharing a set of dependencies.
+///
+/// Created by [`with_deps`], and used with [`try_or_handle_deps`].
+#[derive(Copy, Clone)]
+pub struct DepHandlers<D, T> {
+    deps: D,
+    handlers: T,
+}
+
+impl<D, T> DepHandlers<D, T> {
+    /// Access the dependencies.
+    pub fn deps(&self) -> &D {
+        &self.deps
+    }
+}
+
+impl<D, T> ErrorHandlingContext for DepHandlers<D, T>
+where
+    T: ErrorHandlingContext,
+{
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        self.handlers.try_set_error(error)
+    }
+}
+
+/// Builder for [`DepHandlers`], see [`with_deps`].
+#[derive(Copy, Clone)]
+pub struct DepsBuilder<D, T> {
+    deps: D,
+    handlers: T,
+}
+
+impl<D> DepsBuilder<D, ()> {
+    /// Add the first error handler to the builder.
+    ///
+    /// The handler receives the error and a reference to the dependencies.
+    #[track_caller]
+    pub fn handle<H, E, V>(self, handler: H) -> DepsBuilder<D, BoundHandler<E, H>>
+    where
+        H: FnOnce(E, &D) -> crate::Result<V>
+    {
+        DepsBuilder {
+            deps: self.deps,
+            handlers: BoundHandler::new(handler),
+        }
+    }
+}
+
+impl<D, T> DepsBuilder<D, T>
+where
+    T: TryHandleWithDeps<D> + ErrorHandlingContext
+{
+    /// Add a new error handler to the builder.
+    ///
+    /// The handler receives the error and a reference to the dependencies.
+    #[track_caller]
+    pub fn handle<H, E>(self, handler: H) -> DepsBuilder<D, Sequence<T, BoundHandler<E, H>>>
+    where
+        H: FnOnce(E, &D) -> crate::Result<T::Value>
+    {
+        DepsBuilder {
+            deps: self.deps,
+            handlers: Sequence {
+                left: self.handlers,
+                right: BoundHandler::new(handler),
+            },
         }
+    }
+
+    /// Convert the builder to a handling context for [`try_or_handle_deps`].
+    pub fn build(self) -> DepHandlers<D, T> {
+        DepHandlers {
+            deps: self.deps,
+            handlers: self.handlers,
+        }
+    }
+}
+
+/// Create a builder for handlers that share `deps`.
+///
+/// Each handler receives a reference to the dependencies when it runs, so handlers can be
+/// plain functions that are easy to test with mock dependencies.
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::RefCell;
+///
+/// struct Logger(RefCell<Vec<String>>);
+///
+/// let logger = Logger(RefCell::new(Vec::new()));
+/// let handlers = xcept::multihandler::with_deps(&logger)
+///     .handle(|err: i32, log: &&Logger| {
+///         log.0.borrow_mut().push(format!("error {err}"));
+///         xcept::Result::new(-1)
+///     })
+///     .build();
+///
+/// let res = xcept::multihandler::try_or_handle_deps(|| xcept::Result::new_error(5), handlers);
+/// assert_eq!(res.unwrap(), -1);
+/// assert_eq!(logger.0.borrow()[0], "error 5");
+/// ```
+pub fn with_deps<D>(deps: D) -> DepsBuilder<D, ()> {
+    DepsBuilder {
+        deps,
+        handlers: (),
+    }
+}
+
+/// Try to execute a function, and try to handle any error with handlers sharing dependencies.
+///
+/// See [`with_deps`] for how to create the handlers.
+#[inline]
+pub fn try_or_handle_deps<F, D, T, V>(func: F, mut handlers: DepHandlers<D, T>) -> crate::Result<V>
+    where
+        F: FnOnce() -> crate::Result<V>,
+        T: TryHandleWithDeps<D, Value = V> + ErrorHandlingContext,
+{
+    let res = {
+        let scope = core::pin::pin!(crate::context::ScopeNode::new(&mut handlers.handlers));
+        // Safety: the guard is dropped at the end of the block
+        let _guard = unsafe { scope.register() };
+        func()
+    };
+    if res.is_error() {
+        handle_error_with_deps(res, handlers)
     } else {
         res
     }
 }
+
+#[cold]
+#[inline(never)]
+fn handle_error_with_deps<D, T, V>(res: crate::Result<V>, handlers: DepHandlers<D, T>) -> crate::Result<V>
+    where
+        T: TryHandleWithDeps<D, Value = V>,
+{
+    // Safety: only called with error results
+    match handlers.handlers.try_handle_with_deps(ErrorId::from_raw(unsafe { res.error_id_unchecked() }), &handlers.deps) {
+        None => res,
+        Some(x) => x,
+    }
+}
+
+/// A reusable handler remembering the values produced for each distinct error value.
+///
+/// Created by [`dedup_by_value`]. A mutable reference is a handling context, so the same
+/// wrapper can be passed to [`try_or_handle`] repeatedly, or start a chain with [`compose`].
+pub struct DedupByValue<E, H, V> {
+    storage: SingleErrorStorage<E>,
+    handled: std::collections::HashMap<E, V>,
+    handler: H,
+}
+
+impl<E, H, V> DedupByValue<E, H, V> {
+    /// The number of distinct error values handled so far.
+    pub fn seen(&self) -> usize {
+        self.handled.len()
+    }
+
+    /// Forget all handled values, so the next error of each value runs the handler again.
+    pub fn reset(&mut self) {
+        self.handled.clear();
+    }
+}
+
+impl<E, H, V> DedupByValue<E, H, V>
+where
+    E: Eq + std::hash::Hash + Clone,
+    H: FnMut(E) -> crate::Result<V>,
+    V: Clone,
+{
+    fn handle(&mut self, err: E) -> crate::Result<V> {
+        if let Some(value) = self.handled.get(&err) {
+            return crate::Result::new(value.clone());
+        }
+        let res = (self.handler)(err.clone());
+        // Errors raised by the handler are not remembered, the next repeat tries again
+        if let Ok(value) = &res.value {
+            self.handled.insert(err, value.clone());
+        }
+        res
+    }
+}
+
+impl<E: crate::Error, H, V> ErrorHandlingContext for DedupByValue<E, H, V> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        self.storage.try_set_error(error)
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        self.storage.ext()
+    }
+}
+
+impl<E, H, V> TryHandleMut for DedupByValue<E, H, V>
+where
+    E: crate::Error + Eq + std::hash::Hash + Clone,
+    H: FnMut(E) -> crate::Result<V>,
+    V: Clone,
+{
+    type Value = V;
+    fn try_handle_mut(&mut self, error_id: ErrorId) -> Option<crate::Result<V>> {
+        match std::mem::take(&mut self.storage).into_inner() {
+            Some((id, err)) if id == error_id => Some(self.handle(err)),
+            _ => None,
+        }
+    }
+}
+
+opaque_flat_chain! {
+    impl ['a, H: TryHandleMut] for &'a mut H;
+    impl ['a, H: TryHandleMut] for &'a RefCell<H>;
+    impl [H: TryHandleMut] for Rc<RefCell<H>>;
+}
+
+/// Wrap a handler so it runs only once per distinct error value.
+///
+/// Repeats of an already handled value return a clone of the value produced the first time,
+/// without calling `handler` again.
+///
+/// # Examples
+///
+/// ```
+/// #[derive(Clone, PartialEq, Eq, Hash)]
+/// struct MissingKey(&'static str);
+///
+/// let mut logged = 0;
+/// let mut handler = xcept::multihandler::dedup_by_value(|MissingKey(key)| {
+///     logged += 1;
+///     xcept::Result::new(format!("{key} is missing"))
+/// });
+///
+/// for _ in 0..3 {
+///     let res = xcept::try_or_handle(|| xcept::Result::new_error(MissingKey("db.host")), &mut handler);
+///     assert_eq!(res.unwrap(), "db.host is missing");
+/// }
+/// assert_eq!(handler.seen(), 1);
+/// drop(handler);
+/// assert_eq!(logged, 1);
+/// ```
+pub fn dedup_by_value<E, H, V>(handler: H) -> DedupByValue<E, H, V>
+where
+    E: crate::Error + Eq + std::hash::Hash + Clone,
+    H: FnMut(E) -> crate::Result<V>,
+    V: Clone,
+{
+    DedupByValue {
+        storage: SingleErrorStorage::default(),
+        handled: std::collections::HashMap::new(),
+        handler,
+    }
+}
@@ -0,0 +1,73 @@
+//! Handling policies that can be replaced while the program is running.
+
+use std::sync::{Arc, RwLock};
+
+/// A shared value that can be replaced atomically, for instance on a configuration reload.
+///
+/// Readers get an `Arc` to the current value and keep using it even if a new value is stored
+/// meanwhile, so a handler chain built from one policy never sees a mix of old and new settings.
+///
+/// # Examples
+///
+/// ```
+/// let cell = xcept::policy::PolicyCell::new(3u32);
+/// let old = cell.load();
+/// cell.store(5);
+/// assert_eq!((*old, *cell.load()), (3, 5));
+/// ```
+pub struct PolicyCell<P> {
+    current: RwLock<Arc<P>>,
+}
+
+impl<P> PolicyCell<P> {
+    /// Create a cell holding `policy`.
+    pub fn new(policy: P) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(policy)),
+        }
+    }
+
+    /// The current policy.
+    pub fn load(&self) -> Arc<P> {
+        self.current.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Replace the current policy.
+    pub fn store(&self, policy: P) {
+        self.swap(policy);
+    }
+
+    /// Replace the current policy, returning the previous one.
+    pub fn swap(&self, policy: P) -> Arc<P> {
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+        std::mem::replace(&mut *current, Arc::new(policy))
+    }
+}
+
+impl<P: Default> Default for PolicyCell<P> {
+    fn default() -> Self {
+        Self::new(P::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PolicyCell;
+    use std::sync::Arc;
+
+    #[test]
+    fn readers_keep_their_snapshot() {
+        let cell = Arc::new(PolicyCell::new(vec![1]));
+        let snapshot = cell.load();
+
+        let writer = {
+            let cell = cell.clone();
+            std::thread::spawn(move || cell.swap(vec![2]))
+        };
+        let previous = writer.join().unwrap();
+
+        assert_eq!(*snapshot, [1]);
+        assert!(Arc::ptr_eq(&snapshot, &previous));
+        assert_eq!(*cell.load(), [2]);
+    }
+}
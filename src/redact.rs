@@ -0,0 +1,262 @@
+//! Redaction of error content before it is rendered.
+//!
+//! Error values can hold personal data. Anything rendering the *content* of an error, as opposed
+//! to its type name or ID, goes through [`summarize`] or one of its variants, which use the
+//! redactor registered for the type with [`register`]. Types without a redactor are rendered as
+//! their type name only.
+//!
+//! With the `strict-redaction` feature [`summarize_debug`] no longer falls back to the `Debug`
+//! output, so the content of a type can only be rendered by its registered redactor.
+
+use crate::context::ReportedError;
+use std::any::TypeId;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+type Redactor = Arc<dyn Fn(*const ()) -> RedactedSummary + Send + Sync>;
+
+static REDACTORS: RwLock<Vec<(TypeId, Redactor)>> = RwLock::new(Vec::new());
+/// Set by the first [`register`], so lookups skip the lock while no redactor exists.
+static ANY_REGISTERED: AtomicBool = AtomicBool::new(false);
+
+/// The rendered form of an error that is safe to show.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RedactedSummary {
+    type_name: &'static str,
+    content: Option<String>,
+}
+
+impl RedactedSummary {
+    /// A summary with the given content, returned by redactors.
+    ///
+    /// The type name is filled in when the summary is created by [`summarize`].
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            type_name: "",
+            content: Some(content.into()),
+        }
+    }
+
+    fn type_name_only(type_name: &'static str) -> Self {
+        Self {
+            type_name,
+            content: None,
+        }
+    }
+
+    /// The name of the type of the error.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// The redacted content, `None` if the type has no redactor.
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_deref()
+    }
+
+    #[cfg(feature = "log")]
+    pub(crate) fn into_content(self) -> Option<String> {
+        self.content
+    }
+}
+
+impl std::fmt::Display for RedactedSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.content {
+            Some(content) => write!(f, "{}: {}", self.type_name, content),
+            None => f.write_str(self.type_name),
+        }
+    }
+}
+
+/// Register the redactor used to render errors of type `E`, replacing any earlier one.
+///
+/// Redactors are shared by all threads. They must not register redactors themselves.
+///
+/// # Examples
+///
+/// ```
+/// use xcept::redact::RedactedSummary;
+///
+/// struct ValidationError {
+///     email: String,
+///     reason: &'static str,
+/// }
+///
+/// xcept::redact::register(|err: &ValidationError| RedactedSummary::new(err.reason));
+///
+/// let err = ValidationError { email: "someone@example.com".into(), reason: "email is taken" };
+/// let summary = xcept::redact::summarize_value(&err);
+/// assert_eq!(summary.content(), Some("email is taken"));
+/// # let _ = err.email;
+/// ```
+pub fn register<E: crate::Error>(redactor: impl Fn(&E) -> RedactedSummary + Send + Sync + 'static) {
+    // Safety: only called by `summarize_erased` with a pointer to a live `E`
+    let redactor: Redactor = Arc::new(move |value| redactor(unsafe { &*(value as *const E) }));
+    let mut redactors = REDACTORS.write().unwrap_or_else(|e| e.into_inner());
+    match redactors.iter_mut().find(|(type_id, _)| *type_id == TypeId::of::<E>()) {
+        Some(entry) => entry.1 = redactor,
+        None => redactors.push((TypeId::of::<E>(), redactor)),
+    }
+    ANY_REGISTERED.store(true, Ordering::Release);
+}
+
+/// Test if a redactor is registered for `E`.
+pub fn is_registered<E: crate::Error>() -> bool {
+    find(TypeId::of::<E>()).is_some()
+}
+
+fn find(type_id: TypeId) -> Option<Redactor> {
+    if !ANY_REGISTERED.load(Ordering::Acquire) {
+        return None;
+    }
+    let redactors = REDACTORS.read().unwrap_or_else(|e| e.into_inner());
+    redactors
+        .iter()
+        .find(|(id, _)| *id == type_id)
+        .map(|(_, redactor)| redactor.clone())
+}
+
+/// # Safety
+///
+/// `value` must point to a live value of the type `type_id`.
+pub(crate) unsafe fn summarize_erased(
+    type_id: TypeId,
+    type_name: &'static str,
+    value: *const (),
+) -> RedactedSummary {
+    match find(type_id) {
+        Some(redactor) => RedactedSummary {
+            type_name,
+            ..redactor(value)
+        },
+        None => RedactedSummary::type_name_only(type_name),
+    }
+}
+
+/// Render a reported error through its redactor, or as its type name only.
+///
+/// # Safety
+///
+/// The value of `error` must not have been moved out by a context yet.
+pub unsafe fn summarize(error: &ReportedError) -> RedactedSummary {
+    summarize_erased(error.type_id(), error.type_name(), error.value_ptr())
+}
+
+/// Render an error through its redactor, or as its type name only.
+pub fn summarize_value<E: crate::Error>(err: &E) -> RedactedSummary {
+    // Safety: `err` is a live `E`
+    unsafe { summarize_erased(TypeId::of::<E>(), std::any::type_name::<E>(), err as *const E as *const ()) }
+}
+
+/// Render an error through its redactor, or with its `Debug` output if it has none.
+///
+/// With the `strict-redaction` feature this is the same as [`summarize_value`].
+pub fn summarize_debug<E: crate::Error + std::fmt::Debug>(err: &E) -> RedactedSummary {
+    let summary = summarize_value(err);
+    if cfg!(feature = "strict-redaction") || summary.content.is_some() {
+        summary
+    } else {
+        RedactedSummary {
+            content: Some(format!("{err:?}")),
+            ..summary
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{register, summarize, summarize_debug, summarize_value, RedactedSummary};
+
+    #[derive(Debug)]
+    struct ValidationError {
+        email: &'static str,
+        field: &'static str,
+    }
+
+    #[derive(Debug)]
+    struct Unregistered {
+        email: &'static str,
+    }
+
+    #[test]
+    fn registered_and_unregistered() {
+        register(|err: &ValidationError| RedactedSummary::new(format!("invalid {}", err.field)));
+
+        let err = ValidationError {
+            email: "someone@example.com",
+            field: "email",
+        };
+        let summary = summarize_value(&err);
+        assert_eq!(summary.type_name(), std::any::type_name::<ValidationError>());
+        assert_eq!(summary.content(), Some("invalid email"));
+        assert!(!summary.to_string().contains(err.email));
+        assert_eq!(summarize_debug(&err), summary);
+
+        let err = Unregistered {
+            email: "someone@example.com",
+        };
+        let summary = summarize_value(&err);
+        assert_eq!(summary.content(), None);
+        assert_eq!(summary.to_string(), std::any::type_name::<Unregistered>());
+
+        let debug = summarize_debug(&err);
+        if cfg!(feature = "strict-redaction") {
+            assert_eq!(debug, summary);
+        } else {
+            assert!(debug.content().unwrap().contains(err.email));
+        }
+    }
+
+    #[test]
+    fn reported_error() {
+        struct Secret(&'static str);
+        register(|_: &Secret| RedactedSummary::new("<redacted>"));
+
+        let mut err = Secret("hunter2");
+        let reported = crate::context::ReportedError::new(crate::ErrorId::from_raw(1), &mut err);
+        // Safety: the value is still owned by `err`
+        let summary = unsafe { summarize(&reported) };
+        assert_eq!(summary.content(), Some("<redacted>"));
+        assert_eq!(err.0, "hunter2");
+    }
+
+    #[test]
+    fn abandoned_report() {
+        struct Login;
+        struct Plain;
+        register(|_: &Login| RedactedSummary::new("login failed"));
+
+        let reports = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let hook = {
+            let reports = reports.clone();
+            crate::context::on_abandoned(move |lost| reports.borrow_mut().push(lost.summary.clone().unwrap()))
+        };
+        let abandon = |login: bool| {
+            let res = std::panic::catch_unwind(|| {
+                let handlers = crate::builder(|_: Login| crate::Result::new(()))
+                    .handle(|_: Plain| crate::Result::new(()))
+                    .build();
+                crate::try_or_handle(
+                    || {
+                        let _: crate::Result<()> = if login {
+                            crate::Result::new_error(Login)
+                        } else {
+                            crate::Result::new_error(Plain)
+                        };
+                        std::panic::resume_unwind(Box::new("cancelled"))
+                    },
+                    handlers,
+                )
+            });
+            assert!(res.is_err());
+        };
+        abandon(true);
+        abandon(false);
+        drop(hook);
+
+        let reports = reports.borrow();
+        assert_eq!(reports[0].to_string(), format!("{}: login failed", std::any::type_name::<Login>()));
+        assert_eq!(reports[1].to_string(), std::any::type_name::<Plain>());
+    }
+}
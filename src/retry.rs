@@ -0,0 +1,363 @@
+//! Retrying work with exponential backoff.
+//!
+//! [`retry_backoff`] runs a closure until it succeeds, asking a classifier whether each error of
+//! a chosen type is worth retrying. The delay between attempts grows by [`BackoffPolicy`], and is
+//! spent in a [`Sleeper`], so tests can replace the clock. Jitter comes from a [`JitterSource`],
+//! which keeps the crate free of an RNG dependency.
+
+use crate::context::{ScopeNode, SingleErrorStorage};
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+/// How an error is retried, returned by the classifier of [`retry_backoff`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RetryClass {
+    /// Retry after the delay of the policy
+    Transient,
+    /// Raise the error to the enclosing scopes without retrying
+    Permanent,
+    /// Retry after the given delay instead of the delay of the policy
+    RateLimited(Duration),
+}
+
+/// The delays between attempts of [`retry_backoff`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackoffPolicy {
+    /// The delay before the first retry
+    pub initial_delay: Duration,
+    /// The largest delay, before jitter is applied
+    pub max_delay: Duration,
+    /// The factor the delay grows by after every retry
+    pub multiplier: f64,
+    /// The fraction, between 0 and 1, the delay is randomly moved by in either direction
+    pub jitter: f64,
+    /// Give up once the next retry would start after this much time, `None` to never give up
+    pub max_elapsed: Option<Duration>,
+}
+
+impl Default for BackoffPolicy {
+    /// 100 ms doubling up to 10 s, with 10 % jitter, giving up after a minute.
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.1,
+            max_elapsed: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+impl BackoffPolicy {
+    /// The delay before retry number `retry`, counting from 0.
+    ///
+    /// The result is within `jitter` of the un-jittered delay.
+    pub fn delay(&self, retry: u32, jitter: &mut impl JitterSource) -> Duration {
+        let exponent = i32::try_from(retry).unwrap_or(i32::MAX);
+        let base = (self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent)).min(self.max_delay.as_secs_f64());
+        let spread = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 - spread + 2.0 * spread * jitter.next_unit().clamp(0.0, 1.0);
+        Duration::from_secs_f64(base.max(0.0) * factor)
+    }
+}
+
+/// Where [`retry_backoff_with`] spends its delays and reads the time.
+pub trait Sleeper {
+    /// Block for `duration`.
+    fn sleep(&mut self, duration: Duration);
+
+    /// The current time.
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A [`Sleeper`] blocking the current thread.
+///
+/// With the `simulation` feature it uses the clock installed by
+/// [`sim::install_clock`](crate::sim::install_clock), if any.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ThreadSleeper;
+
+impl Sleeper for ThreadSleeper {
+    fn sleep(&mut self, duration: Duration) {
+        #[cfg(feature = "simulation")]
+        crate::sim::sleep(duration);
+        #[cfg(not(feature = "simulation"))]
+        std::thread::sleep(duration)
+    }
+
+    fn now(&self) -> Instant {
+        #[cfg(feature = "simulation")]
+        return crate::sim::now();
+        #[cfg(not(feature = "simulation"))]
+        Instant::now()
+    }
+}
+
+/// Random numbers for the jitter of a [`BackoffPolicy`].
+pub trait JitterSource {
+    /// A number in `0.0..=1.0`.
+    fn next_unit(&mut self) -> f64;
+}
+
+impl<F: FnMut() -> f64> JitterSource for F {
+    fn next_unit(&mut self) -> f64 {
+        self()
+    }
+}
+
+/// A xorshift generator, good enough for jitter but not for anything else.
+#[derive(Clone, Debug)]
+pub struct XorShift(u64);
+
+impl XorShift {
+    /// A generator starting from `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+}
+
+impl Default for XorShift {
+    /// A generator with a random seed.
+    fn default() -> Self {
+        Self::new(std::collections::hash_map::RandomState::new().build_hasher().finish())
+    }
+}
+
+impl JitterSource for XorShift {
+    fn next_unit(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Run `func` until it succeeds, retrying errors of type `E` the way `classify` decides.
+///
+/// Uses [`ThreadSleeper`] and a randomly seeded [`XorShift`], see [`retry_backoff_with`].
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use xcept::retry::{BackoffPolicy, RetryClass};
+///
+/// struct Busy;
+///
+/// let policy = BackoffPolicy { initial_delay: Duration::from_millis(1), ..Default::default() };
+/// let mut attempts = 0;
+/// let res = xcept::retry_backoff(
+///     &policy,
+///     || {
+///         attempts += 1;
+///         if attempts < 3 { xcept::Result::new_error(Busy) } else { xcept::Result::new(attempts) }
+///     },
+///     |_: &Busy| RetryClass::Transient,
+/// );
+/// assert_eq!(res.unwrap(), 3);
+/// ```
+pub fn retry_backoff<T, E: crate::Error>(
+    policy: &BackoffPolicy,
+    func: impl FnMut() -> crate::Result<T>,
+    classify: impl FnMut(&E) -> RetryClass,
+) -> crate::Result<T> {
+    retry_backoff_with(policy, &mut ThreadSleeper, &mut XorShift::default(), func, classify)
+}
+
+/// Run `func` until it succeeds, retrying errors of type `E` the way `classify` decides.
+///
+/// Errors of type `E` never reach the enclosing scopes while they are retried. Once `classify`
+/// returns [`RetryClass::Permanent`], or the next retry would start after
+/// [`BackoffPolicy::max_elapsed`], the last error is raised again, with a new ID. Errors of other
+/// types are not retried, they reach the enclosing scopes as usual and are returned at once.
+pub fn retry_backoff_with<T, E: crate::Error>(
+    policy: &BackoffPolicy,
+    sleeper: &mut impl Sleeper,
+    jitter: &mut impl JitterSource,
+    mut func: impl FnMut() -> crate::Result<T>,
+    mut classify: impl FnMut(&E) -> RetryClass,
+) -> crate::Result<T> {
+    let start = sleeper.now();
+    let mut retry = 0;
+    loop {
+        let mut storage = SingleErrorStorage::<E>::default();
+        let res = {
+            let scope = std::pin::pin!(ScopeNode::new(&mut storage));
+            // Safety: the guard is dropped at the end of the block
+            let _guard = unsafe { scope.register() };
+            func()
+        };
+        let err = match res.try_unwrap() {
+            Ok(value) => return crate::Result::new(value),
+            Err(id) => match storage.into_inner() {
+                Some((captured, err)) if captured == id => err,
+                _ => return crate::Result::new_with_error_id(id),
+            },
+        };
+        let delay = match classify(&err) {
+            RetryClass::Permanent => return crate::Result::new_error(err),
+            RetryClass::Transient => policy.delay(retry, jitter),
+            RetryClass::RateLimited(delay) => delay,
+        };
+        if let Some(max_elapsed) = policy.max_elapsed {
+            if sleeper.now().saturating_duration_since(start) + delay > max_elapsed {
+                return crate::Result::new_error(err);
+            }
+        }
+        sleeper.sleep(delay);
+        retry = retry.saturating_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{retry_backoff_with, BackoffPolicy, JitterSource, RetryClass, Sleeper, XorShift};
+    use std::time::{Duration, Instant};
+
+    struct MockClock {
+        now: Instant,
+        slept: Vec<Duration>,
+    }
+
+    impl MockClock {
+        fn new() -> Self {
+            Self {
+                now: Instant::now(),
+                slept: Vec::new(),
+            }
+        }
+    }
+
+    impl Sleeper for MockClock {
+        fn sleep(&mut self, duration: Duration) {
+            self.now += duration;
+            self.slept.push(duration);
+        }
+
+        fn now(&self) -> Instant {
+            self.now
+        }
+    }
+
+    #[derive(Debug)]
+    enum Remote {
+        Unavailable,
+        Denied,
+        Throttled(u64),
+    }
+
+    fn policy() -> BackoffPolicy {
+        BackoffPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.0,
+            max_elapsed: Some(Duration::from_secs(10)),
+        }
+    }
+
+    fn classify(err: &Remote) -> RetryClass {
+        match err {
+            Remote::Unavailable => RetryClass::Transient,
+            Remote::Denied => RetryClass::Permanent,
+            Remote::Throttled(secs) => RetryClass::RateLimited(Duration::from_secs(*secs)),
+        }
+    }
+
+    fn run(clock: &mut MockClock, mut errors: Vec<Remote>) -> (crate::Result<&'static str>, usize) {
+        let mut calls = 0;
+        let res = crate::try_or_handle_one(
+            || {
+                retry_backoff_with(
+                    &policy(),
+                    clock,
+                    &mut || 0.5,
+                    || {
+                        calls += 1;
+                        if errors.is_empty() {
+                            crate::Result::new("done")
+                        } else {
+                            crate::Result::new_error(errors.remove(0))
+                        }
+                    },
+                    classify,
+                )
+            },
+            |err: Remote| crate::Result::new(if matches!(err, Remote::Denied) { "denied" } else { "gave up" }),
+        );
+        (res, calls)
+    }
+
+    #[test]
+    fn transient_then_success() {
+        let mut clock = MockClock::new();
+        let (res, calls) = run(&mut clock, vec![Remote::Unavailable, Remote::Unavailable]);
+        assert_eq!(res.unwrap(), "done");
+        assert_eq!(calls, 3);
+        assert_eq!(clock.slept, [Duration::from_secs(1), Duration::from_secs(2)]);
+    }
+
+    #[test]
+    fn permanent_short_circuits() {
+        let mut clock = MockClock::new();
+        let (res, calls) = run(&mut clock, vec![Remote::Unavailable, Remote::Denied, Remote::Unavailable]);
+        assert_eq!(res.unwrap(), "denied");
+        assert_eq!(calls, 2);
+        assert_eq!(clock.slept, [Duration::from_secs(1)]);
+    }
+
+    #[test]
+    fn rate_limit_overrides_delay() {
+        let mut clock = MockClock::new();
+        let (res, _) = run(&mut clock, vec![Remote::Throttled(5), Remote::Unavailable]);
+        assert_eq!(res.unwrap(), "done");
+        // The rate limited retry still counts towards the backoff
+        assert_eq!(clock.slept, [Duration::from_secs(5), Duration::from_secs(2)]);
+    }
+
+    #[test]
+    fn max_elapsed_expires() {
+        let mut clock = MockClock::new();
+        let (res, calls) = run(&mut clock, (0..10).map(|_| Remote::Unavailable).collect());
+        assert_eq!(res.unwrap(), "gave up");
+        // 1 + 2 + 4 seconds slept, another 8 would end after the 10 allowed
+        assert_eq!(calls, 4);
+        assert_eq!(clock.slept.iter().sum::<Duration>(), Duration::from_secs(7));
+    }
+
+    #[test]
+    fn other_types_pass_through() {
+        let mut clock = MockClock::new();
+        let res = crate::try_or_handle_one(
+            || retry_backoff_with(&policy(), &mut clock, &mut || 0.5, || crate::Result::<()>::new_error(3u8), classify),
+            |_: u8| crate::Result::new(()),
+        );
+        assert!(res.is_ok());
+        assert!(clock.slept.is_empty());
+    }
+
+    #[test]
+    fn jitter_bounds() {
+        let policy = BackoffPolicy {
+            jitter: 0.25,
+            ..policy()
+        };
+        assert_eq!(policy.delay(2, &mut || 0.0), Duration::from_secs(3));
+        assert_eq!(policy.delay(2, &mut || 1.0), Duration::from_secs(5));
+        // Capped before jitter is applied
+        assert_eq!(policy.delay(40, &mut || 0.5), Duration::from_secs(30));
+
+        let mut rng = XorShift::new(7);
+        for retry in 0..8 {
+            let base = policy.delay(retry, &mut || 0.5).as_secs_f64();
+            for _ in 0..100 {
+                let unit = rng.next_unit();
+                assert!((0.0..=1.0).contains(&unit));
+                let delay = policy.delay(retry, &mut || unit).as_secs_f64();
+                assert!(delay >= base * 0.75 - 1e-9 && delay <= base * 1.25 + 1e-9);
+            }
+        }
+    }
+}
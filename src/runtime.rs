@@ -0,0 +1,110 @@
+//! Settings changed while the program is running.
+
+use std::any::TypeId;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+static SUPPRESSED: RwLock<Option<HashSet<TypeId>>> = RwLock::new(None);
+
+/// Incremented on every change of `SUPPRESSED`, 0 until the first change.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static CACHED_GENERATION: Cell<u64> = const { Cell::new(0) };
+    static CACHED: RefCell<HashSet<TypeId>> = RefCell::new(HashSet::new());
+}
+
+/// Stop, or resume, handling errors of type `E` in every handler on every thread.
+///
+/// While suppressed, handlers for `E` decline the error, so it escapes to outer scopes as if
+/// the handler wasn't there. Catch-all contexts still capture it.
+///
+/// This is meant as a kill switch, changing it is slow but checking it when raising is cheap.
+///
+/// # Examples
+///
+/// ```
+/// struct NoisyError;
+///
+/// xcept::runtime::suppress_handling::<NoisyError>(true);
+/// let res = xcept::try_or_handle_one(
+///     || xcept::Result::new_error(NoisyError),
+///     |_: NoisyError| xcept::Result::new(()),
+/// );
+/// assert!(res.is_error());
+/// xcept::runtime::suppress_handling::<NoisyError>(false);
+/// ```
+pub fn suppress_handling<E: crate::Error>(on: bool) {
+    let mut suppressed = SUPPRESSED.write().unwrap_or_else(|e| e.into_inner());
+    let suppressed = suppressed.get_or_insert_with(HashSet::new);
+    let changed = if on {
+        suppressed.insert(TypeId::of::<E>())
+    } else {
+        suppressed.remove(&TypeId::of::<E>())
+    };
+    if changed {
+        GENERATION.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// Test if handling of `type_id` is suppressed.
+///
+/// Usually a single atomic load, the set is only read again after it has changed.
+#[inline]
+pub(crate) fn is_suppressed(type_id: TypeId) -> bool {
+    let generation = GENERATION.load(Ordering::Acquire);
+    if generation == 0 {
+        return false;
+    }
+    is_suppressed_slow(type_id, generation)
+}
+
+#[cold]
+fn is_suppressed_slow(type_id: TypeId, generation: u64) -> bool {
+    CACHED
+        .try_with(|cached| {
+            if CACHED_GENERATION.get() != generation {
+                let suppressed = SUPPRESSED.read().unwrap_or_else(|e| e.into_inner());
+                *cached.borrow_mut() = suppressed.clone().unwrap_or_default();
+                CACHED_GENERATION.set(generation);
+            }
+            cached.borrow().contains(&type_id)
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    struct NoisyError;
+
+    #[test]
+    fn toggle_between_raises() {
+        let run = || {
+            crate::try_or_handle_one(
+                || {
+                    crate::try_or_handle_one(
+                        || crate::Result::new_error(NoisyError),
+                        |_: NoisyError| crate::Result::new("inner"),
+                    )
+                },
+                |_: NoisyError| crate::Result::new("outer"),
+            )
+        };
+        assert_eq!(run().unwrap(), "inner");
+
+        super::suppress_handling::<NoisyError>(true);
+        let mut ctx = crate::context::CatchAllContext { inner: None };
+        let res = {
+            let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut ctx));
+            let _guard = unsafe { scope.register() };
+            run()
+        };
+        assert!(res.is_error());
+        assert_eq!(ctx.inner.map(|(id, _)| id), res.error_id());
+
+        super::suppress_handling::<NoisyError>(false);
+        assert_eq!(run().unwrap(), "inner");
+    }
+}
@@ -0,0 +1,160 @@
+//! Closures bundled with their handlers.
+//!
+//! [`scoped`] pairs a closure with a handler chain without running it, so the pair can be stored,
+//! queued or passed to other code as a single value. The scope only exists while the [`Scoped`]
+//! value is being called, on the calling thread.
+//!
+//! Stable Rust doesn't allow implementing `FnOnce` for a type, so a [`Scoped`] is run with
+//! [`Scoped::call`], or converted to a closure with [`Scoped::into_fn`] where an `FnOnce` is
+//! needed, for instance to store it as `Box<dyn FnOnce() -> xcept::Result<T>>`.
+
+use crate::context::ErrorHandlingContext;
+use crate::multihandler::TryHandle;
+
+/// The body of a [`Scoped`], either a closure returning a [`Result`] or another [`Scoped`].
+///
+/// [`Result`]: crate::Result
+pub trait ScopeBody {
+    /// The value type of the returned `Result`.
+    type Value;
+
+    /// Run the body.
+    fn run(self) -> crate::Result<Self::Value>;
+}
+
+impl<T, F: FnOnce() -> crate::Result<T>> ScopeBody for F {
+    type Value = T;
+
+    #[inline]
+    fn run(self) -> crate::Result<T> {
+        self()
+    }
+}
+
+/// A closure bundled with its handlers, created by [`scoped`].
+#[derive(Copy, Clone)]
+pub struct Scoped<H, F> {
+    handlers: H,
+    func: F,
+}
+
+impl<H, F> Scoped<H, F>
+where
+    F: ScopeBody,
+    H: TryHandle<Value = F::Value> + ErrorHandlingContext,
+{
+    /// Run the closure with the handlers installed, like [`try_or_handle`].
+    ///
+    /// [`try_or_handle`]: crate::try_or_handle
+    #[inline]
+    pub fn call(self) -> crate::Result<F::Value> {
+        let func = self.func;
+        crate::try_or_handle(move || func.run(), self.handlers)
+    }
+
+    /// Convert to a closure running [`Scoped::call`].
+    pub fn into_fn(self) -> impl FnOnce() -> crate::Result<F::Value> {
+        move || self.call()
+    }
+}
+
+impl<H, F, T> Scoped<H, F>
+where
+    F: FnMut() -> crate::Result<T>,
+    H: TryHandle<Value = T> + ErrorHandlingContext + Clone,
+{
+    /// Run the closure with a copy of the handlers, keeping the `Scoped` for another call.
+    pub fn call_mut(&mut self) -> crate::Result<T> {
+        crate::try_or_handle(&mut self.func, self.handlers.clone())
+    }
+}
+
+impl<H, F> ScopeBody for Scoped<H, F>
+where
+    F: ScopeBody,
+    H: TryHandle<Value = F::Value> + ErrorHandlingContext,
+{
+    type Value = F::Value;
+
+    #[inline]
+    fn run(self) -> crate::Result<F::Value> {
+        self.call()
+    }
+}
+
+/// Bundle `func` with `handlers` without running it.
+///
+/// `func` is either a closure, or another [`Scoped`] which then runs nested inside the scope of
+/// `handlers`. Neither the handlers nor the closure need to be `Send`, but if they are the
+/// `Scoped` can be sent to and called on another thread.
+///
+/// # Examples
+///
+/// ```
+/// let inner = xcept::scoped(xcept::builder(|x: i32| xcept::Result::new(x)).build(), || {
+///     xcept::Result::new_error("not an i32")
+/// });
+/// let outer = xcept::scoped(xcept::builder(|s: &str| xcept::Result::new(s.len() as i32)).build(), inner);
+///
+/// let queued: Vec<Box<dyn FnOnce() -> xcept::Result<i32>>> = vec![Box::new(outer.into_fn())];
+/// for task in queued {
+///     assert_eq!(task().unwrap(), 10);
+/// }
+/// ```
+pub fn scoped<H, F>(handlers: H, func: F) -> Scoped<H, F>
+where
+    F: ScopeBody,
+    H: TryHandle<Value = F::Value> + ErrorHandlingContext,
+{
+    Scoped { handlers, func }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scoped;
+
+    #[test]
+    fn queued_scopes() {
+        let mut queue: Vec<Box<dyn FnOnce() -> crate::Result<String>>> = Vec::new();
+        for i in 0..3u8 {
+            let handlers = crate::builder(|x: u8| crate::Result::new(format!("u8 {x}")))
+                .handle(|s: &str| crate::Result::new(format!("str {s}")))
+                .build();
+            let task = scoped(handlers, move || match i {
+                0 => crate::Result::new_error(i),
+                1 => crate::Result::new_error("one"),
+                _ => crate::Result::new("ok".to_string()),
+            });
+            queue.push(Box::new(task.into_fn()));
+        }
+
+        let results: Vec<_> = queue.into_iter().map(|task| task().unwrap()).collect();
+        assert_eq!(results, ["u8 0", "str one", "ok"]);
+    }
+
+    #[test]
+    fn nested_scopes() {
+        let inner = scoped(crate::builder(|x: u8| crate::Result::new(x as u32)).build(), || {
+            crate::Result::new_error(9u16)
+        });
+        let outer = scoped(crate::builder(|x: u16| crate::Result::new(x as u32 * 2)).build(), inner);
+        assert_eq!(outer.call().unwrap(), 18);
+
+        let inner = scoped(crate::builder(|x: u8| crate::Result::new(x as u32)).build(), || {
+            crate::Result::new_error(3u8)
+        });
+        let outer = scoped(crate::builder(|x: u16| crate::Result::new(x as u32 * 2)).build(), inner);
+        assert_eq!(outer.call().unwrap(), 3);
+    }
+
+    #[test]
+    fn call_mut_reuses() {
+        let mut calls = 0u8;
+        let mut task = scoped(crate::builder(|x: u8| crate::Result::new(x)).build(), || {
+            calls += 1;
+            crate::Result::new_error(calls)
+        });
+        assert_eq!(task.call_mut().unwrap(), 1);
+        assert_eq!(task.call_mut().unwrap(), 2);
+    }
+}
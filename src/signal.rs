@@ -0,0 +1,403 @@
+//! Deferred raising for code reachable from signal handlers.
+//!
+//! Raising an error is **not** async-signal-safe:
+//!
+//!   * The handling scopes live in a `thread_local!`, and accessing thread locals is not
+//!     async-signal-safe on all platforms.
+//!   * The scopes are guarded by a `RefCell`, so a signal arriving while the interrupted code is
+//!     raising panics on the re-entrant borrow.
+//!   * Handling contexts run arbitrary code, including allocation and dropping of values.
+//!
+//! Code that may run inside a signal handler should instead use [`deferred_raise`], which moves
+//! the error into a pre-allocated lock-free ring without touching any of the above. The normal
+//! program flow then calls [`drain_raises`] to convert the deferred errors into real raises.
+//!
+//! The ring is a bounded multi-producer multi-consumer queue: every slot carries a sequence
+//! number, and writers and readers claim slots with a compare-and-swap, so several signal
+//! handlers may defer errors and several threads may drain them at the same time.
+
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicPtr, AtomicU8, AtomicUsize, Ordering};
+
+/// The maximum size, in bytes, of an error deferred with [`deferred_raise`].
+pub const MAX_ERROR_SIZE: usize = 48;
+
+/// The maximum alignment of an error deferred with [`deferred_raise`].
+pub const MAX_ERROR_ALIGN: usize = 16;
+
+/// What [`deferred_raise`] does when the ring is full.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Discard the error that didn't fit and count it, see [`dropped_count`]. The error is
+    /// forgotten, its destructor isn't run inside the signal handler.
+    DropNewest,
+    /// Abort the process.
+    Abort,
+}
+
+/// Storage for a deferred error, aligned for any error of at most [`MAX_ERROR_ALIGN`].
+#[derive(Copy, Clone)]
+#[repr(C, align(16))]
+struct Payload([MaybeUninit<u8>; MAX_ERROR_SIZE]);
+
+const _: () = assert!(std::mem::align_of::<Payload>() == MAX_ERROR_ALIGN);
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+struct Slot {
+    // First, so it keeps the alignment of the payload inside the slot too
+    data: Payload,
+    raise: unsafe fn(*mut u8) -> (crate::ErrorId, &'static crate::ErrorType),
+}
+
+struct Cell {
+    /// The position the slot can be written at next if equal to it, or read at next if one
+    /// more than it
+    sequence: AtomicUsize,
+    slot: UnsafeCell<MaybeUninit<Slot>>,
+}
+
+struct Ring {
+    cells: Box<[Cell]>,
+}
+
+impl Ring {
+    /// Claim the cell at the position `position` holds for writing (`lap` 0) or reading
+    /// (`lap` 1), or `None` if the ring is full or empty.
+    fn claim(&self, position: &AtomicUsize, lap: usize) -> Option<(usize, &Cell)> {
+        let mut pos = position.load(Ordering::Relaxed);
+        loop {
+            let cell = &self.cells[pos % self.cells.len()];
+            let sequence = cell.sequence.load(Ordering::Acquire);
+            match (sequence as isize).wrapping_sub(pos.wrapping_add(lap) as isize) {
+                0 => match position.compare_exchange_weak(pos, pos.wrapping_add(1), Ordering::Relaxed, Ordering::Relaxed) {
+                    Ok(_) => return Some((pos, cell)),
+                    Err(current) => pos = current,
+                },
+                // Not yet released by the previous lap, or not yet written
+                diff if diff < 0 => return None,
+                // Another thread claimed the position first
+                _ => pos = position.load(Ordering::Relaxed),
+            }
+        }
+    }
+}
+
+static RING: AtomicPtr<Ring> = AtomicPtr::new(std::ptr::null_mut());
+static HEAD: AtomicUsize = AtomicUsize::new(0);
+static TAIL: AtomicUsize = AtomicUsize::new(0);
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+static POLICY: AtomicU8 = AtomicU8::new(OverflowPolicy::DropNewest as u8);
+static HANDLER_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Set by [`raise_next`] when it raised an error, for [`drain_raises`]
+    static RAISED: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Allocate the ring used by [`deferred_raise`].
+///
+/// This must be called from normal program flow before any signal handler defers an error.
+/// Returns `false` if the ring was already initialized, in which case the existing ring is kept.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn init(capacity: usize) -> bool {
+    assert!(capacity > 0, "the deferred raise ring needs a non-zero capacity");
+    let cells = (0..capacity)
+        .map(|index| Cell {
+            sequence: AtomicUsize::new(index),
+            slot: UnsafeCell::new(MaybeUninit::uninit()),
+        })
+        .collect();
+    let ring = Box::into_raw(Box::new(Ring { cells }));
+    match RING.compare_exchange(std::ptr::null_mut(), ring, Ordering::AcqRel, Ordering::Acquire) {
+        Ok(_) => true,
+        Err(_) => {
+            // Safety: `ring` was never published
+            drop(unsafe { Box::from_raw(ring) });
+            false
+        }
+    }
+}
+
+/// Select what happens when the ring is full.
+pub fn set_overflow_policy(policy: OverflowPolicy) {
+    POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+/// The number of errors dropped because the ring was full or not initialized.
+pub fn dropped_count() -> usize {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// Marks the current code as running inside a signal handler, see [`enter_handler`].
+pub struct HandlerGuard(());
+
+impl Drop for HandlerGuard {
+    fn drop(&mut self) {
+        HANDLER_DEPTH.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// Mark the start of a signal handler.
+///
+/// While the returned guard is alive [`is_raise_safe`] returns `false`.
+pub fn enter_handler() -> HandlerGuard {
+    HANDLER_DEPTH.fetch_add(1, Ordering::Acquire);
+    HandlerGuard(())
+}
+
+/// Test if errors can be raised normally.
+///
+/// This is conservative: it returns `false` while any thread is inside a signal handler marked
+/// with [`enter_handler`].
+pub fn is_raise_safe() -> bool {
+    HANDLER_DEPTH.load(Ordering::Acquire) == 0
+}
+
+unsafe fn raise_impl<E: crate::Error>(data: *mut u8) -> (crate::ErrorId, &'static crate::ErrorType) {
+    let id = crate::context::push_error((data as *mut E).read());
+    (id, &<E as crate::HasErrorType>::ERROR_TYPE)
+}
+
+fn overflow<E>(err: E) -> bool {
+    DROPPED.fetch_add(1, Ordering::Relaxed);
+    if POLICY.load(Ordering::Relaxed) == OverflowPolicy::Abort as u8 {
+        std::process::abort();
+    }
+    // Running the destructor isn't signal-safe
+    std::mem::forget(err);
+    false
+}
+
+/// Defer raising an error until [`drain_raises`] is called.
+///
+/// This only uses atomics and copies the value into a pre-allocated slot, so it is safe to call
+/// from a signal handler. Returns `false` if the error didn't fit in the ring, which is handled
+/// according to the [`OverflowPolicy`].
+///
+/// The error type must be at most [`MAX_ERROR_SIZE`] bytes and [`MAX_ERROR_ALIGN`] aligned,
+/// this is checked at compile time.
+pub fn deferred_raise<E: crate::Error + Send>(err: E) -> bool {
+    const {
+        assert!(std::mem::size_of::<E>() <= MAX_ERROR_SIZE, "error type too large to defer");
+        assert!(std::mem::align_of::<E>() <= MAX_ERROR_ALIGN, "error type too aligned to defer");
+    }
+
+    let ring = RING.load(Ordering::Acquire);
+    if ring.is_null() {
+        return overflow(err);
+    }
+    // Safety: a published ring is never freed
+    let ring = unsafe { &*ring };
+    let Some((pos, cell)) = ring.claim(&TAIL, 0) else {
+        return overflow(err);
+    };
+
+    // Safety: the cell is claimed for writing at `pos` by this call only, and isn't read until
+    // its sequence is published. The payload is aligned for `E`, checked above.
+    unsafe {
+        let slot = (*cell.slot.get()).as_mut_ptr();
+        (std::ptr::addr_of_mut!((*slot).data) as *mut E).write(err);
+        std::ptr::addr_of_mut!((*slot).raise).write(raise_impl::<E>);
+    }
+    cell.sequence.store(pos.wrapping_add(1), Ordering::Release);
+    true
+}
+
+/// The number of deferred errors waiting to be raised.
+///
+/// Includes errors still being written by a signal handler.
+pub fn pending() -> usize {
+    // The head never passes the tail, so read it first
+    let head = HEAD.load(Ordering::Acquire);
+    TAIL.load(Ordering::Acquire).wrapping_sub(head)
+}
+
+/// Raise the oldest deferred error in the current handling scopes.
+///
+/// Returns `Ok(())` if there was nothing to raise.
+pub fn raise_next() -> crate::Result<()> {
+    let ring = RING.load(Ordering::Acquire);
+    if ring.is_null() {
+        return crate::Result::new(());
+    }
+    // Safety: a published ring is never freed
+    let ring = unsafe { &*ring };
+    let Some((pos, cell)) = ring.claim(&HEAD, 1) else {
+        return crate::Result::new(());
+    };
+
+    // Safety: the cell was written and published at `pos`, and is claimed for reading by this
+    // call only
+    let mut slot = unsafe { (*cell.slot.get()).assume_init_read() };
+    cell.sequence.store(pos.wrapping_add(ring.cells.len()), Ordering::Release);
+    // Safety: the slot holds a value of the type `raise` was instantiated with, in its aligned
+    // payload
+    let (id, error_type) = unsafe { (slot.raise)(slot.data.0.as_mut_ptr() as *mut u8) };
+    RAISED.with(|raised| raised.set(true));
+    crate::Result::new_with_error_type(id, error_type)
+}
+
+/// Convert all deferred errors into real raises.
+///
+/// `each` is called once per deferred error with a function raising it, so that each raise can
+/// be wrapped in its own handling scope, until the function finds no published error. Errors
+/// still being written by a signal handler are left for a later call. Returns the number of
+/// errors raised.
+///
+/// # Examples
+///
+/// ```
+/// xcept::signal::init(8);
+/// xcept::signal::deferred_raise(10);
+///
+/// let mut handled = Vec::new();
+/// xcept::signal::drain_raises(|raise| {
+///     let _ = xcept::try_or_handle_one(raise, |x: i32| xcept::Result::new(handled.push(x)));
+/// });
+/// assert_eq!(handled, vec![10]);
+/// ```
+pub fn drain_raises<F>(mut each: F) -> usize
+where
+    F: FnMut(fn() -> crate::Result<()>),
+{
+    let mut count = 0;
+    loop {
+        RAISED.with(|raised| raised.set(false));
+        each(raise_next);
+        if !RAISED.with(std::cell::Cell::get) {
+            return count;
+        }
+        count += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    struct SignalError(i32);
+
+    #[repr(align(16))]
+    struct Aligned(u128, u8);
+
+    #[cfg(unix)]
+    extern "C" fn on_sigusr1(_: libc::c_int) {
+        let _guard = super::enter_handler();
+        assert!(!super::is_raise_safe());
+        super::deferred_raise(SignalError(1));
+    }
+
+    // All ring tests share the process wide ring, so they run as one test
+    #[test]
+    fn deferred_raises() {
+        assert!(super::init(4));
+        assert!(!super::init(100));
+        assert!(super::is_raise_safe());
+
+        #[cfg(unix)]
+        unsafe {
+            let handler = on_sigusr1 as extern "C" fn(libc::c_int);
+            libc::signal(libc::SIGUSR1, handler as libc::sighandler_t);
+            assert_eq!(libc::raise(libc::SIGUSR1), 0);
+            libc::signal(libc::SIGUSR1, libc::SIG_DFL);
+        }
+        #[cfg(not(unix))]
+        super::deferred_raise(SignalError(1));
+
+        assert!(super::is_raise_safe());
+        for i in 2..=4 {
+            assert!(super::deferred_raise(SignalError(i)));
+        }
+        assert!(!super::deferred_raise(SignalError(5)));
+        assert_eq!(super::dropped_count(), 1);
+        assert_eq!(super::pending(), 4);
+
+        let mut handled = Vec::new();
+        let count = super::drain_raises(|raise| {
+            let res = crate::try_or_handle_one(raise, |e: SignalError| {
+                handled.push(e.0);
+                crate::Result::new(())
+            });
+            assert!(res.is_ok());
+        });
+
+        assert_eq!(count, 4);
+        assert_eq!(handled, vec![1, 2, 3, 4]);
+        assert_eq!(super::pending(), 0);
+        assert!(super::raise_next().is_ok());
+
+        // The raise keeps the type of the deferred error
+        assert!(super::deferred_raise(SignalError(5)));
+        let res = crate::try_or_handle_one(
+            || {
+                let res = super::raise_next();
+                assert!(res.error_is::<SignalError>());
+                res
+            },
+            |e: SignalError| {
+                assert_eq!(e.0, 5);
+                crate::Result::new(())
+            },
+        );
+        assert!(res.is_ok());
+
+        // A slot claimed by a producer but not yet published isn't counted as raised
+        let handle = |raise: fn() -> crate::Result<()>| {
+            let res = crate::try_or_handle_one(raise, |_: SignalError| crate::Result::new(()));
+            assert!(res.is_ok());
+        };
+        let ring = unsafe { &*super::RING.load(std::sync::atomic::Ordering::Acquire) };
+        let (pos, cell) = ring.claim(&super::TAIL, 0).unwrap();
+        assert_eq!(super::drain_raises(handle), 0);
+        assert_eq!(super::pending(), 1);
+        unsafe {
+            let slot = (*cell.slot.get()).as_mut_ptr();
+            (std::ptr::addr_of_mut!((*slot).data) as *mut SignalError).write(SignalError(6));
+            std::ptr::addr_of_mut!((*slot).raise).write(super::raise_impl::<SignalError>);
+        }
+        cell.sequence.store(pos.wrapping_add(1), std::sync::atomic::Ordering::Release);
+        assert_eq!(super::drain_raises(handle), 1);
+        assert_eq!(super::pending(), 0);
+
+        // Errors of the maximum alignment are read back from an aligned slot
+        for i in 0..6u8 {
+            assert!(super::deferred_raise(Aligned(u128::MAX - u128::from(i), i)));
+            let res = crate::try_or_handle_one(super::raise_next, |e: Aligned| {
+                assert_eq!(&e as *const Aligned as usize % 16, 0);
+                assert_eq!((e.0, e.1), (u128::MAX - u128::from(i), i));
+                crate::Result::new(())
+            });
+            assert!(res.is_ok());
+        }
+
+        // Several producers and consumers at once
+        let raised = std::sync::atomic::AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for i in 0..200 {
+                        while !super::deferred_raise(SignalError(i)) {
+                            std::thread::yield_now();
+                        }
+                    }
+                });
+            }
+            for _ in 0..2 {
+                scope.spawn(|| {
+                    while raised.load(std::sync::atomic::Ordering::Relaxed) < 800 {
+                        let res = crate::try_or_handle_one(super::raise_next, |_: SignalError| {
+                            raised.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            crate::Result::new(())
+                        });
+                        assert!(res.is_ok());
+                    }
+                });
+            }
+        });
+        assert_eq!(raised.into_inner(), 800);
+        assert_eq!(super::pending(), 0);
+    }
+}
@@ -0,0 +1,264 @@
+//! Control over the sources of nondeterminism, for deterministic simulation testing.
+//!
+//! Enabled by the `simulation` feature. Replaying an execution requires the error IDs and every
+//! time reading to be the same on each run, regardless of thread scheduling. Both are per thread
+//! and can be replaced:
+//!
+//!   * [`install_id_source`] replaces the counter the raise path takes error IDs from,
+//!   * [`install_clock`] replaces the clock, which everything in the crate reading the time or
+//!     sleeping goes through, like [`retry::ThreadSleeper`](crate::retry::ThreadSleeper).
+//!
+//! Nothing else captured by the crate depends on scheduling: raise locations are fixed at compile
+//! time, and no backtraces are captured.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+type IdSource = Box<dyn FnMut() -> u64>;
+
+thread_local! {
+    static ID_SOURCE: RefCell<Option<IdSource>> = const { RefCell::new(None) };
+    static CLOCK: RefCell<Option<Rc<dyn Clock>>> = const { RefCell::new(None) };
+    static BASE: Instant = Instant::now();
+}
+
+/// A source of time, see [`install_clock`].
+pub trait Clock {
+    /// The time passed since an arbitrary, fixed, point.
+    fn elapsed(&self) -> Duration;
+
+    /// Let `duration` pass.
+    fn sleep(&self, duration: Duration);
+}
+
+/// A [`Clock`] that only moves when slept on or advanced.
+///
+/// Clones share the same time.
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    elapsed: Rc<std::cell::Cell<Duration>>,
+}
+
+impl ManualClock {
+    /// A clock at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.set(self.elapsed.get() + duration);
+    }
+}
+
+impl Clock for ManualClock {
+    fn elapsed(&self) -> Duration {
+        self.elapsed.get()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
+
+/// Restores what was installed before when dropped, returned by [`install_id_source`] and
+/// [`install_clock`].
+#[must_use = "the previous state is restored when this is dropped"]
+pub struct Installed {
+    restore: Option<Box<dyn FnOnce()>>,
+}
+
+impl Drop for Installed {
+    fn drop(&mut self) {
+        if let Some(restore) = self.restore.take() {
+            restore();
+        }
+    }
+}
+
+/// Take the error IDs of this thread from `source` instead of the counter.
+///
+/// Each value is the counter part of an ID, see [`counter_of`](crate::context::counter_of), the
+/// epoch is filled in as usual. Values wrap modulo `2^32 - 1` like the counter does, so the
+/// counter of [`TAKEN_ID`](crate::context::TAKEN_ID) is never produced. `source` is
+/// called before the raise takes its ID, so it may use the handling scopes, like
+/// [`current_epoch`](crate::context::current_epoch), and raise errors itself, which take their IDs
+/// from the counter.
+///
+/// # Examples
+///
+/// ```
+/// let mut next = 0;
+/// let _ids = xcept::sim::install_id_source(move || {
+///     next += 1;
+///     next
+/// });
+/// let id = |res: xcept::Result<()>| xcept::context::counter_of(res.error_id().unwrap().to_raw());
+/// assert_eq!(id(xcept::Result::new_error(1u8)), 1);
+/// assert_eq!(id(xcept::Result::new_error(2u8)), 2);
+/// ```
+pub fn install_id_source(source: impl FnMut() -> u64 + 'static) -> Installed {
+    let previous = ID_SOURCE.with(|ids| ids.borrow_mut().replace(Box::new(source)));
+    Installed {
+        restore: Some(Box::new(move || {
+            let _ = ID_SOURCE.try_with(|ids| *ids.borrow_mut() = previous);
+        })),
+    }
+}
+
+/// The next ID of the installed source, `None` to use the counter.
+pub(crate) fn next_id() -> Option<u64> {
+    ID_SOURCE
+        .try_with(|ids| ids.try_borrow_mut().ok()?.as_mut().map(|source| source()))
+        .ok()
+        .flatten()
+}
+
+/// Use `clock` for every time reading and sleep of this thread.
+pub fn install_clock(clock: impl Clock + 'static) -> Installed {
+    let previous = CLOCK.with(|current| current.borrow_mut().replace(Rc::new(clock)));
+    Installed {
+        restore: Some(Box::new(move || {
+            let _ = CLOCK.try_with(|current| *current.borrow_mut() = previous);
+        })),
+    }
+}
+
+fn installed_clock() -> Option<Rc<dyn Clock>> {
+    CLOCK.try_with(|clock| clock.borrow().clone()).ok().flatten()
+}
+
+/// The current time, read from the installed clock if there is one.
+///
+/// With a clock installed this is a fixed instant plus [`Clock::elapsed`], so only differences
+/// between readings are deterministic.
+pub fn now() -> Instant {
+    match installed_clock() {
+        Some(clock) => BASE.with(|base| *base) + clock.elapsed(),
+        None => Instant::now(),
+    }
+}
+
+/// Block for `duration`, on the installed clock if there is one.
+pub fn sleep(duration: Duration) {
+    match installed_clock() {
+        Some(clock) => clock.sleep(duration),
+        None => std::thread::sleep(duration),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{install_clock, install_id_source, ManualClock};
+    use std::time::Duration;
+
+    fn sequence() -> Vec<u64> {
+        let mut ids = Vec::new();
+        let res = crate::try_or_handle_one(
+            || {
+                let res: crate::Result<()> = crate::Result::new_error(1u8);
+                ids.push(res.error_id().unwrap().to_raw());
+                let res: crate::Result<()> = crate::Result::new_error("unhandled");
+                ids.push(res.error_id().unwrap().to_raw());
+                crate::Result::<()>::new_error(2u8)
+            },
+            |_: u8| crate::Result::new(()),
+        );
+        assert!(res.is_ok());
+        ids
+    }
+
+    #[test]
+    fn replayed_ids() {
+        let run = || {
+            let mut next = 1000;
+            let _ids = install_id_source(move || {
+                next += 3;
+                next
+            });
+            #[cfg(feature = "diagnostics")]
+            crate::debug::clear();
+            let epoch = crate::context::current_epoch();
+            let ids = sequence();
+            assert!(ids.iter().all(|id| crate::context::epoch_of(*id) == epoch));
+            let ids: Vec<_> = ids.iter().map(|id| crate::context::counter_of(*id)).collect();
+            #[cfg(feature = "diagnostics")]
+            let records: Vec<_> = crate::debug::recent_raises()
+                .into_iter()
+                .map(|record| crate::debug::RaiseRecord {
+                    id: crate::ErrorId::from_raw(u64::from(crate::context::counter_of(record.id.to_raw()))),
+                    ..record
+                })
+                .collect();
+            #[cfg(not(feature = "diagnostics"))]
+            let records = ();
+            (ids, records)
+        };
+        let first = run();
+        // Unrelated raises in between don't shift the replay
+        let _: crate::Result<()> = crate::Result::new_error(0u8);
+        assert_eq!(first, run());
+        assert_eq!(first.0, [1003, 1006]);
+
+        // The counter is used again once uninstalled
+        let after = crate::Result::<()>::new_error(0u8).error_id().unwrap().to_raw();
+        assert_ne!(crate::context::counter_of(after), 1009);
+    }
+
+    #[test]
+    fn source_uses_scopes() {
+        let seen = std::rc::Rc::new(std::cell::Cell::new(None));
+        let _ids = install_id_source({
+            let seen = seen.clone();
+            let mut next = 0;
+            move || {
+                let accepts = crate::context::scopes_accept::<u8>();
+                let inner: crate::Result<()> = crate::Result::new_error(1u16);
+                seen.set(Some((crate::context::current_epoch(), accepts, inner.error_id())));
+                next += 1;
+                next
+            }
+        });
+        let res = crate::try_or_handle_one(|| crate::Result::<()>::new_error(2u8), |_: u8| crate::Result::new(()));
+        assert!(res.is_ok());
+        let (epoch, accepts, inner) = seen.get().unwrap();
+        assert_eq!(accepts, Some(true));
+        // The raise inside the source takes its ID from the counter
+        assert_eq!(crate::context::epoch_of(inner.unwrap().to_raw()), epoch);
+        let id = crate::Result::<()>::new_error(3u8).error_id().unwrap().to_raw();
+        assert_eq!(crate::context::counter_of(id), 2);
+    }
+
+    #[test]
+    fn manual_clock() {
+        use crate::retry::{retry_backoff, BackoffPolicy, RetryClass};
+
+        let clock = ManualClock::new();
+        let installed = install_clock(clock.clone());
+        let start = super::now();
+        let policy = BackoffPolicy {
+            initial_delay: Duration::from_secs(10),
+            jitter: 0.0,
+            max_elapsed: None,
+            ..BackoffPolicy::default()
+        };
+        let mut attempts = 0;
+        let res = retry_backoff(
+            &policy,
+            || {
+                attempts += 1;
+                if attempts < 3 {
+                    crate::Result::new_error(())
+                } else {
+                    crate::Result::new(attempts)
+                }
+            },
+            |_: &()| RetryClass::Transient,
+        );
+        assert_eq!(res.unwrap(), 3);
+        assert_eq!(clock.elapsed.get(), Duration::from_secs(20));
+        assert_eq!(super::now() - start, Duration::from_secs(20));
+        drop(installed);
+    }
+}
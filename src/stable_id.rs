@@ -0,0 +1,318 @@
+//! Error identities that are stable across compilation units.
+//!
+//! Handlers match errors by `TypeId`, which can differ between a host and a plugin loaded as a
+//! `cdylib`, even for what is the same type in the source. Types implementing [`StableError`]
+//! carry a user supplied UUID instead. They are raised with
+//! [`Result::new_error_stable`](crate::Result::new_error_stable), which raises their portable
+//! representation tagged with the UUID, and are handled with
+//! [`Builder::handle_stable`](crate::multihandler::Builder::handle_stable), which matches the UUID
+//! only.
+
+use crate::context::{Acceptance, ContextExt, ErrorHandlingContext, ReportedError, ReportedErrorDescriptor, TrySetErrorResult};
+use crate::multihandler::TryHandle;
+use std::alloc::Layout;
+use std::any::TypeId;
+
+#[cfg(feature = "macros")]
+pub use xcept_macros::StableError;
+
+/// A stable identity of an error type, derived from a UUID.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StableTypeId(u128);
+
+impl StableTypeId {
+    /// The identity with the UUID `uuid`.
+    pub const fn from_u128(uuid: u128) -> Self {
+        Self(uuid)
+    }
+
+    /// Parse a UUID of 32 hex digits, optionally separated by dashes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `uuid` isn't a valid UUID, at compile time when used in a constant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xcept::stable_id::StableTypeId;
+    ///
+    /// const ID: StableTypeId = StableTypeId::parse("6f1c2d3e-0000-4a5b-8c9d-0123456789ab");
+    /// assert_eq!(ID.as_u128(), 0x6f1c2d3e_0000_4a5b_8c9d_0123456789ab);
+    /// ```
+    pub const fn parse(uuid: &str) -> Self {
+        let bytes = uuid.as_bytes();
+        let mut value: u128 = 0;
+        let mut digits = 0;
+        let mut index = 0;
+        while index < bytes.len() {
+            let digit = match bytes[index] {
+                b'-' => {
+                    index += 1;
+                    continue;
+                }
+                byte @ b'0'..=b'9' => byte - b'0',
+                byte @ b'a'..=b'f' => byte - b'a' + 10,
+                byte @ b'A'..=b'F' => byte - b'A' + 10,
+                _ => panic!("invalid character in UUID"),
+            };
+            assert!(digits < 32, "UUID has more than 32 hex digits");
+            value = (value << 4) | digit as u128;
+            digits += 1;
+            index += 1;
+        }
+        assert!(digits == 32, "UUID has less than 32 hex digits");
+        Self(value)
+    }
+
+    /// The UUID as a number.
+    pub const fn as_u128(self) -> u128 {
+        self.0
+    }
+}
+
+/// An error type identified by a UUID instead of its `TypeId`.
+///
+/// Usually derived with `#[derive(StableError)]` and `#[xcept(uuid = "...")]` (requires the
+/// `macros` feature). The type itself is then its portable representation, unless another one is
+/// named with `#[xcept(portable = Type)]`, converted with `From` in both directions.
+///
+/// # Safety
+///
+/// Every type sharing a [`STABLE_ID`](StableError::STABLE_ID), in any compilation unit, must have
+/// a `Portable` type with the same layout and meaning, such as the same `#[repr(C)]` struct of
+/// plain data. Handlers read the value raised by one side as the portable type of the other.
+pub unsafe trait StableError: crate::Error {
+    /// The stable identity of the type.
+    const STABLE_ID: StableTypeId;
+
+    /// The representation the error crosses compilation units in.
+    type Portable: crate::Error;
+
+    /// Convert to the portable representation before raising.
+    fn into_portable(self) -> Self::Portable;
+
+    /// Convert from the portable representation before handling.
+    fn from_portable(portable: Self::Portable) -> Self;
+}
+
+/// A handler chain entry matching errors by their [`StableTypeId`].
+///
+/// Created by [`Builder::handle_stable`](crate::multihandler::Builder::handle_stable).
+pub struct StableHandler<E: StableError, H> {
+    inner: Option<(crate::ErrorId, E::Portable)>,
+    handler: H,
+}
+
+impl<E: StableError, H> StableHandler<E, H> {
+    pub fn new(handler: H) -> Self {
+        Self { inner: None, handler }
+    }
+}
+
+impl<E: StableError, H: Clone> Clone for StableHandler<E, H>
+where
+    E::Portable: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            handler: self.handler.clone(),
+        }
+    }
+}
+
+impl<E, H, V> TryHandle for StableHandler<E, H>
+where
+    E: StableError,
+    H: FnOnce(E) -> crate::Result<V>,
+{
+    type Value = V;
+    fn try_handle(self, error_id: crate::ErrorId) -> Option<crate::Result<V>> {
+        match self.inner {
+            Some((id, portable)) if id == error_id => Some((self.handler)(E::from_portable(portable))),
+            _ => None,
+        }
+    }
+}
+
+impl<E: StableError, H> ErrorHandlingContext for StableHandler<E, H> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        // The layout check only catches the most obvious contract violations of `StableError`
+        if error.stable_id() == Some(E::STABLE_ID) && error.layout() == Layout::new::<E::Portable>() {
+            self.inner = Some((error.id(), (error.value_ptr() as *mut E::Portable).read()));
+            TrySetErrorResult::NeedForget
+        } else {
+            TrySetErrorResult::NotHandled
+        }
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        Some(self)
+    }
+}
+
+impl<E: StableError, H> ContextExt for StableHandler<E, H> {
+    fn accepts(&mut self, type_id: TypeId) -> Option<bool> {
+        // Stable errors are told apart by their stable ID, which a `TypeId` doesn't reveal
+        let _ = type_id;
+        None
+    }
+
+    fn would_accept(&mut self, descriptor: &ReportedErrorDescriptor) -> Acceptance {
+        match (descriptor.stable_id(), descriptor.type_id()) {
+            (Some(stable_id), _) if stable_id == E::STABLE_ID => Acceptance::Accept,
+            (Some(_), _) => Acceptance::Reject,
+            // Described by its `TypeId`, so not raised as a stable error
+            (None, Some(_)) => Acceptance::Reject,
+            (None, None) => Acceptance::Indeterminate,
+        }
+    }
+
+    fn has_pending(&mut self) -> bool {
+        self.inner.is_some()
+    }
+
+    fn pending_type_name(&mut self) -> Option<&'static str> {
+        self.inner.as_ref().map(|_| std::any::type_name::<E>())
+    }
+
+    fn debug_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+crate::multihandler::opaque_flat_chain! {
+    impl [E: StableError, H] for StableHandler<E, H>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StableError, StableTypeId};
+
+    const RECORD_ID: StableTypeId = StableTypeId::parse("a3c9e1f0-5b7d-4e2a-9f10-2b3c4d5e6f70");
+
+    #[repr(C)]
+    struct PluginPortable {
+        code: u32,
+        retryable: bool,
+    }
+
+    /// The error as compiled into the plugin
+    struct PluginError {
+        code: u32,
+        retryable: bool,
+    }
+
+    unsafe impl StableError for PluginError {
+        const STABLE_ID: StableTypeId = RECORD_ID;
+        type Portable = PluginPortable;
+
+        fn into_portable(self) -> PluginPortable {
+            PluginPortable {
+                code: self.code,
+                retryable: self.retryable,
+            }
+        }
+
+        fn from_portable(portable: PluginPortable) -> Self {
+            Self {
+                code: portable.code,
+                retryable: portable.retryable,
+            }
+        }
+    }
+
+    #[repr(C)]
+    struct HostPortable {
+        code: u32,
+        retryable: bool,
+    }
+
+    /// The same error as compiled into the host, with a different `TypeId`
+    #[derive(Debug, PartialEq)]
+    struct HostError {
+        code: u32,
+        retryable: bool,
+    }
+
+    unsafe impl StableError for HostError {
+        const STABLE_ID: StableTypeId = RECORD_ID;
+        type Portable = HostPortable;
+
+        fn into_portable(self) -> HostPortable {
+            HostPortable {
+                code: self.code,
+                retryable: self.retryable,
+            }
+        }
+
+        fn from_portable(portable: HostPortable) -> Self {
+            Self {
+                code: portable.code,
+                retryable: portable.retryable,
+            }
+        }
+    }
+
+    fn plugin_call(fail: bool) -> crate::Result<u32> {
+        if fail {
+            crate::Result::new_error_stable(PluginError {
+                code: 503,
+                retryable: true,
+            })
+        } else {
+            crate::Result::new(1)
+        }
+    }
+
+    #[test]
+    fn match_by_uuid() {
+        assert_ne!(std::any::TypeId::of::<PluginPortable>(), std::any::TypeId::of::<HostPortable>());
+
+        let handlers = || {
+            crate::builder(|_: PluginError| crate::Result::new(0))
+                .handle_stable(|err: HostError| {
+                    assert!(err.retryable);
+                    crate::Result::new(err.code)
+                })
+                .build()
+        };
+        assert_eq!(crate::try_or_handle(|| plugin_call(true), handlers()).unwrap(), 503);
+        assert_eq!(crate::try_or_handle(|| plugin_call(false), handlers()).unwrap(), 1);
+    }
+
+    #[test]
+    fn typed_handlers_miss_stable_raises() {
+        // A handler for the host's portable type never sees the plugin's
+        let res = crate::try_or_handle_one(|| plugin_call(true), |_: HostPortable| crate::Result::new(0));
+        assert!(res.is_error());
+
+        // Neither does a stable handler with a different UUID
+        struct Other;
+        unsafe impl StableError for Other {
+            const STABLE_ID: StableTypeId = StableTypeId::from_u128(1);
+            type Portable = HostPortable;
+            fn into_portable(self) -> HostPortable {
+                HostPortable {
+                    code: 0,
+                    retryable: false,
+                }
+            }
+            fn from_portable(_: HostPortable) -> Self {
+                Other
+            }
+        }
+        let handlers = crate::builder(|_: u8| crate::Result::new(0))
+            .handle_stable(|_: Other| crate::Result::new(0))
+            .build();
+        assert!(crate::try_or_handle(|| plugin_call(true), handlers).is_error());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid character")]
+    fn invalid_uuid() {
+        StableTypeId::parse("not-a-uuid");
+    }
+}
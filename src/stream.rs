@@ -0,0 +1,207 @@
+//! Handling the errors of each item of a [`Stream`], enabled by the `futures` feature.
+//!
+//! [`HandledStreamExt::handle_items`] wraps a stream of [`Result`](crate::Result)s. Every poll of
+//! the inner stream runs in a scope accepting one error type, so errors raised while computing an
+//! item are captured too, and each captured error is turned into an [`ItemAction`] by a handler.
+
+use crate::context::{ScopeNode, SingleErrorStorage};
+use futures_core::Stream;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// What [`HandledStream`] does with an item that failed with a handled error.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ItemAction<T> {
+    /// Yield this value in place of the error
+    Substitute(T),
+    /// Drop the item and poll the inner stream for the next one
+    Skip,
+    /// End the stream
+    End,
+}
+
+/// Stream returned by [`HandledStreamExt::handle_items`].
+#[must_use = "streams do nothing unless polled"]
+pub struct HandledStream<S, E, F> {
+    inner: S,
+    handler: F,
+    done: bool,
+    _error: PhantomData<fn(E)>,
+}
+
+impl<S, T, E, F> Stream for HandledStream<S, E, F>
+where
+    S: Stream<Item = crate::Result<T>>,
+    E: crate::Error,
+    F: FnMut(E) -> ItemAction<T>,
+{
+    type Item = crate::Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // Safety: `inner` is structurally pinned, it is never moved out of `self`
+        let this = unsafe { self.get_unchecked_mut() };
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+            let mut storage = SingleErrorStorage::<E>::default();
+            let polled = {
+                let scope = std::pin::pin!(ScopeNode::new(&mut storage));
+                // Safety: the guard is dropped at the end of the block
+                let _guard = unsafe { scope.register() };
+                unsafe { Pin::new_unchecked(&mut this.inner) }.poll_next(cx)
+            };
+
+            let item = match polled {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => {
+                    this.done = true;
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(Some(item)) => item,
+            };
+            let Some(id) = item.error_id() else {
+                return Poll::Ready(Some(item));
+            };
+            let handler = &mut this.handler;
+            let action = storage.try_handle(crate::Result::new_with_error_id(id), |err| crate::Result::new(handler(err)));
+            if let Some(id) = action.error_id() {
+                // Not an `E`, already delivered to the scopes active where the stream is polled
+                this.done = true;
+                return Poll::Ready(Some(crate::Result::new_with_error_id(id)));
+            }
+            match action.unwrap() {
+                ItemAction::Substitute(value) => return Poll::Ready(Some(crate::Result::new(value))),
+                ItemAction::Skip => {}
+                ItemAction::End => this.done = true,
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            (0, Some(0))
+        } else {
+            (0, self.inner.size_hint().1)
+        }
+    }
+}
+
+/// Adds [`handle_items`](HandledStreamExt::handle_items) to streams of
+/// [`Result`](crate::Result)s.
+pub trait HandledStreamExt<T>: Stream<Item = crate::Result<T>> + Sized {
+    /// Handle errors of type `E` raised while computing each item.
+    ///
+    /// Errors of other types reach the scopes active where the stream is polled, and end the
+    /// stream after it yields the failed item.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::StreamExt;
+    /// use xcept::stream::{HandledStreamExt, ItemAction};
+    ///
+    /// let parsed = futures::stream::iter(["1", "x", "3"])
+    ///     .map(|s| xcept::Result::from(s.parse::<u32>()))
+    ///     .handle_items(|_: std::num::ParseIntError| ItemAction::Substitute(0));
+    /// let values: Vec<_> = futures::executor::block_on(parsed.map(|res| res.unwrap()).collect());
+    /// assert_eq!(values, [1, 0, 3]);
+    /// ```
+    fn handle_items<E, F>(self, handler: F) -> HandledStream<Self, E, F>
+    where
+        E: crate::Error,
+        F: FnMut(E) -> ItemAction<T>,
+    {
+        HandledStream {
+            inner: self,
+            handler,
+            done: false,
+            _error: PhantomData,
+        }
+    }
+}
+
+impl<T, S: Stream<Item = crate::Result<T>>> HandledStreamExt<T> for S {}
+
+#[cfg(test)]
+mod tests {
+    use super::{HandledStreamExt, ItemAction};
+    use futures::executor::block_on;
+    use futures::StreamExt;
+    use futures_core::Stream;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    #[derive(Debug, PartialEq)]
+    struct Corrupt(u32);
+    struct Fatal;
+
+    fn read(i: u32) -> crate::Result<u32> {
+        if i % 2 == 1 {
+            crate::Result::new_error(Corrupt(i))
+        } else {
+            crate::Result::new(i)
+        }
+    }
+
+    fn collect<S: Stream<Item = crate::Result<u32>>>(stream: S) -> Vec<crate::Result<u32>> {
+        block_on(stream.collect())
+    }
+
+    #[test]
+    fn substitute_and_skip() {
+        let items = collect(futures::stream::iter(0..5).map(read).handle_items(|err: Corrupt| ItemAction::Substitute(err.0 * 10)));
+        assert_eq!(items, [0, 10, 2, 30, 4].map(crate::Result::new));
+
+        let items = collect(futures::stream::iter(0..5).map(read).handle_items(|_: Corrupt| ItemAction::Skip));
+        assert_eq!(items, [0, 2, 4].map(crate::Result::new));
+    }
+
+    /// Raises from its own computation instead of from an inner stream.
+    struct Sensor {
+        next: u32,
+    }
+
+    impl Stream for Sensor {
+        type Item = crate::Result<u32>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.next += 1;
+            Poll::Ready(match self.next {
+                1 | 2 => Some(crate::Result::new(self.next)),
+                3 => Some(read(3).map(|v| v + 100)),
+                4 => Some(crate::Result::new_error(Fatal)),
+                _ => None,
+            })
+        }
+    }
+
+    #[test]
+    fn end() {
+        let mut seen = Vec::new();
+        let items = collect(Sensor { next: 0 }.handle_items(|err: Corrupt| {
+            seen.push(err.0);
+            ItemAction::End
+        }));
+        assert_eq!(items, [1, 2].map(crate::Result::new));
+        assert_eq!(seen, [3]);
+    }
+
+    #[test]
+    fn unhandled_reaches_consumer() {
+        let mut fatal = None;
+        let res = crate::try_or_handle_one(
+            || {
+                let items = collect(Sensor { next: 0 }.handle_items(|_: Corrupt| ItemAction::Skip));
+                assert_eq!(items.len(), 3, "{items:?}");
+                assert_eq!(items[..2], [1, 2].map(crate::Result::new));
+                fatal = items[2].error_id();
+                items[2].clone()
+            },
+            |_: Fatal| crate::Result::new(0),
+        );
+        assert_eq!(res.unwrap(), 0);
+        assert!(fatal.is_some());
+    }
+}
@@ -0,0 +1,325 @@
+//! Deliver errors from worker threads to a supervising thread.
+//!
+//! Handling scopes are thread local, so an error raised on a worker thread can never reach a
+//! scope on another thread. A [`Mailbox`] bridges this: workers send errors through a
+//! [`MailboxHandle`], and the supervisor runs its handlers over them using the
+//! [`MailboxScope`].
+
+use crate::context::{ContextExt, ErrorHandlingContext, ReportedError, TrySetErrorResult};
+use crate::multihandler::TryHandle;
+use std::any::TypeId;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+struct Inbox<E> {
+    errors: VecDeque<E>,
+    senders: usize,
+}
+
+struct Shared<E> {
+    inbox: Mutex<Inbox<E>>,
+    available: Condvar,
+}
+
+impl<E> Shared<E> {
+    fn inbox(&self) -> std::sync::MutexGuard<'_, Inbox<E>> {
+        self.inbox.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, err: E) {
+        self.inbox().errors.push_back(err);
+        self.available.notify_all();
+    }
+}
+
+/// Creates connected [`MailboxScope`] and [`MailboxHandle`] pairs.
+pub struct Mailbox<E>(PhantomData<E>);
+
+impl<E: crate::Error + Send> Mailbox<E> {
+    /// Create a new mailbox.
+    ///
+    /// The scope is kept by the supervising thread, the handle is cloned and sent to workers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let (mut scope, handle) = xcept::supervisor::Mailbox::<String>::new();
+    ///
+    /// std::thread::spawn(move || handle.raise("worker failed".to_string()))
+    ///     .join()
+    ///     .unwrap();
+    ///
+    /// let handlers = xcept::builder(|err: String| xcept::Result::new(err.len())).build();
+    /// let mut results = scope.process(handlers);
+    /// assert_eq!(results.pop().unwrap().unwrap(), 13);
+    /// ```
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> (MailboxScope<E>, MailboxHandle<E>) {
+        let shared = Arc::new(Shared {
+            inbox: Mutex::new(Inbox {
+                errors: VecDeque::new(),
+                senders: 1,
+            }),
+            available: Condvar::new(),
+        });
+        (
+            MailboxScope {
+                shared: shared.clone(),
+            },
+            MailboxHandle { shared },
+        )
+    }
+}
+
+/// The worker side of a [`Mailbox`].
+pub struct MailboxHandle<E> {
+    shared: Arc<Shared<E>>,
+}
+
+impl<E> Clone for MailboxHandle<E> {
+    fn clone(&self) -> Self {
+        self.shared.inbox().senders += 1;
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<E> Drop for MailboxHandle<E> {
+    fn drop(&mut self) {
+        self.shared.inbox().senders -= 1;
+        self.shared.available.notify_all();
+    }
+}
+
+impl<E: crate::Error + Send> MailboxHandle<E> {
+    /// Send an error to the supervisor, waking it if it is waiting.
+    ///
+    /// This bypasses the handling scopes of the calling thread.
+    pub fn raise(&self, err: E) {
+        self.shared.push(err);
+    }
+}
+
+/// The supervisor side of a [`Mailbox`].
+///
+/// While registered with [`MailboxScope::run`] it also captures errors of type `E` raised on the
+/// supervising thread itself.
+pub struct MailboxScope<E> {
+    shared: Arc<Shared<E>>,
+}
+
+impl<E: crate::Error + Send> MailboxScope<E> {
+    /// The number of errors waiting to be processed.
+    pub fn len(&self) -> usize {
+        self.shared.inbox().errors.len()
+    }
+
+    /// The number of [`MailboxHandle`]s still alive.
+    pub fn senders(&self) -> usize {
+        self.shared.inbox().senders
+    }
+
+    /// Test if there are no errors waiting to be processed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Block until at least one error is waiting to be processed.
+    pub fn wait(&self) {
+        let inbox = self.shared.inbox();
+        let _inbox = self
+            .shared
+            .available
+            .wait_while(inbox, |inbox| inbox.errors.is_empty())
+            .unwrap_or_else(|e| e.into_inner());
+    }
+
+    /// Block until at least one error is waiting, or `timeout` has passed.
+    ///
+    /// Returns `true` if there are errors waiting to be processed.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let inbox = self.shared.inbox();
+        let (inbox, _) = self
+            .shared
+            .available
+            .wait_timeout_while(inbox, timeout, |inbox| inbox.errors.is_empty())
+            .unwrap_or_else(|e| e.into_inner());
+        !inbox.errors.is_empty()
+    }
+
+    /// Execute `func` with this mailbox registered as a handling scope on the current thread.
+    pub fn run<T>(&mut self, func: impl FnOnce() -> T) -> T {
+        let scope = std::pin::pin!(crate::context::ScopeNode::new(self));
+        // Safety: the guard is dropped on return, also when unwinding
+        let _guard = unsafe { scope.register() };
+        func()
+    }
+
+    /// Run a handler chain over all errors currently waiting.
+    ///
+    /// Each error is raised into its own scope using a copy of `handlers`, and the results are
+    /// returned in the order the errors were received. Errors the handlers decline are passed on
+    /// to the scopes active on the current thread.
+    pub fn process<H, V>(&mut self, handlers: H) -> Vec<crate::Result<V>>
+    where
+        H: TryHandle<Value = V> + ErrorHandlingContext + Clone,
+    {
+        let queued = std::mem::take(&mut self.shared.inbox().errors);
+        queued
+            .into_iter()
+            .map(|err| crate::try_or_handle(|| crate::Result::new_error(err), handlers.clone()))
+            .collect()
+    }
+
+    /// Process errors until every [`MailboxHandle`] has been dropped and no errors are waiting.
+    ///
+    /// Used for a graceful shutdown: stop the workers, then drain what they sent before exiting.
+    /// See [`MailboxScope::process`] for how the errors are handled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let (mut scope, handle) = xcept::supervisor::Mailbox::<u32>::new();
+    /// let worker = std::thread::spawn(move || {
+    ///     handle.raise(1);
+    ///     handle.raise(2);
+    /// });
+    ///
+    /// let handlers = xcept::builder(|x: u32| xcept::Result::new(x)).build();
+    /// let handled: u32 = scope.drain(handlers).into_iter().map(|r| r.unwrap()).sum();
+    /// assert_eq!(handled, 3);
+    /// worker.join().unwrap();
+    /// ```
+    pub fn drain<H, V>(&mut self, handlers: H) -> Vec<crate::Result<V>>
+    where
+        H: TryHandle<Value = V> + ErrorHandlingContext + Clone,
+    {
+        let mut results = Vec::new();
+        loop {
+            let open = {
+                let inbox = self.shared.inbox();
+                let inbox = self
+                    .shared
+                    .available
+                    .wait_while(inbox, |inbox| inbox.errors.is_empty() && inbox.senders > 0)
+                    .unwrap_or_else(|e| e.into_inner());
+                inbox.senders > 0
+            };
+            results.extend(self.process(handlers.clone()));
+            if !open && self.is_empty() {
+                return results;
+            }
+        }
+    }
+}
+
+impl<E: crate::Error + Send> ErrorHandlingContext for MailboxScope<E> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        if TypeId::of::<E>() == error.type_id() {
+            self.shared.push((error.value_ptr() as *mut E).read());
+            TrySetErrorResult::NeedForget
+        } else {
+            TrySetErrorResult::NotHandled
+        }
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        Some(self)
+    }
+}
+
+// Not `has_pending`, the errors outlive the scope in the inbox, so they are never abandoned
+impl<E: crate::Error + Send> ContextExt for MailboxScope<E> {
+    fn accepts(&mut self, type_id: TypeId) -> Option<bool> {
+        Some(type_id == TypeId::of::<E>())
+    }
+
+    fn pending_count(&mut self) -> usize {
+        self.len()
+    }
+
+    fn debug_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mailbox;
+
+    #[derive(Copy, Clone)]
+    struct WorkerError(usize);
+
+    #[test]
+    fn three_workers() {
+        let (mut scope, handle) = Mailbox::<WorkerError>::new();
+
+        let workers: Vec<_> = (0..3)
+            .map(|i| {
+                let handle = handle.clone();
+                std::thread::spawn(move || handle.raise(WorkerError(i)))
+            })
+            .collect();
+
+        let handlers = crate::builder(|e: WorkerError| crate::Result::new(e.0))
+            .handle(|_: &'static str| crate::Result::new(usize::MAX))
+            .build();
+
+        let mut handled = Vec::new();
+        while handled.len() < 3 {
+            scope.wait();
+            handled.extend(scope.process(handlers).into_iter().map(|r| r.unwrap()));
+        }
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        handled.sort();
+        assert_eq!(handled, vec![0, 1, 2]);
+        assert!(scope.is_empty());
+    }
+
+    #[test]
+    fn captures_local_raises() {
+        let (mut scope, _handle) = Mailbox::<WorkerError>::new();
+        let res: crate::Result<()> = scope.run(|| crate::Result::new_error(WorkerError(7)));
+        assert!(res.is_error());
+        assert_eq!(scope.len(), 1);
+        assert!(scope.wait_timeout(std::time::Duration::from_millis(1)));
+
+        let results = scope.process(crate::builder(|e: WorkerError| crate::Result::new(e.0)).build());
+        assert_eq!(results[0].error_id(), None);
+        assert!(!scope.wait_timeout(std::time::Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn drain_until_workers_done() {
+        let (mut scope, handle) = Mailbox::<WorkerError>::new();
+        let workers: Vec<_> = (0..4)
+            .map(|i| {
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    for j in 0..3 {
+                        std::thread::sleep(std::time::Duration::from_millis(1));
+                        handle.raise(WorkerError(i * 3 + j));
+                    }
+                })
+            })
+            .collect();
+        assert_eq!(scope.senders(), 5);
+        drop(handle);
+
+        let results = scope.drain(crate::builder(|e: WorkerError| crate::Result::new(e.0)).build());
+        let mut handled: Vec<_> = results.into_iter().map(|r| r.unwrap()).collect();
+        handled.sort();
+        assert_eq!(handled, (0..12).collect::<Vec<_>>());
+        assert_eq!(scope.senders(), 0);
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,489 @@
+//! Helpers for testing code using xcept.
+
+use crate::context::{ErasedError, ErrorHandlingContext, FirstErrorContext, ReportedError, TrySetErrorResult};
+use crate::multihandler::TryHandle;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::mem::ManuallyDrop;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// A global allocator counting the allocations made by each thread.
+///
+/// Install it in a test binary to use [`assert_no_alloc`]:
+///
+/// ```
+/// #[global_allocator]
+/// static ALLOCATOR: xcept::test_support::CountingAllocator =
+///     xcept::test_support::CountingAllocator::new();
+///
+/// xcept::test_support::assert_no_alloc(|| {
+///     let _ = xcept::Result::new(1);
+/// });
+/// ```
+pub struct CountingAllocator<A = System> {
+    inner: A,
+}
+
+impl CountingAllocator {
+    /// Count the allocations made by the system allocator.
+    pub const fn new() -> Self {
+        Self { inner: System }
+    }
+}
+
+impl Default for CountingAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A> CountingAllocator<A> {
+    /// Count the allocations made by `inner`.
+    pub const fn with_allocator(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+fn count() {
+    INSTALLED.store(true, Ordering::Relaxed);
+    // Not counted while the thread is being torn down
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        self.inner.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count();
+        self.inner.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        self.inner.realloc(ptr, layout, new_size)
+    }
+}
+
+/// The number of allocations made by the current thread so far.
+///
+/// Always 0 unless [`CountingAllocator`] is the global allocator.
+pub fn allocation_count() -> usize {
+    ALLOCATIONS.with(|count| count.get())
+}
+
+/// Run `func` and panic if it allocated on the current thread.
+///
+/// Requires [`CountingAllocator`] to be the global allocator, panics otherwise. Only allocations
+/// made by the current thread are counted, so tests running in parallel don't interfere.
+///
+/// Some features allocate once per thread on first use, like the ring buffer of the
+/// `diagnostics` feature. Run `func` once before asserting to exclude those.
+pub fn assert_no_alloc<R>(func: impl FnOnce() -> R) -> R {
+    // Allocating here makes sure the allocator is detected even if nothing has allocated yet
+    drop(Box::new(0u8));
+    assert!(
+        INSTALLED.load(Ordering::Relaxed),
+        "`assert_no_alloc` requires `CountingAllocator` as the global allocator"
+    );
+    let before = allocation_count();
+    let result = func();
+    let allocations = allocation_count() - before;
+    assert_eq!(allocations, 0, "{allocations} allocation(s) made");
+    result
+}
+
+/// What a handler chain did with an error given to [`drive`].
+pub enum DriveOutcome<V> {
+    /// The chain didn't accept the error, it would be offered to the enclosing scopes.
+    Declined,
+    /// The chain rejected the error, it would not be offered to any other scope.
+    Suppressed,
+    /// The chain accepted the error, but none of its handlers ran.
+    NotHandled,
+    /// A handler produced a value.
+    Handled(V),
+    /// A handler raised a new error, which is returned.
+    Raised(ErasedError),
+    /// A handler returned an error result without raising, with this error ID.
+    Failed(crate::ErrorId),
+}
+
+impl<V> DriveOutcome<V> {
+    /// The value produced by a handler, if any.
+    pub fn handled(self) -> Option<V> {
+        match self {
+            Self::Handled(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Give `err` directly to a handler chain, without raising it.
+///
+/// The chain is offered the error under a fresh error ID and then asked to handle it, the same
+/// way [`try_or_handle`](crate::try_or_handle) does, including dropping or forgetting the value
+/// as the chain requests. Errors raised by the handler are captured by a temporary scope instead
+/// of reaching the active scopes. Pass a copy of the chain, or a mutable reference for chains
+/// like [`DynHandlers`](crate::dynamic::DynHandlers).
+///
+/// # Examples
+///
+/// ```
+/// use xcept::test_support::{drive, DriveOutcome};
+///
+/// let handlers = xcept::builder(|x: i32| xcept::Result::new(x * 2))
+///     .handle(|_: u8| xcept::Result::new_error("bad byte"))
+///     .build();
+///
+/// assert_eq!(drive(handlers, 4).handled(), Some(8));
+/// assert!(matches!(drive(handlers, true), DriveOutcome::Declined));
+/// match drive(handlers, 1u8) {
+///     DriveOutcome::Raised(err) => assert_eq!(err.type_name(), "&str"),
+///     _ => panic!("expected a raise"),
+/// }
+/// ```
+pub fn drive<H, E>(mut handlers: H, err: E) -> DriveOutcome<H::Value>
+where
+    H: ErrorHandlingContext + TryHandle,
+    E: crate::Error,
+{
+    let id = crate::ErrorId::from_raw(crate::context::fresh_id());
+    #[cfg(feature = "paranoid")]
+    crate::id_state::raised(id);
+    let mut err = ManuallyDrop::new(err);
+    let accepted = {
+        let reported = ReportedError::new(id, &mut *err);
+        // Safety: the value is forgotten or dropped below as requested
+        unsafe { handlers.try_set_error(&reported) }
+    };
+    #[cfg(feature = "paranoid")]
+    crate::id_state::delivered(id, crate::context::Disposition::from_result(&accepted));
+    match accepted {
+        TrySetErrorResult::NeedForget => {}
+        TrySetErrorResult::NeedDrop => unsafe { ManuallyDrop::drop(&mut err) },
+        TrySetErrorResult::NotHandled => {
+            unsafe { ManuallyDrop::drop(&mut err) };
+            return DriveOutcome::Declined;
+        }
+        TrySetErrorResult::Suppress => {
+            unsafe { ManuallyDrop::drop(&mut err) };
+            return DriveOutcome::Suppressed;
+        }
+    }
+
+    let mut probe = FirstErrorContext::default();
+    let handled = {
+        let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut probe));
+        // Safety: the guard is dropped at the end of the block
+        let _guard = unsafe { scope.register() };
+        handlers.try_handle(id)
+    };
+
+    match handled.map(crate::Result::try_unwrap) {
+        None => DriveOutcome::NotHandled,
+        Some(Ok(value)) => DriveOutcome::Handled(value),
+        Some(Err(id)) => match probe.into_inner() {
+            Some(raised) if raised.id() == id => DriveOutcome::Raised(raised),
+            _ => DriveOutcome::Failed(id),
+        },
+    }
+}
+
+/// Unwrap the value of a [`Result`](crate::Result), panicking with what is known about the error.
+///
+/// The panic message names the expression and the error ID, and with the `diagnostics` feature
+/// the type and location of the raise.
+///
+/// # Examples
+///
+/// ```
+/// let value = xcept::assert_ok!(xcept::Result::new(3));
+/// assert_eq!(value, 3);
+/// ```
+#[macro_export]
+macro_rules! assert_ok {
+    ($expr:expr $(,)?) => {
+        $crate::test_support::__assert_ok($expr, stringify!($expr))
+    };
+}
+
+/// Assert that evaluating an expression raised an error of exactly type `E`.
+///
+/// By default an error the code under test handled itself counts as well, the value is then
+/// gone and `None` is returned. `Some` holds the last `E` that wasn't handled within the
+/// expression. With `@unhandled_only` only an `E` that wasn't handled within the expression
+/// counts, and it is returned directly.
+///
+/// Errors of type `E` are captured by a scope around the expression, so they never reach the
+/// scopes of the test. Errors raised with
+/// [`Result::new_error_bare`](crate::Result::new_error_bare) are only seen if they aren't
+/// handled within the expression.
+///
+/// # Examples
+///
+/// ```
+/// fn parse(s: &str) -> xcept::Result<u32> {
+///     s.parse().into()
+/// }
+///
+/// // Handled within the expression
+/// let escaped = xcept::assert_raises!(
+///     std::num::ParseIntError,
+///     xcept::try_or_handle_one(|| parse("x"), |_: std::num::ParseIntError| xcept::Result::new(0))
+/// );
+/// assert!(escaped.is_none());
+///
+/// let err = xcept::assert_raises!(@unhandled_only std::num::ParseIntError, parse("-1"));
+/// assert_eq!(err.to_string(), "invalid digit found in string");
+/// ```
+#[macro_export]
+macro_rules! assert_raises {
+    (@unhandled_only $ty:ty, $expr:expr $(,)?) => {
+        $crate::test_support::__assert_raises_unhandled::<$ty, _>(|| $expr, stringify!($expr))
+    };
+    ($ty:ty, $expr:expr $(,)?) => {
+        $crate::test_support::__assert_raises::<$ty, _>(|| $expr, stringify!($expr))
+    };
+}
+
+/// Assert that evaluating an expression raised no error at all, returning its value.
+///
+/// Errors handled within the expression count as well.
+///
+/// # Examples
+///
+/// ```
+/// let sum = xcept::assert_no_raise!(xcept::Result::new(1).map(|x| x + 1));
+/// assert_eq!(sum.unwrap(), 2);
+/// ```
+#[macro_export]
+macro_rules! assert_no_raise {
+    ($expr:expr $(,)?) => {
+        $crate::test_support::__assert_no_raise(|| $expr, stringify!($expr))
+    };
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn __assert_ok<T>(res: crate::Result<T>, expr: &str) -> T {
+    match res.error_id() {
+        None => res.unwrap(),
+        Some(id) => panic!("`{expr}` is an error: {}", crate::describe_unhandled(id)),
+    }
+}
+
+/// Run `func` in a scope capturing `E`, returning the number of `E`s raised and the last one
+/// reaching the scope.
+fn raises_of<E: crate::Error, T>(func: impl FnOnce() -> T) -> (usize, Option<E>) {
+    let raised = std::rc::Rc::new(Cell::new(0));
+    let counter = raised.clone();
+    let probe = crate::on_raise(move |_: &E| counter.set(counter.get() + 1));
+    let mut storage = crate::context::SingleErrorStorage::<E>::default();
+    let _ = {
+        let scope = std::pin::pin!(crate::context::ScopeNode::new(&mut storage));
+        // Safety: the guard is dropped at the end of the block
+        let _guard = unsafe { scope.register() };
+        func()
+    };
+    drop(probe);
+    let captured = storage.into_inner().map(|(_, err)| err);
+    (raised.get().max(usize::from(captured.is_some())), captured)
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn __assert_raises<E: crate::Error, T>(func: impl FnOnce() -> T, expr: &str) -> Option<E> {
+    let (raised, captured) = raises_of::<E, T>(func);
+    if raised == 0 {
+        panic!("`{expr}` raised no `{}`", std::any::type_name::<E>());
+    }
+    captured
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn __assert_raises_unhandled<E: crate::Error, T>(func: impl FnOnce() -> T, expr: &str) -> E {
+    match raises_of::<E, T>(func) {
+        (_, Some(err)) => err,
+        (0, None) => panic!("`{expr}` raised no `{}`", std::any::type_name::<E>()),
+        (raised, None) => panic!(
+            "`{expr}` raised no unhandled `{}`, {raised} raised were handled within",
+            std::any::type_name::<E>()
+        ),
+    }
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn __assert_no_raise<T>(func: impl FnOnce() -> T, expr: &str) -> T {
+    let before = crate::context::raise_count();
+    let value = func();
+    let raised = crate::context::raise_count() - before;
+    if raised != 0 {
+        panic!("`{expr}` raised {raised} error(s)");
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{drive, DriveOutcome};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    struct Tracked(Rc<Cell<usize>>);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn three_handler_chain() {
+        let handlers = || {
+            crate::builder(|x: i32| crate::Result::new(x + 1))
+                .handle(|_: u8| crate::Result::new_error("byte"))
+                .handle(|_: Tracked| crate::Result::new(0))
+                .build()
+        };
+
+        assert_eq!(drive(handlers(), 1).handled(), Some(2));
+        assert!(matches!(drive(handlers(), 1u16), DriveOutcome::Declined));
+        match drive(handlers(), 1u8) {
+            DriveOutcome::Raised(err) => {
+                assert_eq!(err.type_id(), std::any::TypeId::of::<&'static str>());
+                assert_eq!(*err.into_box().downcast::<&str>().unwrap(), "byte");
+            }
+            _ => panic!("expected a raise"),
+        }
+
+        // The re-raise is captured by drive, not by the enclosing scope
+        let res = crate::try_or_handle_one(
+            || {
+                assert!(matches!(drive(handlers(), 1u8), DriveOutcome::Raised(_)));
+                crate::Result::new(false)
+            },
+            |_: &'static str| crate::Result::new(true),
+        );
+        assert!(!res.unwrap());
+    }
+
+    #[test]
+    fn drops_once() {
+        let drops = Rc::new(Cell::new(0));
+        let handlers = crate::builder(|_: Tracked| crate::Result::new(1)).build();
+        assert_eq!(drive(handlers, Tracked(drops.clone())).handled(), Some(1));
+        assert_eq!(drops.get(), 1);
+
+        let handlers = crate::builder(|_: i32| crate::Result::new(1)).build();
+        assert!(matches!(drive(handlers, Tracked(drops.clone())), DriveOutcome::Declined));
+        assert_eq!(drops.get(), 2);
+    }
+
+    #[test]
+    fn suppressed_and_failed() {
+        struct Suppressing;
+        impl crate::context::ErrorHandlingContext for Suppressing {
+            unsafe fn try_set_error(&mut self, _: &crate::context::ReportedError) -> crate::context::TrySetErrorResult {
+                crate::context::TrySetErrorResult::Suppress
+            }
+        }
+        impl crate::multihandler::TryHandle for Suppressing {
+            type Value = ();
+            fn try_handle(self, _: crate::ErrorId) -> Option<crate::Result<()>> {
+                None
+            }
+        }
+        assert!(matches!(drive(Suppressing, 1), DriveOutcome::Suppressed));
+
+        let handlers = crate::builder(|_: i32| crate::Result::<()>::new_with_error_id(crate::ErrorId::from_raw(7))).build();
+        assert!(matches!(drive(handlers, 1), DriveOutcome::Failed(id) if id.to_raw() == 7));
+    }
+
+    fn panic_message(func: impl FnOnce() + std::panic::UnwindSafe) -> String {
+        let payload = std::panic::catch_unwind(func).unwrap_err();
+        match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => payload.downcast_ref::<&str>().unwrap().to_string(),
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Invalid(u32);
+
+    fn validate(x: u32) -> crate::Result<u32> {
+        if x > 10 {
+            crate::Result::new_error(Invalid(x))
+        } else {
+            crate::Result::new(x)
+        }
+    }
+
+    fn validate_or_zero(x: u32) -> crate::Result<u32> {
+        crate::try_or_handle_one(|| validate(x), |_: Invalid| crate::Result::new(0))
+    }
+
+    #[test]
+    fn assert_ok() {
+        assert_eq!(crate::assert_ok!(validate(3)), 3);
+        let message = panic_message(|| {
+            crate::assert_ok!(validate(11));
+        });
+        assert!(message.starts_with("`validate(11)` is an error: unhandled error #"), "{message}");
+        #[cfg(feature = "diagnostics")]
+        assert!(message.contains("Invalid`, raised at src/test_support.rs:"), "{message}");
+    }
+
+    #[test]
+    fn assert_raises() {
+        assert_eq!(crate::assert_raises!(Invalid, validate(11)), Some(Invalid(11)));
+        // Handled within, still seen
+        assert_eq!(crate::assert_raises!(Invalid, validate_or_zero(12)), None);
+        assert_eq!(crate::assert_raises!(@unhandled_only Invalid, validate(13)), Invalid(13));
+
+        let message = panic_message(|| {
+            crate::assert_raises!(Invalid, validate(1));
+        });
+        assert!(message.ends_with("`validate(1)` raised no `xcept::test_support::tests::Invalid`"), "{message}");
+        let message = panic_message(|| {
+            crate::assert_raises!(@unhandled_only Invalid, validate_or_zero(14));
+        });
+        assert!(message.ends_with("no unhandled `xcept::test_support::tests::Invalid`, 1 raised were handled within"), "{message}");
+
+        // Other types pass through to the scopes of the test
+        let res = crate::try_or_handle_one(
+            || {
+                crate::assert_raises!(Invalid, {
+                    let _ = validate(15);
+                    crate::Result::<u32>::new_error(2u8)
+                })
+                .map_or(crate::Result::new(0), |err| crate::Result::new(err.0))
+            },
+            |_: u8| crate::Result::new(1),
+        );
+        assert_eq!(res.unwrap(), 15);
+    }
+
+    #[test]
+    fn assert_no_raise() {
+        assert_eq!(crate::assert_no_raise!(validate(4)).unwrap(), 4);
+        let message = panic_message(|| {
+            crate::assert_no_raise!(validate_or_zero(20));
+        });
+        assert_eq!(message, "`validate_or_zero(20)` raised 1 error(s)");
+    }
+}
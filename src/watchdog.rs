@@ -0,0 +1,321 @@
+//! Timing out a whole scope from a watchdog thread.
+//!
+//! Errors can't be raised into the scopes of another thread, so timeouts are cooperative: a
+//! shared watchdog thread marks the scopes run by [`scope_with_timeout`] as expired, and the
+//! [`checkpoint`]s inside them raise [`ScopeTimedOut`] locally. The checkpoints of
+//! [`blocking`](crate::blocking), including the ones of
+//! [`read_with_checkpoints`](crate::blocking::read_with_checkpoints), observe the timeouts too.
+//!
+//! A single watchdog thread is spawned the first time a timeout is registered, and serves every
+//! thread of the process.
+
+use crate::context::{ContextExt, ErrorHandlingContext, ReportedError, TrySetErrorResult};
+use crate::multihandler::{AsHandlerChain, TryHandle};
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Once};
+use std::time::{Duration, Instant};
+
+/// The error raised at a [`checkpoint`] of a scope that ran longer than its timeout.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ScopeTimedOut {
+    /// The timeout of the scope
+    pub timeout: Duration,
+    scope: u64,
+}
+
+impl std::fmt::Display for ScopeTimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "scope timed out after {:?}", self.timeout)
+    }
+}
+
+impl std::error::Error for ScopeTimedOut {}
+
+type Deadlines = BTreeMap<(Instant, u64), Arc<AtomicBool>>;
+
+struct Watchdog {
+    deadlines: Mutex<Deadlines>,
+    changed: Condvar,
+}
+
+static WATCHDOG: Watchdog = Watchdog {
+    deadlines: Mutex::new(BTreeMap::new()),
+    changed: Condvar::new(),
+};
+static STARTED: Once = Once::new();
+static NEXT_SCOPE: AtomicU64 = AtomicU64::new(0);
+
+impl Watchdog {
+    fn deadlines(&self) -> MutexGuard<'_, Deadlines> {
+        self.deadlines.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn run(&self) {
+        let mut deadlines = self.deadlines();
+        loop {
+            let now = Instant::now();
+            while let Some(entry) = deadlines.first_entry() {
+                if entry.key().0 > now {
+                    break;
+                }
+                entry.remove().store(true, Ordering::Release);
+            }
+            deadlines = match deadlines.keys().next() {
+                Some(&(deadline, _)) => {
+                    self.changed
+                        .wait_timeout(deadlines, deadline - now)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => self.changed.wait(deadlines).unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+}
+
+struct Active {
+    error: ScopeTimedOut,
+    expired: Arc<AtomicBool>,
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Vec<Active>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Registers a scope with the watchdog, and unregisters it when dropped.
+struct Registration {
+    key: Option<(Instant, u64)>,
+}
+
+impl Registration {
+    fn new(timeout: Duration) -> (Self, u64) {
+        let scope = NEXT_SCOPE.fetch_add(1, Ordering::Relaxed);
+        let expired = Arc::new(AtomicBool::new(false));
+        // A deadline that can't be represented is never reached
+        let key = Instant::now().checked_add(timeout).map(|deadline| (deadline, scope));
+        if let Some(key) = key {
+            STARTED.call_once(|| {
+                std::thread::Builder::new()
+                    .name("xcept-watchdog".into())
+                    .spawn(|| WATCHDOG.run())
+                    .expect("failed to spawn the watchdog thread");
+            });
+            let mut deadlines = WATCHDOG.deadlines();
+            deadlines.insert(key, expired.clone());
+            if deadlines.keys().next() == Some(&key) {
+                WATCHDOG.changed.notify_one();
+            }
+        }
+        ACTIVE.with(|active| {
+            active.borrow_mut().push(Active {
+                error: ScopeTimedOut { timeout, scope },
+                expired,
+            })
+        });
+        (Self { key }, scope)
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            WATCHDOG.deadlines().remove(&key);
+        }
+        let _ = ACTIVE.try_with(|active| active.borrow_mut().pop());
+    }
+}
+
+/// Accepts [`ScopeTimedOut`] only from its own scope, so nested timeouts don't catch the
+/// timeout of an outer scope.
+struct OwnTimeout<H> {
+    scope: u64,
+    handlers: H,
+}
+
+impl<H: ErrorHandlingContext> ErrorHandlingContext for OwnTimeout<H> {
+    unsafe fn try_set_error(&mut self, error: &ReportedError) -> TrySetErrorResult {
+        if error.type_id() == TypeId::of::<ScopeTimedOut>() {
+            // Safety: the type ID matches
+            let timed_out = unsafe { &*(error.value_ptr() as *const ScopeTimedOut) };
+            if timed_out.scope != self.scope {
+                return TrySetErrorResult::NotHandled;
+            }
+        }
+        self.handlers.try_set_error(error)
+    }
+
+    #[inline]
+    fn ext(&mut self) -> Option<&mut dyn ContextExt> {
+        self.handlers.ext()
+    }
+}
+
+impl<H: TryHandle> TryHandle for OwnTimeout<H> {
+    type Value = H::Value;
+    fn try_handle(self, error_id: crate::ErrorId) -> Option<crate::Result<H::Value>> {
+        self.handlers.try_handle(error_id)
+    }
+}
+
+/// Execute `func`, raising [`ScopeTimedOut`] at its checkpoints once it has run for `timeout`.
+///
+/// Errors raised by `func` are handled by `handlers`, like [`try_or_handle`](crate::try_or_handle).
+/// `func` is only interrupted at its checkpoints, if it returns without reaching one after the
+/// timeout its result is kept.
+///
+/// Scopes can be nested. The handlers of a scope only receive its own timeout, the timeout of an
+/// outer scope propagates to the outer scope. If several scopes have expired the outermost one is
+/// raised.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use xcept::watchdog::{checkpoint, scope_with_timeout, ScopeTimedOut};
+///
+/// let res = scope_with_timeout(
+///     Duration::from_millis(10),
+///     || loop {
+///         std::thread::sleep(Duration::from_millis(1));
+///         if let Err(id) = checkpoint().try_unwrap() {
+///             return xcept::Result::new_with_error_id(id);
+///         }
+///     },
+///     xcept::builder(|err: ScopeTimedOut| xcept::Result::new(err.timeout)).build(),
+/// );
+/// assert_eq!(res.unwrap(), Duration::from_millis(10));
+/// ```
+pub fn scope_with_timeout<F, H, T>(timeout: Duration, func: F, handlers: H) -> crate::Result<T>
+where
+    F: FnOnce() -> crate::Result<T>,
+    H: AsHandlerChain,
+    H::Chain: TryHandle<Value = T>,
+{
+    let (registration, scope) = Registration::new(timeout);
+    let handlers = OwnTimeout {
+        scope,
+        handlers: handlers.into_handler_chain(),
+    };
+    crate::try_or_handle(
+        move || {
+            let res = func();
+            drop(registration);
+            res
+        },
+        handlers,
+    )
+}
+
+/// The timeout of the outermost expired scope of the current thread.
+pub(crate) fn expired() -> Option<ScopeTimedOut> {
+    ACTIVE.with(|active| {
+        active
+            .borrow()
+            .iter()
+            .find(|active| active.expired.load(Ordering::Acquire))
+            .map(|active| active.error)
+    })
+}
+
+/// Raise [`ScopeTimedOut`] if a scope run by [`scope_with_timeout`] has expired.
+pub fn checkpoint() -> crate::Result<()> {
+    match expired() {
+        Some(err) => crate::Result::new_error(err),
+        None => crate::Result::new(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checkpoint, scope_with_timeout, ScopeTimedOut};
+    use std::time::{Duration, Instant};
+
+    fn run_until_checkpoint_fails(checkpoint: fn() -> crate::Result<()>) -> crate::Result<u32> {
+        loop {
+            std::thread::sleep(Duration::from_millis(2));
+            crate::check!(checkpoint());
+        }
+    }
+
+    #[test]
+    fn slow_body_times_out() {
+        let started = Instant::now();
+        let res = scope_with_timeout(
+            Duration::from_millis(20),
+            || run_until_checkpoint_fails(checkpoint),
+            crate::builder(|err: ScopeTimedOut| crate::Result::new(err.timeout.as_millis() as u32)).build(),
+        );
+        assert_eq!(res.unwrap(), 20);
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // The blocking checkpoints observe timeouts too
+        let res = scope_with_timeout(
+            Duration::from_millis(20),
+            || run_until_checkpoint_fails(crate::blocking::checkpoint),
+            crate::builder(|_: ScopeTimedOut| crate::Result::new(0)).build(),
+        );
+        assert_eq!(res.unwrap(), 0);
+        assert!(checkpoint().is_ok());
+    }
+
+    #[test]
+    fn fast_body_never_times_out() {
+        let res = scope_with_timeout(
+            Duration::from_millis(50),
+            || {
+                crate::check!(checkpoint());
+                crate::Result::new(1)
+            },
+            crate::builder(|_: ScopeTimedOut| -> crate::Result<i32> { panic!("timed out") }).build(),
+        );
+        assert_eq!(res.unwrap(), 1);
+
+        // Unregistered, so the expired deadline is never observed
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(checkpoint().is_ok());
+        let res = scope_with_timeout(Duration::MAX, checkpoint, crate::builder(|_: ScopeTimedOut| crate::Result::new(())).build());
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn nested_scopes() {
+        let inner_handled = std::cell::Cell::new(false);
+        let res = scope_with_timeout(
+            Duration::from_millis(20),
+            || {
+                scope_with_timeout(
+                    Duration::from_secs(60),
+                    || run_until_checkpoint_fails(checkpoint),
+                    crate::builder(|_: ScopeTimedOut| {
+                        inner_handled.set(true);
+                        crate::Result::new(0)
+                    })
+                    .build(),
+                )
+            },
+            crate::builder(|err: ScopeTimedOut| crate::Result::new(err.timeout.as_millis() as u32)).build(),
+        );
+        assert_eq!(res.unwrap(), 20);
+        assert!(!inner_handled.get());
+
+        let res = scope_with_timeout(
+            Duration::from_secs(60),
+            || {
+                let inner = scope_with_timeout(
+                    Duration::from_millis(20),
+                    || run_until_checkpoint_fails(checkpoint),
+                    crate::builder(|_: ScopeTimedOut| crate::Result::new(7)).build(),
+                );
+                assert_eq!(inner.clone().unwrap(), 7);
+                crate::check!(checkpoint());
+                inner
+            },
+            crate::builder(|_: ScopeTimedOut| crate::Result::new(0)).build(),
+        );
+        assert_eq!(res.unwrap(), 7);
+    }
+}
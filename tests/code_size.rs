@@ -0,0 +1,67 @@
+//! Loose regression checks of the generated code.
+//!
+//! `examples/raise_sites.rs` is built in release mode with and without its 200 raises, and the
+//! size difference per site must stay within a small budget. A raise site should only contain a
+//! call to the outlined raise path.
+//!
+//! `examples/handle_fn_sites.rs` is built with its 1000 scopes using `try_or_handle_fn` and the
+//! generic `try_or_handle_one`, reporting the size and build time of both.
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const SITES: u64 = 200;
+const MAX_BYTES_PER_SITE: u64 = 128;
+
+/// Build `example` in release mode, with `switch` set or not, returning the binary size and the
+/// build time.
+fn example_size(target_dir: &Path, example: &str, switch: &str, set: bool) -> (u64, Duration) {
+    let mut cargo = Command::new(env!("CARGO"));
+    cargo
+        .args(["build", "--quiet", "--release", "--example", example, "--target-dir"])
+        .arg(target_dir)
+        .current_dir(env!("CARGO_MANIFEST_DIR"));
+    if set {
+        cargo.env(switch, "1");
+    } else {
+        cargo.env_remove(switch);
+    }
+    let start = Instant::now();
+    assert!(cargo.status().unwrap().success());
+    let elapsed = start.elapsed();
+
+    let binary = target_dir
+        .join("release/examples")
+        .join(format!("{example}{}", std::env::consts::EXE_SUFFIX));
+    (std::fs::metadata(binary).unwrap().len(), elapsed)
+}
+
+#[test]
+#[ignore = "builds an example twice in release mode, run with `--ignored`"]
+fn bytes_per_raise_site() {
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("code-size");
+    let (with_raises, _) = example_size(&target_dir, "raise_sites", "XCEPT_SIZE_BASELINE", false);
+    let (baseline, _) = example_size(&target_dir, "raise_sites", "XCEPT_SIZE_BASELINE", true);
+
+    let per_site = with_raises.saturating_sub(baseline) / SITES;
+    assert!(
+        per_site <= MAX_BYTES_PER_SITE,
+        "{per_site} bytes per raise site, expected at most {MAX_BYTES_PER_SITE}"
+    );
+}
+
+#[test]
+#[ignore = "builds an example three times in release mode, run with `--ignored --nocapture`"]
+fn handle_fn_sites() {
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("code-size");
+    // Builds the dependencies, so the timed builds only compile the example
+    example_size(&target_dir, "handle_fn_sites", "XCEPT_HANDLE_GENERIC", true);
+    let (with_fn, fn_time) = example_size(&target_dir, "handle_fn_sites", "XCEPT_HANDLE_GENERIC", false);
+    let (generic, generic_time) = example_size(&target_dir, "handle_fn_sites", "XCEPT_HANDLE_GENERIC", true);
+
+    // Only reported, build times are too noisy to assert on, and the vtable of each body makes
+    // the `dyn` version somewhat larger once the generic one is fully inlined
+    println!("try_or_handle_fn:   {with_fn} bytes, built in {fn_time:.2?}");
+    println!("try_or_handle_one:  {generic} bytes, built in {generic_time:.2?}");
+}
@@ -0,0 +1,6 @@
+use xcept::conformance::{run_all, ThreadLocalBackend};
+
+#[test]
+fn thread_local_backend() {
+    run_all::<ThreadLocalBackend>();
+}
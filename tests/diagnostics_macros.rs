@@ -0,0 +1,44 @@
+//! `diagnostics` together with `macros`: raises captured by a derived capture set are recorded.
+#![cfg(all(feature = "diagnostics", feature = "macros"))]
+
+use xcept::capture::CaptureSet;
+use xcept::context::Disposition;
+
+#[derive(Debug, PartialEq)]
+struct Timeout(u32);
+
+#[derive(CaptureSet)]
+enum NetError {
+    Timeout(Timeout),
+    Refused(&'static str),
+}
+
+#[test]
+fn derived_capture_is_recorded() {
+    xcept::debug::clear();
+    let handlers = xcept::capture_set::<NetError>().handle(|err| match err {
+        NetError::Timeout(Timeout(ms)) => xcept::Result::new(ms),
+        NetError::Refused(host) => xcept::Result::new(host.len() as u32),
+    });
+    let line = line!() + 1;
+    let res = xcept::try_or_handle(|| xcept::Result::new_error(Timeout(30)), handlers);
+    assert_eq!(res.unwrap(), 30);
+
+    let recent = xcept::debug::recent_raises();
+    assert_eq!(recent.len(), 1);
+    assert_eq!(recent[0].type_name, std::any::type_name::<Timeout>());
+    assert_eq!(recent[0].disposition, Disposition::Captured);
+    assert_eq!(recent[0].location.unwrap().line(), line);
+}
+
+#[test]
+fn unmatched_raise_is_recorded_unhandled() {
+    xcept::debug::clear();
+    let handlers = xcept::capture_set::<NetError>().handle(|_| xcept::Result::new(0));
+    let res = xcept::try_or_handle(|| xcept::Result::new_error(1u8), handlers);
+    assert!(res.is_error(), "{res:?}");
+
+    let recent = xcept::debug::recent_raises();
+    assert_eq!(recent[0].id, res.error_id().unwrap());
+    assert_eq!(recent[0].disposition, Disposition::Unhandled);
+}
@@ -0,0 +1,132 @@
+//! Fatal unhandled errors, run in child processes since they end the process.
+//!
+//! Every test spawns this test binary again, running only `child` with `XCEPT_FATAL_CHILD` naming
+//! the scenario. The shutdown callbacks of the child append to a file, which the parent reads
+//! back along with the exit code.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+use xcept::fatal::FatalConfig;
+
+const SCENARIO: &str = "XCEPT_FATAL_CHILD";
+const LOG: &str = "XCEPT_FATAL_LOG";
+
+struct Corrupt;
+
+fn append(log: &Path, line: &str) {
+    use std::io::Write;
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(log).unwrap();
+    writeln!(file, "{line}").unwrap();
+}
+
+fn config(log: &Path) -> FatalConfig {
+    let (first, second) = (log.to_owned(), log.to_owned());
+    FatalConfig::new()
+        .exit_code(42)
+        .flush_timeout(Duration::from_millis(500))
+        .on_shutdown(move || append(&first, "first"))
+        .on_shutdown(move || append(&second, "second"))
+}
+
+#[test]
+fn child() {
+    let (Ok(scenario), Ok(log)) = (std::env::var(SCENARIO), std::env::var(LOG)) else {
+        return;
+    };
+    let log = PathBuf::from(log);
+    match scenario.as_str() {
+        "unhandled" => {
+            xcept::fatal::install(config(&log));
+            // Handled errors are not fatal
+            let handled = xcept::try_or_handle_one(|| xcept::Result::new_error(Corrupt), |_: Corrupt| xcept::Result::new(1));
+            append(&log, &format!("handled {}", handled.unwrap()));
+            let _: xcept::Result<()> = xcept::Result::new_error(Corrupt);
+        }
+        "reentrant" => {
+            let inner = log.clone();
+            xcept::fatal::install(config(&log).on_shutdown(move || {
+                let _: xcept::Result<()> = xcept::Result::new_error(Corrupt);
+                append(&inner, "raised during shutdown");
+            }));
+            let _: xcept::Result<()> = xcept::Result::new_error(Corrupt);
+        }
+        "hanging" => {
+            let hanging = log.clone();
+            xcept::fatal::install(
+                config(&log).on_shutdown(move || {
+                    append(&hanging, "hanging");
+                    std::thread::sleep(Duration::from_secs(60));
+                    append(&hanging, "woke up");
+                }),
+            );
+            let _: xcept::Result<()> = xcept::Result::new_error(Corrupt);
+        }
+        "abandoned" => {
+            xcept::fatal::install(config(&log).abandoned(true));
+            let _ = std::panic::catch_unwind(|| {
+                xcept::try_or_handle_one(
+                    || {
+                        let _: xcept::Result<()> = xcept::Result::new_error(Corrupt);
+                        std::panic::resume_unwind(Box::new("cancelled"))
+                    },
+                    |_: Corrupt| xcept::Result::new(()),
+                )
+            });
+        }
+        _ => panic!("unknown scenario {scenario}"),
+    }
+    append(&log, "survived");
+}
+
+/// Run `scenario` in a child process, returning its output and the lines of its log.
+fn run(scenario: &str) -> (Output, Vec<String>) {
+    let log = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("fatal-{scenario}-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&log);
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "child", "--nocapture", "--test-threads=1"])
+        .env(SCENARIO, scenario)
+        .env(LOG, &log)
+        .output()
+        .unwrap();
+    let lines = std::fs::read_to_string(&log).unwrap_or_default().lines().map(str::to_owned).collect();
+    let _ = std::fs::remove_file(&log);
+    (output, lines)
+}
+
+#[test]
+fn unhandled_error_exits_after_callbacks() {
+    let (output, lines) = run("unhandled");
+    assert_eq!(output.status.code(), Some(42));
+    assert_eq!(lines, ["handled 1", "first", "second"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("fatal: unhandled error #"), "{stderr}");
+    assert!(stderr.contains("of type `fatal::Corrupt`"), "{stderr}");
+}
+
+#[test]
+fn errors_during_shutdown_are_not_fatal_again() {
+    let (output, lines) = run("reentrant");
+    assert_eq!(output.status.code(), Some(42));
+    assert_eq!(lines, ["first", "second", "raised during shutdown"]);
+}
+
+#[test]
+fn watchdog_exits_when_a_callback_hangs() {
+    let started = Instant::now();
+    let (output, lines) = run("hanging");
+    assert_eq!(output.status.code(), Some(42));
+    assert_eq!(lines, ["first", "second", "hanging"]);
+    assert!(started.elapsed() < Duration::from_secs(30));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("shutdown callbacks did not finish within 500ms"), "{stderr}");
+}
+
+#[test]
+fn abandoned_error_is_fatal_when_enabled() {
+    let (output, lines) = run("abandoned");
+    assert_eq!(output.status.code(), Some(42));
+    assert_eq!(lines, ["first", "second"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("fatal: abandoned error of type `fatal::Corrupt`"), "{stderr}");
+}
@@ -0,0 +1,46 @@
+//! Both implementations of the migration example must produce the same reports.
+
+#[path = "../examples/migration/report.rs"]
+mod report;
+#[path = "../examples/migration/std_impl.rs"]
+mod std_impl;
+#[path = "../examples/migration/xcept_impl.rs"]
+mod xcept_impl;
+
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/migration/fixtures").join(name)
+}
+
+fn same_report(name: &str) -> String {
+    let path = fixture(name);
+    let report = std_impl::ingest(&path);
+    assert_eq!(report, xcept_impl::ingest(&path), "{name}");
+    report
+}
+
+#[test]
+fn good_input() {
+    assert_eq!(same_report("good.csv"), "3 records, mean score 71, oldest carol");
+    assert_eq!(same_report("empty.csv"), "no records");
+}
+
+#[test]
+fn bad_input() {
+    assert_eq!(same_report("bad_number.csv"), "error: line 3: invalid age 'twenty'");
+    assert_eq!(same_report("out_of_range.csv"), "error: line 3: score 640 out of range");
+    assert_eq!(same_report("missing_field.csv"), "error: line 2: expected 3 fields, got 2");
+}
+
+#[test]
+fn io_errors() {
+    let missing = fixture("missing.csv");
+    assert_eq!(same_report("missing.csv"), format!("error: {} not found", missing.display()));
+
+    // Reading a directory fails with an error other than `NotFound`
+    let dir = fixture("");
+    let report = std_impl::ingest(&dir);
+    assert!(report.starts_with(&format!("error: reading {}: ", dir.display())), "{report}");
+    assert_eq!(report, xcept_impl::ingest(&dir));
+}
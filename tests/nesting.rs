@@ -0,0 +1,169 @@
+//! Single handler scopes nested in multihandler scopes and the other way around.
+//!
+//! The inner scope handles `io::Error`, the outer scope handles `io::Error` as well, and with a
+//! multihandler also `Other`. Every combination of inner scope kind, raised type and whether the
+//! inner handler raises again is checked against the exact sequence of handler invocations.
+
+use std::cell::RefCell;
+use std::io;
+
+struct Other(u32);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Inner {
+    Single,
+    Multi,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Raised {
+    Io,
+    Other,
+}
+
+type Log = RefCell<Vec<String>>;
+
+fn raise(raised: Raised) -> xcept::Result<u32> {
+    match raised {
+        Raised::Io => xcept::Result::new_error(io::Error::other("io")),
+        Raised::Other => xcept::Result::new_error(Other(7)),
+    }
+}
+
+fn inner_handler<'a>(log: &'a Log, rethrow: bool) -> impl FnOnce(io::Error) -> xcept::Result<u32> + 'a {
+    move |err| {
+        log.borrow_mut().push(format!("inner io {err}"));
+        if rethrow {
+            xcept::Result::new_error(io::Error::other("rethrown"))
+        } else {
+            xcept::Result::new(1)
+        }
+    }
+}
+
+fn inner_scope(log: &Log, inner: Inner, raised: Raised, rethrow: bool) -> xcept::Result<u32> {
+    match inner {
+        Inner::Single => xcept::try_or_handle_one(|| raise(raised), inner_handler(log, rethrow)),
+        Inner::Multi => {
+            let handlers = xcept::builder(inner_handler(log, rethrow))
+                .handle(|_: bool| xcept::Result::new(0))
+                .build();
+            xcept::try_or_handle(|| raise(raised), handlers)
+        }
+    }
+}
+
+/// Runs the combination and returns the log and the final value, `None` if the error escaped.
+fn run(inner: Inner, raised: Raised, rethrow: bool) -> (Vec<String>, Option<u32>) {
+    let log = Log::default();
+    // The outer scope is the other kind of scope
+    let res = match inner {
+        Inner::Single => {
+            let handlers = xcept::builder(|err: io::Error| {
+                log.borrow_mut().push(format!("outer io {err}"));
+                xcept::Result::new(2)
+            })
+            .handle(|err: Other| {
+                log.borrow_mut().push(format!("outer other {}", err.0));
+                xcept::Result::new(3)
+            })
+            .build();
+            xcept::try_or_handle(|| inner_scope(&log, inner, raised, rethrow), handlers)
+        }
+        Inner::Multi => xcept::try_or_handle_one(
+            || inner_scope(&log, inner, raised, rethrow),
+            |err: io::Error| {
+                log.borrow_mut().push(format!("outer io {err}"));
+                xcept::Result::new(2)
+            },
+        ),
+    };
+    (log.into_inner(), res.ok())
+}
+
+#[test]
+fn matrix() {
+    for inner in [Inner::Single, Inner::Multi] {
+        for rethrow in [false, true] {
+            let case = format!("{inner:?} inner, rethrow {rethrow}");
+
+            // The innermost scope always wins, the outer never sees what the inner captured
+            let (log, value) = run(inner, Raised::Io, rethrow);
+            if rethrow {
+                assert_eq!(log, ["inner io io", "outer io rethrown"], "{case}");
+                assert_eq!(value, Some(2), "{case}");
+            } else {
+                assert_eq!(log, ["inner io io"], "{case}");
+                assert_eq!(value, Some(1), "{case}");
+            }
+
+            // What the inner declines reaches the outer, the inner handler never runs
+            let (log, value) = run(inner, Raised::Other, rethrow);
+            match inner {
+                Inner::Single => {
+                    assert_eq!(log, ["outer other 7"], "{case}");
+                    assert_eq!(value, Some(3), "{case}");
+                }
+                Inner::Multi => {
+                    assert!(log.is_empty(), "{case}");
+                    assert_eq!(value, None, "{case}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn captured_error_not_returned() {
+    // The inner scope captures an `io::Error` but its closure returns a later error. The captured
+    // error must not be handled by anyone, and the returned one only by the scope that captured it
+    for inner in [Inner::Single, Inner::Multi] {
+        let log = Log::default();
+        let body = || {
+            let _ = xcept::Result::<u32>::new_error(io::Error::other("dropped"));
+            xcept::Result::<u32>::new_error(Other(9))
+        };
+        let inner_scope = || match inner {
+            Inner::Single => xcept::try_or_handle_one(body, inner_handler(&log, false)),
+            Inner::Multi => xcept::try_or_handle(
+                body,
+                xcept::builder(inner_handler(&log, false))
+                    .handle(|_: bool| xcept::Result::new(0))
+                    .build(),
+            ),
+        };
+        let handlers = xcept::builder(|err: io::Error| {
+            log.borrow_mut().push(format!("outer io {err}"));
+            xcept::Result::new(2)
+        })
+        .handle(|err: Other| {
+            log.borrow_mut().push(format!("outer other {}", err.0));
+            xcept::Result::new(3)
+        })
+        .build();
+        let res = xcept::try_or_handle(inner_scope, handlers);
+        assert_eq!(res.unwrap(), 3, "{inner:?}");
+        assert_eq!(*log.borrow(), ["outer other 9"], "{inner:?}");
+    }
+
+    // With the outer scope only handling `io::Error`, the returned error escapes both scopes
+    // instead of aliasing the captured one
+    let log = Log::default();
+    let res = xcept::try_or_handle_one(
+        || {
+            xcept::try_or_handle_one(
+                || {
+                    let _ = xcept::Result::<u32>::new_error(io::Error::other("dropped"));
+                    xcept::Result::<u32>::new_error(Other(9))
+                },
+                inner_handler(&log, false),
+            )
+        },
+        |err: io::Error| {
+            log.borrow_mut().push(format!("outer io {err}"));
+            xcept::Result::new(2)
+        },
+    );
+    assert!(res.is_error(), "{res:?}");
+    assert!(log.borrow().is_empty());
+}
@@ -0,0 +1,99 @@
+//! The happy path, and raising small errors, must not allocate.
+
+use xcept::test_support::{assert_no_alloc, CountingAllocator};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator::new();
+
+#[derive(Copy, Clone)]
+struct Marker;
+
+#[derive(Copy, Clone)]
+struct Code(u16);
+
+/// One-time per thread setup, like the `diagnostics` ring buffer.
+fn warm_up() {
+    let _: xcept::Result<()> = xcept::Result::new_error(Marker);
+    xcept::flush_enrichment();
+}
+
+#[test]
+fn new_value() {
+    warm_up();
+    assert_no_alloc(|| xcept::Result::new(5).unwrap());
+}
+
+#[test]
+fn ok_path_single_handler() {
+    warm_up();
+    let res = assert_no_alloc(|| {
+        xcept::try_or_handle_one(|| xcept::Result::new(1), |_: Code| xcept::Result::new(0))
+    });
+    assert_eq!(res.unwrap(), 1);
+}
+
+#[test]
+fn ok_path_four_handlers() {
+    warm_up();
+    let res = assert_no_alloc(|| {
+        let handlers = xcept::builder(|_: Code| xcept::Result::new(0))
+            .handle(|_: Marker| xcept::Result::new(1))
+            .handle(|_: u8| xcept::Result::new(2))
+            .handle(|_: bool| xcept::Result::new(3))
+            .build();
+        xcept::try_or_handle(|| xcept::Result::new(4), handlers)
+    });
+    assert_eq!(res.unwrap(), 4);
+}
+
+#[test]
+fn raise_zero_sized() {
+    warm_up();
+    let res: xcept::Result<()> = assert_no_alloc(|| xcept::Result::new_error(Marker));
+    assert!(res.is_error(), "{res:?}");
+}
+
+#[test]
+fn raise_and_handle_copy() {
+    warm_up();
+    let res = assert_no_alloc(|| {
+        xcept::try_or_handle_one(
+            || xcept::Result::new_error(Code(7)),
+            |code: Code| xcept::Result::new(code.0),
+        )
+    });
+    assert_eq!(res.unwrap(), 7);
+}
+
+xcept::flag!(Timeout, Cancelled);
+
+#[test]
+fn raise_and_handle_flags() {
+    warm_up();
+    let run = |cancel: bool| {
+        assert_no_alloc(|| {
+            let handlers = xcept::builder(|_: Code| xcept::Result::new(0))
+                .handle_flag::<Timeout>(|| xcept::Result::new(1))
+                .handle_flag::<Cancelled>(|| xcept::Result::new(2))
+                .build();
+            xcept::try_or_handle(
+                || {
+                    if cancel {
+                        xcept::Result::new_error(Cancelled)
+                    } else {
+                        xcept::Result::new_error(Timeout)
+                    }
+                },
+                handlers,
+            )
+        })
+    };
+    assert_eq!(run(false).unwrap(), 1);
+    assert_eq!(run(true).unwrap(), 2);
+}
+
+#[test]
+fn detects_allocations() {
+    let res = std::panic::catch_unwind(|| assert_no_alloc(|| Box::new(1)));
+    assert!(res.is_err());
+}